@@ -0,0 +1,97 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+
+use planner::algorithm::{
+    calculate_fitness,
+    config::{AlgorithmConfig, CrossoverOperator},
+    conflicts::ConflictMatrix,
+    create_first_population,
+    datatypes::{Individual, Population, Tuple},
+    mutate,
+    operators::Operators,
+    random::get_random_generator,
+    synthetic::{self, InstanceSpec},
+};
+
+/// Numbers of tuples of the benchmarked instances, with a teacher and a room per 25 tuples
+const SIZES: [usize; 3] = [200, 1000, 4000];
+
+/// A synthetic instance with the conflict matrix built and a small evaluated population
+fn instance(tuples: usize) -> (AlgorithmConfig, Vec<Tuple>, Population) {
+    let tuples = synthetic::generate(&InstanceSpec {
+        tuples,
+        rooms: tuples / 25,
+        teachers: tuples / 25,
+        density: 0.3,
+        seed: 42,
+    });
+    let config = AlgorithmConfig {
+        population_size: 16,
+        ..AlgorithmConfig::default()
+    };
+    let matrix = ConflictMatrix::new(&config, &tuples);
+    let config = config.with_conflict_matrix(matrix);
+
+    let mut population = create_first_population(&config, &tuples, &mut get_random_generator(42));
+    for individual in &mut population {
+        individual.adaptation = calculate_fitness(&config, individual, &tuples);
+    }
+    (config, tuples, population)
+}
+
+fn fitness(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fitness");
+    for size in SIZES {
+        let (config, tuples, population) = instance(size);
+        group.bench_with_input(
+            BenchmarkId::from_parameter(size),
+            &population[0],
+            |b, individual| b.iter(|| calculate_fitness(&config, individual, &tuples)),
+        );
+    }
+    group.finish();
+}
+
+fn crossover(c: &mut Criterion) {
+    let mut group = c.benchmark_group("crossover");
+    for size in SIZES {
+        let (config, tuples, population) = instance(size);
+        for operator in [CrossoverOperator::OnePoint, CrossoverOperator::Order] {
+            let config = AlgorithmConfig {
+                crossover_operator: operator,
+                mutation_probability: 0.0,
+                ..config.clone()
+            };
+            let operators = Operators::from_config(&config);
+            let mut rng = get_random_generator(42);
+            let mut child = Individual::default();
+
+            let id = BenchmarkId::new(format!("{:?}", operator), size);
+            group.bench_function(id, |b| {
+                b.iter(|| {
+                    operators.breed_into(&config, &population, &tuples, &mut rng, &mut child);
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+fn mutation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("mutation");
+    for size in SIZES {
+        let (config, tuples, population) = instance(size);
+        let mut rng = get_random_generator(42);
+
+        group.bench_function(BenchmarkId::from_parameter(size), |b| {
+            b.iter_batched_ref(
+                || population[0].clone(),
+                |individual| mutate(&config, individual, &tuples, &mut rng),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, fitness, crossover, mutation);
+criterion_main!(benches);
@@ -0,0 +1,56 @@
+use std::panic;
+
+use tracing::error;
+
+use crate::mpi_utils::TransferError;
+
+/// Why the planner stops early, each reason with its own exit code for batch systems
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    /// The config or an input file can't be used
+    InvalidInput = 1,
+    /// A message between ranks was truncated, corrupted or sent by another build
+    InvalidMessage = 3,
+    /// A message arrived intact, but doesn't hold the expected type
+    Deserialization = 4,
+    /// Ranks disagree on the size or contents of data they should share
+    SizeMismatch = 5,
+    /// A rank panicked for any other reason, Rust's usual code of a panic
+    Panic = 101,
+    /// Second Ctrl-C or SIGTERM
+    Interrupted = 130,
+}
+
+impl ExitReason {
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+
+    /// Exit this process, the other ranks of an MPI job are stopped by the launcher
+    pub fn exit(self) -> ! {
+        std::process::exit(self.code())
+    }
+}
+
+/// Make a panic on any rank stop all of them, calling `abort` with the code of its [`ExitReason`]
+///
+/// Without it the other ranks would wait for the messages of the panicking one forever. A
+/// [`TransferError`] is logged as it is, other panics by the default hook first.
+pub fn install_abort_hook(abort: impl Fn(i32) + Send + Sync + 'static) {
+    let default_hook = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        let reason = match info.payload().downcast_ref::<TransferError>() {
+            Some(err) => {
+                error!("{}", err);
+                err.exit_reason()
+            }
+            None => {
+                default_hook(info);
+                ExitReason::Panic
+            }
+        };
+        error!(exit_code = reason.code(), "Aborting all ranks");
+        abort(reason.code());
+    }));
+}
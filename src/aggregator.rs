@@ -0,0 +1,302 @@
+use std::{cell::RefCell, path::Path};
+
+use crate::mpi::{topology::SimpleCommunicator, Rank};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+
+use crate::{
+    algorithm::{datatypes::Individual, metrics::PopulationStats},
+    mpi_utils::{receive_serde_from_any, send_serde},
+};
+
+/// Metrics of every generation of every island, written by the logger rank
+const METRICS_PATH: &str = "metrics.csv";
+
+/// Best schedule reported so far, rewritten by the logger rank whenever it improves
+const BEST_SO_FAR_PATH: &str = "best_so_far.csv";
+
+/// Message of an island to the logger rank
+#[derive(Debug, Serialize, Deserialize)]
+enum Report {
+    Generation {
+        run: usize,
+        generation: usize,
+        stats: PopulationStats,
+        /// Best individual of the island, only when it improved since the last report
+        improved: Option<Individual>,
+    },
+    /// The island finished all its runs
+    Finished,
+}
+
+/// Row of [`METRICS_PATH`], also kept by the islands themselves, see [`keep_history`]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct MetricsRecord {
+    pub run: usize,
+    pub island: Rank,
+    pub generation: usize,
+    pub size: usize,
+    pub best_adaptation: i32,
+    pub worst_adaptation: i32,
+    pub mean_adaptation: f64,
+    pub median_adaptation: i32,
+    pub std_dev_adaptation: f64,
+}
+
+impl MetricsRecord {
+    fn new(run: usize, island: Rank, generation: usize, stats: &PopulationStats) -> Self {
+        MetricsRecord {
+            run,
+            island,
+            generation,
+            size: stats.size,
+            best_adaptation: stats.best,
+            worst_adaptation: stats.worst,
+            mean_adaptation: stats.mean,
+            median_adaptation: stats.median,
+            std_dev_adaptation: stats.std_dev,
+        }
+    }
+}
+
+/// Where the reports of this rank go, see [`connect`]
+struct Reporter {
+    communicator: SimpleCommunicator,
+    logger_rank: Rank,
+    run: usize,
+    /// Best adaptation reported in the current run
+    best_reported: i32,
+}
+
+/// Metrics of the generations of this rank's island, see [`keep_history`]
+struct History {
+    island: Rank,
+    run: usize,
+    records: Vec<MetricsRecord>,
+}
+
+thread_local! {
+    static REPORTER: RefCell<Option<Reporter>> = const { RefCell::new(None) };
+    static HISTORY: RefCell<Option<History>> = const { RefCell::new(None) };
+}
+
+/// Send the reports of this rank to the logger rank of `communicator` until [`finish`]
+///
+/// The communicator should only carry reports, e.g. a duplicate of the world. Like the timings,
+/// reporters are kept per thread, ranks report from their main threads.
+pub fn connect(communicator: SimpleCommunicator, logger_rank: Rank) {
+    REPORTER.with(|reporter| {
+        *reporter.borrow_mut() = Some(Reporter {
+            communicator,
+            logger_rank,
+            run: 0,
+            best_reported: i32::MIN,
+        })
+    });
+}
+
+/// Keep the metrics of every generation of this rank's island, numbered `island`, until
+/// [`take_history`]
+///
+/// Unlike the reports, the history stays on the rank, so it costs no messages during the runs.
+pub fn keep_history(island: Rank) {
+    HISTORY.with(|history| {
+        *history.borrow_mut() = Some(History {
+            island,
+            run: 0,
+            records: Vec::new(),
+        })
+    });
+}
+
+/// Metrics kept since [`keep_history`], which stops keeping them
+pub fn take_history() -> Vec<MetricsRecord> {
+    HISTORY
+        .with(|history| history.borrow_mut().take())
+        .map_or_else(Vec::new, |history| history.records)
+}
+
+/// Number the following reports with `run`
+pub fn begin_run(run: usize) {
+    with_reporter(|reporter| {
+        reporter.run = run;
+        reporter.best_reported = i32::MIN;
+    });
+    HISTORY.with(|history| {
+        if let Some(history) = history.borrow_mut().as_mut() {
+            history.run = run;
+        }
+    });
+}
+
+/// Report the statistics of a generation of the island, and its best individual if it improved
+///
+/// Does nothing for ranks without a population. Without a logger rank connected the statistics
+/// only go to the history, if it's kept.
+pub fn report_generation(generation: usize, stats: &PopulationStats, population: &[Individual]) {
+    if population.is_empty() {
+        return;
+    }
+
+    HISTORY.with(|history| {
+        if let Some(history) = history.borrow_mut().as_mut() {
+            let record = MetricsRecord::new(history.run, history.island, generation, stats);
+            history.records.push(record);
+        }
+    });
+
+    with_reporter(|reporter| {
+        let improved = (stats.best > reporter.best_reported)
+            .then(|| {
+                population
+                    .iter()
+                    .max_by_key(|individual| individual.adaptation)
+            })
+            .flatten()
+            .cloned();
+        if improved.is_some() {
+            reporter.best_reported = stats.best;
+        }
+
+        let report = Report::Generation {
+            run: reporter.run,
+            generation,
+            stats: *stats,
+            improved,
+        };
+        send_serde(&report, reporter.logger_rank, &reporter.communicator);
+    });
+}
+
+/// Tell the logger rank this island is done and disconnect from it
+pub fn finish() {
+    if let Some(reporter) = REPORTER.with(|reporter| reporter.borrow_mut().take()) {
+        send_serde(
+            &Report::Finished,
+            reporter.logger_rank,
+            &reporter.communicator,
+        );
+    }
+}
+
+fn with_reporter(report: impl FnOnce(&mut Reporter)) {
+    REPORTER.with(|reporter| {
+        if let Some(reporter) = reporter.borrow_mut().as_mut() {
+            report(reporter);
+        }
+    });
+}
+
+/// Receive the reports of `islands` islands until all of them [`finish`], writing their metrics
+/// to [`METRICS_PATH`] and the best individual so far to [`BEST_SO_FAR_PATH`]
+///
+/// Runs on the logger rank, the only rank writing files during the runs.
+pub fn serve(communicator: &SimpleCommunicator, islands: usize) -> Result<(), csv::Error> {
+    serve_in(communicator, islands, Path::new(""))
+}
+
+/// [`serve`] writing the files to `dir`
+fn serve_in(
+    communicator: &SimpleCommunicator,
+    islands: usize,
+    dir: &Path,
+) -> Result<(), csv::Error> {
+    let mut metrics = csv::Writer::from_path(dir.join(METRICS_PATH))?;
+    let mut best_adaptation = i32::MIN;
+    let mut finished = 0;
+
+    while finished < islands {
+        let (report, island) = receive_serde_from_any::<Report>(communicator);
+        let Report::Generation {
+            run,
+            generation,
+            stats,
+            improved,
+        } = report
+        else {
+            finished += 1;
+            debug!(island, "Island finished");
+            continue;
+        };
+
+        metrics.serialize(MetricsRecord::new(run, island, generation, &stats))?;
+        metrics.flush()?;
+
+        if let Some(best) = improved.filter(|best| best.adaptation > best_adaptation) {
+            best_adaptation = best.adaptation;
+            info!(
+                run,
+                island,
+                generation,
+                adaptation = best_adaptation,
+                "New best individual"
+            );
+            best.to_csv(dir.join(BEST_SO_FAR_PATH))?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, not(feature = "mpi")))]
+mod tests {
+    use super::*;
+    use crate::{
+        algorithm::metrics::PopulationMetrics,
+        mpi::{run_ranks, traits::*},
+    };
+
+    #[test]
+    fn test_the_logger_rank_receives_the_metrics_of_every_island() {
+        let dir = std::env::temp_dir().join("planner_aggregator_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        run_ranks(3, |world| {
+            let rank = world.rank();
+            if rank == 2 {
+                serve_in(&world, 2, &dir).unwrap();
+                return;
+            }
+
+            connect(world, 2);
+            begin_run(1);
+            for generation in 1..=3 {
+                // island 1 finds better individuals than island 0, but stops improving
+                let adaptation = -10 * (2 - rank) + generation.min(2) as i32;
+                let population = vec![Individual {
+                    adaptation,
+                    ..Individual::default()
+                }];
+                report_generation(generation, &population.stats(), &population);
+            }
+            finish();
+        });
+
+        let mut records: Vec<MetricsRecord> = csv::Reader::from_path(dir.join(METRICS_PATH))
+            .unwrap()
+            .deserialize()
+            .map(Result::unwrap)
+            .collect();
+        records.sort_by_key(|record| (record.island, record.generation));
+        let best: Vec<(Rank, usize, i32)> = records
+            .iter()
+            .map(|record| (record.island, record.generation, record.best_adaptation))
+            .collect();
+        assert_eq!(
+            best,
+            [
+                (0, 1, -19),
+                (0, 2, -18),
+                (0, 3, -18),
+                (1, 1, -9),
+                (1, 2, -8),
+                (1, 3, -8),
+            ]
+        );
+        assert!(records.iter().all(|record| record.run == 1));
+        assert!(dir.join(BEST_SO_FAR_PATH).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,72 @@
+use rand::Rng;
+
+use super::{
+    config::AlgorithmConfig,
+    datatypes::{Individual, Population},
+};
+
+/// Temperature of the given generation, `initial_temperature * cooling_rate^generation`
+///
+/// Returns `None` when simulated-annealing acceptance is disabled.
+pub fn temperature(config: &AlgorithmConfig, generation_number: usize) -> Option<f64> {
+    config.initial_temperature.map(|initial_temperature| {
+        initial_temperature as f64 * (config.cooling_rate as f64).powi(generation_number as i32)
+    })
+}
+
+/// Let every child compete with the better of its parents
+///
+/// A child at least as good as the parent is always kept. A worse child is kept with probability
+/// `e^((child - parent) / temperature)`, otherwise the parent takes its place. Early on most
+/// worse children survive, which helps to leave local optima, and as the temperature falls the
+/// replacement gets greedy. `parents[i]` is the better parent of `offspring[i]`.
+pub fn accept_offspring(
+    offspring: &mut Population,
+    parents: Vec<Individual>,
+    temperature: f64,
+    rng: &mut impl Rng,
+) {
+    for (child, parent) in offspring.iter_mut().zip(parents) {
+        if child.adaptation >= parent.adaptation {
+            continue;
+        }
+
+        let loss = (parent.adaptation - child.adaptation) as f64;
+        let acceptance = if temperature > 0.0 {
+            f64::exp(-loss / temperature)
+        } else {
+            0.0
+        };
+        if !rng.gen_bool(acceptance) {
+            *child = parent;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::random::get_random_generator;
+
+    fn with_adaptation(adaptation: i32) -> Individual {
+        Individual {
+            adaptation,
+            ..Individual::with_chromosomes(Vec::new())
+        }
+    }
+
+    #[test]
+    fn test_worse_children_are_rejected_when_cold() {
+        let mut rng = get_random_generator(11);
+        let parents = vec![with_adaptation(-10), with_adaptation(-10)];
+
+        let mut offspring = vec![with_adaptation(-5), with_adaptation(-30)];
+        accept_offspring(&mut offspring, parents.clone(), 0.0, &mut rng);
+        assert_eq!(offspring[0].adaptation, -5);
+        assert_eq!(offspring[1].adaptation, -10);
+
+        let mut offspring = vec![with_adaptation(-5), with_adaptation(-30)];
+        accept_offspring(&mut offspring, parents, 1e9, &mut rng);
+        assert_eq!(offspring[1].adaptation, -30);
+    }
+}
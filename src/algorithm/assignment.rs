@@ -0,0 +1,270 @@
+use std::{borrow::Cow, collections::BTreeMap};
+
+use rand::prelude::*;
+
+use super::{
+    config::AlgorithmConfig,
+    datatypes::{Gene, Individual, Tuple},
+};
+
+/// Where the choices of a tuple and the chosen values of an individual are kept
+struct Resource {
+    enabled: fn(&AlgorithmConfig) -> bool,
+    choices: fn(&Tuple) -> &[String],
+    chosen: fn(&mut Individual) -> &mut BTreeMap<Gene, String>,
+    chosen_of: fn(&Individual) -> &BTreeMap<Gene, String>,
+    mutation_probability: fn(&AlgorithmConfig) -> f32,
+}
+
+const ROOMS: Resource = Resource {
+    enabled: |config| config.room_assignment,
+    choices: |tuple| &tuple.allowed_rooms,
+    chosen: |individual| &mut individual.rooms,
+    chosen_of: |individual| &individual.rooms,
+    mutation_probability: |config| config.room_mutation_probability,
+};
+
+const TEACHERS: Resource = Resource {
+    enabled: |config| config.teacher_assignment,
+    choices: |tuple| &tuple.qualified_teachers,
+    chosen: |individual| &mut individual.teachers,
+    chosen_of: |individual| &individual.teachers,
+    mutation_probability: |config| config.teacher_mutation_probability,
+};
+
+/// Tuples with the rooms and teachers chosen for them by the individual, see
+/// [`Individual::rooms`] and [`Individual::teachers`]
+///
+/// Without `room_assignment` and `teacher_assignment` the tuples are returned as they are. Chosen
+/// names take the ids interned for the allowed rooms and qualified teachers, other names keep the
+/// ids of the tuple's own room and teacher.
+pub fn effective_tuples<'a>(
+    config: &AlgorithmConfig,
+    individual: &Individual,
+    tuples: &'a [Tuple],
+) -> Cow<'a, [Tuple]> {
+    let rooms = Some(&individual.rooms).filter(|_| config.room_assignment);
+    let teachers = Some(&individual.teachers).filter(|_| config.teacher_assignment);
+    if rooms.is_none_or(BTreeMap::is_empty) && teachers.is_none_or(BTreeMap::is_empty) {
+        return Cow::Borrowed(tuples);
+    }
+
+    Cow::Owned(
+        tuples
+            .iter()
+            .map(|tuple| {
+                let mut tuple = tuple.clone();
+                if let Some(room) = rooms.and_then(|rooms| rooms.get(&tuple.id)) {
+                    if let Some(index) = tuple.allowed_rooms.iter().position(|r| r == room) {
+                        tuple.room_id = tuple.allowed_room_ids[index];
+                    }
+                    tuple.room = room.clone();
+                }
+                if let Some(teacher) = teachers.and_then(|teachers| teachers.get(&tuple.id)) {
+                    if let Some(index) = tuple.qualified_teachers.iter().position(|t| t == teacher)
+                    {
+                        tuple.teacher_id = tuple.qualified_teacher_ids[index];
+                    }
+                    tuple.teacher = teacher.clone();
+                }
+                tuple
+            })
+            .collect(),
+    )
+}
+
+/// Choose a random allowed room and qualified teacher for every tuple listing them, for the
+/// assignments turned on in the configuration
+pub fn assign_randomly(
+    config: &AlgorithmConfig,
+    individual: &mut Individual,
+    tuples: &[Tuple],
+    rng: &mut impl Rng,
+) {
+    for resource in [ROOMS, TEACHERS] {
+        if !(resource.enabled)(config) {
+            continue;
+        }
+        *(resource.chosen)(individual) = tuples
+            .iter()
+            .filter_map(|tuple| Some((tuple.id, (resource.choices)(tuple).choose(rng)?.clone())))
+            .collect();
+    }
+}
+
+/// Give the child the room and teacher of every tuple from a random parent, like a uniform
+/// crossover
+///
+/// Choices the tuple no longer allows are replaced with random allowed ones.
+pub fn inherit(
+    config: &AlgorithmConfig,
+    child: &mut Individual,
+    mother: &Individual,
+    father: &Individual,
+    tuples: &[Tuple],
+    rng: &mut impl Rng,
+) {
+    for resource in [ROOMS, TEACHERS] {
+        if !(resource.enabled)(config) {
+            continue;
+        }
+        *(resource.chosen)(child) = tuples
+            .iter()
+            .filter(|tuple| !(resource.choices)(tuple).is_empty())
+            .map(|tuple| {
+                let choices = (resource.choices)(tuple);
+                let parent = if rng.gen_bool(0.5) { mother } else { father };
+                let chosen = (resource.chosen_of)(parent)
+                    .get(&tuple.id)
+                    .filter(|chosen| choices.contains(chosen))
+                    .cloned()
+                    .unwrap_or_else(|| choices.choose(rng).unwrap().clone());
+                (tuple.id, chosen)
+            })
+            .collect();
+    }
+}
+
+/// Move every tuple with more than one choice to another allowed room with
+/// `room_mutation_probability` and to another qualified teacher with
+/// `teacher_mutation_probability`
+pub fn mutate(
+    config: &AlgorithmConfig,
+    individual: &mut Individual,
+    tuples: &[Tuple],
+    rng: &mut impl Rng,
+) {
+    for resource in [ROOMS, TEACHERS] {
+        if !(resource.enabled)(config) {
+            continue;
+        }
+        let probability = (resource.mutation_probability)(config);
+
+        for tuple in tuples {
+            let choices = (resource.choices)(tuple);
+            if choices.len() < 2 || !rng.gen_bool(probability.into()) {
+                continue;
+            }
+
+            let chosen = (resource.chosen)(individual);
+            let current = chosen.get(&tuple.id);
+            if let Some(other) = choices
+                .iter()
+                .filter(|choice| Some(*choice) != current)
+                .choose(rng)
+            {
+                chosen.insert(tuple.id, other.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::{
+        calculate_fitness,
+        datatypes::{Chromosome, PeriodId, TupleId},
+        random::get_random_generator,
+        symbols::Symbols,
+    };
+    use smallvec::smallvec;
+
+    #[test]
+    fn test_fitness_checks_the_chosen_rooms() {
+        let mut tuples: Vec<Tuple> = [(1, "Smith"), (2, "Jones")]
+            .into_iter()
+            .map(|(id, teacher)| Tuple {
+                id: TupleId(id),
+                room: "A".to_string(),
+                teacher: teacher.to_string(),
+                allowed_rooms: vec!["A".to_string(), "B".to_string()],
+                ..Tuple::default()
+            })
+            .collect();
+        Symbols::intern(&mut tuples);
+        let config = AlgorithmConfig {
+            number_of_periods: 1,
+            room_assignment: true,
+            ..AlgorithmConfig::default()
+        };
+        let mut individual = Individual::with_chromosomes(vec![Chromosome {
+            id: PeriodId(0),
+            genes: smallvec![TupleId(1), TupleId(2)],
+        }]);
+
+        individual.rooms = [(TupleId(1), "A".to_string()), (TupleId(2), "A".to_string())].into();
+        assert_eq!(calculate_fitness(&config, &individual, &tuples), -40);
+
+        individual.rooms.insert(TupleId(2), "B".to_string());
+        assert_eq!(calculate_fitness(&config, &individual, &tuples), 0);
+
+        let mut rng = get_random_generator(4);
+        let mut child = Individual::with_chromosomes(individual.chromosomes.clone());
+        inherit(
+            &config,
+            &mut child,
+            &individual,
+            &individual,
+            &tuples,
+            &mut rng,
+        );
+        assert_eq!(child.rooms, individual.rooms);
+        assert!(child.teachers.is_empty());
+    }
+
+    #[test]
+    fn test_chosen_teachers_are_balanced() {
+        let mut tuples: Vec<Tuple> = (1..=2)
+            .map(|id| Tuple {
+                id: TupleId(id),
+                room: format!("Room {}", id),
+                teacher: "Smith".to_string(),
+                qualified_teachers: vec!["Smith".to_string(), "Jones".to_string()],
+                ..Tuple::default()
+            })
+            .collect();
+        Symbols::intern(&mut tuples);
+        let config = AlgorithmConfig {
+            number_of_periods: 2,
+            teacher_assignment: true,
+            ..AlgorithmConfig::default()
+        };
+        let mut individual = Individual::with_chromosomes(vec![
+            Chromosome {
+                id: PeriodId(0),
+                genes: smallvec![TupleId(1)],
+            },
+            Chromosome {
+                id: PeriodId(1),
+                genes: smallvec![TupleId(2)],
+            },
+        ]);
+
+        assert_eq!(calculate_fitness(&config, &individual, &tuples), -2);
+
+        individual.teachers = [
+            (TupleId(1), "Smith".to_string()),
+            (TupleId(2), "Jones".to_string()),
+        ]
+        .into();
+        assert_eq!(calculate_fitness(&config, &individual, &tuples), 0);
+        assert_eq!(
+            effective_tuples(&config, &individual, &tuples)[1].teacher,
+            "Jones"
+        );
+
+        let mut rng = get_random_generator(4);
+        mutate(
+            &AlgorithmConfig {
+                teacher_mutation_probability: 1.0,
+                ..config
+            },
+            &mut individual,
+            &tuples,
+            &mut rng,
+        );
+        assert_eq!(individual.teachers[&TupleId(1)], "Jones");
+        assert_eq!(individual.teachers[&TupleId(2)], "Smith");
+    }
+}
@@ -0,0 +1,265 @@
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+};
+
+use rand::prelude::*;
+use rayon::prelude::*;
+
+use super::{
+    calculate_fitness,
+    config::AlgorithmConfig,
+    datatypes::{Gene, Individual, Population, Tuple},
+    operators::{Crossover, Mutator, Operators},
+    random::PlannerRng,
+    random_start_period, repair_child,
+};
+
+/// Period of every gene of the individual
+fn periods_of(individual: &Individual) -> HashMap<Gene, usize> {
+    individual
+        .chromosomes
+        .iter()
+        .enumerate()
+        .flat_map(|(period_id, chromosome)| chromosome.genes.iter().map(move |g| (*g, period_id)))
+        .collect()
+}
+
+/// Number of genes placed in different periods by the two individuals
+pub fn placement_distance(first: &Individual, second: &Individual) -> usize {
+    let second_periods = periods_of(second);
+
+    periods_of(first)
+        .into_iter()
+        .filter(|(gene, period_id)| second_periods.get(gene) != Some(period_id))
+        .count()
+}
+
+/// Half uniform crossover (HUX): the child takes exactly half of the genes the parents place
+/// differently from the father, chosen at random, and everything else from the mother
+#[derive(Debug, Clone, Copy)]
+pub struct HuxCrossover;
+
+impl Crossover for HuxCrossover {
+    fn crossover(
+        &self,
+        config: &AlgorithmConfig,
+        mother: &Individual,
+        father: &Individual,
+        tuples: &[Tuple],
+        rng: &mut PlannerRng,
+    ) -> Individual {
+        let father_periods = periods_of(father);
+        let mut differing: Vec<Gene> = periods_of(mother)
+            .into_iter()
+            .filter(|(gene, period_id)| {
+                father_periods
+                    .get(gene)
+                    .is_some_and(|period| period != period_id)
+            })
+            .map(|(gene, _)| gene)
+            .collect();
+        differing.sort_unstable();
+        differing.shuffle(rng);
+        let from_father = &differing[..differing.len() / 2];
+        let moved: HashSet<Gene> = from_father.iter().copied().collect();
+
+        let mut child = mother.clone();
+        for chromosome in &mut child.chromosomes {
+            chromosome.genes.retain(|gene| !moved.contains(gene));
+        }
+        for gene in from_father {
+            child.chromosomes[father_periods[gene]].genes.push(*gene);
+        }
+
+        repair_child(config, &mut child, mother, tuples, rng);
+        child
+    }
+}
+
+/// CHC doesn't mutate children, diversity only comes back with restarts
+#[derive(Debug, Clone, Copy)]
+pub struct NoMutation;
+
+impl Mutator for NoMutation {
+    fn mutate(&self, _: &AlgorithmConfig, _: &mut Individual, _: &[Tuple], _: &mut PlannerRng) {}
+}
+
+/// Evolution of a population by CHC (cross-generational elitist selection, heterogeneous
+/// recombination and cataclysmic mutation)
+///
+/// > Eshelman, L. J. (1991). The CHC Adaptive Search Algorithm: How to Have Safe Search When
+/// > Engaging in Nontraditional Genetic Recombination. Foundations of Genetic Algorithms, 1.
+///
+/// Every generation the population is paired at random and only parents differing in more than
+/// twice the incest threshold of genes mate, by [`HuxCrossover`]. Parents and children compete
+/// together and the best survive. When no child survives, the threshold drops by one. Once it is
+/// spent the population has converged: the best individual is kept and the others are replaced by
+/// copies of it with a `chc_divergence` fraction of the genes moved to random periods.
+pub struct Chc {
+    operators: Operators,
+    initial_threshold: usize,
+    threshold: usize,
+}
+
+impl Chc {
+    pub fn new(config: &AlgorithmConfig, tuples: &[Tuple]) -> Self {
+        let initial_threshold = tuples.len() / 4;
+
+        Chc {
+            operators: Operators {
+                crossover: Box::new(HuxCrossover),
+                mutator: Box::new(NoMutation),
+                ..Operators::from_config(config)
+            },
+            initial_threshold,
+            threshold: initial_threshold,
+        }
+    }
+
+    /// Evolve the population by one generation, returns whether it was restarted
+    pub fn generation(
+        &mut self,
+        config: &AlgorithmConfig,
+        population: &mut Population,
+        tuples: &[Tuple],
+        rng: &mut PlannerRng,
+    ) -> bool {
+        let mut order: Vec<usize> = (0..population.len()).collect();
+        order.shuffle(rng);
+
+        let mut offspring: Population = order
+            .chunks_exact(2)
+            .map(|pair| (&population[pair[0]], &population[pair[1]]))
+            .filter(|(mother, father)| placement_distance(mother, father) > 2 * self.threshold)
+            .map(|(mother, father)| {
+                self.operators
+                    .breed_parents(config, mother, father, tuples, rng)
+            })
+            .collect();
+        offspring.par_iter_mut().for_each(|child| {
+            child.adaptation = calculate_fitness(config, child, tuples);
+        });
+
+        // parents come first, so they win ties with their children
+        let size = population.len();
+        let mut candidates: Vec<(bool, Individual)> = population
+            .drain(..)
+            .map(|individual| (false, individual))
+            .chain(offspring.into_iter().map(|child| (true, child)))
+            .collect();
+        candidates.sort_by_key(|(_, individual)| Reverse(individual.adaptation));
+        candidates.truncate(size);
+        let accepted_children = candidates.iter().filter(|(is_child, _)| *is_child).count();
+        population.extend(candidates.into_iter().map(|(_, individual)| individual));
+
+        if accepted_children > 0 {
+            return false;
+        }
+        if self.threshold > 0 {
+            self.threshold -= 1;
+            return false;
+        }
+
+        self.restart(config, population, tuples, rng);
+        self.threshold = self.initial_threshold;
+        true
+    }
+
+    /// Cataclysmic mutation: replace everything but the best individual with heavily mutated
+    /// copies of it
+    fn restart(
+        &self,
+        config: &AlgorithmConfig,
+        population: &mut Population,
+        tuples: &[Tuple],
+        rng: &mut PlannerRng,
+    ) {
+        let Some(best) = population.first().cloned() else {
+            return;
+        };
+        let layout = config.period_layout();
+        let movable: Vec<&Tuple> = tuples
+            .iter()
+            .filter(|tuple| !config.locks.contains_key(&tuple.id))
+            .collect();
+        let moved = (movable.len() as f32 * config.chc_divergence).round() as usize;
+
+        for individual in population.iter_mut().skip(1) {
+            *individual = best.clone();
+            for tuple in movable.choose_multiple(rng, moved) {
+                for chromosome in &mut individual.chromosomes {
+                    chromosome.genes.retain(|gene| *gene != tuple.id);
+                }
+                let period_id = random_start_period(config, tuple, &layout, rng);
+                individual.chromosomes[period_id].genes.push(tuple.id);
+            }
+        }
+        population.par_iter_mut().skip(1).for_each(|individual| {
+            individual.adaptation = calculate_fitness(config, individual, tuples);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::{
+        create_first_population, datatypes::TupleId, random::get_random_generator,
+    };
+
+    fn tuples() -> Vec<Tuple> {
+        (1..=8)
+            .map(|id| Tuple {
+                id: TupleId(id),
+                teacher: format!("Teacher {}", id % 4),
+                room: format!("Room {}", id % 3),
+                ..Tuple::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_hux_takes_half_of_the_differences() {
+        let tuples = tuples();
+        let config = AlgorithmConfig {
+            population_size: 2,
+            number_of_periods: 6,
+            ..AlgorithmConfig::default()
+        };
+        let mut rng = get_random_generator(5);
+        let population = create_first_population(&config, &tuples, &mut rng);
+        let (mother, father) = (&population[0], &population[1]);
+        let distance = placement_distance(mother, father);
+
+        let child = HuxCrossover.crossover(&config, mother, father, &tuples, &mut rng);
+
+        assert_eq!(placement_distance(&child, mother), distance / 2);
+        assert_eq!(placement_distance(&child, father), distance - distance / 2);
+    }
+
+    #[test]
+    fn test_converged_population_restarts_around_the_best() {
+        let tuples = tuples();
+        let config = AlgorithmConfig {
+            population_size: 6,
+            number_of_periods: 6,
+            ..AlgorithmConfig::default()
+        };
+        let mut rng = get_random_generator(8);
+        let mut best = create_first_population(&config, &tuples, &mut rng).remove(0);
+        best.adaptation = calculate_fitness(&config, &best, &tuples);
+        let mut population = vec![best.clone(); 6];
+
+        let mut chc = Chc::new(&config, &tuples);
+        let restarts = (0..=tuples.len() / 4)
+            .filter(|_| chc.generation(&config, &mut population, &tuples, &mut rng))
+            .count();
+
+        assert_eq!(restarts, 1);
+        assert_eq!(population[0].placement_hash(), best.placement_hash());
+        assert!(population[1..]
+            .iter()
+            .any(|individual| placement_distance(individual, &best) > 0));
+    }
+}
@@ -1,15 +1,302 @@
-use std::{fs::File, path::Path};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    path::Path,
+    sync::Arc,
+};
 
-use mpi::traits::Equivalence;
-use serde::{Deserialize, Serialize};
+use chrono::{NaiveDate, NaiveTime};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
 use thiserror::Error;
 
+use super::{
+    conflicts::ConflictMatrix,
+    datatypes::{Locks, Tuple, TupleKind},
+    periods::PeriodLayout,
+    propagation::Domains,
+    resources::{Groups, Rooms, Teachers},
+};
+
 #[derive(Error, Debug)]
 pub enum ConfigLoadError {
     #[error("Configuration file not found")]
     FileNotFound(#[from] std::io::Error),
     #[error(transparent)]
     JsonError(#[from] serde_json::Error),
+    #[error(transparent)]
+    TomlError(#[from] toml::de::Error),
+    #[error(transparent)]
+    YamlError(#[from] serde_yaml::Error),
+    #[error("Invalid value {value:?} for {field}: {source}")]
+    InvalidOverride {
+        field: String,
+        value: String,
+        source: serde_json::Error,
+    },
+    #[error("Unknown config fields: {}", .0.join(", "))]
+    UnknownFields(Vec<String>),
+    #[error("Invalid configuration: {}", .0.join("; "))]
+    Invalid(Vec<String>),
+}
+
+/// Read a JSON, TOML or YAML file, the format is picked by extension like for the configuration
+pub fn read_file<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, ConfigLoadError> {
+    let path = path.as_ref();
+
+    Ok(match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => toml::from_str(&fs::read_to_string(path)?)?,
+        Some("yaml" | "yml") => serde_yaml::from_reader(File::open(path)?)?,
+        _ => serde_json::from_reader(File::open(path)?)?,
+    })
+}
+
+/// Prefix of environment variables overriding config fields, e.g. `PLANNER_POPULATION_SIZE`
+pub const ENV_PREFIX: &str = "PLANNER_";
+
+/// Fields every island must share, because all nodes step through generations and migrations
+/// together or compare adaptations. Fields ending with `_weight` are shared as well, like the
+/// limits and room sharing the fitness and the broadcast [`ConflictMatrix`] depend on
+pub const ISLAND_SHARED_FIELDS: [&str; 27] = [
+    "solver",
+    "max_generations",
+    "target_fitness",
+    "number_of_periods",
+    "days",
+    "slots_per_day",
+    "room_assignment",
+    "teacher_assignment",
+    "multi_objective",
+    "locks",
+    "rooms",
+    "teachers",
+    "student_groups",
+    "constraints",
+    "room_sharing",
+    "max_consecutive_hours",
+    "max_consecutive_hours_per_teacher",
+    "max_classes_per_period",
+    "max_classes_per_room_kind",
+    "penalty_adaptation_interval",
+    "diversity_interval",
+    "parallel_model",
+    "migration_interval",
+    "node_migration_interval",
+    "migration_topology",
+    "global_best_window",
+    "islands",
+];
+
+/// Read island overrides from text values, or from any values in human-readable formats
+///
+/// Values are kept as text for [`AlgorithmConfig::with_overrides`], so the configuration still
+/// round-trips through bincode when broadcast.
+fn deserialize_islands<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<BTreeMap<String, String>>, D::Error> {
+    if !deserializer.is_human_readable() {
+        return Vec::deserialize(deserializer);
+    }
+
+    let islands: Vec<BTreeMap<String, serde_json::Value>> = Vec::deserialize(deserializer)?;
+    Ok(islands
+        .into_iter()
+        .map(|fields| {
+            fields
+                .into_iter()
+                .map(|(field, value)| match value {
+                    serde_json::Value::String(text) => (field, text),
+                    value => (field, value.to_string()),
+                })
+                .collect()
+        })
+        .collect())
+}
+
+/// How the individuals of the first population are built
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum InitialPopulationStrategy {
+    /// Every tuple is assigned to a random period
+    #[default]
+    Random,
+    /// A fraction of individuals places each tuple in the period with the fewest clashes so far
+    Greedy,
+    /// Every tuple is placed in one of the periods with the fewest classes so far, spreading the
+    /// classes evenly like a round-robin
+    Balanced,
+}
+
+/// Algorithm searching for the schedule, see the `Solver`s of the planner
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SolverKind {
+    /// The genetic algorithm
+    #[default]
+    Genetic,
+    /// Tabu search from a greedy schedule on every node, see
+    /// [`tabu_search`](super::tabu::tabu_search)
+    Tabu,
+    /// CHC with restarts on every node, see [`Chc`](super::chc::Chc)
+    Chc,
+}
+
+/// How parents are picked for crossover, see [`Operators`](super::operators::Operators)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionOperator {
+    /// Weights decrease exponentially with the rank in the population sorted by adaptation
+    #[default]
+    Rank,
+    /// Fitness-proportionate selection, adaptations are shifted by the worst one (windowing), so
+    /// negative adaptations work
+    Roulette,
+}
+
+/// What every grid of the HTML timetable shows, see `timetable_grouping`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TimetableGrouping {
+    /// The classes of a room
+    #[default]
+    Room,
+    /// The classes of a student group, classes without a group are left out
+    Group,
+}
+
+/// What every sheet of `timetable.xlsx` shows, see `spreadsheet_layout`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SpreadsheetLayout {
+    /// The classes of a day, a column per room
+    #[default]
+    Day,
+    /// The classes of a student group, a column per day. Classes without a group are left out
+    Group,
+}
+
+/// How the genes of two parents are combined into a child
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CrossoverOperator {
+    /// Every period takes the genes of one parent up to a random mating point and of the other
+    /// parent after it, lost and duplicated genes are repaired afterwards
+    #[default]
+    OnePoint,
+    /// Order crossover of the gene sequences of the parents, treated as a permutation cut into
+    /// periods, which keeps every gene exactly once
+    Order,
+}
+
+/// Where genes lost in crossover are put back
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RepairStrategy {
+    /// Every lost gene goes to a random period
+    #[default]
+    Random,
+    /// Every lost gene goes to the period where it adds the least clash penalty, see
+    /// [`place_least_conflicting`](super::place_least_conflicting)
+    LeastConflict,
+}
+
+/// Penalty of a declared constraint when none is given
+fn default_constraint_weight() -> i32 {
+    10
+}
+
+/// Constraint declared in the configuration, compiled into a
+/// [`Constraint`](super::fitness::Constraint) by [`Fitness::from_config`](super::fitness::Fitness::from_config)
+///
+/// Declared as an object with a `type`, e.g. `{"type": "max_per_period", "limit": 10}`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ConstraintSpec {
+    /// At most `limit` classes take place in a period, every class over it costs `weight`
+    MaxPerPeriod {
+        limit: usize,
+        #[serde(default = "default_constraint_weight")]
+        weight: i32,
+    },
+    /// No classes in the `periods`, or only no classes of the `teacher` or `group` if given.
+    /// Every class in such a period costs `weight`
+    ForbiddenPeriod {
+        periods: Vec<usize>,
+        #[serde(default)]
+        teacher: Option<String>,
+        #[serde(default)]
+        group: Option<String>,
+        #[serde(default = "default_constraint_weight")]
+        weight: i32,
+    },
+}
+
+/// How the work is distributed across MPI nodes
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ParallelModel {
+    /// One population on root, every node breeds and evaluates a part of the offspring of each
+    /// generation from it
+    #[default]
+    Global,
+    /// Every node evolves its own part of the population, best individuals periodically migrate
+    /// to the next node
+    Island,
+    /// Root holds the whole population and creates offspring, nodes only evaluate fitness
+    #[serde(alias = "master-worker")]
+    MasterWorker,
+}
+
+/// Which islands send their migrants to which, see `neighbours` in `mpi_utils`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationTopology {
+    /// Every island sends to the next rank
+    #[default]
+    Ring,
+    /// Every island sends to all others
+    #[serde(alias = "fully-connected")]
+    FullyConnected,
+    /// A ring over the ranks shuffled anew for every migration
+    Random,
+    /// Islands on a torus of rows and columns as square as the number of ranks allows, sending
+    /// to the islands above, below, left and right
+    Grid,
+}
+
+/// How the payloads of MPI messages are compressed, see the `compression` module of the planner
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Compression {
+    #[default]
+    None,
+    /// Fast, for networks where compression time matters
+    Lz4,
+    /// Smaller payloads at a higher cost
+    Zstd,
+}
+
+/// Which individuals of an island migrate, see [`IslandConfig`](super::migration::IslandConfig)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum EmigrantPolicy {
+    /// The best individuals
+    #[default]
+    Best,
+    /// Individuals picked uniformly at random
+    Random,
+    /// Winners of tournaments of `migration_tournament_size` individuals
+    Tournament,
+}
+
+/// Which individuals of an island arriving migrants replace
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplacementPolicy {
+    /// The worst individuals
+    #[default]
+    Worst,
+    /// Individuals picked at random, except the best one
+    Random,
 }
 
 /// Configuration for the genetic algorithm
@@ -17,39 +304,1023 @@ pub enum ConfigLoadError {
 /// * Chromosome - a period of time with a list of genes (classes that are
 ///   happening at that time)
 /// * Gene - an id of tuple consisting of teacher, subject, room and class
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Equivalence)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(default)]
 pub struct AlgorithmConfig {
+    /// Algorithm searching for the schedule
+    pub solver: SolverKind,
+
     /// How many generations maximum to run
     pub max_generations: usize,
 
     /// How many individuals to have in the population
     pub population_size: usize,
 
+    /// The run stops once any node finds an individual with at least this adaptation
+    pub target_fitness: i32,
+
     /// How many genes are in each chromosome. Chromosome length
     pub number_of_periods: usize,
 
+    /// Number of days the periods are split into, see [`PeriodLayout`]
+    pub days: Option<usize>,
+
+    /// Number of periods (time slots) on each day
+    pub slots_per_day: Option<usize>,
+
+    /// How many children are bred every generation, the population size by default. With fewer
+    /// children the best parents survive to fill the population, with more only the best children
+    /// survive, see [`replace_generation`](super::replace_generation)
+    pub offspring_per_generation: Option<usize>,
+
     /// The probability of mutation occurring
     pub mutation_probability: f32,
+
+    /// The probability of a guided mutation of an individual, which moves one of its conflicting
+    /// genes to the period where it clashes the least, see [`mutate`](super::mutate)
+    pub guided_mutation_probability: f32,
+
+    /// Let the algorithm choose the room of every tuple with `allowed_rooms`, see
+    /// [`assignment`](super::assignment). Clashes are then not precomputed
+    pub room_assignment: bool,
+
+    /// The probability of moving a tuple to another of its allowed rooms in a mutation
+    pub room_mutation_probability: f32,
+
+    /// Let the algorithm choose the teacher of every tuple with `qualified_teachers`, see
+    /// [`assignment`](super::assignment). Clashes are then not precomputed
+    pub teacher_assignment: bool,
+
+    /// The probability of moving a tuple to another of its qualified teachers in a mutation
+    pub teacher_mutation_probability: f32,
+
+    /// Penalty for every period a qualified teacher teaches more or less than the mean of all
+    /// qualified teachers, with `teacher_assignment`
+    pub workload_balance_weight: i32,
+
+    /// After how many generations without improvement of the best adaptation the mutation
+    /// probability bursts to `hypermutation_probability`, 0 disables hypermutation. See
+    /// [`HypermutationController`](super::hypermutation::HypermutationController)
+    pub hypermutation_stagnation_generations: usize,
+
+    /// Mutation probability right after a burst
+    pub hypermutation_probability: f32,
+
+    /// Fraction of the raise over `mutation_probability` kept after every generation of a burst
+    pub hypermutation_decay: f32,
+
+    /// Every how many generations constraints still violated by the best individuals get more
+    /// weight and satisfied ones less, 0 disables adaptive penalties. See
+    /// [`PenaltyController`](super::penalties::PenaltyController)
+    pub penalty_adaptation_interval: usize,
+
+    /// Fraction the multiplier of a violated constraint grows by at every adaptation
+    pub penalty_increase_rate: f32,
+
+    /// Fraction the multiplier of a satisfied constraint shrinks by at every adaptation
+    pub penalty_decrease_rate: f32,
+
+    /// Lowest multiplier of a constraint's penalty
+    pub min_penalty_multiplier: f32,
+
+    /// Highest multiplier of a constraint's penalty
+    pub max_penalty_multiplier: f32,
+
+    /// Fraction of the worst individuals replaced by new random individuals every generation, see
+    /// [`add_random_immigrants`](super::add_random_immigrants)
+    pub immigrant_fraction: f32,
+
+    /// How many copies of the same schedule may live in a population, extra copies are mutated
+    /// every generation, see [`deduplicate_population`](super::deduplicate_population). 0
+    /// disables de-duplication
+    pub max_duplicate_copies: usize,
+
+    /// Every how many generations the diversity of all nodes is reduced and logged, 0 never
+    pub diversity_interval: usize,
+
+    /// How many best individuals of every node are improved by hill climbing after each
+    /// generation, 0 disables the local search, see [`improve_elites`](super::local_search::improve_elites)
+    pub local_search_elites: usize,
+
+    /// How many moves and swaps hill climbing tries on each of the `local_search_elites`
+    pub local_search_iterations: usize,
+
+    /// Starting temperature of simulated-annealing acceptance of offspring, unset keeps every
+    /// child. See [`accept_offspring`](super::annealing::accept_offspring)
+    pub initial_temperature: Option<f32>,
+
+    /// Factor the temperature is multiplied by every generation
+    pub cooling_rate: f32,
+
+    /// How parents are picked for crossover
+    pub selection_operator: SelectionOperator,
+
+    /// How strongly [`SelectionOperator::Rank`] favours the best individuals, the weight of the
+    /// individual at rank `x` is `e^(-selection_pressure * x)`
+    pub selection_pressure: f32,
+
+    /// How the genes of the parents are combined
+    pub crossover_operator: CrossoverOperator,
+
+    /// Where genes lost in crossover are put back
+    pub repair_strategy: RepairStrategy,
+
+    /// Select the parents of a whole generation at once with stochastic universal sampling, see
+    /// [`universal_parent_pairs`](super::selection::universal_parent_pairs)
+    pub universal_sampling: bool,
+
+    /// Optimize violations, teacher preferences and compactness as separate objectives with
+    /// NSGA-II instead of their sum, see [`pareto`](super::pareto). Parents are then picked by
+    /// crowded tournaments regardless of `selection_operator`
+    pub multi_objective: bool,
+
+    /// Kinds of classes that may take place in the same room at the same time, e.g.
+    /// `{"lab": ["lab"]}` lets labs of different teachers share a room. Listing a pair once is
+    /// enough, by default no classes share rooms
+    pub room_sharing: BTreeMap<TupleKind, Vec<TupleKind>>,
+
+    /// Penalty for every idle period between a teacher's first and last class of a day
+    pub teacher_gap_weight: i32,
+
+    /// Penalty for every idle period between a student group's first and last class of a day
+    pub group_gap_weight: i32,
+
+    /// Penalty for every class of a student group with no other class of the group right before
+    /// or after it on the same day
+    pub compactness_weight: i32,
+
+    /// How many back-to-back periods a teacher may teach in a day, unlimited if not set
+    pub max_consecutive_hours: Option<usize>,
+
+    /// Limits of consecutive periods for specific teachers, overriding `max_consecutive_hours`
+    pub max_consecutive_hours_per_teacher: BTreeMap<String, usize>,
+
+    /// Penalty for every period over the consecutive hours limit
+    pub consecutive_hours_weight: i32,
+
+    /// Penalty for every period over the daily or weekly limit of a teacher, see
+    /// [`Teacher`](super::resources::Teacher)
+    pub workload_weight: i32,
+
+    /// Penalty for every session of a course on a day that already has one, see
+    /// [`Tuple::course`](super::datatypes::Tuple::course)
+    pub course_spread_weight: i32,
+
+    /// Penalty for every period a teacher teaches outside their preferred periods
+    pub preferred_period_weight: i32,
+
+    /// Penalty for every period a teacher teaches in one of their disliked periods
+    pub disliked_period_weight: i32,
+
+    /// Tuples pinned to fixed periods, merged with the file given with `--locks`
+    pub locks: Locks,
+
+    /// Rooms with their metadata by id, merged with the file given with `--rooms`. When set,
+    /// the room of every tuple must be one of them
+    pub rooms: Rooms,
+
+    /// Teachers with their workload limits by id, merged with the file given with `--teachers`.
+    /// When set, the teacher of every tuple must be one of them
+    pub teachers: Teachers,
+
+    /// Student groups by id, merged with the file given with `--student-groups`. When set, the
+    /// group of every tuple with one must be one of them
+    pub student_groups: Groups,
+
+    /// Penalty for every locked tuple outside its period
+    pub lock_violation_weight: i32,
+
+    /// Penalty for every tuple placed in one of its forbidden periods
+    pub forbidden_period_weight: i32,
+
+    /// Penalty for every class whose block of periods doesn't fit within its day
+    pub block_overflow_weight: i32,
+
+    /// Most classes taking place in one period, unlimited by default
+    pub max_classes_per_period: Option<usize>,
+
+    /// Most classes taking place in one period in the rooms of a kind, e.g. the labs. Classes in
+    /// rooms without a known kind (see [`Room`](super::resources::Room)) count as their own kind
+    pub max_classes_per_room_kind: BTreeMap<TupleKind, usize>,
+
+    /// Penalty for every class over `max_classes_per_period` or `max_classes_per_room_kind`
+    pub period_overflow_weight: i32,
+
+    /// Additional constraints declared in the configuration
+    pub constraints: Vec<ConstraintSpec>,
+
+    /// Separator of the values of a CSV tuples file
+    pub csv_delimiter: char,
+
+    /// Whether the first row of a CSV tuples file holds the column names
+    pub csv_headers: bool,
+
+    /// Columns of the tuple fields in a CSV tuples file, by header or 1-based number, e.g.
+    /// `{"id": "Kod zajęć", "teacher": "Prowadzący"}`. Without it the columns are read by position
+    pub csv_columns: BTreeMap<String, String>,
+
+    /// Id of the instance read from an XHSTT archive given as the tuples file, the first one when
+    /// not set
+    pub xhstt_instance: Option<String>,
+
+    /// Whether `timetable.html` has a week grid per room or per student group
+    pub timetable_grouping: TimetableGrouping,
+
+    /// Whether `timetable.xlsx` has a sheet per day or per student group
+    pub spreadsheet_layout: SpreadsheetLayout,
+
+    /// Date of the first day of the layout, e.g. `2024-10-07`. When set, calendars of the teachers
+    /// and groups are written too, with the following days of the layout on the following dates
+    pub start_date: Option<NaiveDate>,
+
+    /// Start time of every slot of a day, e.g. `["08:00", "09:45"]`, needed with `start_date`
+    pub slot_times: Vec<NaiveTime>,
+
+    /// Length of a period in the calendars
+    pub period_minutes: u32,
+
+    /// Number of weeks the classes repeat weekly in the calendars
+    pub weeks: u32,
+
+    /// Clash penalties of all pairs of tuples, attached after loading the tuples with
+    /// [`with_conflict_matrix`](AlgorithmConfig::with_conflict_matrix). Not part of the file,
+    /// without it clashes are computed pair by pair
+    #[serde(skip)]
+    pub conflict_matrix: Option<Arc<ConflictMatrix>>,
+
+    /// Multipliers of the penalties of constraints by name, set during a run by adaptive
+    /// penalties. Not part of the file, missing constraints keep their penalty
+    #[serde(skip)]
+    pub penalty_multipliers: BTreeMap<String, f64>,
+
+    /// Allowed starts of the tuples, attached with
+    /// [`with_domains`](AlgorithmConfig::with_domains) when `constraint_propagation` is on. Not
+    /// part of the file
+    #[serde(skip)]
+    pub domains: Option<Arc<Domains>>,
+
+    /// How the work is distributed across nodes
+    pub parallel_model: ParallelModel,
+
+    /// Every how many generations islands exchange individuals, 0 disables migration
+    ///
+    /// With `node_migration_interval` only the exchanges between nodes happen at this interval.
+    pub migration_interval: usize,
+
+    /// Every how many generations islands on the same shared-memory node exchange individuals
+    ///
+    /// 0 (default) makes all islands migrate over the world every `migration_interval`, otherwise
+    /// every island migrates within its node at this interval and with the islands of the same
+    /// local rank on other nodes every `migration_interval`.
+    pub node_migration_interval: usize,
+
+    /// How many best individuals of an island migrate to each of its neighbours
+    pub migration_size: usize,
+
+    /// Which islands are neighbours
+    pub migration_topology: MigrationTopology,
+
+    /// Which individuals migrate
+    pub emigrant_policy: EmigrantPolicy,
+
+    /// Individuals in every tournament picking a migrant with the `tournament` emigrant policy
+    pub migration_tournament_size: usize,
+
+    /// Which individuals migrants replace
+    pub replacement_policy: ReplacementPolicy,
+
+    /// Seconds an island waits for its neighbours after breeding, 0 (default) waits forever
+    ///
+    /// Neighbours that don't answer in time are left out of all later migrations of the island, and
+    /// islands don't wait for each other between migrations.
+    pub migration_timeout: f64,
+
+    /// Share the best adaptation of all islands through a one-sided MPI window instead of a
+    /// reduction every generation
+    ///
+    /// Islands then read a value that may miss the current generation of the others, and root
+    /// alone decides when `target_fitness` is reached.
+    pub global_best_window: bool,
+
+    /// How payloads sent between nodes are compressed
+    pub compression: Compression,
+
+    /// Payloads smaller than this many bytes are sent uncompressed
+    pub compression_threshold: usize,
+
+    /// Most bytes sent by a single MPI broadcast, larger data is broadcast in chunks
+    pub broadcast_chunk_size: usize,
+
+    /// Threads of every process, by default `OMP_NUM_THREADS` or the cores of the node divided
+    /// among its processes
+    pub threads: Option<usize>,
+
+    /// Fields overridden on single islands, the island of rank `r` uses entry `r % islands.len()`,
+    /// see [`for_island`](AlgorithmConfig::for_island)
+    #[serde(deserialize_with = "deserialize_islands")]
+    pub islands: Vec<BTreeMap<String, String>>,
+
+    /// How the first population is created
+    pub initial_population_strategy: InitialPopulationStrategy,
+
+    /// Whether to compute the periods every tuple may start in before the algorithm starts and
+    /// only place tuples there, see [`Domains`]
+    pub constraint_propagation: bool,
+
+    /// Fraction of the first population built greedily when using
+    /// [`InitialPopulationStrategy::Greedy`], the rest is random
+    pub greedy_population_fraction: f32,
+
+    /// Whether to move the conflicting classes of the best schedule by solving an integer program
+    /// after the run, needs the `ilp` feature. See `ilp::refine`
+    pub ilp_refinement: bool,
+
+    /// Most classes the integer program of `ilp_refinement` moves
+    pub ilp_max_tuples: usize,
+
+    /// How many iterations tabu search runs at most
+    pub tabu_iterations: usize,
+
+    /// For how many iterations a gene may not move back to the period it left in tabu search
+    pub tabu_tenure: usize,
+
+    /// How many random moves tabu search tries in every iteration
+    pub tabu_neighbours: usize,
+
+    /// Fraction of the genes of the best individual moved to random periods in the copies
+    /// replacing a converged population of CHC
+    pub chc_divergence: f32,
+
+    /// How many best individuals are exported with `--export-elites`
+    pub elite_count: usize,
+
+    /// Fraction of the first population replaced by copies and mutated variants of the
+    /// schedule given with `--initial-schedule`
+    pub warm_start_fraction: f32,
+
+    /// Whether the population size is adjusted during the run, see
+    /// [`PopulationSizeController`](super::population_size::PopulationSizeController)
+    pub adaptive_population_size: bool,
+
+    /// Lower bound of the adaptive population size
+    pub min_population_size: usize,
+
+    /// Upper bound of the adaptive population size
+    pub max_population_size: usize,
+
+    /// Relative change of the population size on a single adjustment
+    pub population_resize_step: f32,
+
+    /// How many generations without improvement make the adaptive population grow
+    pub population_stagnation_generations: usize,
 }
 
 impl AlgorithmConfig {
+    /// Load the configuration from a file, the format is picked by extension (`.toml`,
+    /// `.yaml`/`.yml`, anything else is read as JSON)
+    pub fn from_file(path: impl AsRef<Path>) -> Result<AlgorithmConfig, ConfigLoadError> {
+        let path = path.as_ref();
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => AlgorithmConfig::from_toml(path),
+            Some("yaml" | "yml") => AlgorithmConfig::from_yaml(path),
+            _ => AlgorithmConfig::from_json(path),
+        }
+    }
+
+    /// Like [`AlgorithmConfig::from_file`], but fields not known to the configuration are
+    /// reported as [`ConfigLoadError::UnknownFields`] instead of being ignored
+    pub fn from_file_strict(path: impl AsRef<Path>) -> Result<AlgorithmConfig, ConfigLoadError> {
+        let path = path.as_ref();
+        let config = AlgorithmConfig::from_file(path)?;
+
+        let fields: serde_json::Value = read_file(path)?;
+        let known = AlgorithmConfig::field_names();
+        let unknown: Vec<String> = match fields {
+            serde_json::Value::Object(fields) => fields
+                .keys()
+                .filter(|field| !known.contains(field))
+                .cloned()
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        if unknown.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigLoadError::UnknownFields(unknown))
+        }
+    }
+
     /// Load the configuration from a JSON file
     pub fn from_json(path: impl AsRef<Path>) -> Result<AlgorithmConfig, ConfigLoadError> {
         let mut file = File::open(path)?;
-        let config = serde_json::from_reader(&mut file)?;
+        let mut config: AlgorithmConfig = serde_json::from_reader(&mut file)?;
+        config.apply_period_layout();
+        Ok(config)
+    }
+
+    /// Load the configuration from a TOML file
+    pub fn from_toml(path: impl AsRef<Path>) -> Result<AlgorithmConfig, ConfigLoadError> {
+        let mut config: AlgorithmConfig = toml::from_str(&fs::read_to_string(path)?)?;
+        config.apply_period_layout();
+        Ok(config)
+    }
+
+    /// Load the configuration from a YAML file
+    pub fn from_yaml(path: impl AsRef<Path>) -> Result<AlgorithmConfig, ConfigLoadError> {
+        let file = File::open(path)?;
+        let mut config: AlgorithmConfig = serde_yaml::from_reader(file)?;
+        config.apply_period_layout();
+        Ok(config)
+    }
+
+    /// Names of all configuration fields
+    pub fn field_names() -> Vec<String> {
+        match serde_json::to_value(AlgorithmConfig::default()) {
+            Ok(serde_json::Value::Object(fields)) => fields.keys().cloned().collect(),
+            _ => unreachable!("config serializes to an object"),
+        }
+    }
+
+    /// Override fields with `(field, value)` pairs given as text
+    ///
+    /// Values are parsed as JSON, so numbers, booleans, lists and maps can be given, and fall back
+    /// to plain strings (e.g. `island`). Unknown fields are ignored.
+    pub fn with_overrides(
+        self,
+        overrides: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<AlgorithmConfig, ConfigLoadError> {
+        let mut config = self;
+
+        for (field, value) in overrides {
+            let mut fields = serde_json::to_value(&config)?;
+            let parsed = serde_json::from_str(&value)
+                .unwrap_or_else(|_| serde_json::Value::String(value.clone()));
+
+            match fields.get_mut(&field) {
+                Some(slot) => *slot = parsed,
+                None => continue,
+            }
+
+            config = serde_json::from_value(fields).map_err(|source| {
+                ConfigLoadError::InvalidOverride {
+                    field,
+                    value,
+                    source,
+                }
+            })?;
+        }
+
+        config.apply_period_layout();
         Ok(config)
     }
+
+    /// Configuration of the island of the given rank with its `islands` entry applied
+    ///
+    /// Sizes are still for the whole population, like the fields they override.
+    pub fn for_island(&self, rank: usize) -> Result<AlgorithmConfig, ConfigLoadError> {
+        if self.islands.is_empty() {
+            return Ok(self.clone());
+        }
+
+        let overrides = self.islands[rank % self.islands.len()].clone();
+        let mut config = self.clone().with_overrides(overrides)?;
+        config.islands.clear();
+        Ok(config)
+    }
+
+    /// Overrides from `PLANNER_<FIELD>` environment variables, see [`ENV_PREFIX`]
+    pub fn env_overrides() -> Vec<(String, String)> {
+        AlgorithmConfig::field_names()
+            .into_iter()
+            .filter_map(|field| {
+                let variable = format!("{}{}", ENV_PREFIX, field.to_uppercase());
+                std::env::var(variable).ok().map(|value| (field, value))
+            })
+            .collect()
+    }
+
+    /// Check that the values make sense together, reporting every problem found
+    pub fn validate(&self) -> Result<(), ConfigLoadError> {
+        let mut problems = Vec::new();
+
+        if self.population_size < 2 {
+            problems.push(format!(
+                "population_size must be at least 2, got {}",
+                self.population_size
+            ));
+        }
+        if self.number_of_periods == 0 {
+            problems.push("number_of_periods must be at least 1".to_string());
+        }
+        if self.offspring_per_generation == Some(0) {
+            problems.push("offspring_per_generation must be at least 1".to_string());
+        }
+        if self.days == Some(0) {
+            problems.push("days must be at least 1".to_string());
+        }
+        if self.slots_per_day == Some(0) {
+            problems.push("slots_per_day must be at least 1".to_string());
+        }
+        if self.max_classes_per_period == Some(0) {
+            problems.push("max_classes_per_period must be at least 1".to_string());
+        }
+
+        if self.selection_pressure < 0.0 {
+            problems.push(format!(
+                "selection_pressure can't be negative, got {}",
+                self.selection_pressure
+            ));
+        }
+
+        if self
+            .initial_temperature
+            .is_some_and(|temperature| temperature < 0.0)
+        {
+            problems.push(format!(
+                "initial_temperature can't be negative, got {}",
+                self.initial_temperature.unwrap()
+            ));
+        }
+
+        let fractions = [
+            ("mutation_probability", self.mutation_probability),
+            (
+                "guided_mutation_probability",
+                self.guided_mutation_probability,
+            ),
+            (
+                "greedy_population_fraction",
+                self.greedy_population_fraction,
+            ),
+            ("room_mutation_probability", self.room_mutation_probability),
+            (
+                "teacher_mutation_probability",
+                self.teacher_mutation_probability,
+            ),
+            ("warm_start_fraction", self.warm_start_fraction),
+            ("cooling_rate", self.cooling_rate),
+            ("immigrant_fraction", self.immigrant_fraction),
+            ("hypermutation_probability", self.hypermutation_probability),
+            ("hypermutation_decay", self.hypermutation_decay),
+            ("population_resize_step", self.population_resize_step),
+            ("penalty_decrease_rate", self.penalty_decrease_rate),
+            ("chc_divergence", self.chc_divergence),
+        ];
+        for (field, value) in fractions {
+            if !(0.0..=1.0).contains(&value) {
+                problems.push(format!("{} must be within [0, 1], got {}", field, value));
+            }
+        }
+
+        if self.solver == SolverKind::Tabu && self.tabu_neighbours == 0 {
+            problems.push("tabu_neighbours must be at least 1 for tabu search".to_string());
+        }
+
+        if self.ilp_refinement && !cfg!(feature = "ilp") {
+            problems
+                .push("ilp_refinement needs the planner built with the `ilp` feature".to_string());
+        }
+
+        if self.penalty_increase_rate < 0.0 {
+            problems.push(format!(
+                "penalty_increase_rate can't be negative, got {}",
+                self.penalty_increase_rate
+            ));
+        }
+        if !(0.0 < self.min_penalty_multiplier
+            && self.min_penalty_multiplier <= self.max_penalty_multiplier)
+        {
+            problems.push(format!(
+                "min_penalty_multiplier ({}) must be positive and can't exceed max_penalty_multiplier ({})",
+                self.min_penalty_multiplier, self.max_penalty_multiplier
+            ));
+        }
+
+        if self.multi_objective && self.universal_sampling {
+            problems.push("multi_objective can't be combined with universal_sampling".to_string());
+        }
+        if self.multi_objective && self.initial_temperature.is_some() {
+            problems.push("multi_objective can't be combined with initial_temperature".to_string());
+        }
+
+        let known = AlgorithmConfig::field_names();
+        for (index, overrides) in self.islands.iter().enumerate() {
+            for field in overrides.keys() {
+                if !known.contains(field) {
+                    problems.push(format!("islands[{}] sets unknown field {}", index, field));
+                } else if ISLAND_SHARED_FIELDS.contains(&field.as_str())
+                    || field.ends_with("_weight")
+                {
+                    problems.push(format!(
+                        "islands[{}] can't set {}, all islands share it",
+                        index, field
+                    ));
+                }
+            }
+            match self.for_island(index).and_then(|config| config.validate()) {
+                Ok(()) => {}
+                Err(ConfigLoadError::Invalid(island_problems)) => problems.extend(
+                    island_problems
+                        .into_iter()
+                        .map(|problem| format!("islands[{}]: {}", index, problem)),
+                ),
+                Err(err) => problems.push(format!("islands[{}]: {}", index, err)),
+            }
+        }
+
+        if self.emigrant_policy == EmigrantPolicy::Tournament && self.migration_tournament_size == 0
+        {
+            problems.push("migration_tournament_size must be at least 1".to_string());
+        }
+        if self.broadcast_chunk_size == 0 {
+            problems.push("broadcast_chunk_size must be at least 1".to_string());
+        }
+        if self.threads == Some(0) {
+            problems.push("threads must be at least 1".to_string());
+        }
+        if !(0.0..f64::INFINITY).contains(&self.migration_timeout) {
+            problems.push(format!(
+                "migration_timeout must be a non-negative number of seconds, got {}",
+                self.migration_timeout
+            ));
+        }
+        if self.migration_size > self.population_size {
+            problems.push(format!(
+                "migration_size ({}) can't exceed population_size ({})",
+                self.migration_size, self.population_size
+            ));
+        }
+        if self.adaptive_population_size {
+            if self.min_population_size < 2 {
+                problems.push(format!(
+                    "min_population_size must be at least 2, got {}",
+                    self.min_population_size
+                ));
+            }
+            if self.min_population_size > self.max_population_size {
+                problems.push(format!(
+                    "min_population_size ({}) can't exceed max_population_size ({})",
+                    self.min_population_size, self.max_population_size
+                ));
+            }
+        }
+        if self.start_date.is_some() {
+            let slots_per_day = self.period_layout().slots_per_day;
+            if self.slot_times.len() < slots_per_day {
+                problems.push(format!(
+                    "slot_times must give the start of all {} slots of a day for the calendars, got {}",
+                    slots_per_day,
+                    self.slot_times.len()
+                ));
+            }
+            if self.period_minutes == 0 || self.weeks == 0 {
+                problems.push("period_minutes and weeks must be at least 1".to_string());
+            }
+        }
+        if let Some((gene, period)) = self
+            .locks
+            .iter()
+            .find(|(_, period)| **period >= self.number_of_periods)
+        {
+            problems.push(format!(
+                "tuple {} is locked to period {}, but there are only {} periods",
+                gene, period, self.number_of_periods
+            ));
+        }
+        for spec in &self.constraints {
+            if let ConstraintSpec::ForbiddenPeriod { periods, .. } = spec {
+                if let Some(period) = periods.iter().find(|p| **p >= self.number_of_periods) {
+                    problems.push(format!(
+                        "forbidden_period constraint lists period {}, but there are only {} periods",
+                        period, self.number_of_periods
+                    ));
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigLoadError::Invalid(problems))
+        }
+    }
+
+    /// Look clashes up in the given matrix, built for the tuples and `room_sharing` of this
+    /// configuration
+    pub fn with_conflict_matrix(self, matrix: ConflictMatrix) -> Self {
+        AlgorithmConfig {
+            conflict_matrix: Some(Arc::new(matrix)),
+            ..self
+        }
+    }
+
+    /// Only place tuples in the given allowed starts
+    pub fn with_domains(self, domains: Domains) -> Self {
+        AlgorithmConfig {
+            domains: Some(Arc::new(domains)),
+            ..self
+        }
+    }
+
+    /// Whether the tuple may start in the period: [`Tuple::can_start_at`] and, when attached, one
+    /// of its allowed starts in [`Domains`]
+    pub fn can_start_at(&self, tuple: &Tuple, period_id: usize, layout: &PeriodLayout) -> bool {
+        tuple.can_start_at(period_id, layout)
+            && self
+                .domains
+                .as_deref()
+                .is_none_or(|domains| domains.allows(tuple.id, period_id))
+    }
+
+    /// The conflict matrix, if attached and it gives the exact change of the fitness of moves
+    pub fn incremental_conflicts(&self) -> Option<&ConflictMatrix> {
+        self.conflict_matrix
+            .as_deref()
+            .filter(|matrix| matrix.covers_moves())
+    }
+
+    /// Whether the algorithm chooses rooms or teachers, so that the tuples differ between
+    /// individuals
+    pub fn assigns_resources(&self) -> bool {
+        self.room_assignment || self.teacher_assignment
+    }
+
+    /// Whether the number of classes in a period is limited, in total or in a kind of rooms
+    pub fn limits_classes_per_period(&self) -> bool {
+        self.max_classes_per_period.is_some() || !self.max_classes_per_room_kind.is_empty()
+    }
+
+    /// Layout of the periods in days and time slots
+    pub fn period_layout(&self) -> PeriodLayout {
+        PeriodLayout::from_config(self)
+    }
+
+    /// Whether classes of the two kinds may share a room, see `room_sharing`
+    pub fn can_share_room(&self, kind: TupleKind, other: TupleKind) -> bool {
+        let allows = |kind, other| {
+            self.room_sharing
+                .get(&kind)
+                .is_some_and(|kinds| kinds.contains(&other))
+        };
+        allows(kind, other) || allows(other, kind)
+    }
+
+    /// Consecutive hours limit of a teacher, if any
+    pub fn max_consecutive_hours_of(&self, teacher: &str) -> Option<usize> {
+        self.max_consecutive_hours_per_teacher
+            .get(teacher)
+            .copied()
+            .or(self.max_consecutive_hours)
+    }
+
+    /// When `days` or `slots_per_day` is given, make `number_of_periods` match the layout
+    pub fn apply_period_layout(&mut self) {
+        if self.days.is_some() || self.slots_per_day.is_some() {
+            self.number_of_periods = self.period_layout().number_of_periods();
+        }
+    }
 }
 
 impl Default for AlgorithmConfig {
     /// Default configuration
     fn default() -> Self {
         AlgorithmConfig {
+            solver: SolverKind::Genetic,
             max_generations: 100,
             population_size: 10_000,
+            target_fitness: 0,
             number_of_periods: 8,
+            days: None,
+            slots_per_day: None,
+            offspring_per_generation: None,
             mutation_probability: 0.05,
+            guided_mutation_probability: 0.0,
+            room_assignment: false,
+            room_mutation_probability: 0.05,
+            teacher_assignment: false,
+            teacher_mutation_probability: 0.05,
+            workload_balance_weight: 1,
+            hypermutation_stagnation_generations: 0,
+            hypermutation_probability: 0.3,
+            hypermutation_decay: 0.5,
+            penalty_adaptation_interval: 0,
+            penalty_increase_rate: 0.1,
+            penalty_decrease_rate: 0.05,
+            min_penalty_multiplier: 0.5,
+            max_penalty_multiplier: 5.0,
+            immigrant_fraction: 0.0,
+            max_duplicate_copies: 0,
+            diversity_interval: 10,
+            local_search_elites: 0,
+            local_search_iterations: 100,
+            initial_temperature: None,
+            cooling_rate: 0.95,
+            selection_operator: SelectionOperator::Rank,
+            selection_pressure: 0.3,
+            universal_sampling: false,
+            multi_objective: false,
+            crossover_operator: CrossoverOperator::OnePoint,
+            repair_strategy: RepairStrategy::Random,
+            room_sharing: BTreeMap::new(),
+            teacher_gap_weight: 0,
+            group_gap_weight: 0,
+            compactness_weight: 0,
+            max_consecutive_hours: None,
+            max_consecutive_hours_per_teacher: BTreeMap::new(),
+            consecutive_hours_weight: 10,
+            workload_weight: 10,
+            course_spread_weight: 0,
+            preferred_period_weight: 1,
+            disliked_period_weight: 5,
+            locks: Locks::new(),
+            rooms: Rooms::new(),
+            teachers: Teachers::new(),
+            student_groups: Groups::new(),
+            lock_violation_weight: 100,
+            forbidden_period_weight: 100,
+            block_overflow_weight: 100,
+            max_classes_per_period: None,
+            max_classes_per_room_kind: BTreeMap::new(),
+            period_overflow_weight: 10,
+            constraints: Vec::new(),
+            csv_delimiter: ',',
+            csv_headers: true,
+            csv_columns: BTreeMap::new(),
+            xhstt_instance: None,
+            timetable_grouping: TimetableGrouping::Room,
+            spreadsheet_layout: SpreadsheetLayout::Day,
+            start_date: None,
+            slot_times: Vec::new(),
+            period_minutes: 90,
+            weeks: 1,
+            conflict_matrix: None,
+            penalty_multipliers: BTreeMap::new(),
+            domains: None,
+            parallel_model: ParallelModel::Global,
+            migration_interval: 10,
+            node_migration_interval: 0,
+            migration_size: 5,
+            migration_topology: MigrationTopology::Ring,
+            emigrant_policy: EmigrantPolicy::Best,
+            migration_tournament_size: 2,
+            replacement_policy: ReplacementPolicy::Worst,
+            migration_timeout: 0.0,
+            global_best_window: false,
+            compression: Compression::None,
+            compression_threshold: 64 * 1024,
+            broadcast_chunk_size: 16 * 1024 * 1024,
+            threads: None,
+            islands: Vec::new(),
+            initial_population_strategy: InitialPopulationStrategy::Random,
+            constraint_propagation: true,
+            greedy_population_fraction: 0.5,
+            ilp_refinement: false,
+            ilp_max_tuples: 20,
+            tabu_iterations: 10_000,
+            tabu_tenure: 10,
+            tabu_neighbours: 50,
+            chc_divergence: 0.35,
+            elite_count: 10,
+            warm_start_fraction: 0.1,
+            adaptive_population_size: false,
+            min_population_size: 1_000,
+            max_population_size: 20_000,
+            population_resize_step: 0.1,
+            population_stagnation_generations: 5,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formats_agree() {
+        let json = r#"{"population_size": 500, "parallel_model": "master_worker",
+            "room_sharing": {"lab": ["lab"]}}"#;
+        let toml = "population_size = 500\nparallel_model = \"master_worker\"\n\n\
+                    [room_sharing]\nlab = [\"lab\"]\n";
+        let yaml =
+            "population_size: 500\nparallel_model: master_worker\nroom_sharing:\n  lab: [lab]\n";
+
+        let from_json: AlgorithmConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(from_json.population_size, 500);
+        assert_eq!(from_json, toml::from_str(toml).unwrap());
+        assert_eq!(from_json, serde_yaml::from_str(yaml).unwrap());
+    }
+
+    #[test]
+    fn test_overrides_parse_values_by_field_type() {
+        let config = AlgorithmConfig::default()
+            .with_overrides([
+                ("population_size".to_string(), "64".to_string()),
+                ("parallel_model".to_string(), "master_worker".to_string()),
+                ("days".to_string(), "5".to_string()),
+                ("slots_per_day".to_string(), "6".to_string()),
+                ("no_such_field".to_string(), "1".to_string()),
+            ])
+            .unwrap();
+
+        assert_eq!(config.population_size, 64);
+        assert_eq!(config.parallel_model, ParallelModel::MasterWorker);
+        assert_eq!(config.number_of_periods, 30);
+
+        let error = AlgorithmConfig::default()
+            .with_overrides([("population_size".to_string(), "many".to_string())])
+            .unwrap_err();
+        assert!(matches!(error, ConfigLoadError::InvalidOverride { .. }));
+    }
+
+    #[test]
+    fn test_zero_days_are_reported_by_validation() {
+        let path = std::env::temp_dir().join("planner_zero_days.json");
+        fs::write(&path, r#"{"days": 0}"#).unwrap();
+        let config = AlgorithmConfig::from_json(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(config.number_of_periods, 8);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_islands_override_their_own_fields() {
+        let toml = "[[islands]]\nmutation_probability = 0.5\n\n\
+                    [[islands]]\nlocal_search_elites = 4\ncrossover_operator = \"order\"\n";
+        let config: AlgorithmConfig = toml::from_str(toml).unwrap();
+        assert!(config.validate().is_ok());
+
+        assert_eq!(config.for_island(0).unwrap().mutation_probability, 0.5);
+        let exploiting = config.for_island(3).unwrap();
+        assert_eq!(exploiting.local_search_elites, 4);
+        assert_eq!(
+            exploiting.mutation_probability,
+            AlgorithmConfig::default().mutation_probability
+        );
+        assert!(exploiting.islands.is_empty());
+
+        // the broadcast keeps the overrides
+        let bytes = bincode::serialize(&config).unwrap();
+        assert_eq!(
+            bincode::deserialize::<AlgorithmConfig>(&bytes).unwrap(),
+            config
+        );
+
+        let config = AlgorithmConfig {
+            islands: vec![
+                [("max_generations".to_string(), "5".to_string())].into(),
+                [("mutation_probability".to_string(), "2".to_string())].into(),
+                [(
+                    "room_sharing".to_string(),
+                    r#"{"lab": ["lab"]}"#.to_string(),
+                )]
+                .into(),
+            ],
+            ..AlgorithmConfig::default()
+        };
+        match config.validate() {
+            Err(ConfigLoadError::Invalid(problems)) => assert_eq!(problems.len(), 3),
+            other => panic!("expected validation errors, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validate_reports_every_problem() {
+        assert!(AlgorithmConfig::default().validate().is_ok());
+
+        let config = AlgorithmConfig {
+            population_size: 1,
+            mutation_probability: 1.5,
+            ..AlgorithmConfig::default()
+        };
+        match config.validate() {
+            Err(ConfigLoadError::Invalid(problems)) => assert_eq!(problems.len(), 3),
+            other => panic!("expected validation errors, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_strict_loading_rejects_unknown_fields() {
+        let path = std::env::temp_dir().join("planner_strict_config_test.json");
+        fs::write(
+            &path,
+            r#"{"population_size": 64, "mutation_probabilty": 0.1}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            AlgorithmConfig::from_file(&path).unwrap().population_size,
+            64
+        );
+        match AlgorithmConfig::from_file_strict(&path) {
+            Err(ConfigLoadError::UnknownFields(fields)) => {
+                assert_eq!(fields, vec!["mutation_probabilty".to_string()])
+            }
+            other => panic!("expected unknown fields, got {:?}", other),
         }
+
+        fs::remove_file(path).unwrap();
     }
 }
@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+use fixedbitset::FixedBitSet;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    clash_penalties,
+    config::AlgorithmConfig,
+    datatypes::{Gene, Tuple},
+    fitness::Fitness,
+};
+
+/// Penalty of every pair of tuples taking place at the same time, see [`clash_penalties`]
+///
+/// Built once after loading the tuples, so the fitness only looks clashes up instead of comparing
+/// teachers, rooms and kinds of classes of every pair of classes in a period. The matrix depends
+/// on `room_sharing` and has to be rebuilt when it changes.
+///
+/// When moving a class changes nothing but its clashes, see
+/// [`Fitness::only_clashes_change_on_move`], the matrix also gives the exact change of the fitness
+/// of a move, see [`Individual::apply_move`](super::datatypes::Individual::apply_move).
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ConflictMatrix {
+    size: usize,
+    /// Row of the matrix of every gene
+    indices: HashMap<Gene, usize>,
+    /// `size * size` penalties, row by row, 0 on the diagonal
+    penalties: Vec<i32>,
+    /// Tuples every tuple clashes with, see [`ConflictMatrix::clashing`]
+    clashing: Vec<FixedBitSet>,
+    covers_moves: bool,
+}
+
+impl ConflictMatrix {
+    pub fn new(config: &AlgorithmConfig, tuples: &[Tuple]) -> Self {
+        let size = tuples.len();
+        let penalties: Vec<i32> = tuples
+            .par_iter()
+            .enumerate()
+            .flat_map_iter(|(row, tuple)| {
+                tuples.iter().enumerate().map(move |(column, other)| {
+                    if row == column {
+                        return 0;
+                    }
+                    let (teacher_penalty, room_penalty) = clash_penalties(config, tuple, other);
+                    teacher_penalty + room_penalty
+                })
+            })
+            .collect();
+
+        let clashing = (0..size)
+            .map(|row| {
+                (0..size)
+                    .filter(|column| {
+                        penalties[row * size + column] != 0 || penalties[column * size + row] != 0
+                    })
+                    .collect()
+            })
+            .collect();
+
+        ConflictMatrix {
+            size,
+            indices: tuples
+                .iter()
+                .enumerate()
+                .map(|(index, tuple)| (tuple.id, index))
+                .collect(),
+            penalties,
+            clashing,
+            covers_moves: config.penalty_adaptation_interval == 0
+                && Fitness::from_config(config).only_clashes_change_on_move(tuples),
+        }
+    }
+
+    /// Whether [`Individual::apply_move`](super::datatypes::Individual::apply_move) gives the
+    /// exact change of the fitness
+    pub fn covers_moves(&self) -> bool {
+        self.covers_moves
+    }
+
+    /// Number of rows of the matrix
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Rows of the tuples clashing with the tuple at the given row, i.e. with a teacher or room in
+    /// common that can't be shared
+    pub fn clashing(&self, row: usize) -> &FixedBitSet {
+        &self.clashing[row]
+    }
+
+    /// Row of the gene in the matrix, `None` for genes of unknown tuples
+    pub fn index_of(&self, gene: Gene) -> Option<usize> {
+        self.indices.get(&gene).copied()
+    }
+
+    /// Penalty of the tuples at the given rows taking place together
+    pub fn penalty(&self, first: usize, second: usize) -> i32 {
+        self.penalties[first * self.size + second]
+    }
+
+    /// Penalty of the gene taking place together with the other genes, both ways, skipping the
+    /// gene itself
+    ///
+    /// Panics on genes of unknown tuples.
+    pub fn gene_penalty(&self, gene: Gene, others: &[Gene]) -> i32 {
+        let row = self.row_of(gene);
+        others
+            .iter()
+            .map(|other| {
+                let column = self.row_of(*other);
+                self.penalty(row, column) + self.penalty(column, row)
+            })
+            .sum()
+    }
+
+    fn row_of(&self, gene: Gene) -> usize {
+        self.index_of(gene)
+            .unwrap_or_else(|| panic!("Tuple with id {} not found", gene))
+    }
+
+    /// Sum of the penalties of every ordered pair of the genes, like
+    /// [`calculate_fitness`](super::calculate_fitness) counts the classes of a period
+    ///
+    /// Panics on genes of unknown tuples.
+    pub fn period_penalty(&self, genes: &[Gene]) -> i32 {
+        let rows: Vec<usize> = genes.iter().map(|gene| self.row_of(*gene)).collect();
+
+        rows.iter()
+            .map(|row| {
+                rows.iter()
+                    .map(|column| self.penalty(*row, *column))
+                    .sum::<i32>()
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::{
+        calculate_fitness, create_first_population, datatypes::TupleId, mutate,
+        random::get_random_generator,
+    };
+
+    fn tuples() -> Vec<Tuple> {
+        (1..=12)
+            .map(|id| Tuple {
+                id: TupleId(id),
+                label: format!("Class {}", id % 3),
+                room: format!("Room {}", id % 4),
+                teacher: format!("Teacher {}", id % 5),
+                ..Tuple::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_matrix_fitness_matches_pairwise_fitness() {
+        let tuples = tuples();
+        let config = AlgorithmConfig {
+            population_size: 20,
+            number_of_periods: 4,
+            ..AlgorithmConfig::default()
+        };
+        let with_matrix = config
+            .clone()
+            .with_conflict_matrix(ConflictMatrix::new(&config, &tuples));
+
+        let population = create_first_population(&config, &tuples, &mut get_random_generator(2));
+        for individual in &population {
+            assert_eq!(
+                calculate_fitness(&with_matrix, individual, &tuples),
+                calculate_fitness(&config, individual, &tuples)
+            );
+        }
+    }
+
+    #[test]
+    fn test_mutation_keeps_adaptation_up_to_date() {
+        let tuples = tuples();
+        let config = AlgorithmConfig {
+            population_size: 10,
+            number_of_periods: 4,
+            mutation_probability: 0.5,
+            ..AlgorithmConfig::default()
+        };
+        let config = config
+            .clone()
+            .with_conflict_matrix(ConflictMatrix::new(&config, &tuples));
+        assert!(config.incremental_conflicts().is_some());
+
+        let mut rng = get_random_generator(3);
+        let mut population = create_first_population(&config, &tuples, &mut rng);
+        for individual in &mut population {
+            individual.adaptation = calculate_fitness(&config, individual, &tuples);
+            for _ in 0..5 {
+                mutate(&config, individual, &tuples, &mut rng);
+                assert_eq!(
+                    individual.adaptation,
+                    calculate_fitness(&config, individual, &tuples)
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,586 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::{
+    datatypes::{Gene, Individual, Locks, Tuple, TupleKind},
+    periods::{DaySlot, PeriodLayout},
+    resources::{Rooms, Teachers},
+};
+
+/// Sorted, distinct slots occupied on each day by classes with the same `key` (e.g. teacher or
+/// student group), tuples with an empty key are skipped
+///
+/// Classes longer than one period occupy all slots of their block.
+pub(super) fn occupied_slots<'a>(
+    individual: &Individual,
+    tuples: &'a [Tuple],
+    layout: &PeriodLayout,
+    key: impl Fn(&'a Tuple) -> &'a str,
+) -> HashMap<(&'a str, usize), Vec<usize>> {
+    let mut occupied_slots: HashMap<(&str, usize), Vec<usize>> = HashMap::new();
+
+    for (period_id, chromosome) in individual.chromosomes.iter().enumerate() {
+        let DaySlot { day, slot } = layout.day_slot(period_id);
+
+        for gene in &chromosome.genes {
+            let Some(tuple) = tuples.iter().find(|tuple| tuple.id == *gene) else {
+                continue;
+            };
+            let key = key(tuple);
+            if !key.is_empty() {
+                let block_len = layout.clamped_block(period_id, tuple.length()).len();
+                occupied_slots
+                    .entry((key, day))
+                    .or_default()
+                    .extend(slot..slot + block_len);
+            }
+        }
+    }
+
+    for slots in occupied_slots.values_mut() {
+        slots.sort_unstable();
+        slots.dedup();
+    }
+
+    occupied_slots
+}
+
+/// Count idle periods between the first and the last class of the day
+///
+/// Classes are grouped by `key` and day, see [`occupied_slots`]. A key with classes in slots 1, 2
+/// and 5 of a day has 2 gaps on that day.
+pub fn count_gaps<'a>(
+    individual: &Individual,
+    tuples: &'a [Tuple],
+    layout: &PeriodLayout,
+    key: impl Fn(&'a Tuple) -> &'a str,
+) -> usize {
+    occupied_slots(individual, tuples, layout, key)
+        .into_values()
+        .map(|slots| slots[slots.len() - 1] - slots[0] + 1 - slots.len())
+        .sum()
+}
+
+/// Slots of a day with no occupied slot right before or after them
+pub fn isolated_slots(slots: &[usize]) -> Vec<usize> {
+    slots
+        .iter()
+        .copied()
+        .filter(|slot| !slots.contains(&(slot + 1)) && (*slot == 0 || !slots.contains(&(slot - 1))))
+        .collect()
+}
+
+/// Count isolated classes of the student groups, see [`isolated_slots`]
+///
+/// A group with classes in slots 1, 2 and 5 of a day has 1 isolated class on that day.
+pub fn count_isolated_classes(
+    individual: &Individual,
+    tuples: &[Tuple],
+    layout: &PeriodLayout,
+) -> usize {
+    occupied_slots(individual, tuples, layout, |t| &t.group)
+        .into_values()
+        .map(|slots| isolated_slots(&slots).len())
+        .sum()
+}
+
+/// Count periods taught over the teacher's limit of back-to-back periods in a day
+///
+/// A teacher limited to 2 consecutive periods who teaches slots 1 to 4 of a day exceeds the
+/// limit by 2. Teachers without a limit are skipped.
+pub fn count_consecutive_overruns(
+    individual: &Individual,
+    tuples: &[Tuple],
+    layout: &PeriodLayout,
+    limit: impl Fn(&str) -> Option<usize>,
+) -> usize {
+    occupied_slots(individual, tuples, layout, |tuple| &tuple.teacher)
+        .into_iter()
+        .filter_map(|((teacher, _), slots)| Some((limit(teacher)?, slots)))
+        .map(|(limit, slots)| {
+            slots
+                .chunk_by(|previous, next| previous + 1 == *next)
+                .map(|run| run.len().saturating_sub(limit))
+                .sum::<usize>()
+        })
+        .sum()
+}
+
+/// Count periods taught over the daily and weekly limits of the teachers
+///
+/// A teacher limited to 4 periods a day who teaches 5 periods on two days exceeds the limit by 2.
+/// The weekly limit applies to the periods of all days together.
+pub fn count_workload_overruns(
+    individual: &Individual,
+    tuples: &[Tuple],
+    layout: &PeriodLayout,
+    teachers: &Teachers,
+) -> usize {
+    let mut weekly_hours: HashMap<&str, usize> = HashMap::new();
+    let mut overruns = 0;
+
+    for ((teacher, _), slots) in occupied_slots(individual, tuples, layout, |t| &t.teacher) {
+        let Some(limits) = teachers.get(teacher) else {
+            continue;
+        };
+        if let Some(limit) = limits.max_daily_hours {
+            overruns += slots.len().saturating_sub(limit);
+        }
+        *weekly_hours.entry(teacher).or_default() += slots.len();
+    }
+
+    for (teacher, hours) in weekly_hours {
+        if let Some(limit) = teachers[teacher].max_weekly_hours {
+            overruns += hours.saturating_sub(limit);
+        }
+    }
+
+    overruns
+}
+
+/// How many periods every teacher who can be chosen for a tuple teaches more or less than the
+/// rounded mean of all of them
+///
+/// The tuples are expected to carry the chosen teachers. Teachers teaching the mean are left out.
+pub fn workload_deviations(tuples: &[Tuple]) -> BTreeMap<&str, usize> {
+    let mut loads: BTreeMap<&str, usize> = tuples
+        .iter()
+        .flat_map(|tuple| &tuple.qualified_teachers)
+        .map(|teacher| (teacher.as_str(), 0))
+        .collect();
+    if loads.is_empty() {
+        return loads;
+    }
+
+    for tuple in tuples {
+        if let Some(load) = loads.get_mut(tuple.teacher.as_str()) {
+            *load += tuple.length();
+        }
+    }
+
+    let total: usize = loads.values().sum();
+    let mean = (total + loads.len() / 2) / loads.len();
+    loads
+        .into_iter()
+        .map(|(teacher, load)| (teacher, load.abs_diff(mean)))
+        .filter(|(_, deviation)| *deviation != 0)
+        .collect()
+}
+
+/// Count classes of one period over `max_classes`, and over the limit of every kind of rooms in
+/// `max_per_room_kind`
+///
+/// Classes in rooms without a known kind count as the kind of the class.
+pub fn count_period_overflow(
+    genes: &[Gene],
+    tuples: &[Tuple],
+    rooms: &Rooms,
+    max_classes: Option<usize>,
+    max_per_room_kind: &BTreeMap<TupleKind, usize>,
+) -> usize {
+    let mut overflow = max_classes.map_or(0, |limit| genes.len().saturating_sub(limit));
+    if max_per_room_kind.is_empty() {
+        return overflow;
+    }
+
+    let mut classes: BTreeMap<TupleKind, usize> = BTreeMap::new();
+    for tuple in genes
+        .iter()
+        .filter_map(|gene| tuples.iter().find(|tuple| tuple.id == *gene))
+    {
+        let kind = rooms
+            .get(&tuple.room)
+            .and_then(|room| room.kind)
+            .unwrap_or(tuple.kind);
+        *classes.entry(kind).or_default() += 1;
+    }
+    for (kind, count) in classes {
+        if let Some(limit) = max_per_room_kind.get(&kind) {
+            overflow += count.saturating_sub(*limit);
+        }
+    }
+
+    overflow
+}
+
+/// Periods taught by a teacher with preferences, see [`count_preferences`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PreferenceCounts {
+    pub taught: usize,
+    /// Periods outside the preferred ones, 0 without preferred periods
+    pub outside_preferred: usize,
+    pub disliked: usize,
+}
+
+/// Count periods taught by every teacher with preferred or disliked periods
+///
+/// Classes longer than one period count in all periods of their block.
+pub fn count_preferences<'a>(
+    active_genes: &[Vec<Gene>],
+    tuples: &'a [Tuple],
+    teachers: &Teachers,
+) -> BTreeMap<&'a str, PreferenceCounts> {
+    let mut counts: BTreeMap<&str, PreferenceCounts> = BTreeMap::new();
+
+    for (period, genes) in active_genes.iter().enumerate() {
+        for tuple in genes
+            .iter()
+            .filter_map(|gene| tuples.iter().find(|tuple| tuple.id == *gene))
+        {
+            let Some(teacher) = teachers
+                .get(&tuple.teacher)
+                .filter(|teacher| teacher.has_preferences())
+            else {
+                continue;
+            };
+
+            let count = counts.entry(&tuple.teacher).or_default();
+            count.taught += 1;
+            if !teacher.preferred_periods.is_empty() && !teacher.preferred_periods.contains(&period)
+            {
+                count.outside_preferred += 1;
+            }
+            if teacher.disliked_periods.contains(&period) {
+                count.disliked += 1;
+            }
+        }
+    }
+
+    counts
+}
+
+/// Sessions of every course starting on the same day, by course and day
+///
+/// Tuples without a course are skipped.
+pub fn course_sessions<'a>(
+    individual: &Individual,
+    tuples: &'a [Tuple],
+    layout: &PeriodLayout,
+) -> BTreeMap<(&'a str, usize), Vec<Gene>> {
+    let mut sessions: BTreeMap<(&str, usize), Vec<Gene>> = BTreeMap::new();
+
+    for (period_id, chromosome) in individual.chromosomes.iter().enumerate() {
+        let day = layout.day_slot(period_id).day;
+        for tuple in chromosome
+            .genes
+            .iter()
+            .filter_map(|gene| tuples.iter().find(|tuple| tuple.id == *gene))
+            .filter(|tuple| !tuple.course.is_empty())
+        {
+            sessions
+                .entry((&tuple.course, day))
+                .or_default()
+                .push(tuple.id);
+        }
+    }
+
+    sessions
+}
+
+/// Count sessions of a course beyond the first one on every day, see [`course_sessions`]
+pub fn count_course_repeats(
+    individual: &Individual,
+    tuples: &[Tuple],
+    layout: &PeriodLayout,
+) -> usize {
+    course_sessions(individual, tuples, layout)
+        .into_values()
+        .map(|genes| genes.len() - 1)
+        .sum()
+}
+
+/// Count locked genes placed outside the period they are pinned to
+pub fn count_lock_violations(individual: &Individual, locks: &Locks) -> usize {
+    individual
+        .chromosomes
+        .iter()
+        .enumerate()
+        .flat_map(|(period_id, chromosome)| {
+            chromosome
+                .genes
+                .iter()
+                .filter(move |gene| locks.get(gene).is_some_and(|locked| *locked != period_id))
+        })
+        .count()
+}
+
+/// Genes taking place in every period, classes longer than one period appear in all periods of
+/// their block
+pub fn active_genes(
+    individual: &Individual,
+    tuples: &[Tuple],
+    layout: &PeriodLayout,
+) -> Vec<Vec<Gene>> {
+    let number_of_periods = individual.chromosomes.len();
+    let mut active_genes = vec![Vec::new(); number_of_periods];
+
+    for (period_id, chromosome) in individual.chromosomes.iter().enumerate() {
+        for gene in &chromosome.genes {
+            let length = tuples
+                .iter()
+                .find(|tuple| tuple.id == *gene)
+                .map_or(1, Tuple::length);
+
+            let block = layout.clamped_block(period_id, length);
+            let block_end = block.end.min(number_of_periods);
+            for period_genes in &mut active_genes[block.start..block_end] {
+                period_genes.push(*gene);
+            }
+        }
+    }
+
+    active_genes
+}
+
+/// Count periods taken by tuples in periods they can't occupy, see [`Tuple::forbidden_periods`]
+pub fn count_forbidden_periods(
+    individual: &Individual,
+    tuples: &[Tuple],
+    layout: &PeriodLayout,
+) -> usize {
+    individual
+        .chromosomes
+        .iter()
+        .enumerate()
+        .map(|(period_id, chromosome)| {
+            chromosome
+                .genes
+                .iter()
+                .filter_map(|gene| tuples.iter().find(|tuple| tuple.id == *gene))
+                .map(|tuple| {
+                    layout
+                        .clamped_block(period_id, tuple.length())
+                        .filter(|period_id| !tuple.can_take_place_in(*period_id))
+                        .count()
+                })
+                .sum::<usize>()
+        })
+        .sum()
+}
+
+/// Count tuples whose block of periods doesn't fit within the day they start in
+pub fn count_block_overflows(
+    individual: &Individual,
+    tuples: &[Tuple],
+    layout: &PeriodLayout,
+) -> usize {
+    individual
+        .chromosomes
+        .iter()
+        .enumerate()
+        .map(|(period_id, chromosome)| {
+            chromosome
+                .genes
+                .iter()
+                .filter_map(|gene| tuples.iter().find(|tuple| tuple.id == *gene))
+                .filter(|tuple| layout.block(period_id, tuple.length()).is_none())
+                .count()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::{
+        datatypes::{Chromosome, PeriodId, TupleId},
+        resources::Room,
+    };
+
+    #[test]
+    fn test_gaps_are_counted_per_day() {
+        let tuples: Vec<Tuple> = (1..=4)
+            .map(|id| Tuple {
+                id: TupleId(id),
+                teacher: "Smith".to_string(),
+                ..Tuple::default()
+            })
+            .collect();
+
+        // day 1: slots 0 and 3, day 2: slots 0 and 1
+        let individual = Individual::with_chromosomes(
+            [
+                vec![1],
+                vec![],
+                vec![],
+                vec![2],
+                vec![3],
+                vec![4],
+                vec![],
+                vec![],
+            ]
+            .into_iter()
+            .enumerate()
+            .map(|(id, genes)| Chromosome {
+                id: PeriodId(id),
+                genes: genes.into_iter().map(TupleId).collect(),
+            })
+            .collect(),
+        );
+
+        let layout = PeriodLayout::new(2, 4);
+        assert_eq!(count_gaps(&individual, &tuples, &layout, |t| &t.teacher), 2);
+        assert_eq!(count_gaps(&individual, &tuples, &layout, |t| &t.group), 0);
+    }
+
+    #[test]
+    fn test_isolated_classes_of_groups() {
+        let tuples: Vec<Tuple> = (1..=4)
+            .map(|id| Tuple {
+                id: TupleId(id),
+                group: "1A".to_string(),
+                ..Tuple::default()
+            })
+            .collect();
+
+        // day 1: slots 1, 2 and 4, day 2: slot 0
+        let individual = Individual::with_chromosomes(
+            [vec![], vec![1], vec![2], vec![], vec![3], vec![4], vec![]]
+                .into_iter()
+                .enumerate()
+                .map(|(id, genes)| Chromosome {
+                    id: PeriodId(id),
+                    genes: genes.into_iter().map(TupleId).collect(),
+                })
+                .collect(),
+        );
+
+        assert_eq!(isolated_slots(&[0, 2, 3, 5]), vec![0, 5]);
+        let layout = PeriodLayout::new(2, 5);
+        assert_eq!(count_isolated_classes(&individual, &tuples, &layout), 2);
+    }
+
+    #[test]
+    fn test_blocks_are_active_in_all_their_periods() {
+        let tuples = vec![
+            Tuple {
+                id: TupleId(1),
+                duration: 3,
+                ..Tuple::default()
+            },
+            Tuple {
+                id: TupleId(2),
+                ..Tuple::default()
+            },
+        ];
+
+        // tuple 1 starts in the last but one slot of the first day
+        let individual = Individual::with_chromosomes(
+            [vec![2], vec![], vec![1], vec![], vec![]]
+                .into_iter()
+                .enumerate()
+                .map(|(id, genes)| Chromosome {
+                    id: PeriodId(id),
+                    genes: genes.into_iter().map(TupleId).collect(),
+                })
+                .collect(),
+        );
+
+        let layout = PeriodLayout::new(2, 4);
+        assert_eq!(
+            active_genes(&individual, &tuples, &layout),
+            [vec![2], vec![], vec![1], vec![1], vec![]]
+                .map(|genes| -> Vec<Gene> { genes.into_iter().map(TupleId).collect() })
+        );
+        assert_eq!(count_block_overflows(&individual, &tuples, &layout), 1);
+    }
+
+    #[test]
+    fn test_consecutive_overruns_are_counted_per_run() {
+        let tuples: Vec<Tuple> = (1..=5)
+            .map(|id| Tuple {
+                id: TupleId(id),
+                teacher: "Smith".to_string(),
+                ..Tuple::default()
+            })
+            .collect();
+
+        // slots 0-2 and 4-5 of a single day
+        let individual = Individual::with_chromosomes(
+            [vec![1], vec![2], vec![3], vec![], vec![4], vec![5]]
+                .into_iter()
+                .enumerate()
+                .map(|(id, genes)| Chromosome {
+                    id: PeriodId(id),
+                    genes: genes.into_iter().map(TupleId).collect(),
+                })
+                .collect(),
+        );
+
+        let layout = PeriodLayout::new(1, 6);
+        let overruns = |limit| count_consecutive_overruns(&individual, &tuples, &layout, |_| limit);
+        assert_eq!(overruns(Some(1)), 3);
+        assert_eq!(overruns(Some(2)), 1);
+        assert_eq!(overruns(None), 0);
+    }
+
+    #[test]
+    fn test_period_overflow_counts_room_kinds() {
+        let tuples: Vec<Tuple> = [
+            ("Lab 1", TupleKind::Lecture),
+            ("Lab 2", TupleKind::Lab),
+            ("A", TupleKind::Lab),
+        ]
+        .into_iter()
+        .zip(1..)
+        .map(|((room, kind), id)| Tuple {
+            id: TupleId(id),
+            room: room.to_string(),
+            kind,
+            ..Tuple::default()
+        })
+        .collect();
+        // the lecture takes place in a lab, the lab in a room without a kind
+        let rooms: Rooms = [("Lab 1", TupleKind::Lab), ("A", TupleKind::Lecture)]
+            .into_iter()
+            .map(|(id, kind)| {
+                let room = Room {
+                    id: id.to_string(),
+                    kind: Some(kind),
+                    ..Room::default()
+                };
+                (room.id.clone(), room)
+            })
+            .collect();
+        let genes = [1, 2, 3].map(TupleId);
+
+        let overflow = |max_classes, limits: &[(TupleKind, usize)]| {
+            let limits = limits.iter().copied().collect();
+            count_period_overflow(&genes, &tuples, &rooms, max_classes, &limits)
+        };
+        assert_eq!(overflow(None, &[]), 0);
+        assert_eq!(overflow(Some(2), &[]), 1);
+        assert_eq!(overflow(None, &[(TupleKind::Lab, 1)]), 1);
+        assert_eq!(overflow(Some(1), &[(TupleKind::Lecture, 0)]), 3);
+    }
+
+    #[test]
+    fn test_course_repeats_are_counted_per_day() {
+        let tuples: Vec<Tuple> = [(1, "Physics"), (2, "Physics"), (3, "Physics"), (4, "")]
+            .into_iter()
+            .map(|(id, course)| Tuple {
+                id: TupleId(id),
+                course: course.to_string(),
+                ..Tuple::default()
+            })
+            .collect();
+
+        // two sessions and a class without a course on the first day, one on the second
+        let individual = Individual::with_chromosomes(
+            [vec![1, 4], vec![2], vec![3], vec![]]
+                .into_iter()
+                .enumerate()
+                .map(|(id, genes)| Chromosome {
+                    id: PeriodId(id),
+                    genes: genes.into_iter().map(TupleId).collect(),
+                })
+                .collect(),
+        );
+
+        let layout = PeriodLayout::new(2, 2);
+        assert_eq!(count_course_repeats(&individual, &tuples, &layout), 1);
+        assert_eq!(
+            course_sessions(&individual, &tuples, &layout)[&("Physics", 0)],
+            [1, 2].map(TupleId)
+        );
+    }
+}
@@ -1,15 +1,113 @@
 use std::fmt::Display;
-use std::{fs::File, path::Path};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::{collections::BTreeMap, fs::File, num::ParseIntError, path::Path, str::FromStr};
 
 use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
 use thiserror::Error;
 
+use super::{
+    config::AlgorithmConfig,
+    conflicts::ConflictMatrix,
+    periods::PeriodLayout,
+    sources::{format_extension, CsvTuples, JsonTuples, Located, TupleSource, YamlTuples},
+    symbols::{RoomId, Symbols, TeacherId},
+    xhstt::XhsttTuples,
+};
+
+/// Version of the serialized form of [`Tuple`], [`Individual`], [`Chromosome`] and [`Population`]
+///
+/// Migration messages, elite archives and `schedule.json` all serialize these types with their
+/// serde derives. New fields with `#[serde(default)]` keep the version, renaming or removing a
+/// field or changing its type bumps it, together with `PROTOCOL_VERSION` in `mpi_utils`.
+pub const SCHEMA_VERSION: u16 = 2;
+
+/// Most tuples an instance may have, every id has to fit in a [`Gene`]
+pub const MAX_TUPLES: usize = u16::MAX as usize + 1;
+
 #[derive(Debug, Error)]
 pub enum TuplesLoadError {
     #[error("Configuration file not found")]
     Io(#[from] std::io::Error),
     #[error(transparent)]
     Csv(#[from] csv::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("Line {line}: invalid tuple id {id:?}, ids are numbers from 0 to 65535")]
+    InvalidId { line: u64, id: String },
+    #[error("Line {line}: invalid {field} {value:?}")]
+    InvalidValue {
+        line: u64,
+        field: &'static str,
+        value: String,
+    },
+    #[error("Too many tuples: {0}, at most {MAX_TUPLES} are supported")]
+    TooManyTuples(usize),
+    #[error("Invalid CSV delimiter {0:?}, it has to be an ASCII character")]
+    InvalidDelimiter(char),
+    #[error("Unknown tuple field {0:?} in csv_columns")]
+    UnknownField(String),
+    #[error("Column {0:?} not found in the tuples file")]
+    UnknownColumn(String),
+    #[error("No column for the field {0:?} of the tuples, map one in csv_columns")]
+    MissingColumn(&'static str),
+    #[error(transparent)]
+    Xml(#[from] roxmltree::Error),
+    #[error("The file has no XHSTT instance")]
+    NoInstance,
+    #[error("No XHSTT instance {0:?} in the file, see xhstt_instance")]
+    UnknownInstance(String),
+}
+
+#[derive(Debug, Error)]
+pub enum ScheduleLoadError {
+    #[error("Schedule file not found")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("Schedule has {found} periods, expected {expected}")]
+    PeriodCountMismatch { found: usize, expected: usize },
+}
+
+/// Type of a class, decides which classes may share a room
+#[derive(
+    Debug, Clone, Copy, Serialize, Deserialize, Default, Eq, PartialEq, Hash, Ord, PartialOrd,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum TupleKind {
+    #[default]
+    Lecture,
+    Exercise,
+    Lab,
+}
+
+impl TupleKind {
+    /// Parse the kind from the `Kind` column of the tuples CSV
+    pub fn parse(kind: &str) -> Option<TupleKind> {
+        match kind.trim().to_lowercase().as_str() {
+            "lecture" => Some(TupleKind::Lecture),
+            "exercise" => Some(TupleKind::Exercise),
+            "lab" => Some(TupleKind::Lab),
+            _ => None,
+        }
+    }
+
+    /// Guess the kind from a label like `Wykład | Automaty komórkowe`, lectures by default
+    pub fn from_label(label: &str) -> TupleKind {
+        let prefix = label.split('|').next().unwrap_or_default().trim();
+
+        if prefix.starts_with("Laboratorium") {
+            TupleKind::Lab
+        } else if prefix.starts_with("Ćwiczenia") {
+            TupleKind::Exercise
+        } else {
+            TupleKind::Lecture
+        }
+    }
 }
 
 /// Tuple
@@ -28,10 +126,45 @@ pub enum TuplesLoadError {
 /// for example, taking into account the teacher's preferences.
 #[derive(Debug, Serialize, Deserialize, Clone, Default, Eq, PartialEq, Hash)]
 pub struct Tuple {
-    pub id: i32,
+    pub id: TupleId,
     pub label: String,
     pub room: String,
     pub teacher: String,
+    /// Student group attending the class, empty if unknown
+    #[serde(default)]
+    pub group: String,
+    /// Periods the class can't take place in (e.g. evening-only lecturers)
+    #[serde(default)]
+    pub forbidden_periods: Vec<usize>,
+    /// Number of consecutive periods the class takes within one day, 0 is treated as 1
+    #[serde(default)]
+    pub duration: usize,
+    #[serde(default)]
+    pub kind: TupleKind,
+    /// Rooms the class may take place in when rooms are assigned by the algorithm, see
+    /// `room_assignment`. Empty to always use `room`
+    #[serde(default)]
+    pub allowed_rooms: Vec<String>,
+    /// Teachers who may teach the class when teachers are assigned by the algorithm, see
+    /// `teacher_assignment`. Empty to always use `teacher`
+    #[serde(default)]
+    pub qualified_teachers: Vec<String>,
+    /// Course the class belongs to, e.g. the lectures and labs of one subject. Sessions of a course
+    /// should take place on different days. Empty if unknown
+    #[serde(default)]
+    pub course: String,
+    /// Id of `teacher`, set by [`Symbols::intern`] like the other ids. Not serialized, tuples
+    /// read from files or messages are interned again
+    #[serde(skip)]
+    pub teacher_id: TeacherId,
+    #[serde(skip)]
+    pub room_id: RoomId,
+    /// Ids of `qualified_teachers`, in the same order
+    #[serde(skip)]
+    pub qualified_teacher_ids: Vec<TeacherId>,
+    /// Ids of `allowed_rooms`, in the same order
+    #[serde(skip)]
+    pub allowed_room_ids: Vec<RoomId>,
 }
 
 impl Display for Tuple {
@@ -45,36 +178,192 @@ impl Display for Tuple {
 }
 
 impl Tuple {
-    pub fn from_csv(path: impl AsRef<Path>) -> Result<Vec<Tuple>, TuplesLoadError> {
-        let file = File::open(path)?;
-        let mut reader = csv::Reader::from_reader(file);
+    /// Whether two tuples can't take place in the same period (same teacher or same room)
+    pub fn clashes_with(&self, other: &Tuple) -> bool {
+        self.teacher_id == other.teacher_id || self.room_id == other.room_id
+    }
 
-        let mut tuples = Vec::new();
-
-        for result in reader.records() {
-            let record = result?;
-            let tuple = Tuple {
-                id: record[0].parse().unwrap(),
-                label: record[1].to_string(),
-                room: record[2].to_string(),
-                teacher: record[3].to_string(),
-            };
-            tuples.push(tuple);
+    /// Whether the tuple may be placed in the period
+    pub fn can_take_place_in(&self, period_id: usize) -> bool {
+        !self.forbidden_periods.contains(&period_id)
+    }
+
+    /// Number of periods the class takes, at least 1
+    pub fn length(&self) -> usize {
+        self.duration.max(1)
+    }
+
+    /// Whether the class can start in the period: the whole block fits within the day and none of
+    /// its periods is forbidden
+    pub fn can_start_at(&self, period_id: usize, layout: &PeriodLayout) -> bool {
+        layout
+            .block(period_id, self.length())
+            .is_some_and(|mut block| block.all(|period_id| self.can_take_place_in(period_id)))
+    }
+
+    /// Load tuples from a file, the format is picked by extension (`.json`, `.yaml`/`.yml`, `.xml`
+    /// for XHSTT, anything else is read as CSV with the layout of the config), and intern their
+    /// teachers and rooms
+    ///
+    /// `.gz` and `.zst` files are decompressed, see [`open_input`](super::sources::open_input),
+    /// and `-` reads CSV from the standard input.
+    pub fn from_file(
+        path: impl AsRef<Path>,
+        config: &AlgorithmConfig,
+    ) -> Result<Vec<Tuple>, TuplesLoadError> {
+        Ok(Tuple::from_file_located(path, config)?.0)
+    }
+
+    /// Like [`Tuple::from_file`], with the line or entry of the file every tuple was read from
+    pub fn from_file_located(
+        path: impl AsRef<Path>,
+        config: &AlgorithmConfig,
+    ) -> Result<Located, TuplesLoadError> {
+        let path = path.as_ref();
+
+        let (mut tuples, locations) = match format_extension(path) {
+            Some("json") => JsonTuples.load_located(path),
+            Some("yaml" | "yml") => YamlTuples.load_located(path),
+            Some("xml") => XhsttTuples::from_config(config).load_located(path),
+            _ => CsvTuples::from_config(config).load_located(path),
+        }?;
+        if tuples.len() > MAX_TUPLES {
+            return Err(TuplesLoadError::TooManyTuples(tuples.len()));
         }
+        Symbols::intern(&mut tuples);
+        Ok((tuples, locations))
+    }
+
+    /// Write tuples as a CSV file with the `Id`, `Label`, `Room` and `Teacher` columns of the
+    /// default [`CsvTuples`] layout, other fields are left out
+    pub fn to_csv(tuples: &[Tuple], path: impl AsRef<Path>) -> Result<(), csv::Error> {
+        let mut writer = csv::Writer::from_path(path)?;
 
-        Ok(tuples)
+        writer.write_record(["Id", "Label", "Room", "Teacher"])?;
+        for tuple in tuples {
+            writer.write_record([
+                &tuple.id.to_string(),
+                &tuple.label,
+                &tuple.room,
+                &tuple.teacher,
+            ])?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Load tuples from a CSV file with the given layout, see [`CsvTuples`]
+    pub fn from_csv(
+        path: impl AsRef<Path>,
+        csv: &CsvTuples,
+    ) -> Result<Vec<Tuple>, TuplesLoadError> {
+        csv.load_tuples(path)
+    }
+
+    /// Load tuples from a JSON array of tuples
+    pub fn from_json(path: impl AsRef<Path>) -> Result<Vec<Tuple>, TuplesLoadError> {
+        JsonTuples.load_tuples(path)
+    }
+
+    /// Load tuples from a YAML sequence of tuples
+    pub fn from_yaml(path: impl AsRef<Path>) -> Result<Vec<Tuple>, TuplesLoadError> {
+        YamlTuples.load_tuples(path)
     }
 }
 
-/// Gene is [`Tuple::id`]. Used internally to minimize the size of the data being sent/copied. For example,
-/// crossover can operate only on the ids of the tuples.
-pub type Gene = i32;
+/// Id of a [`Tuple`]
+///
+/// A type of its own, so ids can't be mixed up with period or population indices. Serialized as
+/// the plain number. Ids are 16-bit to keep chromosomes small, see [`Genes`].
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct TupleId(pub u16);
+
+impl Display for TupleId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for TupleId {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(TupleId)
+    }
+}
+
+/// Id of a period, its position among the chromosomes of an individual
+///
+/// Kept apart from tuple ids and population indices like [`TupleId`]. Code walking the period
+/// layout still counts periods with plain indices and converts at the chromosomes.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct PeriodId(pub usize);
+
+impl PeriodId {
+    /// Position of the period's chromosome in an individual
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+impl Display for PeriodId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Gene is a [`TupleId`]. Used internally to minimize the size of the data being sent/copied. For
+/// example, crossover can operate only on the ids of the tuples.
+pub type Gene = TupleId;
+
+/// Genes of a chromosome, stored inline up to a handful of them
+///
+/// Periods rarely hold more classes than that, so crossover and mutation copy chromosomes without
+/// allocating.
+pub type Genes = SmallVec<[Gene; 8]>;
+
+/// Tuples pinned to fixed periods, tuple id to period index
+pub type Locks = BTreeMap<Gene, usize>;
+
+/// Load locks from a CSV file with `Period` and `TupleId` columns, the same format as schedules
+/// written by [`Individual::to_csv`]
+pub fn locks_from_csv(path: impl AsRef<Path>) -> Result<Locks, csv::Error> {
+    let mut reader = csv::Reader::from_path(path)?;
+
+    reader
+        .deserialize()
+        .map(|result| result.map(|record: ScheduleRecord| (record.tuple_id, record.period.index())))
+        .collect()
+}
+
+/// Single row of a schedule file, assigning a tuple to a period
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ScheduleRecord {
+    period: PeriodId,
+    tuple_id: Gene,
+}
 
 /// Individual is a timetable. It has adaptation value and a list of chromosomes = periods.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Individual {
     pub adaptation: i32,
     pub chromosomes: Vec<Chromosome>,
+    /// Rooms chosen for the tuples with [`Tuple::allowed_rooms`], see
+    /// [`assignment`](super::assignment). Tuples without a room here use [`Tuple::room`]
+    #[serde(default)]
+    pub rooms: BTreeMap<Gene, String>,
+    /// Teachers chosen for the tuples with [`Tuple::qualified_teachers`], see
+    /// [`assignment`](super::assignment). Tuples without a teacher here use [`Tuple::teacher`]
+    #[serde(default)]
+    pub teachers: BTreeMap<Gene, String>,
 }
 
 impl Individual {
@@ -91,6 +380,115 @@ impl Individual {
             ..Self::default()
         }
     }
+
+    /// Hash of the placement of genes in periods, equal for individuals with the same schedule
+    ///
+    /// The order of genes within a period doesn't matter, the adaptation is not included.
+    pub fn placement_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for chromosome in &self.chromosomes {
+            let mut genes = chromosome.genes.clone();
+            genes.sort_unstable();
+            genes.hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Move the gene at `index` of period `from` to the end of period `to`, returning the gene
+    pub fn move_gene(&mut self, from: usize, index: usize, to: usize) -> Gene {
+        let gene = self.chromosomes[from].genes.remove(index);
+        self.chromosomes[to].genes.push(gene);
+        gene
+    }
+
+    /// Move a gene like [`Individual::move_gene`] and update the adaptation without evaluating
+    /// the whole individual, returning the change of the adaptation
+    ///
+    /// Only the clashes of the gene in the two periods are looked up in the matrix, so the result
+    /// is exact when [`ConflictMatrix::covers_moves`].
+    pub fn apply_move(
+        &mut self,
+        from: usize,
+        index: usize,
+        to: usize,
+        matrix: &ConflictMatrix,
+    ) -> i32 {
+        let gene = self.move_gene(from, index, to);
+        let delta = matrix.gene_penalty(gene, &self.chromosomes[from].genes)
+            - matrix.gene_penalty(gene, &self.chromosomes[to].genes);
+        self.adaptation += delta;
+        delta
+    }
+
+    /// Load a schedule from a file. Files with `.json` extension are read as a serialized
+    /// [`Individual`], everything else as CSV written by [`Individual::to_csv`].
+    pub fn from_file(
+        path: impl AsRef<Path>,
+        number_of_periods: usize,
+    ) -> Result<Individual, ScheduleLoadError> {
+        let path = path.as_ref();
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => {
+                let individual: Individual = serde_json::from_reader(File::open(path)?)?;
+                if individual.chromosomes.len() != number_of_periods {
+                    return Err(ScheduleLoadError::PeriodCountMismatch {
+                        found: individual.chromosomes.len(),
+                        expected: number_of_periods,
+                    });
+                }
+                Ok(individual)
+            }
+            _ => Individual::from_csv(path, number_of_periods),
+        }
+    }
+
+    /// Load a schedule from a CSV file with `Period` and `TupleId` columns
+    pub fn from_csv(
+        path: impl AsRef<Path>,
+        number_of_periods: usize,
+    ) -> Result<Individual, ScheduleLoadError> {
+        let file = File::open(path)?;
+        let mut reader = csv::Reader::from_reader(file);
+
+        let mut individual = Individual::new(number_of_periods);
+        for period_id in 0..number_of_periods {
+            individual
+                .chromosomes
+                .push(Chromosome::new(PeriodId(period_id)));
+        }
+
+        for result in reader.deserialize() {
+            let record: ScheduleRecord = result?;
+            let period = record.period.index();
+            if period >= number_of_periods {
+                return Err(ScheduleLoadError::PeriodCountMismatch {
+                    found: period + 1,
+                    expected: number_of_periods,
+                });
+            }
+            individual.chromosomes[period].genes.push(record.tuple_id);
+        }
+
+        Ok(individual)
+    }
+
+    /// Write the schedule as a CSV file with `Period` and `TupleId` columns
+    pub fn to_csv(&self, path: impl AsRef<Path>) -> Result<(), csv::Error> {
+        let mut writer = csv::Writer::from_path(path)?;
+
+        for (period, chromosome) in self.chromosomes.iter().enumerate() {
+            for gene in &chromosome.genes {
+                writer.serialize(ScheduleRecord {
+                    period: PeriodId(period),
+                    tuple_id: *gene,
+                })?;
+            }
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
 }
 
 impl Default for Individual {
@@ -98,6 +496,8 @@ impl Default for Individual {
         Individual {
             adaptation: -1000,
             chromosomes: Vec::new(),
+            rooms: BTreeMap::new(),
+            teachers: BTreeMap::new(),
         }
     }
 }
@@ -109,15 +509,15 @@ pub type Population = Vec<Individual>;
 /// we are only interested in their number.
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Chromosome {
-    pub id: i32,
-    pub genes: Vec<Gene>,
+    pub id: PeriodId,
+    pub genes: Genes,
 }
 
 impl Chromosome {
-    pub fn new(id: i32) -> Self {
+    pub fn new(id: PeriodId) -> Self {
         Chromosome {
             id,
-            genes: Vec::new(),
+            genes: Genes::new(),
         }
     }
 }
@@ -125,6 +525,61 @@ impl Chromosome {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use smallvec::smallvec;
+
+    #[test]
+    fn test_individuals_keep_the_documented_schema() {
+        let mut individual = Individual::with_chromosomes(vec![Chromosome {
+            id: PeriodId(0),
+            genes: smallvec![TupleId(3), TupleId(1)],
+        }]);
+        individual.rooms.insert(TupleId(3), "A1".to_string());
+        let json = serde_json::to_string(&individual).unwrap();
+        assert_eq!(
+            json,
+            r#"{"adaptation":-1000,"chromosomes":[{"id":0,"genes":[3,1]}],"rooms":{"3":"A1"},"teachers":{}}"#
+        );
+
+        let older: Individual =
+            serde_json::from_str(r#"{"adaptation":0,"chromosomes":[{"id":0,"genes":[3,1]}]}"#)
+                .unwrap();
+        assert!(older.rooms.is_empty() && older.teachers.is_empty());
+    }
+
+    #[test]
+    fn test_ids_are_written_as_plain_numbers() {
+        let individual = Individual::with_chromosomes(
+            [vec![], vec![TupleId(7), TupleId(2)]]
+                .into_iter()
+                .enumerate()
+                .map(|(period, genes)| Chromosome {
+                    id: PeriodId(period),
+                    genes: genes.into(),
+                })
+                .collect(),
+        );
+        let path = std::env::temp_dir().join("planner_schedule_ids_test.csv");
+
+        individual.to_csv(&path).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "Period,TupleId\n1,7\n1,2\n"
+        );
+        let read = Individual::from_csv(&path, 2).unwrap();
+        assert_eq!(read.chromosomes[1].id, PeriodId(1));
+        assert_eq!(
+            read.chromosomes[1].genes.as_slice(),
+            [TupleId(7), TupleId(2)]
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!("42".parse::<TupleId>(), Ok(TupleId(42)));
+        assert!("-1".parse::<TupleId>().is_err());
+        assert_eq!(
+            (TupleId(42).to_string(), PeriodId(3).to_string()),
+            ("42".to_string(), "3".to_string())
+        );
+    }
 
     #[test]
     fn test_default_value_of_individuals() {
@@ -136,8 +591,8 @@ mod tests {
     #[test]
     fn test_individual_with_chromosomes() {
         let chromosomes = vec![Chromosome {
-            id: 1,
-            genes: vec![1, 2, 3],
+            id: PeriodId(1),
+            genes: smallvec![TupleId(1), TupleId(2), TupleId(3)],
         }];
         let individual = Individual::with_chromosomes(chromosomes);
         assert_eq!(individual.adaptation, -1000);
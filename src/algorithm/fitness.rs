@@ -0,0 +1,593 @@
+use super::{
+    assignment, clash_penalties,
+    config::{AlgorithmConfig, ConstraintSpec},
+    constraints,
+    datatypes::{Gene, Individual, Tuple},
+    periods::PeriodLayout,
+    resources::Teacher,
+};
+
+/// What a [`Constraint`] may need besides the individual, computed once per evaluation
+pub struct FitnessContext<'a> {
+    pub config: &'a AlgorithmConfig,
+    pub tuples: &'a [Tuple],
+    pub layout: PeriodLayout,
+    /// Genes taking place in every period, see [`constraints::active_genes`]
+    pub active_genes: Vec<Vec<Gene>>,
+}
+
+/// A rule the schedule should follow, lowering the fitness by its penalty when broken
+pub trait Constraint: Send + Sync {
+    /// Short name, e.g. for logs
+    fn name(&self) -> &str;
+
+    /// How much the individual breaks the rule, 0 if it doesn't
+    fn penalty(&self, individual: &Individual, ctx: &FitnessContext) -> i32;
+}
+
+/// Teachers and rooms used by more than one class at a time, see [`clash_penalties`]
+///
+/// Looked up in the [`ConflictMatrix`](super::conflicts::ConflictMatrix) of the configuration when
+/// one is attached.
+pub struct Clashes;
+
+impl Constraint for Clashes {
+    fn name(&self) -> &str {
+        "clashes"
+    }
+
+    fn penalty(&self, _individual: &Individual, ctx: &FitnessContext) -> i32 {
+        if let Some(matrix) = &ctx.config.conflict_matrix {
+            return ctx
+                .active_genes
+                .iter()
+                .map(|genes| matrix.period_penalty(genes))
+                .sum();
+        }
+
+        let mut penalty = 0;
+
+        for genes in &ctx.active_genes {
+            for gene_id in genes {
+                let tuple = ctx
+                    .tuples
+                    .iter()
+                    .find(|t| t.id == *gene_id)
+                    .unwrap_or_else(|| panic!("Tuple with id {} not found", *gene_id));
+
+                let other_classes = ctx
+                    .tuples
+                    .iter()
+                    .filter(|t| genes.contains(&t.id))
+                    .filter(|t| t.id != tuple.id);
+
+                for other in other_classes {
+                    let (teacher_penalty, room_penalty) = clash_penalties(ctx.config, tuple, other);
+                    penalty += teacher_penalty + room_penalty;
+                }
+            }
+        }
+
+        penalty
+    }
+}
+
+/// Idle periods of teachers, weighted by `teacher_gap_weight`
+pub struct TeacherGaps;
+
+impl Constraint for TeacherGaps {
+    fn name(&self) -> &str {
+        "teacher_gaps"
+    }
+
+    fn penalty(&self, individual: &Individual, ctx: &FitnessContext) -> i32 {
+        let gaps = constraints::count_gaps(individual, ctx.tuples, &ctx.layout, |t| &t.teacher);
+        gaps as i32 * ctx.config.teacher_gap_weight
+    }
+}
+
+/// Idle periods of student groups, weighted by `group_gap_weight`
+pub struct GroupGaps;
+
+impl Constraint for GroupGaps {
+    fn name(&self) -> &str {
+        "group_gaps"
+    }
+
+    fn penalty(&self, individual: &Individual, ctx: &FitnessContext) -> i32 {
+        let gaps = constraints::count_gaps(individual, ctx.tuples, &ctx.layout, |t| &t.group);
+        gaps as i32 * ctx.config.group_gap_weight
+    }
+}
+
+/// Isolated classes of student groups, weighted by `compactness_weight`
+pub struct Compactness;
+
+impl Constraint for Compactness {
+    fn name(&self) -> &str {
+        "compactness"
+    }
+
+    fn penalty(&self, individual: &Individual, ctx: &FitnessContext) -> i32 {
+        let isolated = constraints::count_isolated_classes(individual, ctx.tuples, &ctx.layout);
+        isolated as i32 * ctx.config.compactness_weight
+    }
+}
+
+/// Periods taught over the consecutive hours limit, weighted by `consecutive_hours_weight`
+pub struct ConsecutiveHours;
+
+impl Constraint for ConsecutiveHours {
+    fn name(&self) -> &str {
+        "consecutive_hours"
+    }
+
+    fn penalty(&self, individual: &Individual, ctx: &FitnessContext) -> i32 {
+        let overruns =
+            constraints::count_consecutive_overruns(individual, ctx.tuples, &ctx.layout, |t| {
+                ctx.config.max_consecutive_hours_of(t)
+            });
+        overruns as i32 * ctx.config.consecutive_hours_weight
+    }
+}
+
+/// Periods taught over the limits of the teachers, weighted by `workload_weight`
+pub struct TeacherWorkload;
+
+impl Constraint for TeacherWorkload {
+    fn name(&self) -> &str {
+        "teacher_workload"
+    }
+
+    fn penalty(&self, individual: &Individual, ctx: &FitnessContext) -> i32 {
+        let overruns = constraints::count_workload_overruns(
+            individual,
+            ctx.tuples,
+            &ctx.layout,
+            &ctx.config.teachers,
+        );
+        overruns as i32 * ctx.config.workload_weight
+    }
+}
+
+/// Deviations of the chosen teachers from their mean workload, weighted by
+/// `workload_balance_weight`
+pub struct WorkloadBalance;
+
+impl Constraint for WorkloadBalance {
+    fn name(&self) -> &str {
+        "workload_balance"
+    }
+
+    fn penalty(&self, _individual: &Individual, ctx: &FitnessContext) -> i32 {
+        let deviation: usize = constraints::workload_deviations(ctx.tuples).values().sum();
+        deviation as i32 * ctx.config.workload_balance_weight
+    }
+}
+
+/// Classes over the limits of their periods, weighted by `period_overflow_weight`
+pub struct PeriodOverflows;
+
+impl Constraint for PeriodOverflows {
+    fn name(&self) -> &str {
+        "period_overflows"
+    }
+
+    fn penalty(&self, _individual: &Individual, ctx: &FitnessContext) -> i32 {
+        let overflow: usize = ctx
+            .active_genes
+            .iter()
+            .map(|genes| {
+                constraints::count_period_overflow(
+                    genes,
+                    ctx.tuples,
+                    &ctx.config.rooms,
+                    ctx.config.max_classes_per_period,
+                    &ctx.config.max_classes_per_room_kind,
+                )
+            })
+            .sum();
+        overflow as i32 * ctx.config.period_overflow_weight
+    }
+}
+
+/// Periods taught outside the preferred and in the disliked periods of the teachers, weighted by
+/// `preferred_period_weight` and `disliked_period_weight`
+pub struct TeacherPreferences;
+
+impl Constraint for TeacherPreferences {
+    fn name(&self) -> &str {
+        "teacher_preferences"
+    }
+
+    fn penalty(&self, _individual: &Individual, ctx: &FitnessContext) -> i32 {
+        constraints::count_preferences(&ctx.active_genes, ctx.tuples, &ctx.config.teachers)
+            .values()
+            .map(|count| {
+                count.outside_preferred as i32 * ctx.config.preferred_period_weight
+                    + count.disliked as i32 * ctx.config.disliked_period_weight
+            })
+            .sum()
+    }
+}
+
+/// Sessions of a course on a day that already has one, weighted by `course_spread_weight`
+pub struct CourseSpread;
+
+impl Constraint for CourseSpread {
+    fn name(&self) -> &str {
+        "course_spread"
+    }
+
+    fn penalty(&self, individual: &Individual, ctx: &FitnessContext) -> i32 {
+        let repeats = constraints::count_course_repeats(individual, ctx.tuples, &ctx.layout);
+        repeats as i32 * ctx.config.course_spread_weight
+    }
+}
+
+/// Locked tuples outside their periods, weighted by `lock_violation_weight`
+pub struct LockedPeriods;
+
+impl Constraint for LockedPeriods {
+    fn name(&self) -> &str {
+        "locks"
+    }
+
+    fn penalty(&self, individual: &Individual, ctx: &FitnessContext) -> i32 {
+        let violations = constraints::count_lock_violations(individual, &ctx.config.locks);
+        violations as i32 * ctx.config.lock_violation_weight
+    }
+}
+
+/// Tuples in their forbidden periods, weighted by `forbidden_period_weight`
+pub struct ForbiddenPeriods;
+
+impl Constraint for ForbiddenPeriods {
+    fn name(&self) -> &str {
+        "forbidden_periods"
+    }
+
+    fn penalty(&self, individual: &Individual, ctx: &FitnessContext) -> i32 {
+        let violations = constraints::count_forbidden_periods(individual, ctx.tuples, &ctx.layout);
+        violations as i32 * ctx.config.forbidden_period_weight
+    }
+}
+
+/// Blocks not fitting within their day, weighted by `block_overflow_weight`
+pub struct BlockOverflows;
+
+impl Constraint for BlockOverflows {
+    fn name(&self) -> &str {
+        "block_overflows"
+    }
+
+    fn penalty(&self, individual: &Individual, ctx: &FitnessContext) -> i32 {
+        let overflows = constraints::count_block_overflows(individual, ctx.tuples, &ctx.layout);
+        overflows as i32 * ctx.config.block_overflow_weight
+    }
+}
+
+/// Classes over a limit in a period, see [`ConstraintSpec::MaxPerPeriod`]
+pub struct MaxPerPeriod {
+    pub limit: usize,
+    pub weight: i32,
+}
+
+impl Constraint for MaxPerPeriod {
+    fn name(&self) -> &str {
+        "max_per_period"
+    }
+
+    fn penalty(&self, _individual: &Individual, ctx: &FitnessContext) -> i32 {
+        ctx.active_genes
+            .iter()
+            .map(|genes| genes.len().saturating_sub(self.limit) as i32 * self.weight)
+            .sum()
+    }
+}
+
+/// Classes in periods declared forbidden, see [`ConstraintSpec::ForbiddenPeriod`]
+pub struct DeclaredForbiddenPeriods {
+    pub periods: Vec<usize>,
+    pub teacher: Option<String>,
+    pub group: Option<String>,
+    pub weight: i32,
+}
+
+impl DeclaredForbiddenPeriods {
+    fn applies_to(&self, tuple: &Tuple) -> bool {
+        self.teacher
+            .as_ref()
+            .is_none_or(|teacher| *teacher == tuple.teacher)
+            && self
+                .group
+                .as_ref()
+                .is_none_or(|group| *group == tuple.group)
+    }
+}
+
+impl Constraint for DeclaredForbiddenPeriods {
+    fn name(&self) -> &str {
+        "forbidden_period"
+    }
+
+    fn penalty(&self, _individual: &Individual, ctx: &FitnessContext) -> i32 {
+        let classes = self
+            .periods
+            .iter()
+            .filter_map(|period| ctx.active_genes.get(*period))
+            .flatten()
+            .filter_map(|gene| ctx.tuples.iter().find(|tuple| tuple.id == *gene))
+            .filter(|tuple| self.applies_to(tuple))
+            .count();
+        classes as i32 * self.weight
+    }
+}
+
+/// Compile a constraint declared in the configuration
+pub fn compile(spec: &ConstraintSpec) -> Box<dyn Constraint> {
+    match spec.clone() {
+        ConstraintSpec::MaxPerPeriod { limit, weight } => Box::new(MaxPerPeriod { limit, weight }),
+        ConstraintSpec::ForbiddenPeriod {
+            periods,
+            teacher,
+            group,
+            weight,
+        } => Box::new(DeclaredForbiddenPeriods {
+            periods,
+            teacher,
+            group,
+            weight,
+        }),
+    }
+}
+
+/// The list of constraints the fitness of an individual is made of
+///
+/// [`Fitness::from_config`] holds the built-in constraints, skipping those the configuration turns
+/// off, followed by the `constraints` declared in the configuration. A domain-specific rule (e.g. no exams on Fridays) implements [`Constraint`] and is pushed to
+/// `constraints`.
+pub struct Fitness {
+    pub constraints: Vec<Box<dyn Constraint>>,
+}
+
+impl Fitness {
+    pub fn from_config(config: &AlgorithmConfig) -> Self {
+        let mut constraints: Vec<Box<dyn Constraint>> = vec![Box::new(Clashes)];
+
+        if config.teacher_gap_weight != 0 {
+            constraints.push(Box::new(TeacherGaps));
+        }
+        if config.group_gap_weight != 0 {
+            constraints.push(Box::new(GroupGaps));
+        }
+        if config.compactness_weight != 0 {
+            constraints.push(Box::new(Compactness));
+        }
+        if config.max_consecutive_hours.is_some()
+            || !config.max_consecutive_hours_per_teacher.is_empty()
+        {
+            constraints.push(Box::new(ConsecutiveHours));
+        }
+        if config.teachers.values().any(Teacher::has_workload_limit) {
+            constraints.push(Box::new(TeacherWorkload));
+        }
+        if config.course_spread_weight != 0 {
+            constraints.push(Box::new(CourseSpread));
+        }
+        if config.teachers.values().any(Teacher::has_preferences) {
+            constraints.push(Box::new(TeacherPreferences));
+        }
+        if config.teacher_assignment && config.workload_balance_weight != 0 {
+            constraints.push(Box::new(WorkloadBalance));
+        }
+        if config.limits_classes_per_period() {
+            constraints.push(Box::new(PeriodOverflows));
+        }
+        constraints.push(Box::new(LockedPeriods));
+        constraints.push(Box::new(ForbiddenPeriods));
+        constraints.push(Box::new(BlockOverflows));
+        constraints.extend(config.constraints.iter().map(compile));
+
+        Fitness { constraints }
+    }
+
+    /// Whether moving a class to another period changes only the [`Clashes`]
+    ///
+    /// Holds when every class takes a single period and has no forbidden periods, and no other
+    /// constraint than the locks (locked genes never move) is turned on.
+    pub fn only_clashes_change_on_move(&self, tuples: &[Tuple]) -> bool {
+        const UNAFFECTED: [&str; 4] = ["clashes", "locks", "forbidden_periods", "block_overflows"];
+
+        tuples
+            .iter()
+            .all(|tuple| tuple.length() == 1 && tuple.forbidden_periods.is_empty())
+            && self
+                .constraints
+                .iter()
+                .all(|constraint| UNAFFECTED.contains(&constraint.name()))
+    }
+
+    /// Penalty of every constraint, by [`Constraint::name`]
+    pub fn penalties(
+        &self,
+        config: &AlgorithmConfig,
+        individual: &Individual,
+        tuples: &[Tuple],
+    ) -> Vec<(&str, i32)> {
+        let tuples = &assignment::effective_tuples(config, individual, tuples);
+        let layout = config.period_layout();
+        let ctx = FitnessContext {
+            config,
+            tuples,
+            active_genes: constraints::active_genes(individual, tuples, &layout),
+            layout,
+        };
+
+        self.constraints
+            .iter()
+            .map(|constraint| (constraint.name(), constraint.penalty(individual, &ctx)))
+            .collect()
+    }
+
+    /// Negated sum of the penalties of all constraints, scaled by
+    /// [`AlgorithmConfig::penalty_multipliers`]
+    pub fn evaluate(
+        &self,
+        config: &AlgorithmConfig,
+        individual: &Individual,
+        tuples: &[Tuple],
+    ) -> i32 {
+        -self
+            .penalties(config, individual, tuples)
+            .iter()
+            .map(
+                |(name, penalty)| match config.penalty_multipliers.get(*name) {
+                    Some(multiplier) => (*penalty as f64 * multiplier).round() as i32,
+                    None => *penalty,
+                },
+            )
+            .sum::<i32>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::{
+        calculate_fitness,
+        datatypes::{Chromosome, PeriodId, TupleId},
+        report::ConflictReport,
+        symbols::Symbols,
+    };
+    use smallvec::smallvec;
+
+    /// Penalizes every class in the last period
+    struct EmptyLastPeriod;
+
+    impl Constraint for EmptyLastPeriod {
+        fn name(&self) -> &str {
+            "empty_last_period"
+        }
+
+        fn penalty(&self, individual: &Individual, _ctx: &FitnessContext) -> i32 {
+            individual
+                .chromosomes
+                .last()
+                .map_or(0, |chromosome| chromosome.genes.len() as i32 * 7)
+        }
+    }
+
+    #[test]
+    fn test_declared_constraints() {
+        let mut tuples: Vec<Tuple> = (1..=3)
+            .map(|id| Tuple {
+                id: TupleId(id),
+                teacher: format!("Teacher {}", id),
+                room: format!("Room {}", id),
+                ..Tuple::default()
+            })
+            .collect();
+        Symbols::intern(&mut tuples);
+        let config: AlgorithmConfig = serde_json::from_str(
+            r#"{
+                "number_of_periods": 2,
+                "constraints": [
+                    {"type": "max_per_period", "limit": 1},
+                    {"type": "forbidden_period", "periods": [1], "teacher": "Teacher 3", "weight": 5}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let individual = Individual::with_chromosomes(vec![
+            Chromosome {
+                id: PeriodId(0),
+                genes: smallvec![TupleId(1)],
+            },
+            Chromosome {
+                id: PeriodId(1),
+                genes: smallvec![TupleId(2), TupleId(3)],
+            },
+        ]);
+
+        assert_eq!(calculate_fitness(&config, &individual, &tuples), -15);
+    }
+
+    #[test]
+    fn test_teacher_workload_limits() {
+        let tuples: Vec<Tuple> = (1..=3)
+            .map(|id| Tuple {
+                id: TupleId(id),
+                teacher: "Smith".to_string(),
+                room: format!("Room {}", id),
+                ..Tuple::default()
+            })
+            .collect();
+        let teacher = Teacher {
+            id: "Smith".to_string(),
+            max_daily_hours: Some(1),
+            max_weekly_hours: Some(2),
+            ..Teacher::default()
+        };
+        let config = AlgorithmConfig {
+            number_of_periods: 4,
+            days: Some(2),
+            slots_per_day: Some(2),
+            teachers: [(teacher.id.clone(), teacher)].into(),
+            ..AlgorithmConfig::default()
+        };
+        let individual = Individual::with_chromosomes(
+            [vec![1], vec![2], vec![3], vec![]]
+                .into_iter()
+                .enumerate()
+                .map(|(id, genes)| Chromosome {
+                    id: PeriodId(id),
+                    genes: genes.into_iter().map(TupleId).collect(),
+                })
+                .collect(),
+        );
+
+        // one period over the daily limit on the first day, one over the weekly limit
+        assert_eq!(calculate_fitness(&config, &individual, &tuples), -20);
+        assert_eq!(
+            ConflictReport::explain(&config, &individual, &tuples).adaptation,
+            -20
+        );
+    }
+
+    #[test]
+    fn test_custom_constraint_adds_to_fitness() {
+        let mut tuples: Vec<Tuple> = (1..=3)
+            .map(|id| Tuple {
+                id: TupleId(id),
+                teacher: format!("Teacher {}", id),
+                room: format!("Room {}", id),
+                ..Tuple::default()
+            })
+            .collect();
+        Symbols::intern(&mut tuples);
+        let config = AlgorithmConfig {
+            number_of_periods: 2,
+            ..AlgorithmConfig::default()
+        };
+        let individual = Individual::with_chromosomes(vec![
+            Chromosome {
+                id: PeriodId(0),
+                genes: smallvec![TupleId(1)],
+            },
+            Chromosome {
+                id: PeriodId(1),
+                genes: smallvec![TupleId(2), TupleId(3)],
+            },
+        ]);
+
+        let mut fitness = Fitness::from_config(&config);
+        assert_eq!(fitness.evaluate(&config, &individual, &tuples), 0);
+
+        fitness.constraints.push(Box::new(EmptyLastPeriod));
+        assert_eq!(fitness.evaluate(&config, &individual, &tuples), -14);
+        assert!(fitness
+            .penalties(&config, &individual, &tuples)
+            .contains(&("empty_last_period", 14)));
+    }
+}
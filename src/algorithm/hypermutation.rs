@@ -0,0 +1,88 @@
+use super::config::AlgorithmConfig;
+
+/// Raises the mutation probability when the search stagnates
+///
+/// After `hypermutation_stagnation_generations` generations without improvement of the best
+/// adaptation the mutation probability bursts to `hypermutation_probability`. Every following
+/// generation only `hypermutation_decay` of the raise over `mutation_probability` is kept, so the
+/// probability decays back to its configured value. Counting starts over after a burst.
+#[derive(Debug, Clone)]
+pub struct HypermutationController {
+    base_probability: f32,
+    burst_probability: f32,
+    decay: f32,
+    stagnation_generations: usize,
+    probability: f32,
+    best_adaptation: Option<i32>,
+    generations_without_improvement: usize,
+}
+
+impl HypermutationController {
+    pub fn new(config: &AlgorithmConfig) -> Self {
+        HypermutationController {
+            base_probability: config.mutation_probability,
+            burst_probability: config.hypermutation_probability,
+            decay: config.hypermutation_decay,
+            stagnation_generations: config.hypermutation_stagnation_generations,
+            probability: config.mutation_probability,
+            best_adaptation: None,
+            generations_without_improvement: 0,
+        }
+    }
+
+    /// Mutation probability for the next generation
+    pub fn mutation_probability(&self) -> f32 {
+        self.probability
+    }
+
+    /// Record the best adaptation of a generation, returns whether a burst starts
+    pub fn observe(&mut self, best_adaptation: i32) -> bool {
+        self.probability =
+            self.base_probability + (self.probability - self.base_probability) * self.decay;
+
+        let improved = self
+            .best_adaptation
+            .is_none_or(|previous_best| best_adaptation > previous_best);
+        if improved {
+            self.best_adaptation = Some(best_adaptation);
+            self.generations_without_improvement = 0;
+            return false;
+        }
+
+        self.generations_without_improvement += 1;
+        if self.generations_without_improvement < self.stagnation_generations {
+            return false;
+        }
+
+        self.generations_without_improvement = 0;
+        self.probability = self.burst_probability;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burst_after_stagnation_then_decay() {
+        let config = AlgorithmConfig {
+            mutation_probability: 0.1,
+            hypermutation_stagnation_generations: 2,
+            hypermutation_probability: 0.5,
+            hypermutation_decay: 0.5,
+            ..AlgorithmConfig::default()
+        };
+        let mut controller = HypermutationController::new(&config);
+
+        assert!(!controller.observe(-10));
+        assert!(!controller.observe(-10));
+        assert!(controller.observe(-10));
+        assert_eq!(controller.mutation_probability(), 0.5);
+
+        assert!(!controller.observe(-5));
+        assert!((controller.mutation_probability() - 0.3).abs() < 1e-6);
+        assert!(!controller.observe(-5));
+        assert!((controller.mutation_probability() - 0.2).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+
+use good_lp::{
+    default_solver, variable, Expression, ProblemVariables, ResolutionError, Solution, SolverModel,
+    Variable,
+};
+use itertools::Itertools;
+
+use super::{
+    assignment, calculate_fitness, clash_penalties,
+    config::AlgorithmConfig,
+    constraints,
+    datatypes::{Gene, Individual, Tuple},
+};
+
+/// Move the conflicting classes of the individual by solving an integer program, `None` if that
+/// doesn't make it better
+///
+/// Up to `ilp_max_tuples` unlocked classes that clash or take place in a forbidden period are
+/// freed, the rest of the schedule stays fixed. Every freed class gets a variable for each period
+/// it can start in, and the program minimizes its clashes with the fixed classes and with the
+/// other freed ones, plus the penalties of forbidden periods and blocks overflowing their day.
+///
+/// minilp can't reliably branch on integer variables, so the program is solved by diving: the
+/// linear relaxation is solved, the class closest to starting wholly in one period is fixed there
+/// and the rest solved again, until every class is placed. The other constraints are left out of
+/// the program, so the result is only kept when [`calculate_fitness`] of the whole schedule
+/// improves.
+pub fn refine(
+    config: &AlgorithmConfig,
+    individual: &Individual,
+    tuples: &[Tuple],
+) -> Result<Option<Individual>, ResolutionError> {
+    let number_of_periods = individual.chromosomes.len();
+    let layout = config.period_layout();
+    let assigned = assignment::effective_tuples(config, individual, tuples);
+    let tuples_by_id: HashMap<Gene, &Tuple> =
+        assigned.iter().map(|tuple| (tuple.id, tuple)).collect();
+    let block = |tuple: &Tuple, start: usize| {
+        let block = layout.clamped_block(start, tuple.length());
+        block.start..block.end.min(number_of_periods)
+    };
+    let pair_penalty = |tuple: &Tuple, other: &Tuple| {
+        let (teacher_penalty, room_penalty) = clash_penalties(config, tuple, other);
+        let (other_teacher_penalty, other_room_penalty) = clash_penalties(config, other, tuple);
+        teacher_penalty + room_penalty + other_teacher_penalty + other_room_penalty
+    };
+
+    let active_genes = constraints::active_genes(individual, &assigned, &layout);
+    let freed: Vec<&Tuple> = individual
+        .chromosomes
+        .iter()
+        .enumerate()
+        .flat_map(|(period_id, chromosome)| chromosome.genes.iter().map(move |g| (period_id, *g)))
+        .filter(|(_, gene)| !config.locks.contains_key(gene))
+        .filter_map(|(period_id, gene)| {
+            let tuple = *tuples_by_id.get(&gene)?;
+            let mut periods = block(tuple, period_id);
+            let conflicting = periods.any(|period| {
+                !tuple.can_take_place_in(period)
+                    || active_genes[period]
+                        .iter()
+                        .filter(|other| **other != gene)
+                        .filter_map(|other| tuples_by_id.get(other))
+                        .any(|other| pair_penalty(tuple, other) > 0)
+            });
+            conflicting.then_some(tuple)
+        })
+        .take(config.ilp_max_tuples)
+        .collect();
+    if freed.is_empty() {
+        return Ok(None);
+    }
+
+    let mut refined = individual.clone();
+    for chromosome in &mut refined.chromosomes {
+        chromosome
+            .genes
+            .retain(|gene| !freed.iter().any(|tuple| tuple.id == *gene));
+    }
+    let fixed_genes = constraints::active_genes(&refined, &assigned, &layout);
+
+    // periods every freed class can start in, with the cost of starting there
+    let starts: Vec<Vec<(usize, i32)>> = freed
+        .iter()
+        .map(|tuple| {
+            let allowed: Vec<usize> = (0..number_of_periods)
+                .filter(|period_id| config.can_start_at(tuple, *period_id, &layout))
+                .collect();
+            let candidates = if allowed.is_empty() {
+                (0..number_of_periods).collect()
+            } else {
+                allowed
+            };
+            candidates
+                .into_iter()
+                .map(|start| {
+                    let mut cost: i32 = block(tuple, start)
+                        .flat_map(|period| &fixed_genes[period])
+                        .filter_map(|other| tuples_by_id.get(other))
+                        .map(|other| pair_penalty(tuple, other))
+                        .sum();
+                    cost += block(tuple, start)
+                        .filter(|period| !tuple.can_take_place_in(*period))
+                        .count() as i32
+                        * config.forbidden_period_weight;
+                    if layout.block(start, tuple.length()).is_none() {
+                        cost += config.block_overflow_weight;
+                    }
+                    (start, cost)
+                })
+                .collect()
+        })
+        .collect();
+
+    // a clash of two freed classes in a period costs when both take place in it
+    let mut clashes = Vec::new();
+    for (first, second) in (0..freed.len()).tuple_combinations() {
+        let penalty = pair_penalty(freed[first], freed[second]);
+        if penalty == 0 {
+            continue;
+        }
+
+        for period_id in 0..number_of_periods {
+            let covering = |index: usize| -> Vec<(usize, usize)> {
+                starts[index]
+                    .iter()
+                    .positions(|(start, _)| block(freed[index], *start).contains(&period_id))
+                    .map(|position| (index, position))
+                    .collect()
+            };
+            let both = [covering(first), covering(second)];
+            if both.iter().all(|covering| !covering.is_empty()) {
+                clashes.push((penalty, both.concat()));
+            }
+        }
+    }
+
+    let mut chosen: Vec<Option<usize>> = vec![None; freed.len()];
+    while chosen.iter().any(Option::is_none) {
+        let values = solve_relaxation(&starts, &clashes, &chosen)?;
+        let (index, position) = (0..freed.len())
+            .filter(|index| chosen[*index].is_none())
+            .flat_map(|index| (0..starts[index].len()).map(move |position| (index, position)))
+            .max_by(|a, b| values[a.0][a.1].total_cmp(&values[b.0][b.1]))
+            .unwrap();
+        chosen[index] = Some(position);
+    }
+
+    for ((tuple, starts), position) in freed.iter().zip(&starts).zip(chosen) {
+        let (start, _) = starts[position.unwrap()];
+        refined.chromosomes[start].genes.push(tuple.id);
+    }
+
+    refined.adaptation = calculate_fitness(config, &refined, tuples);
+    Ok((refined.adaptation > individual.adaptation).then_some(refined))
+}
+
+/// Solve the linear relaxation of the program with the chosen starts fixed, returning how much of
+/// every class starts in each of its periods
+///
+/// `starts` are the periods and costs of every class, `clashes` the penalties of classes taking
+/// place in a period together, with the `(class, start)` positions covering the period.
+fn solve_relaxation(
+    starts: &[Vec<(usize, i32)>],
+    clashes: &[(i32, Vec<(usize, usize)>)],
+    chosen: &[Option<usize>],
+) -> Result<Vec<Vec<f64>>, ResolutionError> {
+    let mut variables = ProblemVariables::new();
+    let placed: Vec<Vec<Variable>> = starts
+        .iter()
+        .zip(chosen)
+        .map(|(starts, chosen)| {
+            (0..starts.len())
+                .map(|position| match chosen {
+                    Some(chosen) => {
+                        let fixed = if *chosen == position { 1 } else { 0 };
+                        variables.add(variable().min(fixed).max(fixed))
+                    }
+                    None => variables.add(variable().min(0).max(1)),
+                })
+                .collect()
+        })
+        .collect();
+    let overlaps: Vec<Variable> = clashes
+        .iter()
+        .map(|_| variables.add(variable().min(0)))
+        .collect();
+
+    let mut objective = Expression::from(0);
+    for (starts, placed) in starts.iter().zip(&placed) {
+        for ((_, cost), placed) in starts.iter().zip(placed) {
+            objective += *cost * *placed;
+        }
+    }
+    for ((penalty, _), overlap) in clashes.iter().zip(&overlaps) {
+        objective += *penalty * *overlap;
+    }
+
+    let mut problem = variables.minimise(objective).using(default_solver);
+    for placed in &placed {
+        let total: Expression = placed.iter().sum();
+        problem = problem.with(total.eq(1));
+    }
+    for ((_, covering), overlap) in clashes.iter().zip(&overlaps) {
+        let both: Expression = covering
+            .iter()
+            .map(|(index, position)| placed[*index][*position])
+            .sum();
+        problem = problem.with((both - 1).leq(*overlap));
+    }
+    let solution = problem.solve()?;
+
+    Ok(placed
+        .iter()
+        .map(|placed| placed.iter().map(|v| solution.value(*v)).collect())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::datatypes::{Chromosome, PeriodId, TupleId};
+    use smallvec::smallvec;
+
+    #[test]
+    fn test_clashing_classes_are_moved_apart() {
+        let tuples: Vec<Tuple> = (1..=3)
+            .map(|id| Tuple {
+                id: TupleId(id),
+                label: format!("Class {}", id),
+                teacher: "Smith".to_string(),
+                room: format!("Room {}", id),
+                ..Tuple::default()
+            })
+            .collect();
+        let config = AlgorithmConfig {
+            number_of_periods: 3,
+            ..AlgorithmConfig::default()
+        };
+        let mut individual = Individual::with_chromosomes(vec![
+            Chromosome {
+                id: PeriodId(0),
+                genes: smallvec![TupleId(1), TupleId(2), TupleId(3)],
+            },
+            Chromosome::new(PeriodId(1)),
+            Chromosome::new(PeriodId(2)),
+        ]);
+        individual.adaptation = calculate_fitness(&config, &individual, &tuples);
+
+        let refined = refine(&config, &individual, &tuples).unwrap().unwrap();
+
+        assert_eq!(refined.adaptation, 0);
+        assert!(refined
+            .chromosomes
+            .iter()
+            .all(|chromosome| chromosome.genes.len() == 1));
+    }
+}
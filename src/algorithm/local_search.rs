@@ -0,0 +1,192 @@
+use std::cmp::Reverse;
+
+use rand::prelude::*;
+use rand::seq::IteratorRandom;
+use rayon::prelude::*;
+
+use super::{
+    calculate_fitness,
+    config::AlgorithmConfig,
+    datatypes::{Individual, Population, Tuple},
+    random::{get_random_generator, PlannerRng},
+};
+
+/// Improve the `local_search_elites` best individuals of the population with [`hill_climb`]
+///
+/// The individuals are improved in parallel, each with its own generator seeded from `rng`, so the
+/// result doesn't depend on thread scheduling. Adaptations of the population must be up to date.
+pub fn improve_elites(
+    config: &AlgorithmConfig,
+    population: &mut Population,
+    tuples: &[Tuple],
+    rng: &mut PlannerRng,
+) {
+    let count = config.local_search_elites.min(population.len());
+    if count == 0 {
+        return;
+    }
+
+    population.sort_by_key(|individual| Reverse(individual.adaptation));
+    let seeds: Vec<u64> = (0..count).map(|_| rng.next_u64()).collect();
+
+    population[..count]
+        .par_iter_mut()
+        .zip(seeds)
+        .for_each(|(individual, seed)| {
+            let mut rng = get_random_generator(seed);
+            hill_climb(config, individual, tuples, &mut rng);
+        });
+}
+
+/// Try `local_search_iterations` random neighbours of the individual, keeping those that are not
+/// worse
+///
+/// A neighbour either moves a gene to another period or swaps two genes of different periods,
+/// each with the same probability. Locked genes never move and genes only move to periods their
+/// tuples can start in. Accepting equally good neighbours lets the search cross plateaus.
+///
+/// Neighbours are evaluated by [`Individual::apply_move`] with
+/// [`AlgorithmConfig::incremental_conflicts`], otherwise as a whole.
+pub fn hill_climb(
+    config: &AlgorithmConfig,
+    individual: &mut Individual,
+    tuples: &[Tuple],
+    rng: &mut impl Rng,
+) {
+    let number_of_periods = individual.chromosomes.len();
+    let layout = config.period_layout();
+    let matrix = config.incremental_conflicts();
+    let move_gene = |individual: &mut Individual, from, index, to| match matrix {
+        Some(matrix) => {
+            individual.apply_move(from, index, to, matrix);
+        }
+        None => {
+            individual.move_gene(from, index, to);
+        }
+    };
+    let can_start_at = |gene, period_id| {
+        tuples
+            .iter()
+            .find(|tuple| tuple.id == gene)
+            .is_none_or(|tuple| config.can_start_at(tuple, period_id, &layout))
+    };
+
+    // positions of the genes allowed to move, as (period, index in the period)
+    let movable = |individual: &Individual| -> Vec<(usize, usize)> {
+        individual
+            .chromosomes
+            .iter()
+            .enumerate()
+            .flat_map(|(period_id, chromosome)| {
+                chromosome
+                    .genes
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, gene)| !config.locks.contains_key(gene))
+                    .map(move |(index, _)| (period_id, index))
+            })
+            .collect()
+    };
+
+    for _ in 0..config.local_search_iterations {
+        let positions = movable(individual);
+        let Some(&(period_id, index)) = positions.choose(rng) else {
+            return;
+        };
+        let gene = individual.chromosomes[period_id].genes[index];
+        let mut neighbour = individual.clone();
+
+        if rng.gen_bool(0.5) {
+            let Some(target_id) = (0..number_of_periods)
+                .filter(|target_id| *target_id != period_id && can_start_at(gene, *target_id))
+                .choose(rng)
+            else {
+                continue;
+            };
+
+            move_gene(&mut neighbour, period_id, index, target_id);
+        } else {
+            let Some(&(other_period_id, other_index)) = positions
+                .iter()
+                .filter(|(other_period_id, _)| *other_period_id != period_id)
+                .choose(rng)
+            else {
+                continue;
+            };
+            let other_gene = individual.chromosomes[other_period_id].genes[other_index];
+            if !can_start_at(gene, other_period_id) || !can_start_at(other_gene, period_id) {
+                continue;
+            }
+
+            move_gene(&mut neighbour, period_id, index, other_period_id);
+            move_gene(&mut neighbour, other_period_id, other_index, period_id);
+        }
+
+        if matrix.is_none() {
+            neighbour.adaptation = calculate_fitness(config, &neighbour, tuples);
+        }
+        if neighbour.adaptation >= individual.adaptation {
+            *individual = neighbour;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::datatypes::{Chromosome, PeriodId, TupleId};
+    use smallvec::smallvec;
+
+    #[test]
+    fn test_hill_climbing_never_gets_worse() {
+        let tuples: Vec<Tuple> = (1..=6)
+            .map(|id| Tuple {
+                id: TupleId(id),
+                room: format!("Room {}", id % 2),
+                teacher: format!("Teacher {}", id % 3),
+                ..Tuple::default()
+            })
+            .collect();
+        let config = AlgorithmConfig {
+            number_of_periods: 3,
+            local_search_iterations: 200,
+            ..AlgorithmConfig::default()
+        };
+        let mut individual = Individual::with_chromosomes(vec![
+            Chromosome {
+                id: PeriodId(0),
+                genes: (1..=6).map(TupleId).collect(),
+            },
+            Chromosome {
+                id: PeriodId(1),
+                genes: smallvec![],
+            },
+            Chromosome {
+                id: PeriodId(2),
+                genes: smallvec![],
+            },
+        ]);
+        individual.adaptation = calculate_fitness(&config, &individual, &tuples);
+        let initial_adaptation = individual.adaptation;
+
+        hill_climb(
+            &config,
+            &mut individual,
+            &tuples,
+            &mut get_random_generator(5),
+        );
+
+        assert!(individual.adaptation > initial_adaptation);
+        assert_eq!(
+            individual.adaptation,
+            calculate_fitness(&config, &individual, &tuples)
+        );
+        let mut genes: Vec<TupleId> = individual
+            .chromosomes
+            .iter()
+            .flat_map(|chromosome| chromosome.genes.clone())
+            .collect();
+        genes.sort_unstable();
+        assert_eq!(genes, (1..=6).map(TupleId).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,222 @@
+use serde::{Deserialize, Serialize};
+
+use super::datatypes::{Gene, Individual};
+
+/// Adaptation statistics of a population, see [`PopulationMetrics::stats`]
+///
+/// An empty population has every adaptation at [`i32::MIN`] and a mean and deviation of 0, so
+/// the best adaptation can still be reduced with a maximum across nodes.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct PopulationStats {
+    pub size: usize,
+    pub best: i32,
+    pub worst: i32,
+    pub mean: f64,
+    /// Population standard deviation of the adaptations
+    pub std_dev: f64,
+    /// Middle adaptation, the better of the two middle ones for even sizes
+    pub median: i32,
+}
+
+/// Summary measures of a population
+///
+/// Implemented for slices, so it can be called on a
+/// [`Population`](super::datatypes::Population) directly.
+pub trait PopulationMetrics {
+    /// How many individuals put each of the `genes` in each of `number_of_periods` periods,
+    /// flattened gene by gene
+    ///
+    /// Counts of different nodes can be summed element-wise, see [`placement_entropy`].
+    fn placement_counts(&self, genes: &[Gene], number_of_periods: usize) -> Vec<u64>;
+
+    /// Mean normalized entropy of the periods each gene is placed in, see [`placement_entropy`]
+    ///
+    /// 0 means all individuals place every gene in the same period, 1 means every gene is spread
+    /// evenly across all periods.
+    fn diversity(&self) -> f64;
+
+    /// Best, worst, mean, standard deviation and median of the adaptations, in linear time
+    fn stats(&self) -> PopulationStats;
+}
+
+impl PopulationMetrics for [Individual] {
+    fn placement_counts(&self, genes: &[Gene], number_of_periods: usize) -> Vec<u64> {
+        let gene_indices: std::collections::HashMap<Gene, usize> = genes
+            .iter()
+            .enumerate()
+            .map(|(index, gene)| (*gene, index))
+            .collect();
+        let mut counts = vec![0; genes.len() * number_of_periods];
+
+        for individual in self {
+            for (period_id, chromosome) in individual
+                .chromosomes
+                .iter()
+                .enumerate()
+                .take(number_of_periods)
+            {
+                for gene in &chromosome.genes {
+                    if let Some(index) = gene_indices.get(gene) {
+                        counts[index * number_of_periods + period_id] += 1;
+                    }
+                }
+            }
+        }
+
+        counts
+    }
+
+    fn diversity(&self) -> f64 {
+        let Some(first) = self.first() else {
+            return 0.0;
+        };
+
+        let mut genes: Vec<Gene> = first
+            .chromosomes
+            .iter()
+            .flat_map(|chromosome| chromosome.genes.iter().copied())
+            .collect();
+        genes.sort_unstable();
+        genes.dedup();
+
+        let number_of_periods = first.chromosomes.len();
+        placement_entropy(
+            &self.placement_counts(&genes, number_of_periods),
+            number_of_periods,
+        )
+    }
+
+    fn stats(&self) -> PopulationStats {
+        let mut adaptations: Vec<i32> = self
+            .iter()
+            .map(|individual| individual.adaptation)
+            .collect();
+        if adaptations.is_empty() {
+            return PopulationStats {
+                size: 0,
+                best: i32::MIN,
+                worst: i32::MIN,
+                mean: 0.0,
+                std_dev: 0.0,
+                median: i32::MIN,
+            };
+        }
+
+        let size = adaptations.len();
+        let mean = adaptations.iter().map(|a| *a as f64).sum::<f64>() / size as f64;
+        let variance = adaptations
+            .iter()
+            .map(|a| (*a as f64 - mean).powi(2))
+            .sum::<f64>()
+            / size as f64;
+        let best = *adaptations.iter().max().unwrap();
+        let worst = *adaptations.iter().min().unwrap();
+        let (_, median, _) = adaptations.select_nth_unstable(size / 2);
+
+        PopulationStats {
+            size,
+            best,
+            worst,
+            mean,
+            std_dev: variance.sqrt(),
+            median: *median,
+        }
+    }
+}
+
+/// Mean over genes of the entropy of their placement counts, normalized by `ln(number_of_periods)`
+///
+/// Genes nobody places are skipped. Returns 0 for a single period or no counts at all.
+pub fn placement_entropy(counts: &[u64], number_of_periods: usize) -> f64 {
+    if number_of_periods < 2 {
+        return 0.0;
+    }
+
+    let entropies: Vec<f64> = counts
+        .chunks(number_of_periods)
+        .filter_map(|gene_counts| {
+            let total: u64 = gene_counts.iter().sum();
+            (total > 0).then(|| {
+                gene_counts
+                    .iter()
+                    .filter(|count| **count > 0)
+                    .map(|count| {
+                        let p = *count as f64 / total as f64;
+                        -p * p.ln()
+                    })
+                    .sum::<f64>()
+            })
+        })
+        .collect();
+
+    if entropies.is_empty() {
+        return 0.0;
+    }
+    entropies.iter().sum::<f64>() / entropies.len() as f64 / (number_of_periods as f64).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::datatypes::{Chromosome, PeriodId, TupleId};
+
+    fn individual(periods: [Vec<u16>; 2]) -> Individual {
+        Individual::with_chromosomes(
+            periods
+                .into_iter()
+                .enumerate()
+                .map(|(id, genes)| Chromosome {
+                    id: PeriodId(id),
+                    genes: genes.into_iter().map(TupleId).collect(),
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_stats_of_adaptations() {
+        let population: Vec<Individual> = [-4, 0, -2, -6]
+            .into_iter()
+            .map(|adaptation| Individual {
+                adaptation,
+                ..Individual::default()
+            })
+            .collect();
+
+        let stats = population.stats();
+        assert_eq!(
+            stats,
+            PopulationStats {
+                size: 4,
+                best: 0,
+                worst: -6,
+                mean: -3.0,
+                std_dev: 5.0f64.sqrt(),
+                median: -2,
+            }
+        );
+        assert_eq!(Vec::<Individual>::new().stats().best, i32::MIN);
+    }
+
+    #[test]
+    fn test_diversity_of_converged_and_spread_populations() {
+        let converged = vec![individual([vec![1], vec![2]]); 4];
+        assert_eq!(converged.diversity(), 0.0);
+
+        let spread = [
+            individual([vec![1], vec![2]]),
+            individual([vec![2], vec![1]]),
+        ];
+        assert!((spread.diversity() - 1.0).abs() < 1e-9);
+
+        // counts of two halves add up to the counts of the whole
+        let genes = [1, 2].map(TupleId);
+        let counts: Vec<u64> = std::iter::zip(
+            spread[..1].placement_counts(&genes, 2),
+            spread[1..].placement_counts(&genes, 2),
+        )
+        .map(|(a, b)| a + b)
+        .collect();
+        assert_eq!(counts, spread.placement_counts(&genes, 2));
+    }
+}
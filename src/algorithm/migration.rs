@@ -0,0 +1,221 @@
+use std::{cmp::Reverse, time::Duration};
+
+use rand::{seq::index, Rng};
+
+use super::{
+    config::{AlgorithmConfig, EmigrantPolicy, MigrationTopology, ReplacementPolicy},
+    datatypes::{Individual, Population},
+};
+
+/// Migration settings of an island, taken from the `*migration_*`, `emigrant_policy` and
+/// `replacement_policy` fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IslandConfig {
+    /// Every how many generations migrants are exchanged, 0 disables migration
+    ///
+    /// Only between nodes when `node_interval` is set
+    pub interval: usize,
+    /// Every how many generations migrants are exchanged within the node, 0 disables the hierarchy
+    pub node_interval: usize,
+    /// How many individuals leave for each neighbour, and at most arrive
+    pub size: usize,
+    pub topology: MigrationTopology,
+    pub emigrant_policy: EmigrantPolicy,
+    /// Individuals in every tournament of [`EmigrantPolicy::Tournament`]
+    pub tournament_size: usize,
+    pub replacement_policy: ReplacementPolicy,
+    /// How long to wait for the neighbours after breeding, forever without one
+    pub timeout: Option<Duration>,
+}
+
+impl IslandConfig {
+    pub fn from_config(config: &AlgorithmConfig) -> Self {
+        IslandConfig {
+            interval: config.migration_interval,
+            node_interval: config.node_migration_interval,
+            size: config.migration_size,
+            topology: config.migration_topology,
+            emigrant_policy: config.emigrant_policy,
+            tournament_size: config.migration_tournament_size,
+            replacement_policy: config.replacement_policy,
+            timeout: (config.migration_timeout > 0.0)
+                .then(|| Duration::from_secs_f64(config.migration_timeout)),
+        }
+    }
+
+    /// Whether migrants are exchanged at the end of the generation
+    pub fn migrates_after(&self, generation_number: usize) -> bool {
+        self.interval > 0 && (generation_number + 1).is_multiple_of(self.interval)
+    }
+
+    /// Whether migrants are exchanged within the node at the end of the generation
+    ///
+    /// Generations migrating between nodes don't migrate within them too.
+    pub fn migrates_within_node_after(&self, generation_number: usize) -> bool {
+        self.is_hierarchical()
+            && (generation_number + 1).is_multiple_of(self.node_interval)
+            && !self.migrates_after(generation_number)
+    }
+
+    /// Whether islands migrate within nodes and between them at different intervals
+    pub fn is_hierarchical(&self) -> bool {
+        self.node_interval > 0
+    }
+
+    /// Pick the individuals sent to the neighbours, each at most once
+    pub fn select_emigrants(&self, population: &Population, rng: &mut impl Rng) -> Vec<Individual> {
+        let count = self.size.min(population.len());
+
+        match self.emigrant_policy {
+            EmigrantPolicy::Best => {
+                let mut emigrants = population.clone();
+                emigrants.sort_by_key(|individual| Reverse(individual.adaptation));
+                emigrants.truncate(count);
+                emigrants
+            }
+            EmigrantPolicy::Random => index::sample(rng, population.len(), count)
+                .into_iter()
+                .map(|index| population[index].clone())
+                .collect(),
+            EmigrantPolicy::Tournament => {
+                let mut candidates: Vec<usize> = (0..population.len()).collect();
+                (0..count)
+                    .map(|_| {
+                        let winner = (0..self.tournament_size.max(1))
+                            .map(|_| rng.gen_range(0..candidates.len()))
+                            .max_by_key(|position| population[candidates[*position]].adaptation)
+                            .unwrap();
+                        population[candidates.swap_remove(winner)].clone()
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Put the best of the arrived immigrants into the population, at most `size` of them
+    ///
+    /// [`ReplacementPolicy::Random`] never replaces the best local individual.
+    pub fn place_immigrants(
+        &self,
+        population: &mut Population,
+        mut immigrants: Vec<Individual>,
+        rng: &mut impl Rng,
+    ) {
+        immigrants.sort_by_key(|individual| Reverse(individual.adaptation));
+        immigrants.truncate(self.size.min(population.len()));
+        population.sort_by_key(|individual| Reverse(individual.adaptation));
+
+        let slots: Vec<usize> = match self.replacement_policy {
+            ReplacementPolicy::Worst => {
+                (population.len() - immigrants.len()..population.len()).collect()
+            }
+            ReplacementPolicy::Random => {
+                let candidates = population.len().saturating_sub(1);
+                index::sample(rng, candidates, immigrants.len().min(candidates))
+                    .into_iter()
+                    .map(|index| index + 1)
+                    .collect()
+            }
+        };
+        for (slot, immigrant) in slots.into_iter().zip(immigrants) {
+            population[slot] = immigrant;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::random::get_random_generator;
+
+    fn population(adaptations: &[i32]) -> Population {
+        adaptations
+            .iter()
+            .map(|adaptation| Individual {
+                adaptation: *adaptation,
+                ..Individual::default()
+            })
+            .collect()
+    }
+
+    fn adaptations(population: &[Individual]) -> Vec<i32> {
+        population
+            .iter()
+            .map(|individual| individual.adaptation)
+            .collect()
+    }
+
+    #[test]
+    fn test_policies_pick_distinct_migrants() {
+        let config = AlgorithmConfig {
+            migration_size: 3,
+            ..AlgorithmConfig::default()
+        };
+        let population = population(&[-5, -1, -9, -3, -7, -2]);
+        let mut rng = get_random_generator(1);
+
+        let best = IslandConfig::from_config(&config).select_emigrants(&population, &mut rng);
+        assert_eq!(adaptations(&best), vec![-1, -2, -3]);
+
+        for emigrant_policy in [EmigrantPolicy::Random, EmigrantPolicy::Tournament] {
+            let island = IslandConfig {
+                emigrant_policy,
+                ..IslandConfig::from_config(&config)
+            };
+            let mut emigrants = adaptations(&island.select_emigrants(&population, &mut rng));
+            emigrants.sort_unstable();
+            emigrants.dedup();
+            assert_eq!(emigrants.len(), 3);
+        }
+    }
+
+    #[test]
+    fn test_node_migrations_fill_the_generations_between_nodes() {
+        let config = AlgorithmConfig {
+            migration_interval: 6,
+            node_migration_interval: 2,
+            ..AlgorithmConfig::default()
+        };
+        let island = IslandConfig::from_config(&config);
+
+        let between_nodes: Vec<usize> = (0..12).filter(|gen| island.migrates_after(*gen)).collect();
+        let within_nodes: Vec<usize> = (0..12)
+            .filter(|gen| island.migrates_within_node_after(*gen))
+            .collect();
+        assert_eq!(between_nodes, vec![5, 11]);
+        assert_eq!(within_nodes, vec![1, 3, 7, 9]);
+
+        let flat = IslandConfig::from_config(&AlgorithmConfig::default());
+        assert!(!flat.is_hierarchical());
+        assert!(!(0..20).any(|gen| flat.migrates_within_node_after(gen)));
+    }
+
+    #[test]
+    fn test_immigrants_replace_worst_or_random_but_not_best() {
+        let config = AlgorithmConfig {
+            migration_size: 2,
+            ..AlgorithmConfig::default()
+        };
+        let island = IslandConfig::from_config(&config);
+        let mut rng = get_random_generator(2);
+
+        let mut local = population(&[-4, -1, -3, -2]);
+        island.place_immigrants(&mut local, population(&[-10, -6, -8]), &mut rng);
+        assert_eq!(adaptations(&local), vec![-1, -2, -6, -8]);
+
+        let island = IslandConfig {
+            replacement_policy: ReplacementPolicy::Random,
+            ..island
+        };
+        let mut local = population(&[-4, -1, -3, -2]);
+        island.place_immigrants(&mut local, population(&[-10, -6, -8]), &mut rng);
+        assert_eq!(local[0].adaptation, -1);
+        assert_eq!(
+            adaptations(&local)
+                .iter()
+                .filter(|adaptation| [-6, -8].contains(*adaptation))
+                .count(),
+            2
+        );
+    }
+}
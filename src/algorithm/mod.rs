@@ -2,59 +2,420 @@ use itertools::Itertools;
 use rand::distributions::WeightedIndex;
 use rand::prelude::*;
 use rand::seq::IteratorRandom;
-use rayon::prelude::*;
-use std::cmp::min;
+use std::cmp::{min, Reverse};
+use std::collections::{HashMap, HashSet};
 
 use rand::Rng;
 
 use self::{
-    config::AlgorithmConfig,
-    datatypes::{Chromosome, Individual, Population, Tuple},
-    random::get_random_generator,
+    config::{AlgorithmConfig, InitialPopulationStrategy, RepairStrategy},
+    datatypes::{Chromosome, Gene, Individual, Locks, PeriodId, Population, Tuple},
+    fitness::Fitness,
+    periods::PeriodLayout,
+    propagation::Domains,
 };
 
+pub mod annealing;
+pub mod assignment;
+pub mod chc;
 pub mod config;
+pub mod conflicts;
+pub mod constraints;
 pub mod datatypes;
-mod random;
+pub mod fitness;
+pub mod hypermutation;
+#[cfg(feature = "ilp")]
+pub mod ilp;
+pub mod local_search;
+pub mod metrics;
+pub mod migration;
+pub mod occupancy;
+pub mod operators;
+pub mod pareto;
+pub mod penalties;
+pub mod periods;
+pub mod pool;
+pub mod population_size;
+pub mod propagation;
+pub mod random;
+pub mod report;
+pub mod resources;
+pub mod selection;
+pub mod sources;
+pub mod symbols;
+pub mod synthetic;
+pub mod tabu;
+pub mod xhstt;
 
 /// Create a first population
 ///
 /// Create a population of size `population_size` with each individual having `number_of_periods`
 /// periods.
-/// Then assign tuple to a random period of individual
-pub fn create_first_population(config: &AlgorithmConfig, tuples: &[Tuple]) -> Population {
+/// Then assign tuple to a random period of individual. With
+/// [`InitialPopulationStrategy::Greedy`] a `greedy_population_fraction` of the individuals is
+/// built with [`create_greedy_individual`] instead, with [`InitialPopulationStrategy::Balanced`]
+/// all of them with [`create_balanced_individual`]. Locked tuples always go to their periods.
+pub fn create_first_population(
+    config: &AlgorithmConfig,
+    tuples: &[Tuple],
+    rng: &mut impl Rng,
+) -> Population {
     let AlgorithmConfig {
         population_size,
-        number_of_periods,
+        initial_population_strategy,
+        greedy_population_fraction,
         ..
-    } = config.to_owned();
+    } = *config;
+
+    let greedy_count = (population_size as f32 * greedy_population_fraction).round() as usize;
 
-    let mut population = Population::with_capacity(population_size);
+    (0..population_size)
+        .map(|index| {
+            let strategy = match initial_population_strategy {
+                InitialPopulationStrategy::Greedy if index >= greedy_count => {
+                    InitialPopulationStrategy::Random
+                }
+                strategy => strategy,
+            };
+            create_individual(config, tuples, strategy, rng)
+        })
+        .collect()
+}
+
+/// Create a single individual of the first population with the given strategy, with locks
+/// applied and random rooms and teachers for the assignments turned on
+pub fn create_individual(
+    config: &AlgorithmConfig,
+    tuples: &[Tuple],
+    strategy: InitialPopulationStrategy,
+    rng: &mut impl Rng,
+) -> Individual {
+    let layout = config.period_layout();
+
+    let mut individual = match strategy {
+        InitialPopulationStrategy::Random => create_random_individual(config, tuples, &layout, rng),
+        InitialPopulationStrategy::Greedy => create_greedy_individual(config, tuples, &layout, rng),
+        InitialPopulationStrategy::Balanced => {
+            create_balanced_individual(config, tuples, &layout, rng)
+        }
+    };
+    apply_locks(&mut individual, &config.locks);
+    if config.assigns_resources() {
+        assignment::assign_randomly(config, &mut individual, tuples, rng);
+    }
+    individual
+}
 
-    let mut rng = get_random_generator();
+/// Replace the worst `immigrant_fraction` of the population with new random individuals
+///
+/// Random immigrants bring back genetic material lost to selection, keeping the population
+/// diverse on long runs. They are evaluated right away.
+pub fn add_random_immigrants(
+    config: &AlgorithmConfig,
+    population: &mut Population,
+    tuples: &[Tuple],
+    rng: &mut impl Rng,
+) {
+    let count = (population.len() as f32 * config.immigrant_fraction).round() as usize;
+    if count == 0 {
+        return;
+    }
 
-    for _ in 0..population_size {
-        let mut individual: Individual = Individual::new(number_of_periods);
+    population.sort_by_key(|individual| Reverse(individual.adaptation));
+    let offset = population.len() - count.min(population.len());
+    for slot in &mut population[offset..] {
+        let mut immigrant =
+            create_individual(config, tuples, InitialPopulationStrategy::Random, rng);
+        immigrant.adaptation = calculate_fitness(config, &immigrant, tuples);
+        *slot = immigrant;
+    }
+}
 
-        // create periods
-        for period_id in 0..number_of_periods {
-            let period = Chromosome::new(period_id.try_into().unwrap());
+/// Replace the last individuals of the population with the given ones
+///
+/// Used to seed the population with individuals coming from outside the algorithm. Their
+/// adaptation is recalculated as it may have been computed by a different fitness setup. Only as
+/// many as the population holds are injected, the first ones. Returns how many were left out.
+pub fn inject_individuals(
+    config: &AlgorithmConfig,
+    population: &mut Population,
+    mut individuals: Vec<Individual>,
+    tuples: &[Tuple],
+) -> usize {
+    let left_out = individuals.len().saturating_sub(population.len());
+    individuals.truncate(population.len());
+    let offset = population.len() - individuals.len();
+
+    for (slot, mut individual) in population[offset..].iter_mut().zip(individuals) {
+        individual.adaptation = calculate_fitness(config, &individual, tuples);
+        *slot = individual;
+    }
+    left_out
+}
 
-            individual.chromosomes.push(period);
+/// Replace copies of a schedule beyond `max_duplicate_copies` with mutated copies
+///
+/// Schedules are compared by [`Individual::placement_hash`]. The replacements are mutated with
+/// [`mutate`] until their placement changes (at most a few attempts) and evaluated, unless mutation
+/// already keeps the adaptation up to date. Returns the
+/// number of replaced individuals, a high number means the population is converging.
+pub fn deduplicate_population(
+    config: &AlgorithmConfig,
+    population: &mut Population,
+    tuples: &[Tuple],
+    rng: &mut impl Rng,
+) -> usize {
+    const MUTATION_ATTEMPTS: usize = 5;
+
+    let mut copies: HashMap<u64, usize> = HashMap::new();
+    let mut replaced = 0;
+
+    for individual in population.iter_mut() {
+        let count = copies.entry(individual.placement_hash()).or_default();
+        *count += 1;
+        if *count <= config.max_duplicate_copies {
+            continue;
         }
 
-        // assign tuple to a random period from individual
-        for tuple in tuples {
-            let random_period_index = rng.gen_range(0..number_of_periods);
-            individual.chromosomes[random_period_index]
-                .genes
-                .push(tuple.id);
+        let original_hash = individual.placement_hash();
+        for _ in 0..MUTATION_ATTEMPTS {
+            mutate(config, individual, tuples, rng);
+            if individual.placement_hash() != original_hash {
+                break;
+            }
+        }
+        if config.incremental_conflicts().is_none() {
+            individual.adaptation = calculate_fitness(config, individual, tuples);
         }
+        replaced += 1;
+    }
 
-        population.push(individual)
+    replaced
+}
+
+/// Replace a generation with its evaluated offspring, keeping the population size
+///
+/// If there are fewer children than individuals, the best parents fill the remaining places
+/// (elitism). If there are more, only the best children survive.
+///
+/// Returns the individuals that didn't survive, e.g. to recycle them in a
+/// [`IndividualPool`](pool::IndividualPool).
+pub fn replace_generation(population: &mut Population, mut offspring: Population) -> Population {
+    let size = population.len();
+
+    if offspring.len() >= size {
+        let mut replaced = if offspring.len() > size {
+            offspring.sort_by_key(|individual| Reverse(individual.adaptation));
+            offspring.split_off(size)
+        } else {
+            Vec::new()
+        };
+        replaced.append(population);
+        *population = offspring;
+        return replaced;
     }
 
-    population
+    population.sort_by_key(|individual| Reverse(individual.adaptation));
+    let replaced = population.split_off(size - offspring.len());
+    population.extend(offspring);
+    replaced
+}
+
+/// Create an individual with `number_of_periods` empty periods
+fn create_empty_individual(number_of_periods: usize) -> Individual {
+    Individual::with_chromosomes(
+        (0..number_of_periods)
+            .map(|period_id| Chromosome::new(PeriodId(period_id)))
+            .collect(),
+    )
+}
+
+/// Create an individual with every tuple assigned to a random period it can start in
+fn create_random_individual(
+    config: &AlgorithmConfig,
+    tuples: &[Tuple],
+    layout: &PeriodLayout,
+    rng: &mut impl Rng,
+) -> Individual {
+    let mut individual = create_empty_individual(config.number_of_periods);
+
+    for tuple in tuples {
+        let random_period_index = random_start_period(config, tuple, layout, rng);
+        individual.chromosomes[random_period_index]
+            .genes
+            .push(tuple.id);
+    }
+
+    individual
+}
+
+/// Random period the tuple can start in (see [`AlgorithmConfig::can_start_at`]), any period if
+/// there is none
+fn random_start_period(
+    config: &AlgorithmConfig,
+    tuple: &Tuple,
+    layout: &PeriodLayout,
+    rng: &mut impl Rng,
+) -> usize {
+    let number_of_periods = config.number_of_periods;
+    if let Some(starts) = config.domains.as_deref().and_then(|d| d.starts(tuple.id)) {
+        return match starts.choose(rng) {
+            Some(period_id) => *period_id,
+            None => rng.gen_range(0..number_of_periods),
+        };
+    }
+    if tuple.forbidden_periods.is_empty() && tuple.length() == 1 {
+        return rng.gen_range(0..number_of_periods);
+    }
+
+    (0..number_of_periods)
+        .filter(|period_id| tuple.can_start_at(*period_id, layout))
+        .choose(rng)
+        .unwrap_or_else(|| rng.gen_range(0..number_of_periods))
+}
+
+/// Create an individual with a greedy conflict-avoiding placement
+///
+/// Tuples are visited in a random order and each one is placed in the period where it clashes
+/// (see [`Tuple::clashes_with`]) with the fewest already placed tuples. Ties are broken randomly,
+/// so greedy individuals still differ from each other. Locked tuples are placed in their periods.
+/// Clashes of classes longer than one period are counted over their whole block.
+fn create_greedy_individual(
+    config: &AlgorithmConfig,
+    tuples: &[Tuple],
+    layout: &PeriodLayout,
+    rng: &mut impl Rng,
+) -> Individual {
+    let AlgorithmConfig {
+        number_of_periods,
+        ref locks,
+        ..
+    } = *config;
+    let mut individual = create_empty_individual(number_of_periods);
+    let mut placed: Vec<Vec<&Tuple>> = vec![Vec::new(); number_of_periods];
+
+    let mut order: Vec<&Tuple> = tuples.iter().collect();
+    order.shuffle(rng);
+
+    for tuple in order {
+        let block = |period_id: usize| {
+            let block = layout.clamped_block(period_id, tuple.length());
+            block.start..block.end.min(number_of_periods)
+        };
+
+        if let Some(period_id) = locks.get(&tuple.id).filter(|p| **p < number_of_periods) {
+            for active_period in block(*period_id) {
+                placed[active_period].push(tuple);
+            }
+            individual.chromosomes[*period_id].genes.push(tuple.id);
+            continue;
+        }
+
+        let clash_counts: Vec<usize> = (0..number_of_periods)
+            .map(|period_id| {
+                block(period_id)
+                    .flat_map(|active_period| placed[active_period].iter())
+                    .filter(|other| tuple.clashes_with(other))
+                    .count()
+            })
+            .collect();
+
+        let candidates = start_candidates(config, tuple, layout);
+        let fewest_clashes = candidates
+            .iter()
+            .map(|period_id| clash_counts[*period_id])
+            .min()
+            .unwrap();
+
+        let period_id = candidates
+            .into_iter()
+            .filter(|period_id| clash_counts[*period_id] == fewest_clashes)
+            .choose(rng)
+            .unwrap();
+
+        for active_period in block(period_id) {
+            placed[active_period].push(tuple);
+        }
+        individual.chromosomes[period_id].genes.push(tuple.id);
+    }
+
+    individual
+}
+
+/// Create an individual with the classes spread evenly over the periods
+///
+/// Tuples are visited in a random order and each one is placed in a period where the busiest
+/// period of its block has the fewest classes so far, ties are broken randomly. Locked tuples are
+/// placed in their periods first, so the others fill up around them.
+fn create_balanced_individual(
+    config: &AlgorithmConfig,
+    tuples: &[Tuple],
+    layout: &PeriodLayout,
+    rng: &mut impl Rng,
+) -> Individual {
+    let AlgorithmConfig {
+        number_of_periods,
+        ref locks,
+        ..
+    } = *config;
+    let mut individual = create_empty_individual(number_of_periods);
+    let mut classes = vec![0; number_of_periods];
+
+    let mut order: Vec<&Tuple> = tuples.iter().collect();
+    order.shuffle(rng);
+    order.sort_by_key(|tuple| !locks.contains_key(&tuple.id));
+
+    for tuple in order {
+        let block = |period_id: usize| {
+            let block = layout.clamped_block(period_id, tuple.length());
+            block.start..block.end.min(number_of_periods)
+        };
+        let busiest = |period_id: usize| {
+            block(period_id)
+                .map(|active_period| classes[active_period])
+                .max()
+                .unwrap_or_default()
+        };
+
+        let period_id = match locks.get(&tuple.id).filter(|p| **p < number_of_periods) {
+            Some(period_id) => *period_id,
+            None => {
+                let candidates = start_candidates(config, tuple, layout);
+                let fewest_classes = candidates
+                    .iter()
+                    .map(|period_id| busiest(*period_id))
+                    .min()
+                    .unwrap();
+                candidates
+                    .into_iter()
+                    .filter(|period_id| busiest(*period_id) == fewest_classes)
+                    .choose(rng)
+                    .unwrap()
+            }
+        };
+
+        for active_period in block(period_id) {
+            classes[active_period] += 1;
+        }
+        individual.chromosomes[period_id].genes.push(tuple.id);
+    }
+
+    individual
+}
+
+/// Periods the tuple can start in, see [`AlgorithmConfig::can_start_at`]
+///
+/// Periods the tuple can't start in are only used if there is no other choice.
+fn start_candidates(config: &AlgorithmConfig, tuple: &Tuple, layout: &PeriodLayout) -> Vec<usize> {
+    let number_of_periods = config.number_of_periods;
+    let allowed: Vec<usize> = (0..number_of_periods)
+        .filter(|period_id| config.can_start_at(tuple, *period_id, layout))
+        .collect();
+    if allowed.is_empty() {
+        (0..number_of_periods).collect()
+    } else {
+        allowed
+    }
 }
 
 /// Get parents from the current population
@@ -67,31 +428,33 @@ pub fn create_first_population(config: &AlgorithmConfig, tuples: &[Tuple]) -> Po
 /// less adapted individuals are selected with relatively high probability.
 ///
 /// Instead, we sort the population by adaptation descending.
-/// Then we apply exponent function (a * e^x + b) to the index of the individual in the sorted population.
-/// Controlling the a and b parameters we can control the probability of selecting the individual.
-/// Current values are selected by trial and error.
+/// Then we apply exponent function e^(-pressure * x) to the index of the individual in the sorted
+/// population. The higher the `selection_pressure`, the more the best individuals are favoured,
+/// with 0 every individual is equally likely. The default of 0.3 was selected by trial and error.
 /// Then we apply roulette wheel selection to select the parents making sure that the parents are different.
-pub fn rand_parents(parents: &Population) -> (&Individual, &Individual) {
+pub fn rand_parents<'a>(
+    parents: &'a Population,
+    selection_pressure: f64,
+    rng: &mut impl Rng,
+) -> (&'a Individual, &'a Individual) {
     assert!(parents.len() > 1);
 
-    let mut rng = get_random_generator();
-
     let sorted_parents = parents
         .into_iter()
         .sorted_by(|a, b| b.adaptation.partial_cmp(&a.adaptation).unwrap())
         .collect::<Vec<_>>();
 
     let weights = (0..sorted_parents.len())
-        .map(|x| f64::exp((-0.3f64 * x as f64) + 2f64))
+        .map(|x| f64::exp(-selection_pressure * x as f64))
         .collect::<Vec<_>>();
 
     let dist = WeightedIndex::new(weights.clone()).unwrap();
 
-    let idx1 = dist.sample(&mut rng);
+    let idx1 = dist.sample(rng);
 
     // Sample the second index ensuring its different from the first
     let idx2 = loop {
-        let idx = dist.sample(&mut rng);
+        let idx = dist.sample(rng);
         if idx != idx1 {
             break idx;
         }
@@ -108,87 +471,369 @@ pub fn rand_parents(parents: &Population) -> (&Individual, &Individual) {
     );
 }
 
+/// The parent with the higher adaptation, the mother on a tie
+pub fn better_of<'a>(mother: &'a Individual, father: &'a Individual) -> &'a Individual {
+    if father.adaptation > mother.adaptation {
+        father
+    } else {
+        mother
+    }
+}
+
+/// Make a freshly combined child a valid schedule with the genes of the `mother`
+///
+/// Lost and duplicated genes are repaired with the configured [`RepairStrategy`], then blocks
+/// overflowing their day are moved and locks applied.
+pub fn repair_child(
+    config: &AlgorithmConfig,
+    child: &mut Individual,
+    mother: &Individual,
+    tuples: &[Tuple],
+    rng: &mut impl Rng,
+) {
+    let AlgorithmConfig {
+        number_of_periods, ..
+    } = *config;
+
+    // at this point there could be duplicated and missing genes, so we want to fix this
+
+    // repair lost
+    let all_genes: Vec<Gene> = mother
+        .chromosomes
+        .iter()
+        .flat_map(|g| g.genes.iter().cloned())
+        .collect();
+
+    match config.repair_strategy {
+        RepairStrategy::Random => repair_genes(
+            child,
+            &all_genes,
+            number_of_periods,
+            config.domains.as_deref(),
+            rng,
+        ),
+        RepairStrategy::LeastConflict => {
+            let lost = remove_foreign_genes(child, &all_genes);
+            place_least_conflicting(config, child, &lost, tuples, rng);
+        }
+    }
+    repair_blocks(config, child, tuples, rng);
+    apply_locks(child, &config.locks);
+}
+
 /// Crossover two parents to create a child
 ///
-/// We are choosing random parents from the readonly current population. Then for each corresponding
+/// Parents are chosen from the readonly current population by a
+/// [`Selector`](operators::Selector). Then for each corresponding
 /// period we are choosing a gene mating point and creating a child by combining the genes from the parents.
-/// Then we need to solve 2 potential problems:
-/// 1. Missing genes. To solve it we are adding missing genes to the random period.
+/// Then [`repair_child`] needs to solve 2 potential problems:
+/// 1. Missing genes. To solve it we are adding missing genes to the random period, or with
+///    [`RepairStrategy::LeastConflict`] to the period where they clash the least.
 /// 2. Duplicated genes. To solve it we are removing duplicated genes from the periods.
 ///
-/// There is most likely a bug in Rust or Rayon as when we use par_bridge instead of (collect, par_iter)
-/// the assert fails meaning it selects items from `mother` and `father` in different order.
-pub fn crossover(config: &AlgorithmConfig, population: &Population) -> Individual {
-    let AlgorithmConfig {
-        number_of_periods, ..
-    } = config.to_owned();
+/// With [`CrossoverOperator::Order`](config::CrossoverOperator::Order) the periods are instead recombined as one permutation of
+/// genes, which needs no repair of lost and duplicated genes.
+///
+/// Periods are processed sequentially with the rank's generator, so the child depends only on the
+/// generator state. Parallelism comes from creating many children at once.
+fn one_point_crossover(
+    child: &mut Individual,
+    mother: &Individual,
+    father: &Individual,
+    rng: &mut impl Rng,
+) {
+    reset_child(child, mother);
+
+    for (chromosome, (mother_chromosome, father_chromosome)) in child.chromosomes.iter_mut().zip(
+        std::iter::zip(mother.chromosomes.iter(), father.chromosomes.iter()),
+    ) {
+        assert_eq!(mother_chromosome.id, father_chromosome.id);
+
+        let mother_genes = &father_chromosome.genes;
+        let father_genes = &mother_chromosome.genes;
+
+        let mating_point_upper_bound = min(mother_genes.len(), father_genes.len());
+
+        let mating_point = rng.gen_range(0..=mating_point_upper_bound);
+
+        chromosome
+            .genes
+            .extend_from_slice(&mother_genes[..mating_point]);
+        chromosome
+            .genes
+            .extend_from_slice(&father_genes[mating_point..]);
+    }
+}
 
-    let (mother, father) = rand_parents(population);
+/// Make `child` an individual with the empty periods of `mother`, keeping its allocations
+///
+/// Crossovers write over children taken from an [`IndividualPool`](pool::IndividualPool), so every
+/// field is reset.
+fn reset_child(child: &mut Individual, mother: &Individual) {
+    child.adaptation = Individual::default().adaptation;
+    child.rooms.clear();
+    child.teachers.clear();
+    child
+        .chromosomes
+        .resize_with(mother.chromosomes.len(), Chromosome::default);
+    for (chromosome, mother_chromosome) in child.chromosomes.iter_mut().zip(&mother.chromosomes) {
+        chromosome.id = mother_chromosome.id;
+        chromosome.genes.clear();
+    }
+}
 
-    let mut child: Individual = Individual::with_chromosomes(
-        std::iter::zip(mother.chromosomes.iter(), father.chromosomes.iter())
-            .collect::<Vec<_>>()
-            .par_iter()
-            // .par_bridge()
-            .map(|(mother_chromosome, father_chromosome)| {
-                assert_eq!(mother_chromosome.id, father_chromosome.id);
-                let mut rng = get_random_generator();
+/// Order crossover (OX) of the gene sequences of the parents
+///
+/// The genes of all periods are concatenated into a permutation. The child keeps a random segment
+/// of the mother's permutation in place and fills the rest with the remaining genes in the order
+/// they have in the father. The permutation is then cut into periods of the same sizes as the
+/// mother's, so no gene is lost or duplicated.
+fn order_crossover(
+    child: &mut Individual,
+    mother: &Individual,
+    father: &Individual,
+    rng: &mut impl Rng,
+) {
+    let mother_genes: Vec<Gene> = mother
+        .chromosomes
+        .iter()
+        .flat_map(|chromosome| chromosome.genes.iter().copied())
+        .collect();
+    let length = mother_genes.len();
 
-                let id = mother_chromosome.id;
+    let first_cut = rng.gen_range(0..=length);
+    let second_cut = rng.gen_range(0..=length);
+    let segment = min(first_cut, second_cut)..first_cut.max(second_cut);
+    let kept: HashSet<Gene> = mother_genes[segment.clone()].iter().copied().collect();
 
-                let mother_genes = &father_chromosome.genes;
-                let father_genes = &mother_chromosome.genes;
+    let mut father_genes = father
+        .chromosomes
+        .iter()
+        .flat_map(|chromosome| chromosome.genes.iter().copied())
+        .filter(|gene| !kept.contains(gene));
+
+    // parents with different genes fall back to the mother's gene, repair sorts them out
+    let mut child_genes = (0..length).map(|position| {
+        if segment.contains(&position) {
+            mother_genes[position]
+        } else {
+            father_genes.next().unwrap_or(mother_genes[position])
+        }
+    });
 
-                let mating_point_upper_bound = min(mother_genes.len(), father_genes.len());
+    reset_child(child, mother);
+    for (chromosome, mother_chromosome) in child.chromosomes.iter_mut().zip(&mother.chromosomes) {
+        chromosome
+            .genes
+            .extend(child_genes.by_ref().take(mother_chromosome.genes.len()));
+    }
+}
 
-                let mating_point = rng.gen_range(0..=mating_point_upper_bound);
+/// Move classes whose block of periods doesn't fit within their day to random periods where it
+/// does
+pub fn repair_blocks(
+    config: &AlgorithmConfig,
+    individual: &mut Individual,
+    tuples: &[Tuple],
+    rng: &mut impl Rng,
+) {
+    let layout = &config.period_layout();
+    let mut overflowing = Vec::new();
+
+    for (period_id, period) in individual.chromosomes.iter_mut().enumerate() {
+        period.genes.retain(|gene| {
+            let Some(tuple) = tuples.iter().find(|tuple| tuple.id == *gene) else {
+                return true;
+            };
+            if tuple.length() == 1 || layout.block(period_id, tuple.length()).is_some() {
+                return true;
+            }
+            overflowing.push(tuple);
+            false
+        });
+    }
 
-                let (mother_left, _) = mother_genes.split_at(mating_point);
-                let (_, father_right) = father_genes.split_at(mating_point);
-                let child_genes = mother_left
-                    .iter()
-                    .chain(father_right.iter())
-                    .cloned()
-                    .collect::<Vec<_>>();
+    for tuple in overflowing {
+        let period_id = random_start_period(config, tuple, layout, rng);
+        individual.chromosomes[period_id].genes.push(tuple.id);
+    }
+}
 
-                Chromosome {
-                    id,
-                    genes: child_genes,
-                }
-            })
-            .collect(),
-    );
+/// Make the individual contain every gene from `genes` exactly once
+///
+/// Duplicated genes and genes not present in `genes` are removed, missing genes are added to
+/// random periods, among the allowed starts of their tuples when `domains` are given.
+pub fn repair_genes(
+    individual: &mut Individual,
+    genes: &[Gene],
+    number_of_periods: usize,
+    domains: Option<&Domains>,
+    rng: &mut impl Rng,
+) {
+    // repair lost
+    for gene in remove_foreign_genes(individual, genes) {
+        let period_id = match domains.and_then(|d| d.starts(gene)?.choose(rng)) {
+            Some(period_id) => *period_id,
+            None => rng.gen_range(0..number_of_periods),
+        };
+        individual.chromosomes[period_id].genes.push(gene);
+    }
+}
 
-    // at this point there could be duplicated and missing genes, so we want to fix this
+/// Remove duplicated genes and genes not present in `genes`, returning the genes that are missing
+fn remove_foreign_genes(individual: &mut Individual, genes: &[Gene]) -> Vec<Gene> {
+    let allowed: HashSet<Gene> = genes.iter().cloned().collect();
 
-    // repair lost
-    let all_genes: Vec<i32> = mother
-        .chromosomes
-        .iter()
-        .flat_map(|g| g.genes.iter().cloned())
-        .collect();
+    // remove unknown genes and duplicates
+    let mut seen = HashSet::new();
+
+    for period in &mut individual.chromosomes {
+        period
+            .genes
+            .retain(|gene| allowed.contains(gene) && seen.insert(*gene));
+    }
 
-    let lost_genes: Vec<i32> = all_genes
+    genes
         .iter()
-        .filter(|g| !child.chromosomes.iter().any(|c| c.genes.contains(g)))
+        .filter(|gene| !seen.contains(gene))
         .cloned()
-        .collect();
+        .collect()
+}
 
-    let mut rng = get_random_generator();
+/// Add the genes to the periods where they add the least penalty
+///
+/// Genes are placed one by one, so later genes see the earlier ones. The penalty of a period is
+/// the sum of [`clash_penalties`] with the classes active in the block the class would take, like
+/// in [`calculate_fitness`]. Periods the class can't start in (see [`AlgorithmConfig::can_start_at`]) are only
+/// used if there is no other choice and ties are broken randomly. Locked genes go to their periods.
+///
+/// With a conflict matrix attached to the configuration the penalties are looked up in the
+/// [`Occupancy`](occupancy::Occupancy) of the periods.
+pub fn place_least_conflicting(
+    config: &AlgorithmConfig,
+    individual: &mut Individual,
+    genes: &[Gene],
+    tuples: &[Tuple],
+    rng: &mut impl Rng,
+) {
+    let number_of_periods = individual.chromosomes.len();
+    let layout = config.period_layout();
+    let tuples_by_id: HashMap<Gene, &Tuple> =
+        tuples.iter().map(|tuple| (tuple.id, tuple)).collect();
+    let mut active_genes = constraints::active_genes(individual, tuples, &layout);
+    let mut occupancy = config
+        .conflict_matrix
+        .as_deref()
+        .map(|matrix| occupancy::Occupancy::new(matrix, &active_genes));
+
+    for gene in genes {
+        let Some(tuple) = tuples_by_id.get(gene).copied() else {
+            let period_id = rng.gen_range(0..number_of_periods);
+            individual.chromosomes[period_id].genes.push(*gene);
+            continue;
+        };
+        let block = |period_id: usize| {
+            let block = layout.clamped_block(period_id, tuple.length());
+            block.start..block.end.min(number_of_periods)
+        };
+
+        let period_id = match config.locks.get(gene).filter(|p| **p < number_of_periods) {
+            Some(period_id) => *period_id,
+            None => {
+                let allowed: Vec<usize> = (0..number_of_periods)
+                    .filter(|period_id| config.can_start_at(tuple, *period_id, &layout))
+                    .collect();
+                let candidates = if allowed.is_empty() {
+                    (0..number_of_periods).collect()
+                } else {
+                    allowed
+                };
+
+                let penalties: Vec<i32> = candidates
+                    .iter()
+                    .map(|period_id| {
+                        if let Some(occupancy) = &occupancy {
+                            return block(*period_id)
+                                .map(|active_period| occupancy.penalty(active_period, *gene))
+                                .sum();
+                        }
+
+                        block(*period_id)
+                            .flat_map(|active_period| &active_genes[active_period])
+                            .filter_map(|other| tuples_by_id.get(other))
+                            .map(|other| {
+                                let (teacher_penalty, room_penalty) =
+                                    clash_penalties(config, tuple, other);
+                                teacher_penalty + room_penalty
+                            })
+                            .sum()
+                    })
+                    .collect();
+                let least_penalty = *penalties.iter().min().unwrap();
+
+                candidates
+                    .into_iter()
+                    .zip(penalties)
+                    .filter(|(_, penalty)| *penalty == least_penalty)
+                    .map(|(period_id, _)| period_id)
+                    .choose(rng)
+                    .unwrap()
+            }
+        };
 
-    for gene in lost_genes {
-        let period_id = rng.gen_range(0..number_of_periods);
-        child.chromosomes[period_id].genes.push(gene);
+        for active_period in block(period_id) {
+            active_genes[active_period].push(*gene);
+            if let Some(occupancy) = &mut occupancy {
+                occupancy.insert(active_period, *gene);
+            }
+        }
+        individual.chromosomes[period_id].genes.push(*gene);
     }
+}
 
-    // remove duplicates
-    let mut seen = std::collections::HashSet::new();
+/// Move locked genes to the periods they are pinned to
+///
+/// Locks pointing past the last period are ignored.
+pub fn apply_locks(individual: &mut Individual, locks: &Locks) {
+    if locks.is_empty() {
+        return;
+    }
+
+    let number_of_periods = individual.chromosomes.len();
+    let mut misplaced = Vec::new();
 
-    for period in &mut child.chromosomes {
-        period.genes.retain(|x| seen.insert(x.clone()));
+    for (period_id, period) in individual.chromosomes.iter_mut().enumerate() {
+        period.genes.retain(|gene| match locks.get(gene) {
+            Some(locked) if *locked != period_id && *locked < number_of_periods => {
+                misplaced.push(*gene);
+                false
+            }
+            _ => true,
+        });
     }
 
-    child
+    for gene in misplaced {
+        individual.chromosomes[locks[&gene]].genes.push(gene);
+    }
+}
+
+/// Create `count` individuals from the given one: an exact copy followed by mutated variants
+pub fn create_variants(
+    config: &AlgorithmConfig,
+    individual: &Individual,
+    tuples: &[Tuple],
+    count: usize,
+    rng: &mut impl Rng,
+) -> Vec<Individual> {
+    (0..count)
+        .map(|index| {
+            let mut variant = individual.clone();
+            if index > 0 {
+                mutate(config, &mut variant, tuples, rng);
+            }
+            variant
+        })
+        .collect()
 }
 
 /// Mutate the individual
@@ -198,12 +843,28 @@ pub fn crossover(config: &AlgorithmConfig, population: &Population) -> Individua
 /// good idea to keep it small.
 ///
 /// For each period, we are checking if the mutation should occur. If it should, we are removing
-/// a random gene from the period and adding it to a random period.
-pub fn mutate(config: &AlgorithmConfig, individual: &mut Individual) {
+/// a random gene from the period and adding it to a random period. Locked genes never move and
+/// genes are never moved to periods their tuples can't start in (see
+/// [`AlgorithmConfig::can_start_at`]).
+///
+/// Then, with `guided_mutation_probability`, a random conflicting gene of the individual is moved
+/// to the period where it clashes the least (see [`guided_move`]).
+///
+/// With `room_assignment` or `teacher_assignment` tuples are also moved between their allowed
+/// rooms and qualified teachers, see [`assignment::mutate`].
+///
+/// With [`AlgorithmConfig::incremental_conflicts`] the adaptation is kept up to date: random moves
+/// update it by [`Individual::apply_move`], only a guided move needs the whole individual
+/// evaluated again.
+pub fn mutate(
+    config: &AlgorithmConfig,
+    individual: &mut Individual,
+    tuples: &[Tuple],
+    rng: &mut impl Rng,
+) {
     let mutation_probability = config.mutation_probability;
     let number_of_periods = usize::try_from(config.number_of_periods).unwrap();
-
-    let mut rng = get_random_generator();
+    let layout = config.period_layout();
 
     for period_id in 0..number_of_periods {
         if rng.gen_bool(mutation_probability.into()) {
@@ -214,103 +875,538 @@ pub fn mutate(config: &AlgorithmConfig, individual: &mut Individual) {
             }
 
             let gene_index = rng.gen_range(0..gene_count);
+            let gene = individual.chromosomes[period_id].genes[gene_index];
 
-            let gene = individual.chromosomes[period_id].genes.remove(gene_index);
+            if config.locks.contains_key(&gene) {
+                continue;
+            }
+
+            // pick a random period the tuple can start in
+            let tuple = tuples.iter().find(|t| t.id == gene);
+            let Some(target_id) = (0..number_of_periods)
+                .filter(|target_id| *target_id != period_id)
+                .filter(|target_id| {
+                    tuple.is_none_or(|t| config.can_start_at(t, *target_id, &layout))
+                })
+                .choose(rng)
+            else {
+                continue;
+            };
+
+            if let Some(matrix) = config.incremental_conflicts() {
+                individual.apply_move(period_id, gene_index, target_id, matrix);
+                continue;
+            }
 
             // remove gene from current period
+            individual.chromosomes[period_id].genes.remove(gene_index);
             individual.chromosomes[period_id]
                 .genes
                 .retain(|g| g != &gene);
 
-            // add gene to random period
-            individual
-                .chromosomes
-                .iter_mut()
-                .filter(|target| target.id != i32::try_from(period_id).unwrap())
-                .choose(&mut rng)
-                .unwrap()
-                .genes
-                .push(gene);
+            // add gene to the target period
+            individual.chromosomes[target_id].genes.push(gene);
         }
     }
+
+    if config.guided_mutation_probability > 0.0
+        && rng.gen_bool(config.guided_mutation_probability.into())
+        && guided_move(config, individual, tuples, rng)
+        && config.incremental_conflicts().is_some()
+    {
+        individual.adaptation = calculate_fitness(config, individual, tuples);
+    }
+
+    if config.assigns_resources() {
+        assignment::mutate(config, individual, tuples, rng);
+    }
+}
+
+/// Move a random conflicting gene to the period where it adds the least penalty
+///
+/// A gene is conflicting when its class clashes (see [`clash_penalties`]) with another class in
+/// any period of its block, or takes place in a forbidden period. Locked genes never move. The
+/// gene may stay in its period if no other period is better. Returns whether a gene was moved.
+///
+/// With a conflict matrix attached to the configuration clashes are counted in the
+/// [`Occupancy`](occupancy::Occupancy) of the periods.
+pub fn guided_move(
+    config: &AlgorithmConfig,
+    individual: &mut Individual,
+    tuples: &[Tuple],
+    rng: &mut impl Rng,
+) -> bool {
+    let number_of_periods = individual.chromosomes.len();
+    let layout = config.period_layout();
+    let tuples_by_id: HashMap<Gene, &Tuple> =
+        tuples.iter().map(|tuple| (tuple.id, tuple)).collect();
+    let active_genes = constraints::active_genes(individual, tuples, &layout);
+    let occupancy = config
+        .conflict_matrix
+        .as_deref()
+        .map(|matrix| occupancy::Occupancy::new(matrix, &active_genes));
+    let clashes_in = |period: usize, gene: &Gene, tuple: &Tuple| match &occupancy {
+        Some(occupancy) => occupancy.clashes(period, *gene) > 0,
+        None => active_genes[period]
+            .iter()
+            .filter(|other| *other != gene)
+            .filter_map(|other| tuples_by_id.get(other))
+            .any(|other| clash_penalties(config, tuple, other) != (0, 0)),
+    };
+
+    let conflicting = individual
+        .chromosomes
+        .iter()
+        .enumerate()
+        .flat_map(|(period_id, chromosome)| chromosome.genes.iter().map(move |g| (period_id, *g)))
+        .filter(|(_, gene)| !config.locks.contains_key(gene))
+        .filter(|(period_id, gene)| {
+            let Some(tuple) = tuples_by_id.get(gene) else {
+                return false;
+            };
+            let block = layout.clamped_block(*period_id, tuple.length());
+            let block = block.start..block.end.min(number_of_periods);
+
+            block.clone().any(|period| !tuple.can_take_place_in(period))
+                || block.clone().any(|period| clashes_in(period, gene, tuple))
+        })
+        .choose(rng);
+
+    let Some((period_id, gene)) = conflicting else {
+        return false;
+    };
+
+    individual.chromosomes[period_id]
+        .genes
+        .retain(|g| *g != gene);
+    place_least_conflicting(config, individual, &[gene], tuples, rng);
+    true
 }
 
 /// Calculate fitness of the individual
 ///
-/// For every period in individual we are checking 2 rules:
+/// For every period in individual we are checking 2 rules, classes longer than one period are
+/// checked in every period of their block (see [`constraints::active_genes`]):
 /// 1) If the same teacher is teaching more than one class at the same time decrease fitness by 10
-/// 2) If different teachers occupy the same room at the same time decrease fitness by 20
-pub fn calculate_fitness(individual: &Individual, tuples: &Vec<Tuple>, debug: bool) -> i32 {
-    let mut individual_fitness = 0;
+/// 2) If different teachers occupy the same room at the same time decrease fitness by 20, unless
+///    `room_sharing` allows their kinds of classes to share it
+///
+/// Then idle periods within a day (see [`constraints::count_gaps`]) of teachers and student
+/// groups are penalized with `teacher_gap_weight` and `group_gap_weight`, and every period a
+/// teacher teaches over their consecutive hours limit with `consecutive_hours_weight`. Locked
+/// tuples outside their periods and tuples in their forbidden periods are hard constraint breaks,
+/// costing `lock_violation_weight` and `forbidden_period_weight`, as are blocks not fitting within
+/// their day (`block_overflow_weight`).
+///
+/// Every rule is a [`Constraint`](fitness::Constraint) of [`Fitness::from_config`].
+/// [`ConflictReport::explain`](report::ConflictReport::explain) breaks the result down into the
+/// individual violations.
+pub fn calculate_fitness(
+    config: &AlgorithmConfig,
+    individual: &Individual,
+    tuples: &[Tuple],
+) -> i32 {
+    Fitness::from_config(config).evaluate(config, individual, tuples)
+}
 
-    for period in &individual.chromosomes {
-        // if teacher is teaching more than one class at the same time decrease fitness by 10
+/// Penalties for `tuple` taking place together with `other`, as `(teacher, room)`
+///
+/// If the same teacher is teaching more than one class at the same time decrease fitness by 10,
+/// plus 10 more in the same room, or 20 when the classes are of different subjects. If different
+/// teachers occupy the same room at the same time decrease fitness by 20, unless the kinds of
+/// their classes may share a room.
+fn clash_penalties(config: &AlgorithmConfig, tuple: &Tuple, other: &Tuple) -> (i32, i32) {
+    let mut teacher_penalty = 0;
+    let mut room_penalty = 0;
+
+    if other.teacher_id == tuple.teacher_id {
+        if other.room_id == tuple.room_id {
+            teacher_penalty += 10;
+        }
+        teacher_penalty += if other.label == tuple.label { 10 } else { 20 };
+    } else if other.room_id == tuple.room_id && !config.can_share_room(other.kind, tuple.kind) {
+        room_penalty += 20;
+    }
 
-        let genes = &period.genes;
+    (teacher_penalty, room_penalty)
+}
 
-        for gene_id in genes {
-            // if the same teacher is teaching more than one class at the same time decrease fitness by 10,
-            // if different teachers occupy the same room at the same time decrease fitness by 20
+#[cfg(test)]
+mod tests {
+    use super::{
+        datatypes::{PeriodId, TupleId, TupleKind},
+        random::get_random_generator,
+        symbols::Symbols,
+        *,
+    };
+    use smallvec::smallvec;
+
+    fn tuple(id: u16, room: &str, teacher: &str) -> Tuple {
+        Tuple {
+            id: TupleId(id),
+            label: format!("Class {}", id),
+            room: room.to_string(),
+            teacher: teacher.to_string(),
+            ..Tuple::default()
+        }
+    }
 
-            // additional rules may be added, for example,
-            // the division of lectures by type of classes, if the types of classes differ for the
-            // same lecture, reduce the suitability by a smaller value
+    #[test]
+    fn test_greedy_individual_separates_clashing_tuples() {
+        let tuples = vec![
+            tuple(1, "A", "Smith"),
+            tuple(2, "B", "Smith"),
+            tuple(3, "A", "Jones"),
+        ];
+
+        let mut rng = get_random_generator(42);
+        let config = AlgorithmConfig {
+            number_of_periods: 3,
+            ..AlgorithmConfig::default()
+        };
+        let layout = PeriodLayout::new(1, 3);
+        let individual = create_greedy_individual(&config, &tuples, &layout, &mut rng);
+
+        let config = AlgorithmConfig::default();
+        assert_eq!(calculate_fitness(&config, &individual, &tuples), 0);
+    }
 
-            let tuple = tuples
-                .iter()
-                .find(|t| t.id == *gene_id)
-                .expect(format!("Tuple with id {} not found", *gene_id).as_str());
+    #[test]
+    fn test_room_sharing_depends_on_kinds() {
+        let mut tuples = vec![
+            Tuple {
+                kind: TupleKind::Lab,
+                ..tuple(1, "A", "Smith")
+            },
+            Tuple {
+                kind: TupleKind::Lab,
+                ..tuple(2, "A", "Jones")
+            },
+        ];
+        Symbols::intern(&mut tuples);
+        let individual = Individual::with_chromosomes(vec![Chromosome {
+            id: PeriodId(0),
+            genes: smallvec![TupleId(1), TupleId(2)],
+        }]);
+
+        let config = AlgorithmConfig {
+            number_of_periods: 1,
+            ..AlgorithmConfig::default()
+        };
+        assert_eq!(calculate_fitness(&config, &individual, &tuples), -40);
+
+        let config = AlgorithmConfig {
+            room_sharing: [(TupleKind::Lab, vec![TupleKind::Lab])].into(),
+            ..config
+        };
+        assert_eq!(calculate_fitness(&config, &individual, &tuples), 0);
+    }
 
-            let other_classes = tuples
-                .iter()
-                .filter(|t| genes.contains(&t.id))
-                .filter(|t| t.id != tuple.id);
-
-            // get count of tuples with the same teacher
-            let same_teacher_different_classes_count = other_classes
-                .clone()
-                .filter(|t| t.room == tuple.room)
-                .filter(|t| t.teacher == tuple.teacher)
-                .count();
-
-            individual_fitness -= (same_teacher_different_classes_count as i32) * 10;
-
-            let same_room_different_teacher_count = other_classes
-                .clone()
-                .filter(|t| t.room == tuple.room)
-                .filter(|t| t.teacher != tuple.teacher)
-                .count();
-
-            individual_fitness -= (same_room_different_teacher_count as i32) * 20;
-
-            let same_teacher_same_subject_count = other_classes
-                .clone()
-                .filter(|t| t.teacher == tuple.teacher)
-                .filter(|t| t.label == tuple.label)
-                .count();
-
-            individual_fitness -= (same_teacher_same_subject_count as i32) * 10;
-
-            let same_teacher_different_subject_count = other_classes
-                .clone()
-                .filter(|t| t.teacher == tuple.teacher)
-                .filter(|t| t.label != tuple.label)
-                .count();
-
-            individual_fitness -= (same_teacher_different_subject_count as i32) * 20;
-
-            if debug {
-                println!(
-                    "same_teacher_different_classes_count: {}, same_room_different_teacher_count: {}",
-                    same_teacher_different_classes_count, same_room_different_teacher_count
-                );
+    #[test]
+    fn test_repair_genes_removes_unknown_and_adds_missing() {
+        let mut individual = Individual::with_chromosomes(vec![
+            Chromosome {
+                id: PeriodId(0),
+                genes: smallvec![TupleId(1), TupleId(7), TupleId(2)],
+            },
+            Chromosome {
+                id: PeriodId(1),
+                genes: smallvec![TupleId(2)],
+            },
+        ]);
+
+        let mut rng = get_random_generator(42);
+        repair_genes(&mut individual, &[1, 2, 3].map(TupleId), 2, None, &mut rng);
+
+        let mut genes: Vec<Gene> = individual
+            .chromosomes
+            .iter()
+            .flat_map(|chromosome| chromosome.genes.iter().cloned())
+            .collect();
+        genes.sort();
+        assert_eq!(genes, [1, 2, 3].map(TupleId));
+    }
+
+    #[test]
+    fn test_locked_genes_stay_in_their_periods() {
+        let tuples: Vec<Tuple> = (1..=6).map(|id| tuple(id, "A", "Smith")).collect();
+        let config = AlgorithmConfig {
+            population_size: 4,
+            number_of_periods: 3,
+            mutation_probability: 1.0,
+            locks: Locks::from([(TupleId(1), 2), (TupleId(4), 0)]),
+            ..AlgorithmConfig::default()
+        };
+
+        let mut rng = get_random_generator(42);
+        let population = create_first_population(&config, &tuples, &mut rng);
+        let operators = operators::Operators::from_config(&config);
+        let mut child = Individual::default();
+        operators.breed_into(&config, &population, &tuples, &mut rng, &mut child);
+
+        for individual in population.iter().chain([&child]) {
+            assert!(individual.chromosomes[2].genes.contains(&TupleId(1)));
+            assert!(individual.chromosomes[0].genes.contains(&TupleId(4)));
+        }
+    }
+
+    #[test]
+    fn test_tuples_avoid_forbidden_periods() {
+        let tuples = vec![Tuple {
+            forbidden_periods: vec![0, 1, 3],
+            ..tuple(1, "A", "Smith")
+        }];
+        let config = AlgorithmConfig {
+            population_size: 4,
+            number_of_periods: 4,
+            mutation_probability: 1.0,
+            initial_population_strategy: InitialPopulationStrategy::Greedy,
+            ..AlgorithmConfig::default()
+        };
+
+        let mut rng = get_random_generator(42);
+        let mut population = create_first_population(&config, &tuples, &mut rng);
+        for individual in &mut population {
+            mutate(&config, individual, &tuples, &mut rng);
+            assert_eq!(individual.chromosomes[2].genes[..], [TupleId(1)]);
+        }
+    }
+
+    #[test]
+    fn test_balanced_individuals_spread_classes_evenly() {
+        let tuples: Vec<Tuple> = (1..=8).map(|id| tuple(id, "A", "Smith")).collect();
+        let config = AlgorithmConfig {
+            population_size: 4,
+            number_of_periods: 4,
+            initial_population_strategy: InitialPopulationStrategy::Balanced,
+            locks: [(TupleId(1), 0), (TupleId(2), 0)].into(),
+            ..AlgorithmConfig::default()
+        };
+
+        let mut rng = get_random_generator(42);
+        for individual in create_first_population(&config, &tuples, &mut rng) {
+            for chromosome in &individual.chromosomes {
+                assert_eq!(chromosome.genes.len(), 2);
             }
+            let mut locked = individual.chromosomes[0].genes.clone();
+            locked.sort();
+            assert_eq!(locked[..], [1, 2].map(TupleId));
+        }
+    }
+
+    #[test]
+    fn test_first_population_contains_all_tuples() {
+        let tuples = vec![tuple(1, "A", "Smith"), tuple(2, "B", "Jones")];
+        let config = AlgorithmConfig {
+            population_size: 4,
+            initial_population_strategy: InitialPopulationStrategy::Greedy,
+            ..AlgorithmConfig::default()
+        };
+
+        let mut rng = get_random_generator(42);
+        let population = create_first_population(&config, &tuples, &mut rng);
+
+        assert_eq!(population.len(), 4);
+        for individual in &population {
+            let mut genes: Vec<Gene> = individual
+                .chromosomes
+                .iter()
+                .flat_map(|chromosome| chromosome.genes.iter().cloned())
+                .collect();
+            genes.sort();
+            assert_eq!(genes, [1, 2].map(TupleId));
+        }
+    }
+
+    #[test]
+    fn test_least_conflict_repair_avoids_clashes() {
+        let tuples = vec![
+            tuple(1, "A", "Smith"),
+            tuple(2, "B", "Smith"),
+            tuple(3, "A", "Jones"),
+        ];
+        let config = AlgorithmConfig {
+            number_of_periods: 3,
+            ..AlgorithmConfig::default()
+        };
+        let mut rng = get_random_generator(7);
+
+        for _ in 0..10 {
+            let mut individual = Individual::with_chromosomes(
+                [vec![1], vec![3], vec![]]
+                    .into_iter()
+                    .enumerate()
+                    .map(|(id, genes)| Chromosome {
+                        id: PeriodId(id),
+                        genes: genes.into_iter().map(TupleId).collect(),
+                    })
+                    .collect(),
+            );
+            place_least_conflicting(&config, &mut individual, &[TupleId(2)], &tuples, &mut rng);
+
+            assert!(!individual.chromosomes[0].genes.contains(&TupleId(2)));
+            assert_eq!(calculate_fitness(&config, &individual, &tuples), 0);
         }
     }
 
-    if debug {
-        println!("Individual fitness: {}", individual_fitness);
+    #[test]
+    fn test_injected_individuals_beyond_the_population_are_left_out() {
+        let tuples = vec![tuple(1, "A", "Smith")];
+        let config = AlgorithmConfig::default();
+        let placed = |period| {
+            Individual::with_chromosomes(vec![Chromosome {
+                id: PeriodId(period),
+                genes: smallvec![TupleId(1)],
+            }])
+        };
+        let mut population = vec![Individual::default(); 2];
+
+        let left_out = inject_individuals(
+            &config,
+            &mut population,
+            (0..3).map(placed).collect(),
+            &tuples,
+        );
+
+        assert_eq!(left_out, 1);
+        let periods: Vec<PeriodId> = population
+            .iter()
+            .map(|individual| individual.chromosomes[0].id)
+            .collect();
+        assert_eq!(periods, [PeriodId(0), PeriodId(1)]);
+        assert_eq!(population[0].adaptation, 0);
     }
 
-    individual_fitness
+    #[test]
+    fn test_immigrants_replace_the_worst_individuals() {
+        let mut tuples = vec![tuple(1, "A", "Smith"), tuple(2, "B", "Jones")];
+        Symbols::intern(&mut tuples);
+        let config = AlgorithmConfig {
+            number_of_periods: 2,
+            immigrant_fraction: 0.5,
+            ..AlgorithmConfig::default()
+        };
+        let mut rng = get_random_generator(9);
+        let mut population: Population = [10, 20, 30, 40]
+            .into_iter()
+            .map(|adaptation| Individual {
+                adaptation,
+                ..create_empty_individual(2)
+            })
+            .collect();
+
+        add_random_immigrants(&config, &mut population, &tuples, &mut rng);
+
+        let adaptations: Vec<i32> = population.iter().map(|i| i.adaptation).collect();
+        assert_eq!(adaptations, vec![40, 30, 0, 0]);
+        assert_eq!(population[3].chromosomes.len(), 2);
+    }
+
+    #[test]
+    fn test_duplicates_are_mutated() {
+        let tuples: Vec<Tuple> = (1..=4).map(|id| tuple(id, "A", "Smith")).collect();
+        let config = AlgorithmConfig {
+            number_of_periods: 4,
+            mutation_probability: 0.5,
+            max_duplicate_copies: 1,
+            ..AlgorithmConfig::default()
+        };
+        let mut rng = get_random_generator(4);
+        let individual = create_individual(
+            &config,
+            &tuples,
+            InitialPopulationStrategy::Random,
+            &mut rng,
+        );
+        let mut population = vec![individual.clone(); 3];
+        population[1].chromosomes[0].genes.reverse();
+
+        let replaced = deduplicate_population(&config, &mut population, &tuples, &mut rng);
+
+        assert_eq!(replaced, 2);
+        assert_eq!(population[0].placement_hash(), individual.placement_hash());
+        assert_ne!(population[2].placement_hash(), individual.placement_hash());
+    }
+
+    #[test]
+    fn test_best_parents_fill_up_the_generation() {
+        let with_adaptations = |adaptations: &[i32]| -> Population {
+            adaptations
+                .iter()
+                .map(|adaptation| Individual {
+                    adaptation: *adaptation,
+                    ..create_empty_individual(1)
+                })
+                .collect()
+        };
+        let adaptations = |population: &Population| -> Vec<i32> {
+            population.iter().map(|i| i.adaptation).collect()
+        };
+
+        let mut population = with_adaptations(&[-3, -1, -2, -4]);
+        replace_generation(&mut population, with_adaptations(&[-10]));
+        assert_eq!(adaptations(&population), vec![-1, -2, -3, -10]);
+
+        replace_generation(&mut population, with_adaptations(&[-8, -5, -6, -7, -9]));
+        assert_eq!(adaptations(&population), vec![-5, -6, -7, -8]);
+    }
+
+    #[test]
+    fn test_guided_move_resolves_a_clash() {
+        let tuples = vec![tuple(1, "A", "Smith"), tuple(2, "A", "Jones")];
+        let config = AlgorithmConfig {
+            number_of_periods: 2,
+            ..AlgorithmConfig::default()
+        };
+        let mut rng = get_random_generator(3);
+        let mut individual = Individual::with_chromosomes(vec![
+            Chromosome {
+                id: PeriodId(0),
+                genes: smallvec![TupleId(1), TupleId(2)],
+            },
+            Chromosome {
+                id: PeriodId(1),
+                genes: smallvec![],
+            },
+        ]);
+
+        assert!(guided_move(&config, &mut individual, &tuples, &mut rng));
+        assert_eq!(calculate_fitness(&config, &individual, &tuples), 0);
+        assert!(!guided_move(&config, &mut individual, &tuples, &mut rng));
+    }
+
+    #[test]
+    fn test_order_crossover_keeps_every_gene_once() {
+        let individual = |periods: [Vec<u16>; 3]| {
+            Individual::with_chromosomes(
+                periods
+                    .into_iter()
+                    .enumerate()
+                    .map(|(id, genes)| Chromosome {
+                        id: PeriodId(id),
+                        genes: genes.into_iter().map(TupleId).collect(),
+                    })
+                    .collect(),
+            )
+        };
+        let mother = individual([vec![1, 2], vec![3], vec![4, 5, 6]]);
+        let father = individual([vec![6, 5, 4], vec![], vec![3, 2, 1]]);
+
+        let mut rng = get_random_generator(42);
+        let mut child = Individual::default();
+        for _ in 0..20 {
+            order_crossover(&mut child, &mother, &father, &mut rng);
+
+            let sizes: Vec<usize> = child.chromosomes.iter().map(|c| c.genes.len()).collect();
+            assert_eq!(sizes, vec![2, 1, 3]);
+
+            let mut genes: Vec<Gene> = child
+                .chromosomes
+                .iter()
+                .flat_map(|chromosome| chromosome.genes.iter().cloned())
+                .collect();
+            genes.sort();
+            assert_eq!(genes, (1..=6).map(TupleId).collect::<Vec<_>>());
+        }
+    }
 }
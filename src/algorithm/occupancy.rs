@@ -0,0 +1,90 @@
+use fixedbitset::FixedBitSet;
+
+use super::{conflicts::ConflictMatrix, datatypes::Gene};
+
+/// Classes taking place in every period, as bitsets over the rows of a [`ConflictMatrix`]
+///
+/// A teacher or room is double-booked in a period when the period shares a bit with the
+/// [`ConflictMatrix::clashing`] row of one of its classes, so clashes are counted with popcounts
+/// on word-sized blocks instead of comparing the teachers and rooms of every pair of classes.
+/// The bitsets are updated class by class with [`Occupancy::insert`]. Genes of tuples unknown to
+/// the matrix are ignored.
+#[derive(Debug, Clone)]
+pub struct Occupancy<'a> {
+    matrix: &'a ConflictMatrix,
+    periods: Vec<FixedBitSet>,
+}
+
+impl<'a> Occupancy<'a> {
+    /// Occupancy of the genes taking place in every period, see
+    /// [`constraints::active_genes`](super::constraints::active_genes)
+    pub fn new(matrix: &'a ConflictMatrix, active_genes: &[Vec<Gene>]) -> Self {
+        let mut occupancy = Occupancy {
+            matrix,
+            periods: vec![FixedBitSet::with_capacity(matrix.size()); active_genes.len()],
+        };
+        for (period_id, genes) in active_genes.iter().enumerate() {
+            for gene in genes {
+                occupancy.insert(period_id, *gene);
+            }
+        }
+        occupancy
+    }
+
+    pub fn insert(&mut self, period_id: usize, gene: Gene) {
+        if let Some(row) = self.matrix.index_of(gene) {
+            self.periods[period_id].insert(row);
+        }
+    }
+
+    /// Number of classes of the period the gene's class clashes with
+    pub fn clashes(&self, period_id: usize, gene: Gene) -> usize {
+        self.matrix.index_of(gene).map_or(0, |row| {
+            self.periods[period_id].intersection_count(self.matrix.clashing(row))
+        })
+    }
+
+    /// Sum of the penalties of the gene's class with the classes of the period it clashes with,
+    /// like [`ConflictMatrix::penalty`]
+    pub fn penalty(&self, period_id: usize, gene: Gene) -> i32 {
+        self.matrix.index_of(gene).map_or(0, |row| {
+            self.periods[period_id]
+                .intersection(self.matrix.clashing(row))
+                .map(|other| self.matrix.penalty(row, other))
+                .sum()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::{
+        config::AlgorithmConfig,
+        datatypes::{Tuple, TupleId},
+        symbols::Symbols,
+    };
+
+    #[test]
+    fn test_occupancy_counts_clashing_classes() {
+        let mut tuples: Vec<Tuple> = [(1, "A", "Smith"), (2, "B", "Smith"), (3, "A", "Jones")]
+            .into_iter()
+            .map(|(id, room, teacher)| Tuple {
+                id: TupleId(id),
+                room: room.to_string(),
+                teacher: teacher.to_string(),
+                ..Tuple::default()
+            })
+            .collect();
+        Symbols::intern(&mut tuples);
+        let matrix = ConflictMatrix::new(&AlgorithmConfig::default(), &tuples);
+
+        let mut occupancy = Occupancy::new(&matrix, &[vec![TupleId(1), TupleId(2)], vec![]]);
+        occupancy.insert(0, TupleId(3));
+
+        assert_eq!(occupancy.clashes(0, TupleId(1)), 2);
+        assert_eq!(occupancy.clashes(0, TupleId(2)), 1);
+        assert_eq!(occupancy.clashes(1, TupleId(1)), 0);
+        assert_eq!(occupancy.penalty(0, TupleId(3)), matrix.penalty(2, 0));
+    }
+}
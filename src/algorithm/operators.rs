@@ -0,0 +1,313 @@
+use super::{
+    assignment,
+    config::{AlgorithmConfig, CrossoverOperator, SelectionOperator},
+    datatypes::{Individual, Population, Tuple},
+    mutate, one_point_crossover, order_crossover, pareto, rand_parents,
+    random::PlannerRng,
+    repair_child,
+    selection::roulette_parents,
+};
+
+/// Picks two different parents from the population
+pub trait Selector: Send + Sync {
+    fn select<'a>(
+        &self,
+        config: &AlgorithmConfig,
+        population: &'a Population,
+        rng: &mut PlannerRng,
+    ) -> (&'a Individual, &'a Individual);
+}
+
+/// Combines two parents into a child that is a valid schedule, e.g. with [`repair_child`]
+pub trait Crossover: Send + Sync {
+    fn crossover(
+        &self,
+        config: &AlgorithmConfig,
+        mother: &Individual,
+        father: &Individual,
+        tuples: &[Tuple],
+        rng: &mut PlannerRng,
+    ) -> Individual;
+
+    /// Like [`Crossover::crossover`], but writes the child over `child`, an individual taken from
+    /// an [`IndividualPool`](super::pool::IndividualPool)
+    ///
+    /// Overriding it lets the operator reuse the allocations of the child, by default it's simply
+    /// replaced.
+    fn crossover_into(
+        &self,
+        config: &AlgorithmConfig,
+        mother: &Individual,
+        father: &Individual,
+        tuples: &[Tuple],
+        rng: &mut PlannerRng,
+        child: &mut Individual,
+    ) {
+        *child = self.crossover(config, mother, father, tuples, rng);
+    }
+}
+
+/// Changes a child in place
+pub trait Mutator: Send + Sync {
+    fn mutate(
+        &self,
+        config: &AlgorithmConfig,
+        individual: &mut Individual,
+        tuples: &[Tuple],
+        rng: &mut PlannerRng,
+    );
+}
+
+/// [`SelectionOperator::Rank`], see [`rand_parents`]
+#[derive(Debug, Clone, Copy)]
+pub struct RankSelector;
+
+impl Selector for RankSelector {
+    fn select<'a>(
+        &self,
+        config: &AlgorithmConfig,
+        population: &'a Population,
+        rng: &mut PlannerRng,
+    ) -> (&'a Individual, &'a Individual) {
+        rand_parents(population, config.selection_pressure as f64, rng)
+    }
+}
+
+/// [`SelectionOperator::Roulette`], see [`roulette_parents`]
+#[derive(Debug, Clone, Copy)]
+pub struct RouletteSelector;
+
+impl Selector for RouletteSelector {
+    fn select<'a>(
+        &self,
+        _config: &AlgorithmConfig,
+        population: &'a Population,
+        rng: &mut PlannerRng,
+    ) -> (&'a Individual, &'a Individual) {
+        roulette_parents(population, rng)
+    }
+}
+
+/// Crowded tournaments of the multi-objective mode, see [`pareto::tournament_parents`]
+#[derive(Debug, Clone, Copy)]
+pub struct TournamentSelector;
+
+impl Selector for TournamentSelector {
+    fn select<'a>(
+        &self,
+        _config: &AlgorithmConfig,
+        population: &'a Population,
+        rng: &mut PlannerRng,
+    ) -> (&'a Individual, &'a Individual) {
+        pareto::tournament_parents(population, rng)
+    }
+}
+
+/// [`CrossoverOperator::OnePoint`], a mating point in every period
+#[derive(Debug, Clone, Copy)]
+pub struct OnePointCrossover;
+
+impl Crossover for OnePointCrossover {
+    fn crossover(
+        &self,
+        config: &AlgorithmConfig,
+        mother: &Individual,
+        father: &Individual,
+        tuples: &[Tuple],
+        rng: &mut PlannerRng,
+    ) -> Individual {
+        let mut child = Individual::default();
+        self.crossover_into(config, mother, father, tuples, rng, &mut child);
+        child
+    }
+
+    fn crossover_into(
+        &self,
+        config: &AlgorithmConfig,
+        mother: &Individual,
+        father: &Individual,
+        tuples: &[Tuple],
+        rng: &mut PlannerRng,
+        child: &mut Individual,
+    ) {
+        one_point_crossover(child, mother, father, rng);
+        repair_child(config, child, mother, tuples, rng);
+    }
+}
+
+/// [`CrossoverOperator::Order`], order crossover of the whole gene sequence
+#[derive(Debug, Clone, Copy)]
+pub struct OrderCrossover;
+
+impl Crossover for OrderCrossover {
+    fn crossover(
+        &self,
+        config: &AlgorithmConfig,
+        mother: &Individual,
+        father: &Individual,
+        tuples: &[Tuple],
+        rng: &mut PlannerRng,
+    ) -> Individual {
+        let mut child = Individual::default();
+        self.crossover_into(config, mother, father, tuples, rng, &mut child);
+        child
+    }
+
+    fn crossover_into(
+        &self,
+        config: &AlgorithmConfig,
+        mother: &Individual,
+        father: &Individual,
+        tuples: &[Tuple],
+        rng: &mut PlannerRng,
+        child: &mut Individual,
+    ) {
+        order_crossover(child, mother, father, rng);
+        repair_child(config, child, mother, tuples, rng);
+    }
+}
+
+/// Random gene moves with the guided and hyper mutation settings, see [`mutate`]
+#[derive(Debug, Clone, Copy)]
+pub struct RandomMoveMutator;
+
+impl Mutator for RandomMoveMutator {
+    fn mutate(
+        &self,
+        config: &AlgorithmConfig,
+        individual: &mut Individual,
+        tuples: &[Tuple],
+        rng: &mut PlannerRng,
+    ) {
+        mutate(config, individual, tuples, rng);
+    }
+}
+
+/// Operators the genetic algorithm breeds children with
+///
+/// [`Operators::from_config`] picks the built-in operators named in the configuration. A new
+/// operator implements one of the traits and gets a variant in the configuration mapped to it here,
+/// the rest of the algorithm only sees the trait objects. Any of them can also be replaced
+/// directly, e.g. `Operators { mutator: Box::new(MyMutator), ..Operators::from_config(&config) }`.
+pub struct Operators {
+    pub selector: Box<dyn Selector>,
+    pub crossover: Box<dyn Crossover>,
+    pub mutator: Box<dyn Mutator>,
+}
+
+impl Operators {
+    pub fn from_config(config: &AlgorithmConfig) -> Self {
+        let selector: Box<dyn Selector> = match config.selection_operator {
+            _ if config.multi_objective => Box::new(TournamentSelector),
+            SelectionOperator::Rank => Box::new(RankSelector),
+            SelectionOperator::Roulette => Box::new(RouletteSelector),
+        };
+        let crossover: Box<dyn Crossover> = match config.crossover_operator {
+            CrossoverOperator::OnePoint => Box::new(OnePointCrossover),
+            CrossoverOperator::Order => Box::new(OrderCrossover),
+        };
+
+        Operators {
+            selector,
+            crossover,
+            mutator: Box::new(RandomMoveMutator),
+        }
+    }
+
+    /// Select two parents, combine them and mutate the child, written over `child` like in
+    /// [`Crossover::crossover_into`]
+    ///
+    /// Returns the better of the parents.
+    pub fn breed_into<'a>(
+        &self,
+        config: &AlgorithmConfig,
+        population: &'a Population,
+        tuples: &[Tuple],
+        rng: &mut PlannerRng,
+        child: &mut Individual,
+    ) -> &'a Individual {
+        let (mother, father) = self.selector.select(config, population, rng);
+        self.breed_parents_into(config, mother, father, tuples, rng, child);
+        super::better_of(mother, father)
+    }
+
+    /// Combine the given parents and mutate the child
+    ///
+    /// With `room_assignment` or `teacher_assignment` the child inherits the rooms and teachers
+    /// of the tuples from the parents, see [`assignment::inherit`].
+    pub fn breed_parents(
+        &self,
+        config: &AlgorithmConfig,
+        mother: &Individual,
+        father: &Individual,
+        tuples: &[Tuple],
+        rng: &mut PlannerRng,
+    ) -> Individual {
+        let mut child = Individual::default();
+        self.breed_parents_into(config, mother, father, tuples, rng, &mut child);
+        child
+    }
+
+    /// Like [`Operators::breed_parents`], but writes the child over `child`
+    pub fn breed_parents_into(
+        &self,
+        config: &AlgorithmConfig,
+        mother: &Individual,
+        father: &Individual,
+        tuples: &[Tuple],
+        rng: &mut PlannerRng,
+        child: &mut Individual,
+    ) {
+        self.crossover
+            .crossover_into(config, mother, father, tuples, rng, child);
+        if config.assigns_resources() {
+            assignment::inherit(config, child, mother, father, tuples, rng);
+        }
+        self.mutator.mutate(config, child, tuples, rng);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::{
+        create_first_population, datatypes::TupleId, random::get_random_generator,
+    };
+
+    /// Mutator leaving children untouched
+    struct Identity;
+
+    impl Mutator for Identity {
+        fn mutate(&self, _: &AlgorithmConfig, _: &mut Individual, _: &[Tuple], _: &mut PlannerRng) {
+        }
+    }
+
+    #[test]
+    fn test_custom_operators_replace_built_in_ones() {
+        let tuples: Vec<Tuple> = (1..=5)
+            .map(|id| Tuple {
+                id: TupleId(id),
+                ..Tuple::default()
+            })
+            .collect();
+        let config = AlgorithmConfig {
+            population_size: 2,
+            number_of_periods: 3,
+            mutation_probability: 1.0,
+            ..AlgorithmConfig::default()
+        };
+        let mut rng = get_random_generator(1);
+        let mut population = create_first_population(&config, &tuples, &mut rng);
+        population[1] = population[0].clone();
+
+        let operators = Operators {
+            mutator: Box::new(Identity),
+            ..Operators::from_config(&config)
+        };
+        let mut child = Individual::default();
+        operators.breed_into(&config, &population, &tuples, &mut rng, &mut child);
+
+        // crossover of identical parents without mutation gives the parents back
+        assert_eq!(child.placement_hash(), population[0].placement_hash());
+    }
+}
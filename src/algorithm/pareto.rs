@@ -0,0 +1,324 @@
+use std::cmp::{Ordering, Reverse};
+
+use rand::prelude::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    config::AlgorithmConfig,
+    datatypes::{Individual, Population, Tuple},
+    fitness::Fitness,
+};
+
+/// Constraints counted in [`Objectives::preferences`]
+const PREFERENCE_CONSTRAINTS: [&str; 1] = ["teacher_preferences"];
+
+/// Constraints counted in [`Objectives::compactness`]
+const COMPACTNESS_CONSTRAINTS: [&str; 3] = ["compactness", "teacher_gaps", "group_gaps"];
+
+/// Penalties of a schedule split into the objectives of the multi-objective mode, lower is better
+///
+/// Together they add up to `-adaptation`, so the individual with the best adaptation is never
+/// dominated.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Objectives {
+    /// Penalties of all constraints that are neither preferences nor compactness
+    pub violations: i32,
+    /// Penalty of the teacher preferences
+    pub preferences: i32,
+    /// Penalties of isolated classes and gaps
+    pub compactness: i32,
+}
+
+impl Objectives {
+    pub fn of(config: &AlgorithmConfig, individual: &Individual, tuples: &[Tuple]) -> Self {
+        let mut objectives = Objectives::default();
+
+        for (name, penalty) in Fitness::from_config(config).penalties(config, individual, tuples) {
+            if PREFERENCE_CONSTRAINTS.contains(&name) {
+                objectives.preferences += penalty;
+            } else if COMPACTNESS_CONSTRAINTS.contains(&name) {
+                objectives.compactness += penalty;
+            } else {
+                objectives.violations += penalty;
+            }
+        }
+
+        objectives
+    }
+
+    fn values(&self) -> [i32; 3] {
+        [self.violations, self.preferences, self.compactness]
+    }
+
+    /// Whether these objectives are at least as good in all and better in at least one
+    pub fn dominates(&self, other: &Objectives) -> bool {
+        let (values, others) = (self.values(), other.values());
+        values
+            .iter()
+            .zip(&others)
+            .all(|(value, other)| value <= other)
+            && values != others
+    }
+}
+
+/// A schedule of the Pareto front with its objectives
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParetoPoint {
+    pub objectives: Objectives,
+    pub individual: Individual,
+}
+
+/// Split individuals into fronts of non-dominated sorting, as indices into `objectives`
+///
+/// The first front isn't dominated by anything, every next one only by the fronts before it.
+pub fn non_dominated_sort(objectives: &[Objectives]) -> Vec<Vec<usize>> {
+    let mut dominated_by = vec![0; objectives.len()];
+    let mut dominates: Vec<Vec<usize>> = vec![Vec::new(); objectives.len()];
+
+    for (first, a) in objectives.iter().enumerate() {
+        for (second, b) in objectives.iter().enumerate().skip(first + 1) {
+            if a.dominates(b) {
+                dominates[first].push(second);
+                dominated_by[second] += 1;
+            } else if b.dominates(a) {
+                dominates[second].push(first);
+                dominated_by[first] += 1;
+            }
+        }
+    }
+
+    let mut fronts = Vec::new();
+    let mut front: Vec<usize> = (0..objectives.len())
+        .filter(|index| dominated_by[*index] == 0)
+        .collect();
+    while !front.is_empty() {
+        let mut next = Vec::new();
+        for index in &front {
+            for dominated in &dominates[*index] {
+                dominated_by[*dominated] -= 1;
+                if dominated_by[*dominated] == 0 {
+                    next.push(*dominated);
+                }
+            }
+        }
+        fronts.push(front);
+        front = next;
+    }
+
+    fronts
+}
+
+/// Crowding distance of every member of a front, in the order of `front`
+///
+/// The extremes of every objective get an infinite distance, the others the sum over objectives
+/// of the normalized distance between their neighbours.
+pub fn crowding_distances(objectives: &[Objectives], front: &[usize]) -> Vec<f64> {
+    let mut distances = vec![0.0; front.len()];
+
+    for objective in 0..3 {
+        let value = |position: usize| objectives[front[position]].values()[objective];
+        let mut order: Vec<usize> = (0..front.len()).collect();
+        order.sort_by_key(|position| value(*position));
+
+        let (Some(first), Some(last)) = (order.first(), order.last()) else {
+            continue;
+        };
+        let range = (value(*last) - value(*first)) as f64;
+        distances[*first] = f64::INFINITY;
+        distances[*last] = f64::INFINITY;
+        if range == 0.0 {
+            continue;
+        }
+
+        for window in order.windows(3) {
+            distances[window[1]] += (value(window[2]) - value(window[0])) as f64 / range;
+        }
+    }
+
+    distances
+}
+
+/// Replace a generation by NSGA-II environmental selection, keeping the population size
+///
+/// Parents and offspring compete together: whole fronts of non-dominated sorting survive in turn,
+/// and the front that doesn't fit is cut by crowding distance, keeping the least crowded. The
+/// individual with the best adaptation always survives, so the best adaptation never gets worse.
+/// The survivors are ordered best first, front by front, for [`tournament_parents`]. Returns the
+/// individuals that didn't survive.
+pub fn replace_generation(
+    config: &AlgorithmConfig,
+    population: &mut Population,
+    offspring: Population,
+    tuples: &[Tuple],
+) -> Population {
+    let size = population.len();
+    let mut candidates: Vec<Option<Individual>> =
+        population.drain(..).chain(offspring).map(Some).collect();
+    let objectives: Vec<Objectives> = candidates
+        .par_iter()
+        .map(|individual| Objectives::of(config, individual.as_ref().unwrap(), tuples))
+        .collect();
+    let best =
+        (0..candidates.len()).max_by_key(|index| candidates[*index].as_ref().unwrap().adaptation);
+
+    for front in non_dominated_sort(&objectives) {
+        if population.len() == size {
+            break;
+        }
+
+        let mut distances = crowding_distances(&objectives, &front);
+        if let Some(position) = front.iter().position(|index| Some(*index) == best) {
+            distances[position] = f64::INFINITY;
+        }
+        let mut order: Vec<usize> = (0..front.len()).collect();
+        order.sort_by(|a, b| {
+            distances[*b]
+                .partial_cmp(&distances[*a])
+                .unwrap_or(Ordering::Equal)
+        });
+
+        for position in order.into_iter().take(size - population.len()) {
+            population.push(candidates[front[position]].take().unwrap());
+        }
+    }
+
+    candidates.into_iter().flatten().collect()
+}
+
+/// Two different parents by binary tournaments on a population ordered by
+/// [`replace_generation`], the one placed earlier wins
+pub fn tournament_parents<'a>(
+    population: &'a Population,
+    rng: &mut impl Rng,
+) -> (&'a Individual, &'a Individual) {
+    assert!(population.len() > 1);
+
+    let mut tournament = || {
+        let first = rng.gen_range(0..population.len());
+        let second = rng.gen_range(0..population.len());
+        first.min(second)
+    };
+
+    let mother = tournament();
+    let father = loop {
+        let father = tournament();
+        if father != mother {
+            break father;
+        }
+    };
+
+    (&population[mother], &population[father])
+}
+
+/// Distinct trade-offs among the individuals: the non-dominated ones, one per objective values,
+/// ordered by adaptation descending
+pub fn front(
+    config: &AlgorithmConfig,
+    individuals: &[Individual],
+    tuples: &[Tuple],
+) -> Vec<ParetoPoint> {
+    let objectives: Vec<Objectives> = individuals
+        .par_iter()
+        .map(|individual| Objectives::of(config, individual, tuples))
+        .collect();
+
+    let mut front: Vec<ParetoPoint> = non_dominated_sort(&objectives)
+        .into_iter()
+        .next()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|index| ParetoPoint {
+            objectives: objectives[index],
+            individual: individuals[index].clone(),
+        })
+        .collect();
+
+    front.sort_by_key(|point| {
+        (
+            Reverse(point.individual.adaptation),
+            point.objectives.values(),
+        )
+    });
+    front.dedup_by_key(|point| point.objectives);
+    front
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::datatypes::TupleId;
+
+    fn objectives(values: &[[i32; 3]]) -> Vec<Objectives> {
+        values
+            .iter()
+            .map(|[violations, preferences, compactness]| Objectives {
+                violations: *violations,
+                preferences: *preferences,
+                compactness: *compactness,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fronts_and_crowding() {
+        let objectives = objectives(&[[0, 4, 0], [1, 1, 0], [2, 0, 0], [1, 2, 0], [3, 3, 3]]);
+
+        let fronts = non_dominated_sort(&objectives);
+        assert_eq!(fronts, vec![vec![0, 1, 2], vec![3], vec![4]]);
+
+        let distances = crowding_distances(&objectives, &fronts[0]);
+        assert!(distances[0].is_infinite() && distances[2].is_infinite());
+        assert_eq!(distances[1], 2.0);
+    }
+
+    #[test]
+    fn test_best_adaptation_survives() {
+        use crate::algorithm::{
+            calculate_fitness, create_first_population, random::get_random_generator,
+        };
+
+        let tuples: Vec<Tuple> = (1..=6)
+            .map(|id| Tuple {
+                id: TupleId(id),
+                teacher: format!("Teacher {}", id % 2),
+                room: format!("Room {}", id % 3),
+                group: "1A".to_string(),
+                ..Tuple::default()
+            })
+            .collect();
+        let config = AlgorithmConfig {
+            population_size: 8,
+            number_of_periods: 4,
+            compactness_weight: 1,
+            multi_objective: true,
+            ..AlgorithmConfig::default()
+        };
+
+        let mut rng = get_random_generator(7);
+        let mut population = create_first_population(&config, &tuples, &mut rng);
+        for individual in &mut population {
+            individual.adaptation = calculate_fitness(&config, individual, &tuples);
+        }
+        let offspring = population.split_off(4);
+        let best = offspring
+            .iter()
+            .chain(&population)
+            .map(|individual| individual.adaptation)
+            .max()
+            .unwrap();
+
+        replace_generation(&config, &mut population, offspring, &tuples);
+
+        assert_eq!(population.len(), 4);
+        assert!(population
+            .iter()
+            .any(|individual| individual.adaptation == best));
+        assert_eq!(
+            front(&config, &population, &tuples)[0]
+                .individual
+                .adaptation,
+            best
+        );
+    }
+}
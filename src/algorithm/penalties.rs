@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+
+use super::config::AlgorithmConfig;
+
+/// Re-weights the constraints that stay violated
+///
+/// Every `penalty_adaptation_interval` generations the multiplier of every constraint the best
+/// individuals still violate grows by `penalty_increase_rate` and the multiplier of every satisfied
+/// one shrinks by `penalty_decrease_rate`, within `min_penalty_multiplier` and
+/// `max_penalty_multiplier`. The multipliers scale the penalties by constraint name, see
+/// [`AlgorithmConfig::penalty_multipliers`].
+#[derive(Debug, Clone)]
+pub struct PenaltyController {
+    interval: usize,
+    increase_rate: f64,
+    decrease_rate: f64,
+    min_multiplier: f64,
+    max_multiplier: f64,
+    multipliers: BTreeMap<String, f64>,
+    generations: usize,
+}
+
+impl PenaltyController {
+    pub fn new(config: &AlgorithmConfig) -> Self {
+        PenaltyController {
+            interval: config.penalty_adaptation_interval,
+            increase_rate: config.penalty_increase_rate.into(),
+            decrease_rate: config.penalty_decrease_rate.into(),
+            min_multiplier: config.min_penalty_multiplier.into(),
+            max_multiplier: config.max_penalty_multiplier.into(),
+            multipliers: BTreeMap::new(),
+            generations: 0,
+        }
+    }
+
+    /// Multiplier of every constraint re-weighted so far, the others keep 1
+    pub fn multipliers(&self) -> &BTreeMap<String, f64> {
+        &self.multipliers
+    }
+
+    /// Record the penalties of the best individuals of a generation by constraint name, returns
+    /// whether the multipliers changed
+    pub fn observe<'a>(&mut self, penalties: impl IntoIterator<Item = (&'a str, i32)>) -> bool {
+        self.generations += 1;
+        if !self.generations.is_multiple_of(self.interval) {
+            return false;
+        }
+
+        let mut changed = false;
+        for (name, penalty) in penalties {
+            let multiplier = self.multipliers.entry(name.to_string()).or_insert(1.0);
+            let factor = if penalty > 0 {
+                1.0 + self.increase_rate
+            } else {
+                1.0 - self.decrease_rate
+            };
+            let adjusted = (*multiplier * factor).clamp(self.min_multiplier, self.max_multiplier);
+            changed |= adjusted != *multiplier;
+            *multiplier = adjusted;
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_violated_constraints_gain_weight_within_bounds() {
+        let config = AlgorithmConfig {
+            penalty_adaptation_interval: 2,
+            penalty_increase_rate: 0.5,
+            penalty_decrease_rate: 0.5,
+            min_penalty_multiplier: 0.5,
+            max_penalty_multiplier: 2.0,
+            ..AlgorithmConfig::default()
+        };
+        let mut controller = PenaltyController::new(&config);
+        let penalties = [("clashes", 10), ("teacher_gaps", 0)];
+
+        assert!(!controller.observe(penalties));
+        assert!(controller.observe(penalties));
+        assert_eq!(controller.multipliers()["clashes"], 1.5);
+        assert_eq!(controller.multipliers()["teacher_gaps"], 0.5);
+
+        controller.observe(penalties);
+        controller.observe(penalties);
+        assert_eq!(controller.multipliers()["clashes"], 2.0);
+        assert_eq!(controller.multipliers()["teacher_gaps"], 0.5);
+    }
+}
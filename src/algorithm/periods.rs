@@ -0,0 +1,120 @@
+use std::ops::Range;
+
+use super::config::AlgorithmConfig;
+
+/// Position of a period in the week
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DaySlot {
+    pub day: usize,
+    pub slot: usize,
+}
+
+/// Maps flat period indices ([`Chromosome::id`](super::datatypes::Chromosome::id)) to days and
+/// time slots
+///
+/// Periods are numbered day by day: the first `slots_per_day` periods belong to the first day and
+/// so on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeriodLayout {
+    pub days: usize,
+    pub slots_per_day: usize,
+}
+
+impl PeriodLayout {
+    pub fn new(days: usize, slots_per_day: usize) -> Self {
+        PeriodLayout {
+            days,
+            slots_per_day,
+        }
+    }
+
+    /// Layout described by the configuration
+    ///
+    /// If only one of `days` and `slots_per_day` is given, the other is derived from
+    /// `number_of_periods`. Without either, all periods belong to a single day. Zeros, which
+    /// [`AlgorithmConfig::validate`] rejects, count as not given.
+    pub fn from_config(config: &AlgorithmConfig) -> Self {
+        let number_of_periods = config.number_of_periods.max(1);
+        let given = |count: Option<usize>| count.filter(|count| *count > 0);
+
+        match (given(config.days), given(config.slots_per_day)) {
+            (Some(days), Some(slots_per_day)) => PeriodLayout::new(days, slots_per_day),
+            (Some(days), None) => PeriodLayout::new(days, number_of_periods.div_ceil(days)),
+            (None, Some(slots_per_day)) => {
+                PeriodLayout::new(number_of_periods.div_ceil(slots_per_day), slots_per_day)
+            }
+            (None, None) => PeriodLayout::new(1, number_of_periods),
+        }
+    }
+
+    pub fn number_of_periods(&self) -> usize {
+        self.days * self.slots_per_day
+    }
+
+    /// Day and slot of a period
+    pub fn day_slot(&self, period_id: usize) -> DaySlot {
+        DaySlot {
+            day: period_id / self.slots_per_day,
+            slot: period_id % self.slots_per_day,
+        }
+    }
+
+    /// Periods occupied by a block of `duration` periods starting at `start`, if it fits within
+    /// the day of `start`
+    pub fn block(&self, start: usize, duration: usize) -> Option<Range<usize>> {
+        let fits = self.day_slot(start).slot + duration <= self.slots_per_day
+            && start + duration <= self.number_of_periods();
+        fits.then_some(start..start + duration)
+    }
+
+    /// Periods occupied by a block of `duration` periods starting at `start`, cut at the end of
+    /// the day
+    pub fn clamped_block(&self, start: usize, duration: usize) -> Range<usize> {
+        let day_end = (self.day_slot(start).day + 1) * self.slots_per_day;
+        start..(start + duration).min(day_end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_day_slot_of_period() {
+        let layout = PeriodLayout::new(5, 6);
+        assert_eq!(layout.number_of_periods(), 30);
+        assert_eq!(layout.day_slot(13), DaySlot { day: 2, slot: 1 });
+        assert_eq!(layout.day_slot(29), DaySlot { day: 4, slot: 5 });
+    }
+
+    #[test]
+    fn test_blocks_stay_within_a_day() {
+        let layout = PeriodLayout::new(2, 4);
+        assert_eq!(layout.block(1, 3), Some(1..4));
+        assert_eq!(layout.block(2, 3), None);
+        assert_eq!(layout.clamped_block(2, 3), 2..4);
+        assert_eq!(layout.block(7, 2), None);
+    }
+
+    #[test]
+    fn test_layout_from_config() {
+        let config = AlgorithmConfig {
+            number_of_periods: 8,
+            ..AlgorithmConfig::default()
+        };
+        assert_eq!(PeriodLayout::from_config(&config), PeriodLayout::new(1, 8));
+
+        let config = AlgorithmConfig {
+            days: Some(4),
+            ..config
+        };
+        assert_eq!(PeriodLayout::from_config(&config), PeriodLayout::new(4, 2));
+
+        let config = AlgorithmConfig {
+            days: Some(0),
+            slots_per_day: Some(0),
+            ..config
+        };
+        assert_eq!(PeriodLayout::from_config(&config), PeriodLayout::new(1, 8));
+    }
+}
@@ -0,0 +1,68 @@
+use super::datatypes::{Individual, Population};
+
+/// Individuals of past generations kept for their allocations
+///
+/// Replaced individuals are [`recycled`](IndividualPool::recycle) instead of dropped, and the
+/// children of the next generation are written over them, reusing their chromosome vectors and
+/// spilled genes. The population size stays the same between generations, so after the first one
+/// the generation loop takes every child from the pool.
+#[derive(Debug, Default)]
+pub struct IndividualPool {
+    free: Vec<Individual>,
+    /// Individuals taken from `free` so far
+    reused: usize,
+    /// Individuals created because `free` was empty
+    created: usize,
+}
+
+impl IndividualPool {
+    /// `count` individuals to overwrite, recycled ones first
+    ///
+    /// Their contents are stale, the crossover resets every field of the child.
+    pub fn take(&mut self, count: usize) -> Population {
+        let recycled = count.min(self.free.len());
+        let mut individuals = self.free.split_off(self.free.len() - recycled);
+        individuals.resize_with(count, Individual::default);
+
+        self.reused += recycled;
+        self.created += count - recycled;
+        individuals
+    }
+
+    /// Keep the individuals for the following [`take`](IndividualPool::take)s
+    pub fn recycle(&mut self, individuals: impl IntoIterator<Item = Individual>) {
+        self.free.extend(individuals);
+    }
+
+    /// Number of taken individuals that were recycled
+    pub fn reused(&self) -> usize {
+        self.reused
+    }
+
+    /// Number of taken individuals that had to be created
+    pub fn created(&self) -> usize {
+        self.created
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::datatypes::{Chromosome, PeriodId};
+
+    #[test]
+    fn test_recycled_individuals_are_taken_first() {
+        let mut pool = IndividualPool::default();
+        let recycled = Individual::with_chromosomes(vec![Chromosome::new(PeriodId(0))]);
+        pool.recycle([recycled.clone(), recycled]);
+
+        let taken = pool.take(3);
+
+        assert_eq!(taken.len(), 3);
+        assert_eq!(taken[0].chromosomes.len(), 1);
+        assert!(taken[2].chromosomes.is_empty());
+        assert_eq!((pool.reused(), pool.created()), (2, 1));
+        assert_eq!(pool.take(1)[0].chromosomes.len(), 0);
+        assert_eq!(pool.created(), 2);
+    }
+}
@@ -0,0 +1,174 @@
+use std::cmp::Reverse;
+
+use rand::Rng;
+
+use super::{
+    calculate_fitness,
+    config::AlgorithmConfig,
+    datatypes::{Individual, Population, Tuple},
+    mutate,
+};
+
+/// Adjusts the population size between generations
+///
+/// While the best adaptation keeps improving, improvements are cheap and the population shrinks
+/// by `population_resize_step`, saving evaluations. After `population_stagnation_generations`
+/// generations without improvement it grows by the same step to bring back diversity.
+///
+/// Sizes are kept within `[min_population_size, max_population_size]` and rounded to a multiple
+/// of `granularity` (the number of nodes the population is split across).
+#[derive(Debug, Clone)]
+pub struct PopulationSizeController {
+    min_size: usize,
+    max_size: usize,
+    resize_step: f32,
+    stagnation_generations: usize,
+    granularity: usize,
+    best_adaptation: Option<i32>,
+    generations_without_improvement: usize,
+}
+
+impl PopulationSizeController {
+    pub fn new(config: &AlgorithmConfig, granularity: usize) -> Self {
+        let min_size = config.min_population_size.div_ceil(granularity) * granularity;
+        let max_size = (config.max_population_size / granularity * granularity).max(min_size);
+
+        PopulationSizeController {
+            min_size,
+            max_size,
+            resize_step: config.population_resize_step,
+            stagnation_generations: config.population_stagnation_generations,
+            granularity,
+            best_adaptation: None,
+            generations_without_improvement: 0,
+        }
+    }
+
+    /// Get the population size for the next generation given the best adaptation of the current
+    pub fn next_size(&mut self, current_size: usize, best_adaptation: i32) -> usize {
+        let improved = self
+            .best_adaptation
+            .is_none_or(|previous_best| best_adaptation > previous_best);
+
+        if improved {
+            self.best_adaptation = Some(best_adaptation);
+            self.generations_without_improvement = 0;
+            return self.clamp(current_size as f32 * (1.0 - self.resize_step));
+        }
+
+        self.generations_without_improvement += 1;
+
+        if self.generations_without_improvement >= self.stagnation_generations {
+            self.generations_without_improvement = 0;
+            return self.clamp(current_size as f32 * (1.0 + self.resize_step));
+        }
+
+        current_size
+    }
+
+    fn clamp(&self, size: f32) -> usize {
+        let size = (size.round() as usize).div_ceil(self.granularity) * self.granularity;
+        size.clamp(self.min_size, self.max_size)
+    }
+}
+
+/// Resize the population to `new_size`
+///
+/// Shrinking drops the worst individuals. Growing adds mutated clones of random individuals,
+/// evaluated again unless the mutation kept their adaptation up to date.
+pub fn resize_population(
+    config: &AlgorithmConfig,
+    population: &mut Population,
+    tuples: &[Tuple],
+    new_size: usize,
+    rng: &mut impl Rng,
+) {
+    if new_size <= population.len() {
+        population.sort_by_key(|individual| Reverse(individual.adaptation));
+        population.truncate(new_size);
+        return;
+    }
+
+    let clones: Vec<Individual> = (population.len()..new_size)
+        .map(|_| {
+            let mut clone = population[rng.gen_range(0..population.len())].clone();
+            mutate(config, &mut clone, tuples, rng);
+            if config.incremental_conflicts().is_none() {
+                clone.adaptation = calculate_fitness(config, &clone, tuples);
+            }
+            clone
+        })
+        .collect();
+
+    population.extend(clones);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::{
+        create_first_population, datatypes::TupleId, random::get_random_generator,
+    };
+
+    fn controller() -> PopulationSizeController {
+        let config = AlgorithmConfig {
+            min_population_size: 40,
+            max_population_size: 200,
+            population_resize_step: 0.5,
+            population_stagnation_generations: 2,
+            ..AlgorithmConfig::default()
+        };
+        PopulationSizeController::new(&config, 4)
+    }
+
+    #[test]
+    fn test_shrinks_on_improvement_within_bounds() {
+        let mut controller = controller();
+        assert_eq!(controller.next_size(100, -50), 52);
+        assert_eq!(controller.next_size(52, -40), 40);
+    }
+
+    #[test]
+    fn test_grows_on_stagnation_within_bounds() {
+        let mut controller = controller();
+        controller.next_size(100, -50);
+        assert_eq!(controller.next_size(100, -50), 100);
+        assert_eq!(controller.next_size(100, -50), 152);
+        assert_eq!(controller.next_size(152, -50), 152);
+        assert_eq!(controller.next_size(152, -50), 200);
+    }
+
+    #[test]
+    fn test_grown_clones_are_evaluated_without_a_conflict_matrix() {
+        let tuples: Vec<Tuple> = (1..=6)
+            .map(|id| Tuple {
+                id: TupleId(id),
+                room: format!("Room {}", id % 2),
+                teacher: "Smith".to_string(),
+                ..Tuple::default()
+            })
+            .collect();
+        let config = AlgorithmConfig {
+            number_of_periods: 4,
+            mutation_probability: 1.0,
+            ..AlgorithmConfig::default()
+        };
+        assert!(config.incremental_conflicts().is_none());
+
+        let mut rng = get_random_generator(11);
+        let mut population = create_first_population(&config, &tuples, &mut rng);
+        population.truncate(4);
+        for individual in &mut population {
+            individual.adaptation = calculate_fitness(&config, individual, &tuples);
+        }
+
+        resize_population(&config, &mut population, &tuples, 20, &mut rng);
+        assert_eq!(population.len(), 20);
+        for individual in &population {
+            assert_eq!(
+                individual.adaptation,
+                calculate_fitness(&config, individual, &tuples)
+            );
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    clash_penalties,
+    config::AlgorithmConfig,
+    datatypes::{Gene, Tuple},
+};
+
+/// Periods every tuple may start in, computed once before the algorithm starts
+///
+/// A tuple can't start where its block doesn't fit within the day or covers one of its forbidden
+/// periods (including the unavailable periods of its teacher), and a locked tuple only starts in
+/// its period. Tuples left with a single start are fixed, so the periods they occupy are removed
+/// from the tuples that always clash with them, which may fix further tuples in turn.
+///
+/// A domain is never emptied: a tuple that can't avoid a clash keeps its starts and is left to
+/// the fitness. Creating, mutating and repairing individuals only uses the allowed starts, see
+/// [`AlgorithmConfig::can_start_at`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Domains {
+    starts: HashMap<Gene, Vec<usize>>,
+}
+
+impl Domains {
+    pub fn propagate(config: &AlgorithmConfig, tuples: &[Tuple]) -> Self {
+        let number_of_periods = config.number_of_periods;
+        let layout = config.period_layout();
+        let block = |tuple: &Tuple, start: usize| {
+            let block = layout.clamped_block(start, tuple.length());
+            block.start..block.end.min(number_of_periods)
+        };
+
+        let mut starts: Vec<Vec<usize>> = tuples
+            .iter()
+            .map(|tuple| {
+                match config
+                    .locks
+                    .get(&tuple.id)
+                    .filter(|p| **p < number_of_periods)
+                {
+                    Some(period_id) => vec![*period_id],
+                    None => (0..number_of_periods)
+                        .filter(|period_id| tuple.can_start_at(*period_id, &layout))
+                        .collect(),
+                }
+            })
+            .collect();
+
+        let mut fixed = vec![false; tuples.len()];
+        let mut queue: Vec<usize> = (0..tuples.len())
+            .filter(|index| starts[*index].len() == 1)
+            .collect();
+
+        while let Some(index) = queue.pop() {
+            if fixed[index] {
+                continue;
+            }
+            fixed[index] = true;
+            let tuple = &tuples[index];
+            let occupied = block(tuple, starts[index][0]);
+
+            for (other_index, other) in tuples.iter().enumerate() {
+                if fixed[other_index] || other_index == index || !always_clash(config, tuple, other)
+                {
+                    continue;
+                }
+
+                let remaining: Vec<usize> = starts[other_index]
+                    .iter()
+                    .copied()
+                    .filter(|start| {
+                        let other_block = block(other, *start);
+                        other_block.end <= occupied.start || occupied.end <= other_block.start
+                    })
+                    .collect();
+                if remaining.is_empty() || remaining.len() == starts[other_index].len() {
+                    continue;
+                }
+
+                if remaining.len() == 1 {
+                    queue.push(other_index);
+                }
+                starts[other_index] = remaining;
+            }
+        }
+
+        Domains {
+            starts: tuples.iter().map(|tuple| tuple.id).zip(starts).collect(),
+        }
+    }
+
+    /// Periods the gene may start in, `None` for genes of unknown tuples. Empty when the tuple
+    /// can't start anywhere without breaking a hard constraint
+    pub fn starts(&self, gene: Gene) -> Option<&[usize]> {
+        self.starts.get(&gene).map(Vec::as_slice)
+    }
+
+    /// Whether the gene may start in the period, genes of unknown tuples may start anywhere
+    pub fn allows(&self, gene: Gene, period_id: usize) -> bool {
+        self.starts(gene)
+            .is_none_or(|starts| starts.binary_search(&period_id).is_ok())
+    }
+
+    /// Number of allowed starts of all tuples
+    pub fn size(&self) -> usize {
+        self.starts.values().map(Vec::len).sum()
+    }
+}
+
+/// Whether the tuples clash whatever rooms and teachers the algorithm assigns
+fn always_clash(config: &AlgorithmConfig, tuple: &Tuple, other: &Tuple) -> bool {
+    let fixed_resources = |tuple: &Tuple| {
+        (!config.room_assignment || tuple.allowed_rooms.is_empty())
+            && (!config.teacher_assignment || tuple.qualified_teachers.is_empty())
+    };
+
+    fixed_resources(tuple)
+        && fixed_resources(other)
+        && (clash_penalties(config, tuple, other) != (0, 0)
+            || clash_penalties(config, other, tuple) != (0, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::{datatypes::TupleId, symbols::Symbols};
+
+    #[test]
+    fn test_fixed_tuples_prune_clashing_starts() {
+        let tuple = |id: u16, teacher: &str, forbidden_periods: Vec<usize>| Tuple {
+            id: TupleId(id),
+            teacher: teacher.to_string(),
+            room: format!("Room {}", id),
+            forbidden_periods,
+            ..Tuple::default()
+        };
+        let mut tuples = vec![
+            tuple(1, "Smith", Vec::new()),
+            tuple(2, "Smith", vec![2, 3]),
+            tuple(3, "Smith", Vec::new()),
+            tuple(4, "Jones", Vec::new()),
+            tuple(5, "Smith", vec![1, 2, 3]),
+        ];
+        Symbols::intern(&mut tuples);
+        let config = AlgorithmConfig {
+            number_of_periods: 4,
+            locks: [(TupleId(1), 0)].into(),
+            ..AlgorithmConfig::default()
+        };
+
+        let domains = Domains::propagate(&config, &tuples);
+
+        let starts = |id| domains.starts(TupleId(id));
+        assert_eq!(starts(1), Some([0].as_slice()));
+        // only period 1 is left after the lock, which leaves periods 2 and 3 for the third
+        assert_eq!(starts(2), Some([1].as_slice()));
+        assert_eq!(starts(3), Some([2, 3].as_slice()));
+        assert_eq!(starts(4), Some([0, 1, 2, 3].as_slice()));
+        // can't avoid the lock, so it keeps its only start
+        assert_eq!(starts(5), Some([0].as_slice()));
+        let allows = |id, period| domains.allows(TupleId(id), period);
+        assert!(allows(4, 2) && !allows(3, 0) && allows(6, 0));
+        assert_eq!(domains.size(), 9);
+    }
+}
@@ -1,11 +1,34 @@
-use rand::Rng;
+use rand::{rngs::StdRng, RngCore, SeedableRng};
 
-/// Returns a random number generator.
+/// Random number generator used by the algorithm.
 ///
-/// Uses [`rand::rngs::ThreadRng`] to get a random number generator.
-/// It refreshes entropy every 64 KiB of random data and on fork.
-pub fn get_random_generator() -> impl Rng {
-    rand::thread_rng()
-    // let seed: [u8; 32] = [42; 32];
-    // StdRng::from_seed(seed)
+/// Every rank holds a single generator seeded with [`rank_seed`] and passes it by `&mut` to the
+/// operators, so runs with the same seed and number of nodes are reproducible while ranks still
+/// explore different parts of the search space.
+pub type PlannerRng = StdRng;
+
+/// Returns a random number generator seeded with `seed`.
+pub fn get_random_generator(seed: u64) -> PlannerRng {
+    StdRng::seed_from_u64(seed)
+}
+
+/// Seed of the generator of the given rank, `seed + rank`.
+pub fn rank_seed(seed: u64, rank: u64) -> u64 {
+    seed.wrapping_add(rank)
+}
+
+/// Seed of the `run`-th of several independent runs, run 0 uses `seed` itself
+///
+/// Other runs get seeds scrambled through a generator, so their rank seeds don't overlap.
+pub fn run_seed(seed: u64, run: u64) -> u64 {
+    if run == 0 {
+        seed
+    } else {
+        get_random_generator(seed.wrapping_add(run)).next_u64()
+    }
+}
+
+/// Returns a seed drawn from [`rand::rngs::ThreadRng`], used when no seed is given.
+pub fn random_seed() -> u64 {
+    rand::thread_rng().next_u64()
 }
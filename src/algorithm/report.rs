@@ -0,0 +1,662 @@
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    assignment, clash_penalties,
+    config::AlgorithmConfig,
+    constraints,
+    datatypes::{Gene, Individual, Tuple},
+    fitness::{self, FitnessContext},
+    periods::PeriodLayout,
+};
+
+/// Attribute of a tuple classes are grouped by, e.g. the teacher
+type TupleKey = fn(&Tuple) -> &str;
+
+/// Constraint broken by a [`Conflict`]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    TeacherClash,
+    RoomClash,
+    TeacherGap,
+    GroupGap,
+    IsolatedClass,
+    ConsecutiveHours,
+    Workload,
+    WorkloadBalance,
+    TeacherPreference,
+    CourseSpread,
+    Lock,
+    ForbiddenPeriod,
+    BlockOverflow,
+    PeriodOverflow,
+    /// A constraint declared in the configuration, see [`ConstraintSpec`](super::config::ConstraintSpec)
+    Declared,
+}
+
+/// A single violation and how much it costs
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Conflict {
+    pub kind: ConflictKind,
+    /// Periods the violation happens in, e.g. the idle periods of a gap
+    pub periods: Vec<usize>,
+    /// Tuples responsible for the violation
+    pub tuples: Vec<Gene>,
+    /// How much the violation lowers the fitness
+    pub penalty: i32,
+}
+
+/// Penalty of one kind of conflict, in total and in every period
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PenaltyBreakdown {
+    pub kind: ConflictKind,
+    /// Number of conflicts of the kind
+    pub count: usize,
+    pub penalty: i32,
+    /// Penalty in every period. The penalty of a conflict spanning several periods is split
+    /// evenly between them, conflicts without periods (declared constraints) only count in
+    /// `penalty`.
+    pub periods: Vec<i32>,
+}
+
+/// How well the schedule of a teacher follows their preferences
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PreferenceSatisfaction {
+    pub teacher: String,
+    /// Periods taught
+    pub taught: usize,
+    /// Percentage of the periods taught in the preferred periods, `None` without preferred periods
+    pub preferred: Option<f64>,
+    /// Percentage of the periods taught outside the disliked periods, `None` without disliked
+    /// periods
+    pub not_disliked: Option<f64>,
+}
+
+/// Why a schedule scores what it scores
+///
+/// The penalties of all conflicts add up to `-adaptation`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ConflictReport {
+    pub adaptation: i32,
+    /// Remaining conflicts summed up by kind, see [`ConflictReport::breakdown`]
+    #[serde(default)]
+    pub breakdown: Vec<PenaltyBreakdown>,
+    pub conflicts: Vec<Conflict>,
+    /// Satisfaction of every teacher with preferred or disliked periods
+    #[serde(default)]
+    pub preferences: Vec<PreferenceSatisfaction>,
+}
+
+impl ConflictReport {
+    /// Break the fitness of an individual down into violations, following
+    /// [`calculate_fitness`](super::calculate_fitness)
+    pub fn explain(
+        config: &AlgorithmConfig,
+        individual: &Individual,
+        tuples: &[Tuple],
+    ) -> ConflictReport {
+        let tuples = &assignment::effective_tuples(config, individual, tuples);
+        let layout = config.period_layout();
+        let active_genes = constraints::active_genes(individual, tuples, &layout);
+        let find = |gene: &Gene| tuples.iter().find(|tuple| tuple.id == *gene);
+        let mut conflicts = Vec::new();
+
+        for (period, genes) in active_genes.iter().enumerate() {
+            let mut pair_penalties: BTreeMap<(ConflictKind, Gene, Gene), i32> = BTreeMap::new();
+
+            for tuple in genes.iter().filter_map(find) {
+                let other_classes = tuples
+                    .iter()
+                    .filter(|t| genes.contains(&t.id))
+                    .filter(|t| t.id != tuple.id);
+
+                for other in other_classes {
+                    let pair = (tuple.id.min(other.id), tuple.id.max(other.id));
+                    let (teacher_penalty, room_penalty) = clash_penalties(config, tuple, other);
+
+                    for (kind, penalty) in [
+                        (ConflictKind::TeacherClash, teacher_penalty),
+                        (ConflictKind::RoomClash, room_penalty),
+                    ] {
+                        if penalty != 0 {
+                            *pair_penalties.entry((kind, pair.0, pair.1)).or_default() += penalty;
+                        }
+                    }
+                }
+            }
+
+            conflicts.extend(
+                pair_penalties
+                    .into_iter()
+                    .map(|((kind, first, second), penalty)| Conflict {
+                        kind,
+                        periods: vec![period],
+                        tuples: vec![first, second],
+                        penalty,
+                    }),
+            );
+        }
+
+        let key_conflicts: [(ConflictKind, i32, TupleKey); 2] = [
+            (ConflictKind::TeacherGap, config.teacher_gap_weight, |t| {
+                &t.teacher
+            }),
+            (ConflictKind::GroupGap, config.group_gap_weight, |t| {
+                &t.group
+            }),
+        ];
+        for (kind, weight, key) in key_conflicts {
+            if weight == 0 {
+                continue;
+            }
+
+            let occupied = constraints::occupied_slots(individual, tuples, &layout, key);
+            for ((name, day), slots) in occupied.into_iter().sorted() {
+                let gaps: Vec<usize> = (slots[0]..=slots[slots.len() - 1])
+                    .filter(|slot| !slots.contains(slot))
+                    .map(|slot| day * layout.slots_per_day + slot)
+                    .collect();
+
+                if !gaps.is_empty() {
+                    conflicts.push(Conflict {
+                        kind,
+                        penalty: gaps.len() as i32 * weight,
+                        periods: gaps,
+                        tuples: genes_of(&active_genes, tuples, &layout, day, &slots, |t| {
+                            key(t) == name
+                        }),
+                    });
+                }
+            }
+        }
+
+        if config.compactness_weight != 0 {
+            let occupied = constraints::occupied_slots(individual, tuples, &layout, |t| &t.group);
+            for ((group, day), slots) in occupied.into_iter().sorted() {
+                for slot in constraints::isolated_slots(&slots) {
+                    conflicts.push(Conflict {
+                        kind: ConflictKind::IsolatedClass,
+                        periods: vec![day * layout.slots_per_day + slot],
+                        tuples: genes_of(&active_genes, tuples, &layout, day, &[slot], |t| {
+                            t.group == group
+                        }),
+                        penalty: config.compactness_weight,
+                    });
+                }
+            }
+        }
+
+        if config.max_consecutive_hours.is_some()
+            || !config.max_consecutive_hours_per_teacher.is_empty()
+        {
+            let occupied = constraints::occupied_slots(individual, tuples, &layout, |t| &t.teacher);
+            for ((teacher, day), slots) in occupied.into_iter().sorted() {
+                let Some(limit) = config.max_consecutive_hours_of(teacher) else {
+                    continue;
+                };
+
+                for run in slots.chunk_by(|previous, next| previous + 1 == *next) {
+                    if run.len() > limit {
+                        conflicts.push(Conflict {
+                            kind: ConflictKind::ConsecutiveHours,
+                            periods: run
+                                .iter()
+                                .map(|slot| day * layout.slots_per_day + slot)
+                                .collect(),
+                            tuples: genes_of(&active_genes, tuples, &layout, day, run, |t| {
+                                t.teacher == teacher
+                            }),
+                            penalty: (run.len() - limit) as i32 * config.consecutive_hours_weight,
+                        });
+                    }
+                }
+            }
+        }
+
+        if config.workload_weight != 0 {
+            let occupied = constraints::occupied_slots(individual, tuples, &layout, |t| &t.teacher);
+            let mut weekly: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+
+            for ((teacher, day), slots) in occupied.into_iter().sorted() {
+                let Some(limits) = config.teachers.get(teacher) else {
+                    continue;
+                };
+                let periods: Vec<usize> = slots
+                    .iter()
+                    .map(|slot| day * layout.slots_per_day + slot)
+                    .collect();
+
+                if let Some(limit) = limits.max_daily_hours.filter(|limit| slots.len() > *limit) {
+                    conflicts.push(Conflict {
+                        kind: ConflictKind::Workload,
+                        periods: periods.clone(),
+                        tuples: genes_of(&active_genes, tuples, &layout, day, &slots, |t| {
+                            t.teacher == teacher
+                        }),
+                        penalty: (slots.len() - limit) as i32 * config.workload_weight,
+                    });
+                }
+                weekly.entry(teacher).or_default().extend(periods);
+            }
+
+            for (teacher, periods) in weekly {
+                let Some(limit) = config.teachers[teacher]
+                    .max_weekly_hours
+                    .filter(|limit| periods.len() > *limit)
+                else {
+                    continue;
+                };
+                conflicts.push(Conflict {
+                    kind: ConflictKind::Workload,
+                    tuples: periods
+                        .iter()
+                        .flat_map(|period| &active_genes[*period])
+                        .filter(|gene| find(gene).is_some_and(|t| t.teacher == teacher))
+                        .copied()
+                        .unique()
+                        .collect(),
+                    penalty: (periods.len() - limit) as i32 * config.workload_weight,
+                    periods,
+                });
+            }
+        }
+
+        if config.course_spread_weight != 0 {
+            for sessions in constraints::course_sessions(individual, tuples, &layout).into_values()
+            {
+                if sessions.len() < 2 {
+                    continue;
+                }
+                conflicts.push(Conflict {
+                    kind: ConflictKind::CourseSpread,
+                    periods: individual
+                        .chromosomes
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, chromosome)| {
+                            chromosome.genes.iter().any(|gene| sessions.contains(gene))
+                        })
+                        .map(|(period, _)| period)
+                        .collect(),
+                    penalty: (sessions.len() - 1) as i32 * config.course_spread_weight,
+                    tuples: sessions,
+                });
+            }
+        }
+
+        for (period, genes) in active_genes.iter().enumerate() {
+            for tuple in genes.iter().filter_map(find) {
+                let Some(teacher) = config.teachers.get(&tuple.teacher) else {
+                    continue;
+                };
+                let mut penalty = 0;
+                if !teacher.preferred_periods.is_empty()
+                    && !teacher.preferred_periods.contains(&period)
+                {
+                    penalty += config.preferred_period_weight;
+                }
+                if teacher.disliked_periods.contains(&period) {
+                    penalty += config.disliked_period_weight;
+                }
+                conflicts.push(Conflict {
+                    kind: ConflictKind::TeacherPreference,
+                    periods: vec![period],
+                    tuples: vec![tuple.id],
+                    penalty,
+                });
+            }
+        }
+
+        if config.teacher_assignment && config.workload_balance_weight != 0 {
+            for (teacher, deviation) in constraints::workload_deviations(tuples) {
+                conflicts.push(Conflict {
+                    kind: ConflictKind::WorkloadBalance,
+                    periods: Vec::new(),
+                    tuples: tuples
+                        .iter()
+                        .filter(|tuple| tuple.teacher == teacher)
+                        .map(|tuple| tuple.id)
+                        .collect(),
+                    penalty: deviation as i32 * config.workload_balance_weight,
+                });
+            }
+        }
+
+        for (period, chromosome) in individual.chromosomes.iter().enumerate() {
+            for gene in &chromosome.genes {
+                if config
+                    .locks
+                    .get(gene)
+                    .is_some_and(|locked| *locked != period)
+                {
+                    conflicts.push(Conflict {
+                        kind: ConflictKind::Lock,
+                        periods: vec![period],
+                        tuples: vec![*gene],
+                        penalty: config.lock_violation_weight,
+                    });
+                }
+
+                let Some(tuple) = find(gene) else {
+                    continue;
+                };
+
+                let forbidden: Vec<usize> = layout
+                    .clamped_block(period, tuple.length())
+                    .filter(|period| !tuple.can_take_place_in(*period))
+                    .collect();
+                if !forbidden.is_empty() {
+                    conflicts.push(Conflict {
+                        kind: ConflictKind::ForbiddenPeriod,
+                        penalty: forbidden.len() as i32 * config.forbidden_period_weight,
+                        periods: forbidden,
+                        tuples: vec![*gene],
+                    });
+                }
+
+                if layout.block(period, tuple.length()).is_none() {
+                    conflicts.push(Conflict {
+                        kind: ConflictKind::BlockOverflow,
+                        periods: vec![period],
+                        tuples: vec![*gene],
+                        penalty: config.block_overflow_weight,
+                    });
+                }
+            }
+        }
+
+        if config.limits_classes_per_period() {
+            for (period, genes) in active_genes.iter().enumerate() {
+                let overflow = constraints::count_period_overflow(
+                    genes,
+                    tuples,
+                    &config.rooms,
+                    config.max_classes_per_period,
+                    &config.max_classes_per_room_kind,
+                );
+                if overflow != 0 {
+                    conflicts.push(Conflict {
+                        kind: ConflictKind::PeriodOverflow,
+                        periods: vec![period],
+                        tuples: genes.clone(),
+                        penalty: overflow as i32 * config.period_overflow_weight,
+                    });
+                }
+            }
+        }
+
+        let ctx = FitnessContext {
+            config,
+            tuples,
+            layout,
+            active_genes,
+        };
+        for spec in &config.constraints {
+            conflicts.push(Conflict {
+                kind: ConflictKind::Declared,
+                periods: Vec::new(),
+                tuples: Vec::new(),
+                penalty: fitness::compile(spec).penalty(individual, &ctx),
+            });
+        }
+
+        conflicts.retain(|conflict| conflict.penalty != 0);
+        conflicts.sort_by_key(|conflict| conflict.kind);
+
+        ConflictReport {
+            adaptation: -conflicts
+                .iter()
+                .map(|conflict| conflict.penalty)
+                .sum::<i32>(),
+            breakdown: Self::breakdown(&conflicts, individual.chromosomes.len()),
+            conflicts,
+            preferences: Self::preferences(config, &ctx.active_genes, tuples),
+        }
+    }
+
+    /// Satisfaction of the teachers with preferences, see [`constraints::count_preferences`]
+    pub fn preferences(
+        config: &AlgorithmConfig,
+        active_genes: &[Vec<Gene>],
+        tuples: &[Tuple],
+    ) -> Vec<PreferenceSatisfaction> {
+        let percentage = |part: usize, whole: usize| {
+            if whole == 0 {
+                100.0
+            } else {
+                part as f64 * 100.0 / whole as f64
+            }
+        };
+
+        constraints::count_preferences(active_genes, tuples, &config.teachers)
+            .into_iter()
+            .map(|(teacher, count)| {
+                let preferences = &config.teachers[teacher];
+                PreferenceSatisfaction {
+                    teacher: teacher.to_string(),
+                    taught: count.taught,
+                    preferred: (!preferences.preferred_periods.is_empty())
+                        .then(|| percentage(count.taught - count.outside_preferred, count.taught)),
+                    not_disliked: (!preferences.disliked_periods.is_empty())
+                        .then(|| percentage(count.taught - count.disliked, count.taught)),
+                }
+            })
+            .collect()
+    }
+
+    /// Sum the penalties of the conflicts, sorted by kind, up by kind. Only kinds with conflicts
+    /// are listed.
+    pub fn breakdown(conflicts: &[Conflict], number_of_periods: usize) -> Vec<PenaltyBreakdown> {
+        let mut breakdown: Vec<PenaltyBreakdown> = Vec::new();
+
+        for group in conflicts.chunk_by(|first, second| first.kind == second.kind) {
+            let mut kind_breakdown = PenaltyBreakdown {
+                kind: group[0].kind,
+                count: 0,
+                penalty: 0,
+                periods: vec![0; number_of_periods],
+            };
+
+            for conflict in group {
+                kind_breakdown.count += 1;
+                kind_breakdown.penalty += conflict.penalty;
+
+                let shares = conflict.periods.len() as i32;
+                for (index, period) in conflict.periods.iter().enumerate() {
+                    // the remainder of the split goes to the first period
+                    let remainder = if index == 0 {
+                        conflict.penalty % shares.max(1)
+                    } else {
+                        0
+                    };
+                    if let Some(penalty) = kind_breakdown.periods.get_mut(*period) {
+                        *penalty += conflict.penalty / shares + remainder;
+                    }
+                }
+            }
+
+            breakdown.push(kind_breakdown);
+        }
+
+        breakdown
+    }
+}
+
+/// Distinct genes matching `filter` active in the given slots of a day
+fn genes_of(
+    active_genes: &[Vec<Gene>],
+    tuples: &[Tuple],
+    layout: &PeriodLayout,
+    day: usize,
+    slots: &[usize],
+    filter: impl Fn(&Tuple) -> bool,
+) -> Vec<Gene> {
+    slots
+        .iter()
+        .flat_map(|slot| &active_genes[day * layout.slots_per_day + slot])
+        .filter(|gene| {
+            tuples
+                .iter()
+                .find(|tuple| tuple.id == **gene)
+                .is_some_and(&filter)
+        })
+        .copied()
+        .unique()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::{
+        calculate_fitness,
+        datatypes::{Chromosome, PeriodId, TupleId},
+        resources::Teacher,
+        symbols::Symbols,
+    };
+    use smallvec::smallvec;
+
+    #[test]
+    fn test_penalties_add_up_to_fitness() {
+        let mut tuples: Vec<Tuple> = [(1, "A", "Smith"), (2, "A", "Jones"), (3, "B", "Smith")]
+            .into_iter()
+            .map(|(id, room, teacher)| Tuple {
+                id: TupleId(id),
+                label: format!("Class {}", id),
+                room: room.to_string(),
+                teacher: teacher.to_string(),
+                forbidden_periods: if id == 3 { vec![3] } else { Vec::new() },
+                ..Tuple::default()
+            })
+            .collect();
+        Symbols::intern(&mut tuples);
+        let individual = Individual::with_chromosomes(
+            [vec![1, 2], vec![], vec![], vec![3]]
+                .into_iter()
+                .enumerate()
+                .map(|(id, genes)| Chromosome {
+                    id: PeriodId(id),
+                    genes: genes.into_iter().map(TupleId).collect(),
+                })
+                .collect(),
+        );
+        let config = AlgorithmConfig {
+            number_of_periods: 4,
+            teacher_gap_weight: 1,
+            ..AlgorithmConfig::default()
+        };
+
+        let report = ConflictReport::explain(&config, &individual, &tuples);
+        let kinds: Vec<ConflictKind> = report.conflicts.iter().map(|c| c.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ConflictKind::RoomClash,
+                ConflictKind::TeacherGap,
+                ConflictKind::ForbiddenPeriod
+            ]
+        );
+        assert_eq!(report.conflicts[1].periods, vec![1, 2]);
+        assert_eq!(report.conflicts[1].tuples, [1, 3].map(TupleId));
+        assert_eq!(report.breakdown.len(), 3);
+        assert_eq!(report.breakdown[0].periods, vec![40, 0, 0, 0]);
+        assert_eq!(report.breakdown[1].periods, vec![0, 1, 1, 0]);
+        assert_eq!(
+            report
+                .breakdown
+                .iter()
+                .map(|kind| kind.periods.iter().sum::<i32>())
+                .sum::<i32>(),
+            -report.adaptation
+        );
+        assert_eq!(
+            report.adaptation,
+            calculate_fitness(&config, &individual, &tuples)
+        );
+    }
+
+    #[test]
+    fn test_penalties_are_split_over_the_periods_of_their_kind() {
+        let conflict = |kind: ConflictKind, periods: Vec<usize>, penalty: i32| Conflict {
+            kind,
+            periods,
+            tuples: Vec::new(),
+            penalty,
+        };
+        let conflicts = vec![
+            conflict(ConflictKind::TeacherClash, vec![2], 40),
+            conflict(ConflictKind::TeacherClash, vec![2], 40),
+            conflict(ConflictKind::TeacherGap, vec![0, 1, 3], 5),
+            conflict(ConflictKind::TeacherGap, vec![9], 2),
+            conflict(ConflictKind::Declared, Vec::new(), 7),
+        ];
+
+        let breakdown = ConflictReport::breakdown(&conflicts, 4);
+        let totals: Vec<(ConflictKind, usize, i32)> = breakdown
+            .iter()
+            .map(|kind| (kind.kind, kind.count, kind.penalty))
+            .collect();
+        assert_eq!(
+            totals,
+            [
+                (ConflictKind::TeacherClash, 2, 80),
+                (ConflictKind::TeacherGap, 2, 7),
+                (ConflictKind::Declared, 1, 7),
+            ]
+        );
+        assert_eq!(breakdown[0].periods, [0, 0, 80, 0]);
+        // the remainder of 5 / 3 goes to the first period, periods past the layout are left out
+        assert_eq!(breakdown[1].periods, [3, 1, 0, 1]);
+        assert_eq!(breakdown[2].periods, [0; 4]);
+    }
+
+    #[test]
+    fn test_preference_satisfaction() {
+        let tuples: Vec<Tuple> = (1..=4)
+            .map(|id| Tuple {
+                id: TupleId(id),
+                room: format!("Room {}", id),
+                teacher: "Smith".to_string(),
+                ..Tuple::default()
+            })
+            .collect();
+        let teacher = Teacher {
+            id: "Smith".to_string(),
+            preferred_periods: vec![0, 1],
+            disliked_periods: vec![3],
+            ..Teacher::default()
+        };
+        let config = AlgorithmConfig {
+            number_of_periods: 4,
+            teachers: [(teacher.id.clone(), teacher)].into(),
+            ..AlgorithmConfig::default()
+        };
+        let individual = Individual::with_chromosomes(
+            (1..=4)
+                .map(|id| Chromosome {
+                    id: PeriodId(id as usize - 1),
+                    genes: smallvec![TupleId(id)],
+                })
+                .collect(),
+        );
+
+        let report = ConflictReport::explain(&config, &individual, &tuples);
+        assert_eq!(report.adaptation, -7);
+        assert_eq!(
+            report.adaptation,
+            calculate_fitness(&config, &individual, &tuples)
+        );
+        assert_eq!(
+            report.preferences,
+            vec![PreferenceSatisfaction {
+                teacher: "Smith".to_string(),
+                taught: 4,
+                preferred: Some(50.0),
+                not_disliked: Some(75.0),
+            }]
+        );
+    }
+}
@@ -0,0 +1,369 @@
+use std::{collections::BTreeMap, fs::File, io::Read, path::Path};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use thiserror::Error;
+
+use super::datatypes::{Gene, Tuple, TupleKind};
+
+#[derive(Debug, Error)]
+pub enum ResourcesLoadError {
+    #[error("Resources file not found")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Yaml(#[from] serde_yaml::Error),
+    #[error("Invalid periods of teacher {teacher}: {value}, expected numbers separated with ;")]
+    InvalidPeriods { teacher: String, value: String },
+}
+
+/// A room classes take place in, referenced by [`Tuple::room`]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Room {
+    pub id: String,
+    /// Number of seats, unknown if not set
+    #[serde(default)]
+    pub capacity: Option<usize>,
+    /// Kind of classes the room is equipped for, any if not set
+    #[serde(default)]
+    pub kind: Option<TupleKind>,
+    #[serde(default)]
+    pub building: String,
+}
+
+/// Single row of a rooms CSV file
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RoomRecord {
+    id: String,
+    #[serde(default)]
+    capacity: Option<usize>,
+    #[serde(default)]
+    kind: Option<TupleKind>,
+    #[serde(default)]
+    building: String,
+}
+
+/// Rooms by [`Room::id`]
+pub type Rooms = BTreeMap<String, Room>;
+
+/// A teacher, referenced by [`Tuple::teacher`], with limits of their workload
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Teacher {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    /// Most periods taught in all days together, unlimited if not set
+    #[serde(default)]
+    pub max_weekly_hours: Option<usize>,
+    /// Most periods taught in a day, unlimited if not set
+    #[serde(default)]
+    pub max_daily_hours: Option<usize>,
+    /// Periods the teacher can't teach in, added to the forbidden periods of their tuples
+    #[serde(default)]
+    pub unavailable_periods: Vec<usize>,
+    /// Periods the teacher would like to teach in, every period taught outside them costs
+    /// `preferred_period_weight`. Empty for no preference
+    #[serde(default)]
+    pub preferred_periods: Vec<usize>,
+    /// Periods the teacher would rather not teach in, every period taught in them costs
+    /// `disliked_period_weight`
+    #[serde(default)]
+    pub disliked_periods: Vec<usize>,
+}
+
+/// Single row of a teachers CSV file
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct TeacherRecord {
+    id: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    max_weekly_hours: Option<usize>,
+    #[serde(default)]
+    max_daily_hours: Option<usize>,
+    #[serde(default)]
+    unavailable_periods: String,
+    #[serde(default)]
+    preferred_periods: String,
+    #[serde(default)]
+    disliked_periods: String,
+}
+
+/// Teachers by [`Teacher::id`]
+pub type Teachers = BTreeMap<String, Teacher>;
+
+/// A group of students attending classes together, referenced by [`Tuple::group`]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Group {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    /// Number of students, unknown if not set
+    #[serde(default)]
+    pub size: Option<usize>,
+}
+
+/// Single row of a student groups CSV file
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct GroupRecord {
+    id: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    size: Option<usize>,
+}
+
+/// Student groups by [`Group::id`]
+pub type Groups = BTreeMap<String, Group>;
+
+/// Load resources from a file. Files with `.json` extension are read as a JSON array and `.yaml`
+/// or `.yml` as a YAML sequence, everything else as CSV with `read_csv`.
+fn load<T: DeserializeOwned>(
+    path: &Path,
+    read_csv: impl FnOnce(File) -> Result<Vec<T>, ResourcesLoadError>,
+) -> Result<Vec<T>, ResourcesLoadError> {
+    let file = File::open(path)?;
+
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => Ok(serde_json::from_reader(file)?),
+        Some("yaml" | "yml") => Ok(serde_yaml::from_reader(file)?),
+        _ => read_csv(file),
+    }
+}
+
+impl Room {
+    /// Load rooms from a JSON, YAML or CSV file with an `Id` and optional `Capacity`, `Kind` and
+    /// `Building` columns
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Vec<Room>, ResourcesLoadError> {
+        load(path.as_ref(), Room::read_csv)
+    }
+
+    /// Read rooms from CSV, see [`Room::from_file`]
+    pub fn read_csv(reader: impl Read) -> Result<Vec<Room>, ResourcesLoadError> {
+        let mut reader = csv::Reader::from_reader(reader);
+
+        reader
+            .deserialize()
+            .map(|result| {
+                let record: RoomRecord = result?;
+                Ok(Room {
+                    id: record.id,
+                    capacity: record.capacity,
+                    kind: record.kind,
+                    building: record.building,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Teacher {
+    /// Load teachers from a JSON, YAML or CSV file with an `Id` and optional `Name`,
+    /// `MaxWeeklyHours`, `MaxDailyHours`, `UnavailablePeriods`, `PreferredPeriods` and
+    /// `DislikedPeriods` columns
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Vec<Teacher>, ResourcesLoadError> {
+        load(path.as_ref(), Teacher::read_csv)
+    }
+
+    /// Read teachers from CSV, periods are separated with `;`
+    pub fn read_csv(reader: impl Read) -> Result<Vec<Teacher>, ResourcesLoadError> {
+        let mut reader = csv::Reader::from_reader(reader);
+
+        reader
+            .deserialize()
+            .map(|result| {
+                let record: TeacherRecord = result?;
+                let periods = |periods: &str| split_periods(&record.id, periods);
+                Ok(Teacher {
+                    unavailable_periods: periods(&record.unavailable_periods)?,
+                    preferred_periods: periods(&record.preferred_periods)?,
+                    disliked_periods: periods(&record.disliked_periods)?,
+                    id: record.id,
+                    name: record.name,
+                    max_weekly_hours: record.max_weekly_hours,
+                    max_daily_hours: record.max_daily_hours,
+                })
+            })
+            .collect()
+    }
+
+    /// Whether the teacher has a daily or weekly limit of periods
+    pub fn has_workload_limit(&self) -> bool {
+        self.max_weekly_hours.is_some() || self.max_daily_hours.is_some()
+    }
+
+    /// Whether the teacher has preferred or disliked periods
+    pub fn has_preferences(&self) -> bool {
+        !self.preferred_periods.is_empty() || !self.disliked_periods.is_empty()
+    }
+}
+
+/// Periods of a teacher separated with `;`, e.g. `6;7`
+fn split_periods(teacher: &str, periods: &str) -> Result<Vec<usize>, ResourcesLoadError> {
+    periods
+        .split(';')
+        .map(str::trim)
+        .filter(|period| !period.is_empty())
+        .map(|period| {
+            period
+                .parse()
+                .map_err(|_| ResourcesLoadError::InvalidPeriods {
+                    teacher: teacher.to_string(),
+                    value: periods.to_string(),
+                })
+        })
+        .collect()
+}
+
+impl Group {
+    /// Load student groups from a JSON, YAML or CSV file with an `Id` and optional `Name` and
+    /// `Size` columns
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Vec<Group>, ResourcesLoadError> {
+        load(path.as_ref(), Group::read_csv)
+    }
+
+    /// Read student groups from CSV, see [`Group::from_file`]
+    pub fn read_csv(reader: impl Read) -> Result<Vec<Group>, ResourcesLoadError> {
+        let mut reader = csv::Reader::from_reader(reader);
+
+        reader
+            .deserialize()
+            .map(|result| {
+                let record: GroupRecord = result?;
+                Ok(Group {
+                    id: record.id,
+                    name: record.name,
+                    size: record.size,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Rooms and allowed rooms of tuples that are not one of the given rooms, as `(tuple id, room)`
+pub fn unknown_rooms<'a>(rooms: &Rooms, tuples: &'a [Tuple]) -> Vec<(Gene, &'a str)> {
+    tuples
+        .iter()
+        .flat_map(|tuple| {
+            std::iter::once(&tuple.room)
+                .chain(&tuple.allowed_rooms)
+                .filter(|room| !rooms.contains_key(*room))
+                .map(|room| (tuple.id, room.as_str()))
+        })
+        .collect()
+}
+
+/// Teachers and qualified teachers of tuples that are not one of the given teachers, as
+/// `(tuple id, teacher)`
+pub fn unknown_teachers<'a>(teachers: &Teachers, tuples: &'a [Tuple]) -> Vec<(Gene, &'a str)> {
+    tuples
+        .iter()
+        .flat_map(|tuple| {
+            std::iter::once(&tuple.teacher)
+                .chain(&tuple.qualified_teachers)
+                .filter(|teacher| !teachers.contains_key(*teacher))
+                .map(|teacher| (tuple.id, teacher.as_str()))
+        })
+        .collect()
+}
+
+/// Tuples with a group that is not one of the given groups, as `(tuple id, group)`. Tuples
+/// without a group are fine.
+pub fn unknown_groups<'a>(groups: &Groups, tuples: &'a [Tuple]) -> Vec<(Gene, &'a str)> {
+    tuples
+        .iter()
+        .filter(|tuple| !tuple.group.is_empty() && !groups.contains_key(&tuple.group))
+        .map(|tuple| (tuple.id, tuple.group.as_str()))
+        .collect()
+}
+
+/// Add the unavailable periods of every teacher to the forbidden periods of their tuples
+///
+/// Tuples with qualified teachers only get the periods none of them is available in.
+pub fn apply_unavailability(teachers: &Teachers, tuples: &mut [Tuple]) {
+    for tuple in tuples {
+        let unavailable: Vec<usize> = if tuple.qualified_teachers.is_empty() {
+            let Some(teacher) = teachers.get(&tuple.teacher) else {
+                continue;
+            };
+            teacher.unavailable_periods.clone()
+        } else {
+            let mut qualified = tuple.qualified_teachers.iter().map(|teacher| {
+                teachers
+                    .get(teacher)
+                    .map(|teacher| &teacher.unavailable_periods)
+            });
+            let Some(Some(first)) = qualified.next() else {
+                continue;
+            };
+            let mut unavailable = first.clone();
+            for periods in qualified {
+                let periods = periods.map(Vec::as_slice).unwrap_or_default();
+                unavailable.retain(|period| periods.contains(period));
+            }
+            unavailable
+        };
+        tuple.forbidden_periods.extend(unavailable);
+        tuple.forbidden_periods.sort_unstable();
+        tuple.forbidden_periods.dedup();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::datatypes::TupleId;
+
+    #[test]
+    fn test_formats_agree() {
+        let csv = "Id,Capacity,Kind,Building\nD-10 108,16,lab,D-10\nD-10 225,,,\n";
+        let json = r#"[{"id": "D-10 108", "capacity": 16, "kind": "lab", "building": "D-10"},
+            {"id": "D-10 225"}]"#;
+
+        let from_csv = Room::read_csv(csv.as_bytes()).unwrap();
+        assert_eq!(from_csv, serde_json::from_str::<Vec<Room>>(json).unwrap());
+        assert_eq!(from_csv[1].capacity, None);
+    }
+
+    #[test]
+    fn test_unavailable_periods_become_forbidden() {
+        let csv =
+            "Id,Name,MaxWeeklyHours,MaxDailyHours,UnavailablePeriods\nSmith,John Smith,,4,6;7\n";
+        let teachers: Teachers = Teacher::read_csv(csv.as_bytes())
+            .unwrap()
+            .into_iter()
+            .map(|teacher| (teacher.id.clone(), teacher))
+            .collect();
+        let mut tuples = vec![Tuple {
+            id: TupleId(1),
+            teacher: "Smith".to_string(),
+            forbidden_periods: vec![7, 8],
+            ..Tuple::default()
+        }];
+
+        apply_unavailability(&teachers, &mut tuples);
+
+        assert_eq!(teachers["Smith"].max_daily_hours, Some(4));
+        assert_eq!(tuples[0].forbidden_periods, vec![6, 7, 8]);
+    }
+
+    #[test]
+    fn test_malformed_periods_are_errors() {
+        for periods in ["\"6,7\"", "Mon1", "6;-1"] {
+            let csv = format!("Id,UnavailablePeriods\nSmith,{}\n", periods);
+            assert!(
+                matches!(
+                    Teacher::read_csv(csv.as_bytes()),
+                    Err(ResourcesLoadError::InvalidPeriods { teacher, .. }) if teacher == "Smith"
+                ),
+                "{} was read",
+                periods
+            );
+        }
+    }
+}
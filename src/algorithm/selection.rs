@@ -0,0 +1,200 @@
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+use std::cmp::Reverse;
+
+use super::{
+    config::{AlgorithmConfig, SelectionOperator},
+    datatypes::{Individual, Population},
+};
+
+/// Fitness-proportionate (roulette wheel) selection of two different parents
+///
+/// Adaptations are mostly negative, so the wheel uses a window: every individual gets a slice of
+/// `adaptation - worst + 1`, which keeps the worst individual in the game with the smallest slice.
+pub fn roulette_parents<'a>(
+    population: &'a Population,
+    rng: &mut impl Rng,
+) -> (&'a Individual, &'a Individual) {
+    assert!(population.len() > 1);
+
+    let dist = WeightedIndex::new(roulette_weights(population)).unwrap();
+
+    let idx1 = dist.sample(rng);
+    let idx2 = loop {
+        let idx = dist.sample(rng);
+        if idx != idx1 {
+            break idx;
+        }
+    };
+
+    (&population[idx1], &population[idx2])
+}
+
+/// Windowed fitness-proportionate weights, see [`roulette_parents`]
+fn roulette_weights(population: &Population) -> Vec<f64> {
+    let worst = population
+        .iter()
+        .map(|individual| individual.adaptation)
+        .min()
+        .unwrap_or_default();
+
+    population
+        .iter()
+        .map(|individual| (individual.adaptation as f64 - worst as f64) + 1.0)
+        .collect()
+}
+
+/// Exponential rank weights, see [`rand_parents`](super::rand_parents), in population order
+fn rank_weights(population: &Population, selection_pressure: f64) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..population.len()).collect();
+    order.sort_by_key(|index| Reverse(population[*index].adaptation));
+
+    let mut weights = vec![0.0; population.len()];
+    for (rank, index) in order.into_iter().enumerate() {
+        weights[index] = f64::exp(-selection_pressure * rank as f64);
+    }
+    weights
+}
+
+/// Select the parents of `count` children at once with stochastic universal sampling
+///
+/// `2 * count` equally spaced pointers with a single random offset are laid over the cumulative
+/// weights of the [`SelectionOperator`], so every individual is picked within one of its expected
+/// number of times. The picks are shuffled into pairs of indices into the population, pairs of the
+/// same individual are broken up where possible.
+pub fn universal_parent_pairs(
+    config: &AlgorithmConfig,
+    population: &Population,
+    count: usize,
+    rng: &mut impl Rng,
+) -> Vec<(usize, usize)> {
+    assert!(population.len() > 1);
+
+    let weights = match config.selection_operator {
+        SelectionOperator::Rank => rank_weights(population, config.selection_pressure as f64),
+        SelectionOperator::Roulette => roulette_weights(population),
+    };
+
+    let pointer_count = 2 * count;
+    let step = weights.iter().sum::<f64>() / pointer_count as f64;
+    let mut pointer = rng.gen_range(0.0..step);
+
+    let mut picks = Vec::with_capacity(pointer_count);
+    let mut cumulative = 0.0;
+    for (index, weight) in weights.iter().enumerate() {
+        cumulative += weight;
+        while pointer < cumulative && picks.len() < pointer_count {
+            picks.push(index);
+            pointer += step;
+        }
+    }
+    // rounding may leave the last pointers past the sum of the weights
+    picks.resize(pointer_count, weights.len() - 1);
+    picks.shuffle(rng);
+
+    let mut pairs: Vec<(usize, usize)> = picks
+        .chunks_exact(2)
+        .map(|pair| (pair[0], pair[1]))
+        .collect();
+
+    for i in 0..pairs.len() {
+        if pairs[i].0 != pairs[i].1 {
+            continue;
+        }
+        let swap =
+            (0..pairs.len()).find(|j| pairs[*j].0 != pairs[i].1 && pairs[*j].1 != pairs[i].0);
+        if let Some(j) = swap {
+            let father = pairs[i].1;
+            pairs[i].1 = pairs[j].1;
+            pairs[j].1 = father;
+        }
+    }
+
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::random::get_random_generator;
+
+    #[test]
+    fn test_roulette_favours_better_individuals() {
+        let population: Population = [0, -99, -99]
+            .into_iter()
+            .map(|adaptation| Individual {
+                adaptation,
+                ..Individual::default()
+            })
+            .collect();
+
+        let mut rng = get_random_generator(7);
+        let best_picked = (0..1000)
+            .filter(|_| {
+                let (mother, _) = roulette_parents(&population, &mut rng);
+                mother.adaptation == 0
+            })
+            .count();
+
+        // the best individual has 100 of the 102 slices of the wheel
+        assert!(best_picked > 900, "best picked {} times", best_picked);
+    }
+
+    #[test]
+    fn test_selection_pressure_shifts_the_rank_weights() {
+        let population: Population = [-5, 0, -9, -1]
+            .into_iter()
+            .map(|adaptation| Individual {
+                adaptation,
+                ..Individual::default()
+            })
+            .collect();
+
+        // without pressure every rank weighs the same
+        assert_eq!(rank_weights(&population, 0.0), [1.0; 4]);
+
+        let weights = rank_weights(&population, 0.3);
+        assert_eq!(weights[1], 1.0);
+        assert!(weights[1] > weights[3] && weights[3] > weights[0] && weights[0] > weights[2]);
+
+        let best_share = |selection_pressure: f32| {
+            let config = AlgorithmConfig {
+                selection_operator: SelectionOperator::Rank,
+                selection_pressure,
+                ..AlgorithmConfig::default()
+            };
+            let mut rng = get_random_generator(5);
+            universal_parent_pairs(&config, &population, 20, &mut rng)
+                .iter()
+                .filter(|(mother, father)| *mother == 1 || *father == 1)
+                .count()
+        };
+        assert!(best_share(0.0) < best_share(2.0));
+    }
+
+    #[test]
+    fn test_universal_sampling_picks_expected_counts() {
+        let population: Population = [0, 0, 0, 0]
+            .into_iter()
+            .map(|adaptation| Individual {
+                adaptation,
+                ..Individual::default()
+            })
+            .collect();
+
+        let mut rng = get_random_generator(3);
+        let config = AlgorithmConfig {
+            selection_operator: SelectionOperator::Roulette,
+            ..AlgorithmConfig::default()
+        };
+        let pairs = universal_parent_pairs(&config, &population, 4, &mut rng);
+
+        let mut counts = [0; 4];
+        for (mother, father) in &pairs {
+            assert_ne!(mother, father);
+            counts[*mother] += 1;
+            counts[*father] += 1;
+        }
+        assert_eq!(counts, [2, 2, 2, 2]);
+    }
+}
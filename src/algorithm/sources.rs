@@ -0,0 +1,421 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    fs::File,
+    io::{self, BufReader, Read},
+    path::Path,
+};
+
+use csv::StringRecord;
+use flate2::read::MultiGzDecoder;
+
+use super::{
+    config::AlgorithmConfig,
+    datatypes::{Tuple, TupleKind, TuplesLoadError},
+};
+
+/// A file format tuples can be read from
+pub trait TupleSource {
+    /// Read all tuples from the reader
+    fn read_tuples(&self, reader: impl Read) -> Result<Vec<Tuple>, TuplesLoadError>;
+
+    /// Read all tuples from the file at `path`, see [`open_input`]
+    fn load_tuples(&self, path: impl AsRef<Path>) -> Result<Vec<Tuple>, TuplesLoadError> {
+        self.read_tuples(open_input(path)?)
+    }
+
+    /// Read all tuples from the reader with where each one was found, by default its entry
+    fn read_located(&self, reader: impl Read) -> Result<Located, TuplesLoadError> {
+        let tuples = self.read_tuples(reader)?;
+        let locations = (1..=tuples.len()).map(TupleLocation::Entry).collect();
+        Ok((tuples, locations))
+    }
+
+    /// Read all tuples from the file at `path` with where each one was found
+    fn load_located(&self, path: impl AsRef<Path>) -> Result<Located, TuplesLoadError> {
+        self.read_located(open_input(path)?)
+    }
+}
+
+/// Path of the standard input among input files
+pub const STDIN_PATH: &str = "-";
+
+/// Open an input file, or the standard input for [`STDIN_PATH`], decompressing `.gz` and `.zst`
+/// files on the fly
+pub fn open_input(path: impl AsRef<Path>) -> io::Result<Box<dyn Read>> {
+    let path = path.as_ref();
+    if path == Path::new(STDIN_PATH) {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+
+    let file = File::open(path)?;
+    Ok(match path.extension().and_then(|ext| ext.to_str()) {
+        Some("gz") => Box::new(MultiGzDecoder::new(BufReader::new(file))),
+        Some("zst") => Box::new(zstd::Decoder::new(file)?),
+        _ => Box::new(BufReader::new(file)),
+    })
+}
+
+/// Extension deciding the format of an input file, the one before `.gz` or `.zst` for
+/// compressed files, e.g. `json` for `tuples.json.gz`
+pub fn format_extension(path: &Path) -> Option<&str> {
+    match path.extension()?.to_str()? {
+        "gz" | "zst" => Path::new(path.file_stem()?).extension()?.to_str(),
+        extension => Some(extension),
+    }
+}
+
+/// Tuples with their locations, in the same order
+pub type Located = (Vec<Tuple>, Vec<TupleLocation>);
+
+/// Where a tuple was read from, for diagnostics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TupleLocation {
+    /// Line of a CSV file the record starts at
+    Line(u64),
+    /// Position in a JSON array or YAML sequence, from 1
+    Entry(usize),
+}
+
+impl Display for TupleLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TupleLocation::Line(line) => write!(f, "line {}", line),
+            TupleLocation::Entry(entry) => write!(f, "entry {}", entry),
+        }
+    }
+}
+
+/// Tuple fields read from CSV, in the order of the default columns
+pub const CSV_FIELDS: [&str; 11] = [
+    "id",
+    "label",
+    "room",
+    "teacher",
+    "group",
+    "forbidden_periods",
+    "duration",
+    "kind",
+    "allowed_rooms",
+    "qualified_teachers",
+    "course",
+];
+
+/// Headers of the default columns, see [`CSV_FIELDS`]
+const CSV_HEADERS: [&str; 11] = [
+    "Id",
+    "Label",
+    "Room",
+    "Teacher",
+    "Group",
+    "ForbiddenPeriods",
+    "Duration",
+    "Kind",
+    "AllowedRooms",
+    "QualifiedTeachers",
+    "Course",
+];
+
+/// The first fields of [`CSV_FIELDS`] every tuple needs
+const REQUIRED_CSV_FIELDS: usize = 4;
+
+/// CSV with `Id`, `Label`, `Room`, `Teacher` and optional `Group`, `ForbiddenPeriods`,
+/// `Duration`, `Kind` and `AllowedRooms` columns
+///
+/// Forbidden periods and allowed rooms are separated with `;`. Without a kind it's guessed from the label, see
+/// [`TupleKind::from_label`].
+///
+/// By default the columns are read by position and the header row is skipped. With `columns`,
+/// the given fields are read from the named columns and the others from the columns with their
+/// default headers, if the file has them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvTuples {
+    /// Separator of the values, an ASCII character
+    pub delimiter: char,
+    /// Whether the first row holds the column names
+    pub has_headers: bool,
+    /// Columns of the fields of [`CSV_FIELDS`], by header or 1-based number
+    pub columns: BTreeMap<String, String>,
+}
+
+impl Default for CsvTuples {
+    fn default() -> Self {
+        CsvTuples {
+            delimiter: ',',
+            has_headers: true,
+            columns: BTreeMap::new(),
+        }
+    }
+}
+
+impl CsvTuples {
+    /// Layout given by the `csv_delimiter`, `csv_headers` and `csv_columns` fields
+    pub fn from_config(config: &AlgorithmConfig) -> Self {
+        CsvTuples {
+            delimiter: config.csv_delimiter,
+            has_headers: config.csv_headers,
+            columns: config.csv_columns.clone(),
+        }
+    }
+
+    /// Position of every field of [`CSV_FIELDS`] in the records, `None` if the file hasn't got it
+    fn positions(
+        &self,
+        headers: Option<&StringRecord>,
+    ) -> Result<[Option<usize>; CSV_FIELDS.len()], TuplesLoadError> {
+        if self.columns.is_empty() {
+            return Ok(std::array::from_fn(Some));
+        }
+        if let Some(field) = self
+            .columns
+            .keys()
+            .find(|field| !CSV_FIELDS.contains(&field.as_str()))
+        {
+            return Err(TuplesLoadError::UnknownField(field.clone()));
+        }
+
+        let find = |column: &str| {
+            headers.and_then(|headers| {
+                headers
+                    .iter()
+                    .position(|header| header.trim() == column.trim())
+            })
+        };
+        let mut positions = [None; CSV_FIELDS.len()];
+        for (position, (field, header)) in positions
+            .iter_mut()
+            .zip(CSV_FIELDS.into_iter().zip(CSV_HEADERS))
+        {
+            *position = match self.columns.get(field) {
+                Some(column) => Some(
+                    find(column)
+                        .or_else(|| column.trim().parse::<usize>().ok()?.checked_sub(1))
+                        .ok_or_else(|| TuplesLoadError::UnknownColumn(column.clone()))?,
+                ),
+                None => find(header),
+            };
+        }
+
+        match positions[..REQUIRED_CSV_FIELDS]
+            .iter()
+            .position(Option::is_none)
+        {
+            Some(missing) => Err(TuplesLoadError::MissingColumn(CSV_FIELDS[missing])),
+            None => Ok(positions),
+        }
+    }
+}
+
+/// JSON array of serialized [`Tuple`]s, optional fields may be omitted
+pub struct JsonTuples;
+
+/// YAML sequence of serialized [`Tuple`]s, optional fields may be omitted
+pub struct YamlTuples;
+
+impl TupleSource for CsvTuples {
+    fn read_tuples(&self, reader: impl Read) -> Result<Vec<Tuple>, TuplesLoadError> {
+        Ok(self.read_located(reader)?.0)
+    }
+
+    fn read_located(&self, reader: impl Read) -> Result<Located, TuplesLoadError> {
+        let delimiter = u8::try_from(self.delimiter)
+            .ok()
+            .filter(u8::is_ascii)
+            .ok_or(TuplesLoadError::InvalidDelimiter(self.delimiter))?;
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(self.has_headers)
+            .from_reader(reader);
+
+        let headers = match self.has_headers {
+            true => Some(reader.headers()?.clone()),
+            false => None,
+        };
+        let positions = self.positions(headers.as_ref())?;
+
+        let mut tuples = Vec::new();
+        let mut locations = Vec::new();
+
+        for result in reader.records() {
+            let record = result?;
+            let line = record.position().map_or(0, |position| position.line());
+            let field = |index: usize| positions[index].and_then(|position| record.get(position));
+            let invalid = |index: usize, value: &str| TuplesLoadError::InvalidValue {
+                line,
+                field: CSV_FIELDS[index],
+                value: value.to_string(),
+            };
+
+            let tuple = Tuple {
+                id: field(0).unwrap_or_default().trim().parse().map_err(|_| {
+                    TuplesLoadError::InvalidId {
+                        line,
+                        id: field(0).unwrap_or_default().to_string(),
+                    }
+                })?,
+                label: field(1).unwrap_or_default().to_string(),
+                room: field(2).unwrap_or_default().to_string(),
+                teacher: field(3).unwrap_or_default().to_string(),
+                group: field(4).unwrap_or_default().to_string(),
+                forbidden_periods: field(5)
+                    .unwrap_or_default()
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|period| !period.is_empty())
+                    .map(|period| period.parse().map_err(|_| invalid(5, period)))
+                    .collect::<Result<_, _>>()?,
+                duration: match field(6).map(str::trim) {
+                    Some(duration) if !duration.is_empty() => {
+                        duration.parse().map_err(|_| invalid(6, duration))?
+                    }
+                    _ => 1,
+                },
+                kind: field(7)
+                    .and_then(TupleKind::parse)
+                    .unwrap_or_else(|| TupleKind::from_label(field(1).unwrap_or_default())),
+                allowed_rooms: field(8)
+                    .unwrap_or_default()
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|room| !room.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+                qualified_teachers: field(9)
+                    .unwrap_or_default()
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|teacher| !teacher.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+                course: field(10).unwrap_or_default().trim().to_string(),
+                ..Tuple::default()
+            };
+            tuples.push(tuple);
+            locations.push(TupleLocation::Line(line));
+        }
+
+        Ok((tuples, locations))
+    }
+}
+
+impl TupleSource for JsonTuples {
+    fn read_tuples(&self, reader: impl Read) -> Result<Vec<Tuple>, TuplesLoadError> {
+        Ok(serde_json::from_reader(reader)?)
+    }
+}
+
+impl TupleSource for YamlTuples {
+    fn read_tuples(&self, reader: impl Read) -> Result<Vec<Tuple>, TuplesLoadError> {
+        Ok(serde_yaml::from_reader(reader)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::datatypes::TupleId;
+
+    #[test]
+    fn test_formats_agree() {
+        let csv = "Id,Label,Room,Teacher\n1,Laboratorium | Sieci,D-10 108,Smith\n";
+        let json = r#"[{"id": 1, "label": "Laboratorium | Sieci", "room": "D-10 108",
+            "teacher": "Smith", "duration": 1, "kind": "lab"}]"#;
+        let yaml = "- id: 1\n  label: Laboratorium | Sieci\n  room: D-10 108\n  teacher: Smith\n  \
+                    duration: 1\n  kind: lab\n";
+
+        let from_csv = CsvTuples::default().read_tuples(csv.as_bytes()).unwrap();
+        assert_eq!(from_csv, JsonTuples.read_tuples(json.as_bytes()).unwrap());
+        assert_eq!(from_csv, YamlTuples.read_tuples(yaml.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn test_ids_must_fit_in_genes() {
+        let csv = "Id,Label,Room,Teacher\n65535,Wyklad,A,Smith\n65536,Wyklad,A,Smith\n";
+
+        assert!(matches!(
+            CsvTuples::default().read_tuples(csv.as_bytes()),
+            Err(TuplesLoadError::InvalidId { line: 3, id }) if id == "65536"
+        ));
+        assert!(JsonTuples
+            .read_tuples(r#"[{"id": -1}]"#.as_bytes())
+            .is_err());
+    }
+
+    #[test]
+    fn test_columns_are_mapped_by_header_or_number() {
+        let export = "Kod;Semestr;Przedmiot;Sala;Prowadzący;Kind\n\
+                      7;2;Wykład | Sieci;D-10 108;Smith;lab\n";
+        let columns = [
+            ("id", "Kod"),
+            ("label", "Przedmiot"),
+            ("room", "4"),
+            ("teacher", "Prowadzący"),
+        ];
+        let csv = CsvTuples {
+            delimiter: ';',
+            columns: columns
+                .into_iter()
+                .map(|(field, column)| (field.to_string(), column.to_string()))
+                .collect(),
+            ..CsvTuples::default()
+        };
+
+        let tuples = csv.read_tuples(export.as_bytes()).unwrap();
+
+        assert_eq!(tuples[0].id, TupleId(7));
+        assert_eq!(
+            (
+                tuples[0].label.as_str(),
+                tuples[0].room.as_str(),
+                tuples[0].teacher.as_str()
+            ),
+            ("Wykład | Sieci", "D-10 108", "Smith")
+        );
+        assert_eq!(
+            (tuples[0].kind, tuples[0].group.as_str()),
+            (TupleKind::Lab, "")
+        );
+
+        let headerless = CsvTuples {
+            has_headers: false,
+            ..csv.clone()
+        };
+        assert!(matches!(
+            headerless.read_tuples(export.as_bytes()),
+            Err(TuplesLoadError::UnknownColumn(column)) if column == "Kod"
+        ));
+        let unmapped = CsvTuples {
+            columns: BTreeMap::from([("label".to_string(), "Przedmiot".to_string())]),
+            ..csv
+        };
+        assert!(matches!(
+            unmapped.read_tuples(export.as_bytes()),
+            Err(TuplesLoadError::MissingColumn("id"))
+        ));
+    }
+
+    #[test]
+    fn test_compressed_inputs_are_read_like_plain_ones() {
+        let csv = "Id,Label,Room,Teacher\n1,Wykład | Sieci,D-10 108,Smith\n";
+        let dir = std::env::temp_dir();
+        let gz = dir.join("planner_sources_test.csv.gz");
+        let zst = dir.join("planner_sources_test.json.zst");
+
+        let mut encoder =
+            flate2::write::GzEncoder::new(File::create(&gz).unwrap(), Default::default());
+        std::io::Write::write_all(&mut encoder, csv.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+        let json = serde_json::to_vec(&CsvTuples::default().read_tuples(csv.as_bytes()).unwrap());
+        std::fs::write(&zst, zstd::encode_all(json.unwrap().as_slice(), 0).unwrap()).unwrap();
+
+        let from_gz = Tuple::from_file(&gz, &AlgorithmConfig::default()).unwrap();
+        let from_zst = Tuple::from_file(&zst, &AlgorithmConfig::default()).unwrap();
+        std::fs::remove_file(gz).unwrap();
+        std::fs::remove_file(zst).unwrap();
+
+        assert_eq!(from_gz[0].label, "Wykład | Sieci");
+        assert_eq!(from_gz, from_zst);
+        assert_eq!(format_extension(Path::new("tuples.yaml.gz")), Some("yaml"));
+        assert_eq!(format_extension(Path::new("tuples.gz")), None);
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::datatypes::Tuple;
+
+/// Interned teacher name, an index into [`Symbols::teachers`]
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct TeacherId(pub u32);
+
+/// Interned room name, an index into [`Symbols::rooms`]
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct RoomId(pub u32);
+
+/// Names of the teachers and rooms of a set of tuples, each stored once
+///
+/// Tuples refer to their teachers and rooms by id, so clashes are found by comparing integers and
+/// the names are broadcast once instead of with every tuple.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Symbols {
+    pub teachers: Vec<String>,
+    pub rooms: Vec<String>,
+}
+
+impl Symbols {
+    /// Number the teachers and rooms of the tuples in order of appearance, qualified teachers and
+    /// allowed rooms included, and set the ids of the tuples
+    ///
+    /// Interning equal tuples gives equal ids, so every rank may intern its own copy.
+    pub fn intern(tuples: &mut [Tuple]) -> Self {
+        let mut teachers = Interner::default();
+        let mut rooms = Interner::default();
+
+        for tuple in tuples.iter_mut() {
+            tuple.teacher_id = TeacherId(teachers.intern(&tuple.teacher));
+            tuple.room_id = RoomId(rooms.intern(&tuple.room));
+            tuple.qualified_teacher_ids = tuple
+                .qualified_teachers
+                .iter()
+                .map(|teacher| TeacherId(teachers.intern(teacher)))
+                .collect();
+            tuple.allowed_room_ids = tuple
+                .allowed_rooms
+                .iter()
+                .map(|room| RoomId(rooms.intern(room)))
+                .collect();
+        }
+
+        Symbols {
+            teachers: teachers.names,
+            rooms: rooms.names,
+        }
+    }
+
+    pub fn teacher(&self, id: TeacherId) -> &str {
+        &self.teachers[id.0 as usize]
+    }
+
+    pub fn room(&self, id: RoomId) -> &str {
+        &self.rooms[id.0 as usize]
+    }
+}
+
+/// Names seen so far and their ids, see [`Symbols::intern`]
+#[derive(Default)]
+struct Interner {
+    names: Vec<String>,
+    ids: HashMap<String, u32>,
+}
+
+impl Interner {
+    fn intern(&mut self, name: &str) -> u32 {
+        if let Some(id) = self.ids.get(name) {
+            return *id;
+        }
+        let id = self.names.len() as u32;
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::datatypes::TupleId;
+
+    #[test]
+    fn test_equal_names_share_an_id() {
+        let mut tuples: Vec<Tuple> = [(1, "Smith", "A"), (2, "Jones", "A"), (3, "Smith", "B")]
+            .into_iter()
+            .map(|(id, teacher, room)| Tuple {
+                id: TupleId(id),
+                teacher: teacher.to_string(),
+                room: room.to_string(),
+                qualified_teachers: vec!["Jones".to_string(), "Brown".to_string()],
+                ..Tuple::default()
+            })
+            .collect();
+
+        let symbols = Symbols::intern(&mut tuples);
+
+        assert_eq!(symbols.teachers, ["Smith", "Jones", "Brown"]);
+        assert_eq!(symbols.rooms, ["A", "B"]);
+        assert_eq!(tuples[0].teacher_id, tuples[2].teacher_id);
+        assert_ne!(tuples[0].room_id, tuples[2].room_id);
+        assert_eq!(
+            tuples[0].qualified_teacher_ids,
+            [TeacherId(1), TeacherId(2)]
+        );
+        assert_eq!(symbols.teacher(tuples[1].teacher_id), "Jones");
+        assert_eq!(symbols.room(tuples[2].room_id), "B");
+    }
+}
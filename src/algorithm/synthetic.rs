@@ -0,0 +1,174 @@
+use std::{collections::HashMap, hash::Hash};
+
+use rand::Rng;
+
+use super::{
+    datatypes::{Tuple, TupleId, TupleKind},
+    random::get_random_generator,
+    symbols::Symbols,
+};
+
+/// Parameters of a synthetic instance, see [`generate`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InstanceSpec {
+    pub tuples: usize,
+    pub rooms: usize,
+    pub teachers: usize,
+    /// Fraction of the pairs of tuples that clash, see [`conflict_density`]
+    pub density: f64,
+    pub seed: u64,
+}
+
+impl Default for InstanceSpec {
+    fn default() -> Self {
+        InstanceSpec {
+            tuples: 200,
+            rooms: 20,
+            teachers: 20,
+            density: 0.3,
+            seed: 0,
+        }
+    }
+}
+
+/// Generate the tuples of a synthetic instance, interned like tuples loaded from a file
+///
+/// Every teacher and room gets a class, the other classes are spread so that two tuples share a
+/// teacher or a room with probability `density`: a part of them goes to the first teacher and
+/// room, the busiest ones, and the rest to random ones. Densities below the one of evenly spread
+/// classes give that one instead. Labels follow the university export, e.g. `Wykład | Course 7`,
+/// so the kinds survive a CSV round trip. The same spec always gives the same tuples.
+pub fn generate(spec: &InstanceSpec) -> Vec<Tuple> {
+    assert!(
+        spec.tuples <= u16::MAX as usize,
+        "Tuple ids have to fit in 16 bits"
+    );
+    assert!(
+        spec.rooms > 0 && spec.teachers > 0,
+        "An instance needs rooms and teachers"
+    );
+
+    let mut rng = get_random_generator(spec.seed);
+    // teachers and rooms are drawn independently, each shared with the same probability
+    let share = 1.0 - (1.0 - spec.density.clamp(0.0, 1.0)).sqrt();
+    let busy_teacher = busy_fraction(share, spec.teachers);
+    let busy_room = busy_fraction(share, spec.rooms);
+
+    let mut tuples: Vec<Tuple> = (0..spec.tuples)
+        .map(|index| {
+            let id = index + 1;
+            let teacher = pick(index, spec.teachers, busy_teacher, &mut rng);
+            let room = pick(index, spec.rooms, busy_room, &mut rng);
+            let (kind, prefix) = match rng.gen_range(0..3) {
+                0 => (TupleKind::Lecture, "Wykład"),
+                1 => (TupleKind::Exercise, "Ćwiczenia"),
+                _ => (TupleKind::Lab, "Laboratorium"),
+            };
+
+            Tuple {
+                id: TupleId(id as u16),
+                label: format!("{} | Course {}", prefix, id),
+                room: format!("Room {}", room + 1),
+                teacher: format!("Teacher {}", teacher + 1),
+                duration: 1,
+                kind,
+                ..Tuple::default()
+            }
+        })
+        .collect();
+
+    Symbols::intern(&mut tuples);
+    tuples
+}
+
+/// Fraction of the pairs of tuples that clash, i.e. share a teacher or a room
+pub fn conflict_density(tuples: &[Tuple]) -> f64 {
+    let pairs = tuples.len() * tuples.len().saturating_sub(1) / 2;
+    if pairs == 0 {
+        return 0.0;
+    }
+
+    let clashing = pairs_sharing(tuples.iter().map(|tuple| tuple.teacher_id))
+        + pairs_sharing(tuples.iter().map(|tuple| tuple.room_id))
+        - pairs_sharing(tuples.iter().map(|tuple| (tuple.teacher_id, tuple.room_id)));
+    clashing as f64 / pairs as f64
+}
+
+/// Number of pairs with equal keys
+fn pairs_sharing<K: Hash + Eq>(keys: impl Iterator<Item = K>) -> usize {
+    let mut counts: HashMap<K, usize> = HashMap::new();
+    for key in keys {
+        *counts.entry(key).or_default() += 1;
+    }
+    counts
+        .into_values()
+        .map(|count| count * (count - 1) / 2)
+        .sum()
+}
+
+/// Fraction of the classes given the first of `count` resources, the rest spread evenly, for two
+/// classes to share a resource with probability `share`
+///
+/// With a fraction `q` going to the first resource and `u = 1 - q`, two classes share one with
+/// probability `1 - 2au + au²` where `a = (count - 1) / count`.
+fn busy_fraction(share: f64, count: usize) -> f64 {
+    let spread = (count - 1) as f64 / count as f64;
+    if spread == 0.0 {
+        return 0.0;
+    }
+    (1.0 - (1.0 - share) / spread).max(0.0).sqrt()
+}
+
+/// Resource of the `index`-th class, the first classes take every resource once
+fn pick(index: usize, count: usize, busy: f64, rng: &mut impl Rng) -> usize {
+    if index < count {
+        index
+    } else if rng.gen_bool(busy) {
+        0
+    } else {
+        rng.gen_range(0..count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::config::AlgorithmConfig;
+
+    #[test]
+    fn test_instances_have_the_requested_shape() {
+        let spec = InstanceSpec {
+            tuples: 1000,
+            rooms: 30,
+            teachers: 40,
+            density: 0.3,
+            seed: 7,
+        };
+
+        let mut tuples = generate(&spec);
+
+        assert_eq!(tuples.len(), 1000);
+        assert!((conflict_density(&tuples) - 0.3).abs() < 0.05);
+        assert_eq!(tuples, generate(&spec));
+        let symbols = Symbols::intern(&mut tuples);
+        assert_eq!((symbols.rooms.len(), symbols.teachers.len()), (30, 40));
+
+        let sparse = generate(&InstanceSpec {
+            density: 0.0,
+            ..spec
+        });
+        assert!(conflict_density(&sparse) < 0.1);
+    }
+
+    #[test]
+    fn test_instances_survive_a_csv_round_trip() {
+        let tuples = generate(&InstanceSpec::default());
+        let path = std::env::temp_dir().join("planner_synthetic_test.csv");
+
+        Tuple::to_csv(&tuples, &path).unwrap();
+        let loaded = Tuple::from_file(&path, &AlgorithmConfig::default()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, tuples);
+    }
+}
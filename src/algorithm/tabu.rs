@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use rand::Rng;
+
+use super::{
+    calculate_fitness,
+    config::{AlgorithmConfig, InitialPopulationStrategy},
+    create_individual,
+    datatypes::{Gene, Individual, Tuple},
+};
+
+/// Improve a greedy schedule by tabu search, returning the best schedule found and the number of
+/// iterations run
+///
+/// Every iteration samples `tabu_neighbours` moves of an unlocked gene to another period its tuple
+/// can start in (see [`AlgorithmConfig::can_start_at`]) and makes the best one, even if it makes
+/// the schedule worse, so the search walks out of local optima. Moving a gene back to the period
+/// it left is tabu for `tabu_tenure` iterations, unless the move beats the best schedule so far.
+///
+/// Stops after `tabu_iterations`, at `target_fitness` or once `should_stop` returns true, which is
+/// asked every iteration. Moves
+/// are evaluated by the [`ConflictMatrix`](super::conflicts::ConflictMatrix) with
+/// [`AlgorithmConfig::incremental_conflicts`], otherwise the whole schedule is evaluated.
+pub fn tabu_search(
+    config: &AlgorithmConfig,
+    tuples: &[Tuple],
+    rng: &mut impl Rng,
+    mut should_stop: impl FnMut() -> bool,
+) -> (Individual, usize) {
+    let number_of_periods = config.number_of_periods;
+    let layout = config.period_layout();
+    let tuples_by_id: HashMap<Gene, &Tuple> =
+        tuples.iter().map(|tuple| (tuple.id, tuple)).collect();
+
+    let mut current = create_individual(config, tuples, InitialPopulationStrategy::Greedy, rng);
+    current.adaptation = calculate_fitness(config, &current, tuples);
+    let mut best = current.clone();
+    // iteration until which moving a gene to a period is tabu
+    let mut tabu: HashMap<(Gene, usize), usize> = HashMap::new();
+
+    let mut iterations = 0;
+    while iterations < config.tabu_iterations
+        && best.adaptation < config.target_fitness
+        && !should_stop()
+    {
+        iterations += 1;
+
+        // best sampled move as (from, index in the period, to, adaptation after the move)
+        let mut chosen: Option<(usize, usize, usize, i32)> = None;
+        for _ in 0..config.tabu_neighbours {
+            let from = rng.gen_range(0..number_of_periods);
+            let gene_count = current.chromosomes[from].genes.len();
+            if gene_count == 0 {
+                continue;
+            }
+            let index = rng.gen_range(0..gene_count);
+            let gene = current.chromosomes[from].genes[index];
+            let to = rng.gen_range(0..number_of_periods);
+            if to == from
+                || config.locks.contains_key(&gene)
+                || tuples_by_id
+                    .get(&gene)
+                    .is_some_and(|tuple| !config.can_start_at(tuple, to, &layout))
+            {
+                continue;
+            }
+
+            let adaptation = match config.incremental_conflicts() {
+                Some(matrix) => {
+                    current.adaptation + matrix.gene_penalty(gene, &current.chromosomes[from].genes)
+                        - matrix.gene_penalty(gene, &current.chromosomes[to].genes)
+                }
+                None => {
+                    let mut neighbour = current.clone();
+                    neighbour.move_gene(from, index, to);
+                    calculate_fitness(config, &neighbour, tuples)
+                }
+            };
+
+            let is_tabu = tabu
+                .get(&(gene, to))
+                .is_some_and(|until| *until >= iterations);
+            if is_tabu && adaptation <= best.adaptation {
+                continue;
+            }
+            if chosen.is_none_or(|(.., chosen_adaptation)| adaptation > chosen_adaptation) {
+                chosen = Some((from, index, to, adaptation));
+            }
+        }
+
+        let Some((from, index, to, adaptation)) = chosen else {
+            continue;
+        };
+        let gene = current.move_gene(from, index, to);
+        current.adaptation = adaptation;
+        tabu.insert((gene, from), iterations + config.tabu_tenure);
+
+        if current.adaptation > best.adaptation {
+            best = current.clone();
+        }
+    }
+
+    (best, iterations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::{datatypes::TupleId, random::get_random_generator};
+
+    #[test]
+    fn test_tabu_search_separates_clashing_classes() {
+        let tuples: Vec<Tuple> = (1..=6)
+            .map(|id| Tuple {
+                id: TupleId(id),
+                label: format!("Class {}", id),
+                teacher: format!("Teacher {}", id % 3),
+                room: format!("Room {}", id % 2),
+                ..Tuple::default()
+            })
+            .collect();
+        let config = AlgorithmConfig {
+            number_of_periods: 6,
+            tabu_iterations: 200,
+            ..AlgorithmConfig::default()
+        };
+
+        let mut rng = get_random_generator(3);
+        let (best, iterations) = tabu_search(&config, &tuples, &mut rng, || false);
+
+        assert_eq!(best.adaptation, 0);
+        assert_eq!(calculate_fitness(&config, &best, &tuples), 0);
+        assert!(iterations <= 200);
+    }
+}
@@ -0,0 +1,340 @@
+use std::{collections::HashMap, io::Read};
+
+use roxmltree::{Document, Node};
+
+use super::{
+    config::AlgorithmConfig,
+    datatypes::{Tuple, TupleId, TuplesLoadError},
+    sources::{Located, TupleLocation, TupleSource},
+};
+
+/// What a resource of an XHSTT instance is to the planner, by the id or name of its type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Teacher,
+    Room,
+    Group,
+}
+
+impl Role {
+    /// Role of a resource type like `Teacher`, `Room` or `Class`, none for the other types
+    fn of_type(resource_type: Node) -> Option<Role> {
+        let name = format!(
+            "{} {}",
+            resource_type.attribute("Id").unwrap_or_default(),
+            child_text(resource_type, "Name").unwrap_or_default()
+        )
+        .to_lowercase();
+
+        if name.contains("teacher") {
+            Some(Role::Teacher)
+        } else if name.contains("room") {
+            Some(Role::Room)
+        } else if ["class", "student", "group"]
+            .iter()
+            .any(|word| name.contains(word))
+        {
+            Some(Role::Group)
+        } else {
+            None
+        }
+    }
+}
+
+/// Events of an instance in the XHSTT format of high-school timetabling, the format of the
+/// benchmark instances of the International Timetabling Competition 2011
+///
+/// An event lasting `n` times becomes `n` tuples of one period, labelled `Name (k/n)`, which
+/// belong to the course of the event or, without one, to the event itself. A preassigned time
+/// forbids every other period. Teachers, rooms and student groups are the resources of the event
+/// whose type is named like one of them. When the event has none of a teacher or a room, it gets
+/// one of its own named after the event. Unassigned resources become `allowed_rooms` and
+/// `qualified_teachers` with every resource of their type. Only the first student group of an
+/// event is kept.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct XhsttTuples {
+    /// Id of the instance read from an archive, the first one when not given
+    pub instance: Option<String>,
+}
+
+impl XhsttTuples {
+    /// Instance given by the `xhstt_instance` field
+    pub fn from_config(config: &AlgorithmConfig) -> Self {
+        XhsttTuples {
+            instance: config.xhstt_instance.clone(),
+        }
+    }
+}
+
+impl TupleSource for XhsttTuples {
+    fn read_tuples(&self, reader: impl Read) -> Result<Vec<Tuple>, TuplesLoadError> {
+        Ok(self.read_located(reader)?.0)
+    }
+
+    fn read_located(&self, mut reader: impl Read) -> Result<Located, TuplesLoadError> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let document = Document::parse(&text)?;
+
+        let mut instances = document
+            .descendants()
+            .filter(|node| node.has_tag_name("Instance"));
+        let instance = match &self.instance {
+            Some(id) => instances
+                .find(|node| node.attribute("Id") == Some(id.as_str()))
+                .ok_or_else(|| TuplesLoadError::UnknownInstance(id.clone()))?,
+            None => instances.next().ok_or(TuplesLoadError::NoInstance)?,
+        };
+
+        let times: HashMap<&str, usize> = children(instance, &["Times"], "Time")
+            .enumerate()
+            .filter_map(|(index, time)| Some((time.attribute("Id")?, index)))
+            .collect();
+        let roles: HashMap<&str, Role> =
+            children(instance, &["Resources", "ResourceTypes"], "ResourceType")
+                .filter_map(|node| Some((node.attribute("Id")?, Role::of_type(node)?)))
+                .collect();
+
+        let mut resources: HashMap<&str, Role> = HashMap::new();
+        let mut of_type: HashMap<&str, Vec<String>> = HashMap::new();
+        let mut members: HashMap<&str, Vec<&str>> = HashMap::new();
+        for resource in children(instance, &["Resources"], "Resource") {
+            let Some(id) = resource.attribute("Id") else {
+                continue;
+            };
+            let resource_type = reference(resource, "ResourceType").unwrap_or_default();
+            if let Some(&role) = roles.get(resource_type) {
+                resources.insert(id, role);
+                of_type
+                    .entry(resource_type)
+                    .or_default()
+                    .push(id.to_string());
+            }
+            for group in children(resource, &["ResourceGroups"], "ResourceGroup") {
+                if let Some(group) = group.attribute("Reference") {
+                    members.entry(group).or_default().push(id);
+                }
+            }
+        }
+
+        let mut tuples = Vec::new();
+        let mut locations = Vec::new();
+        for event in children(instance, &["Events"], "Event") {
+            let id = event.attribute("Id").unwrap_or_default();
+            let line = document.text_pos_at(event.range().start).row as u64;
+            let invalid = |field: &'static str, value: &str| TuplesLoadError::InvalidValue {
+                line,
+                field,
+                value: value.to_string(),
+            };
+
+            let duration_text = child_text(event, "Duration").unwrap_or_default();
+            let duration: usize = duration_text
+                .trim()
+                .parse()
+                .map_err(|_| invalid("duration", duration_text))?;
+            let time = match reference(event, "Time") {
+                Some(time) => Some(*times.get(time).ok_or_else(|| invalid("time", time))?),
+                None => None,
+            };
+
+            let mut assigned: Vec<&str> = Vec::new();
+            let mut unassigned: Vec<&str> = Vec::new();
+            for resource in children(event, &["Resources"], "Resource") {
+                match resource.attribute("Reference") {
+                    Some(reference) => assigned.push(reference),
+                    None => unassigned.extend(reference(resource, "ResourceType")),
+                }
+            }
+            for group in children(event, &["ResourceGroups"], "ResourceGroup") {
+                let group = group.attribute("Reference").unwrap_or_default();
+                assigned.extend(
+                    members
+                        .get(group)
+                        .ok_or_else(|| invalid("resource group", group))?,
+                );
+            }
+
+            let with_role = |role: Role| {
+                assigned
+                    .iter()
+                    .find(|resource| resources.get(*resource) == Some(&role))
+                    .map(|resource| resource.to_string())
+            };
+            let candidates = |role: Role| -> Vec<String> {
+                unassigned
+                    .iter()
+                    .filter(|resource_type| roles.get(*resource_type) == Some(&role))
+                    .flat_map(|resource_type| {
+                        of_type.get(resource_type).cloned().unwrap_or_default()
+                    })
+                    .collect()
+            };
+            let qualified_teachers = candidates(Role::Teacher);
+            let allowed_rooms = candidates(Role::Room);
+            let teacher = with_role(Role::Teacher)
+                .or_else(|| qualified_teachers.first().cloned())
+                .unwrap_or_else(|| id.to_string());
+            let room = with_role(Role::Room)
+                .or_else(|| allowed_rooms.first().cloned())
+                .unwrap_or_else(|| id.to_string());
+
+            let name = child_text(event, "Name").unwrap_or(id);
+            for part in 0..duration {
+                if tuples.len() == u16::MAX as usize {
+                    return Err(TuplesLoadError::TooManyTuples(tuples.len() + 1));
+                }
+                let label = match duration {
+                    1 => name.to_string(),
+                    _ => format!("{} ({}/{})", name, part + 1, duration),
+                };
+                let forbidden_periods = match time {
+                    Some(time) => (0..times.len())
+                        .filter(|&period| period != time + part)
+                        .collect(),
+                    None => Vec::new(),
+                };
+
+                tuples.push(Tuple {
+                    id: TupleId(tuples.len() as u16 + 1),
+                    label,
+                    room: room.clone(),
+                    teacher: teacher.clone(),
+                    group: with_role(Role::Group).unwrap_or_default(),
+                    forbidden_periods,
+                    duration: 1,
+                    allowed_rooms: allowed_rooms.clone(),
+                    qualified_teachers: qualified_teachers.clone(),
+                    course: reference(event, "Course").unwrap_or(id).to_string(),
+                    ..Tuple::default()
+                });
+                locations.push(TupleLocation::Line(line));
+            }
+        }
+
+        Ok((tuples, locations))
+    }
+}
+
+/// Elements named `name` inside the elements on the `path` from the node
+fn children<'a, 'input>(
+    node: Node<'a, 'input>,
+    path: &'static [&'static str],
+    name: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    let mut parents = vec![node];
+    for step in path {
+        parents = parents
+            .into_iter()
+            .flat_map(|parent| parent.children().filter(|child| child.has_tag_name(*step)))
+            .collect();
+    }
+    parents.into_iter().flat_map(move |parent| {
+        parent
+            .children()
+            .filter(move |child| child.has_tag_name(name))
+    })
+}
+
+/// Text of the first child element named `name`
+fn child_text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.children()
+        .find(|child| child.has_tag_name(name))?
+        .text()
+}
+
+/// `Reference` of the first child element named `name`, e.g. `<Time Reference="Mo1"/>`
+fn reference<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    node.children()
+        .find(|child| child.has_tag_name(name))?
+        .attribute("Reference")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARCHIVE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<HighSchoolTimetableArchive Id="Test">
+  <Instances>
+    <Instance Id="Other"/>
+    <Instance Id="School">
+      <Times>
+        <Time Id="Mo1"/><Time Id="Mo2"/><Time Id="Tu1"/><Time Id="Tu2"/>
+      </Times>
+      <Resources>
+        <ResourceTypes>
+          <ResourceType Id="Teacher"><Name>Teacher</Name></ResourceType>
+          <ResourceType Id="Class"><Name>Class</Name></ResourceType>
+          <ResourceType Id="Room"><Name>Room</Name></ResourceType>
+        </ResourceTypes>
+        <ResourceGroups>
+          <ResourceGroup Id="Staff"><Name>Staff</Name><ResourceType Reference="Teacher"/></ResourceGroup>
+        </ResourceGroups>
+        <Resource Id="T1"><Name>T1</Name><ResourceType Reference="Teacher"/>
+          <ResourceGroups><ResourceGroup Reference="Staff"/></ResourceGroups></Resource>
+        <Resource Id="C1"><Name>1A</Name><ResourceType Reference="Class"/></Resource>
+        <Resource Id="R1"><Name>R1</Name><ResourceType Reference="Room"/></Resource>
+        <Resource Id="R2"><Name>R2</Name><ResourceType Reference="Room"/></Resource>
+      </Resources>
+      <Events>
+        <Event Id="E1">
+          <Name>Math 1A</Name><Duration>2</Duration><Course Reference="Math"/>
+          <Resources>
+            <Resource Reference="T1"/><Resource Reference="C1"/>
+            <Resource><Role>Room</Role><ResourceType Reference="Room"/></Resource>
+          </Resources>
+        </Event>
+        <Event Id="E2">
+          <Name>Meeting</Name><Duration>1</Duration><Time Reference="Tu1"/>
+          <ResourceGroups><ResourceGroup Reference="Staff"/></ResourceGroups>
+        </Event>
+      </Events>
+    </Instance>
+  </Instances>
+</HighSchoolTimetableArchive>"#;
+
+    #[test]
+    fn test_events_become_tuples_of_one_period() {
+        let source = XhsttTuples {
+            instance: Some("School".to_string()),
+        };
+        let (tuples, locations) = source.read_located(ARCHIVE.as_bytes()).unwrap();
+
+        let fields: Vec<(&str, &str, &str, &str, &str)> = tuples
+            .iter()
+            .map(|tuple| {
+                (
+                    tuple.label.as_str(),
+                    tuple.room.as_str(),
+                    tuple.teacher.as_str(),
+                    tuple.group.as_str(),
+                    tuple.course.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("Math 1A (1/2)", "R1", "T1", "C1", "Math"),
+                ("Math 1A (2/2)", "R1", "T1", "C1", "Math"),
+                ("Meeting", "E2", "T1", "", "E2"),
+            ]
+        );
+        assert_eq!(tuples[0].allowed_rooms, ["R1", "R2"]);
+        assert_eq!(tuples[2].forbidden_periods, [0, 1, 3]);
+        assert_eq!(locations[0], TupleLocation::Line(25));
+
+        assert!(matches!(
+            XhsttTuples::default().read_tuples(ARCHIVE.as_bytes()),
+            Ok(tuples) if tuples.is_empty()
+        ));
+        assert!(matches!(
+            XhsttTuples {
+                instance: Some("Missing".to_string())
+            }
+            .read_tuples(ARCHIVE.as_bytes()),
+            Err(TuplesLoadError::UnknownInstance(_))
+        ));
+    }
+}
@@ -0,0 +1,118 @@
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+use thiserror::Error;
+
+use crate::algorithm::config::Compression;
+
+/// Flag bytes in front of every payload, telling receivers how to read the rest
+const RAW: u8 = 0;
+const LZ4: u8 = 1;
+const ZSTD: u8 = 2;
+
+/// Level of zstd, the default one of the command line tool
+const ZSTD_LEVEL: i32 = 3;
+
+/// Why a payload can't be read back
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum CompressionError {
+    #[error("Corrupted {algorithm} payload: {reason}")]
+    Corrupted {
+        algorithm: &'static str,
+        reason: String,
+    },
+    #[error("Unknown compression flag {0}")]
+    UnknownFlag(u8),
+}
+
+/// Settings of the process, see [`configure`]
+static ALGORITHM: AtomicU8 = AtomicU8::new(RAW);
+static THRESHOLD: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+/// Compress payloads of at least `threshold` bytes sent by this process from now on
+///
+/// Every payload says how it was compressed, so processes with different settings (e.g. before
+/// the configuration was broadcast) still understand each other.
+pub fn configure(compression: Compression, threshold: usize) {
+    let algorithm = match compression {
+        Compression::None => RAW,
+        Compression::Lz4 => LZ4,
+        Compression::Zstd => ZSTD,
+    };
+    ALGORITHM.store(algorithm, Ordering::Relaxed);
+    THRESHOLD.store(threshold, Ordering::Relaxed);
+}
+
+/// Prefix the payload with its flag byte, compressed as configured when it is large enough
+pub fn pack(bytes: Vec<u8>) -> Vec<u8> {
+    pack_with(
+        bytes,
+        ALGORITHM.load(Ordering::Relaxed),
+        THRESHOLD.load(Ordering::Relaxed),
+    )
+}
+
+/// Payload as given to [`pack`] on any process
+pub fn unpack(packed: &[u8]) -> Result<Vec<u8>, CompressionError> {
+    let Some((flag, bytes)) = packed.split_first() else {
+        return Ok(Vec::new());
+    };
+    let corrupted = |algorithm, reason: String| CompressionError::Corrupted { algorithm, reason };
+
+    match *flag {
+        RAW => Ok(bytes.to_vec()),
+        LZ4 => lz4_flex::decompress_size_prepended(bytes)
+            .map_err(|error| corrupted("lz4", error.to_string())),
+        ZSTD => zstd::decode_all(bytes).map_err(|error| corrupted("zstd", error.to_string())),
+        flag => Err(CompressionError::UnknownFlag(flag)),
+    }
+}
+
+/// Compressed payloads are only kept when they are smaller
+fn pack_with(bytes: Vec<u8>, algorithm: u8, threshold: usize) -> Vec<u8> {
+    let compressed = match algorithm {
+        _ if bytes.len() < threshold => None,
+        LZ4 => Some(lz4_flex::compress_prepend_size(&bytes)),
+        ZSTD => zstd::encode_all(&bytes[..], ZSTD_LEVEL).ok(),
+        _ => None,
+    };
+
+    match compressed.filter(|compressed| compressed.len() < bytes.len()) {
+        Some(compressed) => [&[algorithm], &compressed[..]].concat(),
+        None => [&[RAW], &bytes[..]].concat(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payloads_roundtrip_with_every_algorithm() {
+        let repetitive: Vec<u8> = b"Teacher 1, Room 2; ".repeat(200);
+
+        for algorithm in [RAW, LZ4, ZSTD] {
+            let packed = pack_with(repetitive.clone(), algorithm, 100);
+            assert_eq!(packed[0], algorithm);
+            assert_eq!(unpack(&packed).unwrap(), repetitive);
+        }
+
+        // below the threshold or incompressible payloads are sent as they are
+        let small = pack_with(b"short".to_vec(), ZSTD, 100);
+        assert_eq!(small, b"\0short");
+        let noise: Vec<u8> = (0..=255).collect();
+        assert_eq!(pack_with(noise.clone(), LZ4, 0)[0], RAW);
+        assert!(unpack(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_unreadable_payloads_are_errors() {
+        assert_eq!(unpack(&[7, 1, 2]), Err(CompressionError::UnknownFlag(7)));
+        for algorithm in [LZ4, ZSTD] {
+            let packed = pack_with(b"Teacher 1, Room 2; ".repeat(200), algorithm, 0);
+            assert!(matches!(
+                unpack(&packed[..packed.len() / 2]),
+                Err(CompressionError::Corrupted { .. })
+            ));
+        }
+    }
+}
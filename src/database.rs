@@ -0,0 +1,275 @@
+use std::path::Path;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, types::Value, Connection, Transaction};
+
+use crate::{
+    aggregator::MetricsRecord,
+    algorithm::{
+        config::AlgorithmConfig,
+        datatypes::{Individual, Tuple},
+    },
+    experiments::RunRecord,
+};
+
+/// Tables of the results database, created when missing so that invocations add to one file
+///
+/// Seeds are stored as text, they don't fit SQLite's signed integers.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS invocations (
+    id INTEGER PRIMARY KEY,
+    recorded_at TEXT NOT NULL,
+    tuples TEXT NOT NULL,
+    seed TEXT NOT NULL,
+    best_adaptation INTEGER
+);
+CREATE TABLE IF NOT EXISTS config (
+    invocation_id INTEGER NOT NULL REFERENCES invocations (id),
+    field TEXT NOT NULL,
+    value,
+    PRIMARY KEY (invocation_id, field)
+);
+CREATE TABLE IF NOT EXISTS runs (
+    invocation_id INTEGER NOT NULL REFERENCES invocations (id),
+    run INTEGER NOT NULL,
+    seed TEXT NOT NULL,
+    best_adaptation INTEGER NOT NULL,
+    generations INTEGER NOT NULL,
+    seconds REAL NOT NULL,
+    PRIMARY KEY (invocation_id, run)
+);
+CREATE TABLE IF NOT EXISTS generations (
+    invocation_id INTEGER NOT NULL REFERENCES invocations (id),
+    run INTEGER NOT NULL,
+    island INTEGER NOT NULL,
+    generation INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    best_adaptation INTEGER NOT NULL,
+    worst_adaptation INTEGER NOT NULL,
+    mean_adaptation REAL NOT NULL,
+    median_adaptation INTEGER NOT NULL,
+    std_dev_adaptation REAL NOT NULL,
+    PRIMARY KEY (invocation_id, run, island, generation)
+);
+CREATE TABLE IF NOT EXISTS schedule (
+    invocation_id INTEGER NOT NULL REFERENCES invocations (id),
+    tuple_id INTEGER NOT NULL,
+    period INTEGER NOT NULL,
+    label TEXT NOT NULL,
+    room TEXT NOT NULL,
+    teacher TEXT NOT NULL,
+    student_group TEXT NOT NULL,
+    PRIMARY KEY (invocation_id, tuple_id)
+);";
+
+/// What an invocation of the planner records in the database, see [`record`]
+pub struct Results<'a> {
+    pub config: &'a AlgorithmConfig,
+    pub tuples_path: &'a str,
+    pub seed: u64,
+    pub runs: &'a [RunRecord],
+    pub generations: &'a [MetricsRecord],
+    /// Best individual with the tuples it assigns, see
+    /// [`effective_tuples`](crate::algorithm::assignment::effective_tuples)
+    pub best: Option<(&'a Individual, &'a [Tuple])>,
+}
+
+/// Add the results of an invocation to the SQLite database at `path`, creating it if needed
+///
+/// Every configuration field is a row of `config`, a number, text or JSON of lists and maps, so
+/// runs can be compared by any of them. Returns the id of the invocation.
+pub fn record(path: impl AsRef<Path>, results: &Results) -> rusqlite::Result<i64> {
+    let mut connection = Connection::open(path)?;
+    connection.execute_batch(SCHEMA)?;
+
+    let transaction = connection.transaction()?;
+    let invocation = insert(&transaction, results)?;
+    transaction.commit()?;
+    Ok(invocation)
+}
+
+fn insert(transaction: &Transaction, results: &Results) -> rusqlite::Result<i64> {
+    transaction.execute(
+        "INSERT INTO invocations (recorded_at, tuples, seed, best_adaptation)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![
+            DateTime::<Utc>::from(std::time::SystemTime::now())
+                .to_rfc3339_opts(SecondsFormat::Secs, true),
+            results.tuples_path,
+            results.seed.to_string(),
+            results.best.map(|(best, _)| best.adaptation),
+        ],
+    )?;
+    let invocation = transaction.last_insert_rowid();
+
+    let mut statement = transaction.prepare("INSERT INTO config VALUES (?1, ?2, ?3)")?;
+    let fields = serde_json::to_value(results.config).expect("Config is serializable");
+    for (field, value) in fields.as_object().into_iter().flatten() {
+        statement.execute(params![invocation, field, sql_value(value)])?;
+    }
+
+    let mut statement = transaction.prepare("INSERT INTO runs VALUES (?1, ?2, ?3, ?4, ?5, ?6)")?;
+    for run in results.runs {
+        statement.execute(params![
+            invocation,
+            run.run,
+            run.seed.to_string(),
+            run.best_adaptation,
+            run.generations,
+            run.seconds
+        ])?;
+    }
+
+    let mut statement = transaction
+        .prepare("INSERT INTO generations VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)")?;
+    for metrics in results.generations {
+        statement.execute(params![
+            invocation,
+            metrics.run,
+            metrics.island,
+            metrics.generation,
+            metrics.size,
+            metrics.best_adaptation,
+            metrics.worst_adaptation,
+            metrics.mean_adaptation,
+            metrics.median_adaptation,
+            metrics.std_dev_adaptation
+        ])?;
+    }
+
+    if let Some((best, tuples)) = results.best {
+        let mut statement =
+            transaction.prepare("INSERT INTO schedule VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)")?;
+        for chromosome in &best.chromosomes {
+            for gene in &chromosome.genes {
+                let Some(tuple) = tuples.iter().find(|tuple| tuple.id == *gene) else {
+                    continue;
+                };
+                statement.execute(params![
+                    invocation,
+                    tuple.id.0,
+                    chromosome.id.index(),
+                    tuple.label,
+                    tuple.room,
+                    tuple.teacher,
+                    tuple.group
+                ])?;
+            }
+        }
+    }
+
+    Ok(invocation)
+}
+
+/// Value of a configuration field in its SQLite type, lists and maps as JSON
+fn sql_value(value: &serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(flag) => Value::Integer(*flag as i64),
+        serde_json::Value::Number(number) => match number.as_i64() {
+            Some(integer) => Value::Integer(integer),
+            None => Value::Real(number.as_f64().unwrap_or(f64::NAN)),
+        },
+        serde_json::Value::String(text) => Value::Text(text.clone()),
+        other => Value::Text(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::datatypes::{Chromosome, PeriodId, TupleId};
+    use smallvec::smallvec;
+
+    #[test]
+    fn test_invocations_add_to_the_database() {
+        let tuples = vec![Tuple {
+            id: TupleId(7),
+            label: "Wykład | Sieci".to_string(),
+            room: "A".to_string(),
+            teacher: "Smith".to_string(),
+            ..Tuple::default()
+        }];
+        let mut best = Individual::with_chromosomes(vec![
+            Chromosome {
+                id: PeriodId(0),
+                genes: smallvec![],
+            },
+            Chromosome {
+                id: PeriodId(1),
+                genes: smallvec![TupleId(7)],
+            },
+        ]);
+        best.adaptation = -3;
+        let runs = [RunRecord {
+            run: 1,
+            seed: u64::MAX,
+            best_adaptation: -3,
+            generations: 2,
+            seconds: 0.5,
+        }];
+        let generations: Vec<MetricsRecord> = (1..=2)
+            .map(|generation| MetricsRecord {
+                run: 1,
+                island: 0,
+                generation,
+                size: 10,
+                best_adaptation: -3,
+                worst_adaptation: -9,
+                mean_adaptation: -5.5,
+                median_adaptation: -5,
+                std_dev_adaptation: 1.5,
+            })
+            .collect();
+        let config = AlgorithmConfig {
+            population_size: 10,
+            ..AlgorithmConfig::default()
+        };
+        let results = Results {
+            config: &config,
+            tuples_path: "tuples.csv",
+            seed: u64::MAX,
+            runs: &runs,
+            generations: &generations,
+            best: Some((&best, &tuples)),
+        };
+        let path = std::env::temp_dir().join("planner_database_test.sqlite");
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(record(&path, &results).unwrap(), 1);
+        assert_eq!(record(&path, &results).unwrap(), 2);
+
+        let connection = Connection::open(&path).unwrap();
+        let count = |table: &str| -> i64 {
+            connection
+                .query_row(
+                    &format!("SELECT COUNT(*) FROM {} WHERE invocation_id = 2", table),
+                    [],
+                    |row| row.get(0),
+                )
+                .unwrap()
+        };
+        assert_eq!(
+            (count("runs"), count("generations"), count("schedule")),
+            (1, 2, 1)
+        );
+        let population_size: i64 = connection
+            .query_row(
+                "SELECT value FROM config WHERE invocation_id = 1 AND field = 'population_size'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(population_size, 10);
+        let (seed, period): (String, i64) = connection
+            .query_row(
+                "SELECT runs.seed, schedule.period FROM runs JOIN schedule USING (invocation_id)",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((seed, period), (u64::MAX.to_string(), 1));
+        drop(connection);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
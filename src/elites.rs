@@ -0,0 +1,164 @@
+use std::{cmp::Reverse, fs::File, io::BufWriter, path::Path};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::algorithm::{
+    config::AlgorithmConfig,
+    datatypes::{Individual, Tuple, SCHEMA_VERSION},
+};
+
+#[derive(Debug, Error)]
+pub enum EliteLoadError {
+    #[error("Elites file not found")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(
+        "Elites come from a different instance (data hash {found:016x}, expected {expected:016x})"
+    )]
+    Incompatible { expected: u64, found: u64 },
+    #[error("Elites use schema version {found}, this binary reads version {expected}")]
+    Schema { expected: u16, found: u16 },
+}
+
+/// Best individuals of a finished run, used to seed later runs on the same instance
+///
+/// Individuals are only meaningful for the tuples and number of periods they were evolved with,
+/// so the archive carries a hash of those ([`data_hash`]). The configuration of the run may
+/// differ between runs, its hash is kept for reference only.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EliteArchive {
+    /// [`SCHEMA_VERSION`] of the individuals, archives written before it was recorded have version 1
+    #[serde(default = "first_schema_version")]
+    pub schema_version: u16,
+    pub data_hash: u64,
+    pub config_hash: u64,
+    pub individuals: Vec<Individual>,
+}
+
+impl EliteArchive {
+    /// Take the `count` best individuals of the population
+    pub fn new(
+        config: &AlgorithmConfig,
+        tuples: &[Tuple],
+        population: &[Individual],
+        count: usize,
+    ) -> Self {
+        let mut individuals = population.to_vec();
+        individuals.sort_by_key(|individual| Reverse(individual.adaptation));
+        individuals.truncate(count);
+
+        EliteArchive {
+            schema_version: SCHEMA_VERSION,
+            data_hash: data_hash(config, tuples),
+            config_hash: config_hash(config),
+            individuals,
+        }
+    }
+
+    /// Load elites from a JSON file, rejecting archives exported for a different instance
+    pub fn from_json(
+        path: impl AsRef<Path>,
+        config: &AlgorithmConfig,
+        tuples: &[Tuple],
+    ) -> Result<Self, EliteLoadError> {
+        let file = File::open(path)?;
+        let archive: EliteArchive = serde_json::from_reader(file)?;
+
+        if archive.schema_version != SCHEMA_VERSION {
+            return Err(EliteLoadError::Schema {
+                expected: SCHEMA_VERSION,
+                found: archive.schema_version,
+            });
+        }
+
+        let expected = data_hash(config, tuples);
+        if archive.data_hash != expected {
+            return Err(EliteLoadError::Incompatible {
+                expected,
+                found: archive.data_hash,
+            });
+        }
+
+        Ok(archive)
+    }
+
+    pub fn to_json(&self, path: impl AsRef<Path>) -> Result<(), EliteLoadError> {
+        let file = File::create(path)?;
+        serde_json::to_writer(BufWriter::new(file), self)?;
+        Ok(())
+    }
+}
+
+fn first_schema_version() -> u16 {
+    1
+}
+
+/// Hash of the problem instance: tuples and number of periods
+pub fn data_hash(config: &AlgorithmConfig, tuples: &[Tuple]) -> u64 {
+    let bytes = bincode::serialize(&(tuples, config.number_of_periods)).unwrap();
+    fnv1a(&bytes)
+}
+
+/// Hash of the whole algorithm configuration
+pub fn config_hash(config: &AlgorithmConfig) -> u64 {
+    let bytes = bincode::serialize(config).unwrap();
+    fnv1a(&bytes)
+}
+
+/// FNV-1a, used instead of [`std::hash::DefaultHasher`] whose output may change between Rust
+/// releases, which would make archives from older binaries incompatible
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::datatypes::TupleId;
+
+    #[test]
+    fn test_archives_roundtrip_only_for_the_same_instance() {
+        let tuples = vec![Tuple {
+            id: TupleId(1),
+            room: "A".to_string(),
+            teacher: "Smith".to_string(),
+            ..Tuple::default()
+        }];
+        let config = AlgorithmConfig::default();
+        let population: Vec<Individual> = [-5, 0, -2]
+            .into_iter()
+            .map(|adaptation| Individual {
+                adaptation,
+                ..Individual::new(config.number_of_periods)
+            })
+            .collect();
+        let path = std::env::temp_dir().join("planner_elites_test.json");
+
+        EliteArchive::new(&config, &tuples, &population, 2)
+            .to_json(&path)
+            .unwrap();
+        let archive = EliteArchive::from_json(&path, &config, &tuples).unwrap();
+        let adaptations: Vec<i32> = archive
+            .individuals
+            .iter()
+            .map(|individual| individual.adaptation)
+            .collect();
+        assert_eq!(adaptations, [0, -2]);
+        assert_eq!(archive.config_hash, config_hash(&config));
+
+        let other_instance = AlgorithmConfig {
+            number_of_periods: config.number_of_periods + 1,
+            ..AlgorithmConfig::default()
+        };
+        let error = EliteArchive::from_json(&path, &other_instance, &tuples).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            error,
+            EliteLoadError::Incompatible { found, .. } if found == archive.data_hash
+        ));
+    }
+}
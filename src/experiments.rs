@@ -0,0 +1,175 @@
+use std::{
+    path::Path,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Instant,
+};
+
+use crate::mpi::{
+    ffi::MPI_Comm,
+    topology::{Color, SimpleCommunicator},
+    traits::*,
+};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::{
+    algorithm::{
+        config::AlgorithmConfig,
+        datatypes::{Individual, Tuple},
+        random::{get_random_generator, rank_seed},
+    },
+    mpi_utils::{broadcast_flag, ROOT_RANK},
+    solvers::{self, Solution},
+    timing::{timed, Phase},
+};
+
+/// Outcome of a single run of a batch, see `--runs`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub struct RunRecord {
+    pub run: usize,
+    pub seed: u64,
+    pub best_adaptation: i32,
+    pub generations: usize,
+    pub seconds: f64,
+}
+
+/// Statistics of a batch of independent runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunSummary {
+    pub runs: usize,
+    pub mean_adaptation: f64,
+    /// Population standard deviation of the best adaptations
+    pub std_dev_adaptation: f64,
+    /// Fraction of runs reaching `target_fitness`
+    pub success_rate: f64,
+    pub mean_seconds: f64,
+}
+
+impl RunSummary {
+    pub fn new(records: &[RunRecord], target_fitness: i32) -> Self {
+        let runs = records.len();
+        let mean = |value: &dyn Fn(&RunRecord) -> f64| {
+            records.iter().map(value).sum::<f64>() / runs.max(1) as f64
+        };
+
+        let mean_adaptation = mean(&|record| record.best_adaptation as f64);
+        let variance = mean(&|record| (record.best_adaptation as f64 - mean_adaptation).powi(2));
+        let success_rate = mean(&|record| (record.best_adaptation >= target_fitness) as u8 as f64);
+
+        RunSummary {
+            runs,
+            mean_adaptation,
+            std_dev_adaptation: variance.sqrt(),
+            success_rate,
+            mean_seconds: mean(&|record| record.seconds),
+        }
+    }
+}
+
+/// Split the nodes into `groups` groups, node `rank` joining group `rank % groups`
+///
+/// Returns the group of this node and the communicator of the group.
+pub fn split_into_groups(world: &SimpleCommunicator, groups: usize) -> (usize, SimpleCommunicator) {
+    let group = world.rank() as usize % groups;
+    let communicator = world
+        .split_by_color(Color::with_value(group as i32))
+        .expect("Every node belongs to a group");
+    (group, communicator)
+}
+
+/// Split the nodes into groups of `group_size` consecutive nodes, node `rank` joining group
+/// `rank / group_size`
+///
+/// Consecutive ranks usually share a machine, which keeps the migrations of a group local. The
+/// group size should divide the number of nodes. Returns the group of this node, the number of
+/// groups and the communicator of the group.
+pub fn split_into_groups_of(
+    world: &SimpleCommunicator,
+    group_size: usize,
+) -> (usize, usize, SimpleCommunicator) {
+    let group = world.rank() as usize / group_size;
+    let communicator = world
+        .split_by_color(Color::with_value(group as i32))
+        .expect("Every node belongs to a group");
+    (group, world.size() as usize / group_size, communicator)
+}
+
+/// Whether the root of the communicator was asked to stop, agreed on by all its nodes
+pub fn stop_token(
+    stop_requested: &AtomicBool,
+    communicator: &impl Communicator<Raw = MPI_Comm>,
+) -> bool {
+    let stop = communicator.rank() == ROOT_RANK && stop_requested.load(Ordering::SeqCst);
+    broadcast_flag(stop, communicator, ROOT_RANK)
+}
+
+/// Run the solver selected by the configuration once on all nodes of the communicator
+///
+/// The record and the elites are only filled in at the root of the communicator.
+pub fn execute_run<C: Communicator<Raw = MPI_Comm>>(
+    config: &AlgorithmConfig,
+    tuples: &[Tuple],
+    communicator: &C,
+    run: usize,
+    seed: u64,
+    injected: Vec<Individual>,
+    stop_requested: &AtomicBool,
+) -> (RunRecord, Vec<Individual>) {
+    let rng = get_random_generator(rank_seed(seed, communicator.rank() as u64));
+    let started = Instant::now();
+
+    let mut solver = solvers::from_config(config, communicator, rng, injected, stop_requested);
+    debug!(solver = solver.name());
+    let Solution { elites, iterations } = timed(Phase::Compute, || solver.solve(config, tuples));
+
+    let record = RunRecord {
+        run: run + 1,
+        seed,
+        best_adaptation: elites.first().map_or(i32::MIN, |best| best.adaptation),
+        generations: iterations,
+        seconds: started.elapsed().as_secs_f64(),
+    };
+    (record, elites)
+}
+
+/// Write the records of all runs as CSV, ordered by run
+pub fn write_runs(records: &[RunRecord], path: impl AsRef<Path>) -> Result<(), csv::Error> {
+    let mut writer = csv::Writer::from_path(path)?;
+
+    let mut records = records.to_vec();
+    records.sort_by_key(|record| record.run);
+    for record in records {
+        writer.serialize(record)?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_statistics() {
+        let records: Vec<RunRecord> = [(-4, 2.0), (0, 4.0)]
+            .into_iter()
+            .enumerate()
+            .map(|(index, (best_adaptation, seconds))| RunRecord {
+                run: index + 1,
+                seed: index as u64,
+                best_adaptation,
+                generations: 10,
+                seconds,
+            })
+            .collect();
+
+        let summary = RunSummary::new(&records, 0);
+        assert_eq!(summary.runs, 2);
+        assert_eq!(summary.mean_adaptation, -2.0);
+        assert_eq!(summary.std_dev_adaptation, 2.0);
+        assert_eq!(summary.success_rate, 0.5);
+        assert_eq!(summary.mean_seconds, 3.0);
+    }
+}
@@ -0,0 +1,146 @@
+#[cfg(feature = "mpi")]
+use std::ffi::c_void;
+#[cfg(not(feature = "mpi"))]
+use std::sync::{
+    atomic::{AtomicI32, Ordering},
+    Arc,
+};
+
+#[cfg(feature = "mpi")]
+use crate::mpi::ffi::{self, MPI_Aint, MPI_Win};
+use crate::mpi::{ffi::MPI_Comm, traits::*};
+
+use crate::mpi_utils::ROOT_RANK;
+
+/// Best adaptation offered by any rank of a communicator, see `global_best_window`
+///
+/// The value lives in an MPI window in the memory of the root. Ranks raise it with a one-sided
+/// `MPI_Accumulate(MAX)` and read it with a fetch-and-op of the same operation, neither of which
+/// waits for the other ranks. Creating and dropping it are collective.
+#[cfg(feature = "mpi")]
+pub struct GlobalBest {
+    window: MPI_Win,
+    /// Memory exposed by the window on the root, unused elsewhere
+    _value: Box<i32>,
+}
+
+#[cfg(feature = "mpi")]
+impl GlobalBest {
+    pub fn new(communicator: &impl Communicator<Raw = MPI_Comm>) -> Self {
+        let mut value = Box::new(i32::MIN);
+        let size = if communicator.rank() == ROOT_RANK {
+            std::mem::size_of::<i32>()
+        } else {
+            0
+        };
+
+        let mut window: MPI_Win = std::ptr::null_mut();
+        unsafe {
+            ffi::MPI_Win_create(
+                &mut *value as *mut i32 as *mut c_void,
+                size as MPI_Aint,
+                std::mem::size_of::<i32>() as i32,
+                ffi::RSMPI_INFO_NULL,
+                communicator.as_raw(),
+                &mut window,
+            );
+        }
+        GlobalBest {
+            window,
+            _value: value,
+        }
+    }
+
+    /// Raise the global best to `adaptation`, if it's better
+    pub fn offer(&self, adaptation: i32) {
+        unsafe {
+            ffi::MPI_Win_lock(ffi::MPI_LOCK_SHARED as i32, ROOT_RANK, 0, self.window);
+            ffi::MPI_Accumulate(
+                &adaptation as *const i32 as *const c_void,
+                1,
+                ffi::RSMPI_INT32_T,
+                ROOT_RANK,
+                0,
+                1,
+                ffi::RSMPI_INT32_T,
+                ffi::RSMPI_MAX,
+                self.window,
+            );
+            ffi::MPI_Win_unlock(ROOT_RANK, self.window);
+        }
+    }
+
+    /// Best adaptation offered so far, not necessarily by all ranks in the current generation
+    ///
+    /// Accumulating the smallest value fetches the current one atomically.
+    pub fn read(&self) -> i32 {
+        let mut best = i32::MIN;
+        unsafe {
+            ffi::MPI_Win_lock(ffi::MPI_LOCK_SHARED as i32, ROOT_RANK, 0, self.window);
+            ffi::MPI_Fetch_and_op(
+                &i32::MIN as *const i32 as *const c_void,
+                &mut best as *mut i32 as *mut c_void,
+                ffi::RSMPI_INT32_T,
+                ROOT_RANK,
+                0,
+                ffi::RSMPI_MAX,
+                self.window,
+            );
+            ffi::MPI_Win_unlock(ROOT_RANK, self.window);
+        }
+        best
+    }
+}
+
+#[cfg(feature = "mpi")]
+impl Drop for GlobalBest {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::MPI_Win_free(&mut self.window);
+        }
+    }
+}
+
+/// Best adaptation offered by any rank of a communicator, see `global_best_window`
+///
+/// Ranks are threads of one process, so they share an atomic integer.
+#[cfg(not(feature = "mpi"))]
+pub struct GlobalBest {
+    value: Arc<AtomicI32>,
+}
+
+#[cfg(not(feature = "mpi"))]
+impl GlobalBest {
+    pub fn new(communicator: &impl Communicator<Raw = MPI_Comm>) -> Self {
+        GlobalBest {
+            value: crate::mpi::window::share(communicator, ROOT_RANK, || AtomicI32::new(i32::MIN)),
+        }
+    }
+
+    /// Raise the global best to `adaptation`, if it's better
+    pub fn offer(&self, adaptation: i32) {
+        self.value.fetch_max(adaptation, Ordering::Relaxed);
+    }
+
+    /// Best adaptation offered so far, not necessarily by all ranks in the current generation
+    pub fn read(&self) -> i32 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(all(test, not(feature = "mpi")))]
+mod tests {
+    use super::*;
+    use crate::mpi::run_ranks;
+
+    #[test]
+    fn test_ranks_see_the_best_offer_of_all() {
+        run_ranks(3, |world| {
+            let global_best = GlobalBest::new(&world);
+            global_best.offer(-10 * world.rank());
+            assert!(global_best.read() >= -10 * world.rank());
+            world.process_at_rank(ROOT_RANK).broadcast_into(&mut 0);
+            assert_eq!(global_best.read(), 0);
+        });
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    fs,
+    path::Path,
+};
+
+use crate::{
+    algorithm::{
+        config::{AlgorithmConfig, TimetableGrouping},
+        datatypes::{Gene, Individual, Tuple},
+    },
+    output::{course_hue, group_name},
+};
+
+const STYLE: &str = "\
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 2em; table-layout: fixed; }
+th, td { border: 1px solid #999; padding: 4px; vertical-align: top; }
+td { width: 12em; }
+.class { border-radius: 4px; padding: 4px; margin-bottom: 2px; font-size: 0.85em; }
+.continued { opacity: 0.6; border: 1px dashed #666; }
+.crowded { outline: 2px solid #c00; }";
+
+/// Classes of a grid in every period, with whether the class starts in it
+type Grid<'a> = Vec<Vec<(&'a Tuple, bool)>>;
+
+/// Write a page with a colour-coded week grid of the individual per room or student group, see
+/// `timetable_grouping`
+///
+/// Days are the columns and time slots the rows. Classes of one course share a colour, classes
+/// longer than a period are repeated faded in the following ones and cells with more than one
+/// class are outlined.
+pub fn write_html_timetable(
+    individual: &Individual,
+    tuples: &[Tuple],
+    config: &AlgorithmConfig,
+    path: impl AsRef<Path>,
+) -> std::io::Result<()> {
+    fs::write(path, render(individual, tuples, config))
+}
+
+fn render(individual: &Individual, tuples: &[Tuple], config: &AlgorithmConfig) -> String {
+    let mut html = String::new();
+    write_page(&mut html, individual, tuples, config).expect("Writing to a string can't fail");
+    html
+}
+
+fn write_page(
+    html: &mut String,
+    individual: &Individual,
+    tuples: &[Tuple],
+    config: &AlgorithmConfig,
+) -> std::fmt::Result {
+    let layout = config.period_layout();
+    let by_id: HashMap<Gene, &Tuple> = tuples.iter().map(|tuple| (tuple.id, tuple)).collect();
+
+    let mut grids: BTreeMap<&str, Grid> = BTreeMap::new();
+    for chromosome in &individual.chromosomes {
+        let start = chromosome.id.index();
+        for tuple in chromosome.genes.iter().filter_map(|gene| by_id.get(gene)) {
+            let key = match config.timetable_grouping {
+                TimetableGrouping::Room => tuple.room.as_str(),
+                TimetableGrouping::Group if tuple.group.is_empty() => continue,
+                TimetableGrouping::Group => tuple.group.as_str(),
+            };
+            let grid = grids
+                .entry(key)
+                .or_insert_with(|| vec![Vec::new(); layout.number_of_periods()]);
+            for period in layout.clamped_block(start, tuple.length()) {
+                if let Some(cell) = grid.get_mut(period) {
+                    cell.push((tuple, period == start));
+                }
+            }
+        }
+    }
+
+    writeln!(html, "<!DOCTYPE html>")?;
+    writeln!(html, "<html lang=\"pl\">")?;
+    writeln!(html, "<head>")?;
+    writeln!(html, "<meta charset=\"utf-8\">")?;
+    writeln!(html, "<title>Najlepszy plan zajęć</title>")?;
+    writeln!(html, "<style>\n{}\n</style>", STYLE)?;
+    writeln!(html, "</head>")?;
+    writeln!(html, "<body>")?;
+    writeln!(html, "<h1>Najlepszy plan zajęć</h1>")?;
+    writeln!(html, "<p>Ocena planu: {}</p>", individual.adaptation)?;
+
+    for (key, grid) in &grids {
+        writeln!(html, "<h2>{}</h2>", escape(&title(key, config)))?;
+        writeln!(html, "<table>")?;
+        write!(html, "<tr><th></th>")?;
+        for day in 0..layout.days {
+            write!(html, "<th>Dzień {}</th>", day + 1)?;
+        }
+        writeln!(html, "</tr>")?;
+
+        for slot in 0..layout.slots_per_day {
+            write!(html, "<tr><th>{}</th>", slot + 1)?;
+            for day in 0..layout.days {
+                let cell = &grid[day * layout.slots_per_day + slot];
+                match cell.len() {
+                    0 | 1 => write!(html, "<td>")?,
+                    _ => write!(html, "<td class=\"crowded\">")?,
+                }
+                for (tuple, starts) in cell {
+                    write_class(html, tuple, *starts, config.timetable_grouping)?;
+                }
+                write!(html, "</td>")?;
+            }
+            writeln!(html, "</tr>")?;
+        }
+        writeln!(html, "</table>")?;
+    }
+
+    writeln!(html, "</body>")?;
+    writeln!(html, "</html>")
+}
+
+/// Heading of the grid of a room or student group, with its building or name when known
+fn title(key: &str, config: &AlgorithmConfig) -> String {
+    match config.timetable_grouping {
+        TimetableGrouping::Room => match config.rooms.get(key) {
+            Some(room) if !room.building.is_empty() => format!("Sala {} ({})", key, room.building),
+            _ => format!("Sala {}", key),
+        },
+        TimetableGrouping::Group => format!("Grupa {}", group_name(config, key)),
+    }
+}
+
+/// A class in a cell, with the teacher and whatever the grid isn't grouped by
+fn write_class(
+    html: &mut String,
+    tuple: &Tuple,
+    starts: bool,
+    grouping: TimetableGrouping,
+) -> std::fmt::Result {
+    let details = match grouping {
+        TimetableGrouping::Room if tuple.group.is_empty() => tuple.teacher.clone(),
+        TimetableGrouping::Room => format!("{}, {}", tuple.teacher, tuple.group),
+        TimetableGrouping::Group => format!("{}, {}", tuple.teacher, tuple.room),
+    };
+    let class = if starts { "class" } else { "class continued" };
+
+    writeln!(
+        html,
+        "<div class=\"{}\" style=\"background: {}\" title=\"#{}\"><b>{}</b><br>{}</div>",
+        class,
+        colour(tuple),
+        tuple.id,
+        escape(&tuple.label),
+        escape(&details)
+    )
+}
+
+/// Background of the classes of a course, see [`course_hue`]
+fn colour(tuple: &Tuple) -> String {
+    format!("hsl({}, 70%, 85%)", course_hue(tuple))
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::datatypes::{Chromosome, PeriodId, TupleId};
+    use smallvec::smallvec;
+
+    #[test]
+    fn test_classes_fill_the_cells_of_their_rooms() {
+        let tuples: Vec<Tuple> = [
+            (1, "Wykład | Sieci", "A", 2),
+            (2, "Laboratorium | Sieci", "A", 1),
+            (3, "Wykład | <Bazy>", "B", 1),
+        ]
+        .into_iter()
+        .map(|(id, label, room, duration)| Tuple {
+            id: TupleId(id),
+            label: label.to_string(),
+            room: room.to_string(),
+            teacher: "Smith".to_string(),
+            duration,
+            ..Tuple::default()
+        })
+        .collect();
+        let individual = Individual::with_chromosomes(
+            [smallvec![TupleId(1), TupleId(3)], smallvec![TupleId(2)]]
+                .into_iter()
+                .chain(std::iter::repeat_with(Default::default))
+                .take(4)
+                .enumerate()
+                .map(|(period, genes)| Chromosome {
+                    id: PeriodId(period),
+                    genes,
+                })
+                .collect(),
+        );
+        let config = AlgorithmConfig {
+            number_of_periods: 4,
+            days: Some(2),
+            ..AlgorithmConfig::default()
+        };
+
+        let html = render(&individual, &tuples, &config);
+
+        assert_eq!(html.matches("<h2>").count(), 2);
+        assert!(html.contains("<th>Dzień 2</th>"));
+        assert_eq!(html.matches("class=\"class continued\"").count(), 1);
+        assert_eq!(html.matches("<td class=\"crowded\">").count(), 1);
+        assert!(html.contains("<b>Wykład | &lt;Bazy&gt;</b>"));
+        assert_eq!(colour(&tuples[0]), colour(&tuples[1]));
+        assert_ne!(colour(&tuples[0]), colour(&tuples[2]));
+    }
+}
@@ -0,0 +1,255 @@
+use std::{collections::BTreeSet, fs, path::Path};
+
+use chrono::{DateTime, Days, NaiveDate, NaiveDateTime, TimeDelta, Utc};
+
+use crate::{
+    algorithm::{
+        config::AlgorithmConfig,
+        datatypes::{Individual, Tuple},
+    },
+    output::{file_name, group_name, teacher_name},
+};
+
+/// Format of the dates and times of the events, local time without a zone
+const LOCAL_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+/// A class on the dates of its first week
+struct Event<'a> {
+    tuple: &'a Tuple,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+}
+
+/// Write an iCalendar file of every student group and every teacher of the tuples, if the
+/// configuration maps the periods to dates with `start_date` and `slot_times`
+///
+/// The calendars are written to `groups/<group>.ics` and `teachers/<teacher>.ics` in `dir`, next
+/// to the text timetables of [`write_personal_timetables`](crate::output::write_personal_timetables).
+/// Every class repeats weekly for `weeks` weeks.
+pub fn write_calendars(
+    individual: &Individual,
+    tuples: &[Tuple],
+    config: &AlgorithmConfig,
+    dir: impl AsRef<Path>,
+) -> std::io::Result<()> {
+    let Some(start_date) = config.start_date else {
+        return Ok(());
+    };
+    let events = events(individual, tuples, config, start_date);
+    let stamp = DateTime::<Utc>::from(std::time::SystemTime::now())
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string();
+
+    let groups_dir = dir.as_ref().join("groups");
+    let teachers_dir = dir.as_ref().join("teachers");
+    fs::create_dir_all(&groups_dir)?;
+    fs::create_dir_all(&teachers_dir)?;
+
+    let groups: BTreeSet<&str> = tuples
+        .iter()
+        .map(|tuple| tuple.group.as_str())
+        .filter(|group| !group.is_empty())
+        .collect();
+    for group in groups {
+        let name = format!("Plan zajęć: {}", group_name(config, group));
+        let events = events.iter().filter(|event| event.tuple.group == group);
+        fs::write(
+            groups_dir.join(file_name(group, "ics")),
+            calendar(&name, events, config, &stamp),
+        )?;
+    }
+
+    let teachers: BTreeSet<&str> = tuples.iter().map(|tuple| tuple.teacher.as_str()).collect();
+    for teacher in teachers {
+        let name = format!("Plan zajęć: {}", teacher_name(config, teacher));
+        let events = events.iter().filter(|event| event.tuple.teacher == teacher);
+        fs::write(
+            teachers_dir.join(file_name(teacher, "ics")),
+            calendar(&name, events, config, &stamp),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Classes of the individual on the dates of the first week, the days of the layout following
+/// each other from `start_date`. Classes in slots without a time are left out
+fn events<'a>(
+    individual: &Individual,
+    tuples: &'a [Tuple],
+    config: &AlgorithmConfig,
+    start_date: NaiveDate,
+) -> Vec<Event<'a>> {
+    let layout = config.period_layout();
+    let mut events = Vec::new();
+
+    for chromosome in &individual.chromosomes {
+        let day_slot = layout.day_slot(chromosome.id.index());
+        let Some(date) = start_date.checked_add_days(Days::new(day_slot.day as u64)) else {
+            continue;
+        };
+
+        for gene in &chromosome.genes {
+            let Some(tuple) = tuples.iter().find(|tuple| tuple.id == *gene) else {
+                continue;
+            };
+            let last_slot = (day_slot.slot + tuple.length() - 1).min(layout.slots_per_day - 1);
+            let (Some(start), Some(last)) = (
+                config.slot_times.get(day_slot.slot),
+                config.slot_times.get(last_slot),
+            ) else {
+                continue;
+            };
+
+            events.push(Event {
+                tuple,
+                start: date.and_time(*start),
+                end: date.and_time(*last) + TimeDelta::minutes(config.period_minutes as i64),
+            });
+        }
+    }
+
+    events
+}
+
+/// Text of a calendar with the events, see RFC 5545
+fn calendar<'a>(
+    name: &str,
+    events: impl Iterator<Item = &'a Event<'a>>,
+    config: &AlgorithmConfig,
+    stamp: &str,
+) -> String {
+    let mut ics = String::new();
+    push_line(&mut ics, "BEGIN:VCALENDAR");
+    push_line(&mut ics, "VERSION:2.0");
+    push_line(&mut ics, "PRODID:-//WFiIS//Planner//PL");
+    push_line(&mut ics, "CALSCALE:GREGORIAN");
+    push_line(&mut ics, &format!("X-WR-CALNAME:{}", escape(name)));
+
+    for event in events {
+        let tuple = event.tuple;
+        let mut description = format!("Prowadzący: {}", tuple.teacher);
+        if !tuple.group.is_empty() {
+            description.push_str(&format!("\nGrupa: {}", tuple.group));
+        }
+
+        push_line(&mut ics, "BEGIN:VEVENT");
+        push_line(
+            &mut ics,
+            &format!(
+                "UID:{}-{}@planner",
+                tuple.id,
+                event.start.format(LOCAL_FORMAT)
+            ),
+        );
+        push_line(&mut ics, &format!("DTSTAMP:{}", stamp));
+        push_line(
+            &mut ics,
+            &format!("DTSTART:{}", event.start.format(LOCAL_FORMAT)),
+        );
+        push_line(
+            &mut ics,
+            &format!("DTEND:{}", event.end.format(LOCAL_FORMAT)),
+        );
+        if config.weeks > 1 {
+            push_line(
+                &mut ics,
+                &format!("RRULE:FREQ=WEEKLY;COUNT={}", config.weeks),
+            );
+        }
+        push_line(&mut ics, &format!("SUMMARY:{}", escape(&tuple.label)));
+        push_line(&mut ics, &format!("LOCATION:{}", escape(&tuple.room)));
+        push_line(&mut ics, &format!("DESCRIPTION:{}", escape(&description)));
+        push_line(&mut ics, "END:VEVENT");
+    }
+
+    push_line(&mut ics, "END:VCALENDAR");
+    ics
+}
+
+/// Append a content line ended with CRLF, folded into lines of at most 75 bytes
+fn push_line(ics: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            ics.push_str("\r\n ");
+            width = 1;
+        }
+        ics.push(c);
+        width += c.len_utf8();
+    }
+    ics.push_str("\r\n");
+}
+
+/// Escape a text value, backslashes, separators and newlines have to be written as sequences
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::datatypes::{Chromosome, PeriodId, TupleId};
+    use chrono::NaiveTime;
+    use smallvec::smallvec;
+
+    #[test]
+    fn test_classes_become_weekly_events() {
+        let tuples = vec![Tuple {
+            id: TupleId(4),
+            label: "Wykład | Sieci, część 1".to_string(),
+            room: "D-10 108".to_string(),
+            teacher: "Smith".to_string(),
+            group: "FT-1".to_string(),
+            duration: 2,
+            ..Tuple::default()
+        }];
+        let individual = Individual::with_chromosomes(
+            (0..4)
+                .map(|period| Chromosome {
+                    id: PeriodId(period),
+                    genes: match period {
+                        2 => smallvec![TupleId(4)],
+                        _ => smallvec![],
+                    },
+                })
+                .collect(),
+        );
+        let config = AlgorithmConfig {
+            number_of_periods: 4,
+            days: Some(2),
+            start_date: NaiveDate::from_ymd_opt(2024, 10, 7),
+            slot_times: ["08:00", "09:45"]
+                .map(|time| time.parse::<NaiveTime>().unwrap())
+                .to_vec(),
+            weeks: 15,
+            ..AlgorithmConfig::default()
+        };
+        assert!(config.validate().is_ok());
+
+        let events = events(&individual, &tuples, &config, config.start_date.unwrap());
+        let ics = calendar(
+            "Plan zajęć: Smith",
+            events.iter(),
+            &config,
+            "20241001T000000Z",
+        );
+
+        assert!(ics.contains("\r\nDTSTART:20241008T080000\r\n"));
+        assert!(ics.contains("\r\nDTEND:20241008T111500\r\n"));
+        assert!(ics.contains("\r\nRRULE:FREQ=WEEKLY;COUNT=15\r\n"));
+        assert!(ics.contains("\r\nSUMMARY:Wykład | Sieci\\, część 1\r\n"));
+        assert!(ics.contains("DESCRIPTION:Prowadzący: Smith\\nGrupa: FT-1"));
+        assert!(ics.lines().all(|line| line.len() <= 76));
+    }
+}
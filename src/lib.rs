@@ -0,0 +1,2 @@
+/// For more details, see the [PDF documentation](../Dokumentacja.pdf).
+pub mod algorithm;
@@ -0,0 +1,95 @@
+use tracing::Subscriber;
+use tracing_subscriber::{fmt::MakeWriter, util::SubscriberInitExt, EnvFilter};
+
+/// Level used when neither `--log-level` nor `RUST_LOG` is given
+const DEFAULT_LOG_LEVEL: &str = "info";
+
+/// Install the global tracing subscriber
+///
+/// `level` takes an [`EnvFilter`] directive (e.g. `debug` or `planner=trace`) and wins over the
+/// `RUST_LOG` environment variable. With `json` every event is written as a JSON object per line,
+/// including the fields of the spans it happened in (e.g. the rank and the generation).
+pub fn init_logging(level: Option<&str>, json: bool) {
+    subscriber(level, json, std::io::stdout).init();
+}
+
+/// Subscriber of [`init_logging`] writing to `writer`
+fn subscriber<W>(level: Option<&str>, json: bool, writer: W) -> Box<dyn Subscriber + Send + Sync>
+where
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    let filter = match level {
+        Some(level) => EnvFilter::new(level),
+        None => {
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_LOG_LEVEL))
+        }
+    };
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_target(false)
+        .with_writer(writer);
+
+    if json {
+        Box::new(builder.json().finish())
+    } else {
+        Box::new(builder.finish())
+    }
+}
+
+#[cfg(all(test, not(feature = "mpi")))]
+mod tests {
+    use std::{
+        io::{self, Write},
+        sync::{Arc, Mutex},
+    };
+
+    use tracing::{debug, info, info_span};
+
+    use super::*;
+    use crate::mpi::{run_ranks, traits::*};
+
+    /// Lines written by all ranks
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(bytes);
+            Ok(bytes.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_json_events_carry_the_rank_of_their_span() {
+        let captured = Captured::default();
+
+        run_ranks(2, |world| {
+            let writer = captured.clone();
+            let subscriber = subscriber(Some("info"), true, move || writer.clone());
+            tracing::subscriber::with_default(subscriber, || {
+                let _rank_span = info_span!("rank", rank = world.rank()).entered();
+                info!(best_adaptation = -3 * world.rank());
+                debug!("Left out below the level");
+            });
+        });
+
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        let mut events: Vec<(i64, i64)> = output
+            .lines()
+            .map(|line| {
+                let event: serde_json::Value = serde_json::from_str(line).unwrap();
+                (
+                    event["span"]["rank"].as_i64().unwrap(),
+                    event["fields"]["best_adaptation"].as_i64().unwrap(),
+                )
+            })
+            .collect();
+        events.sort_unstable();
+        assert_eq!(events, [(0, 0), (1, -3)]);
+    }
+}
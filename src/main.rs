@@ -1,60 +1,828 @@
-use clap::{Arg, ArgAction, Command};
-use itertools::Itertools;
-use mpi::{traits::*, Rank, Threading};
-use rayon::prelude::*;
-use std::fs::OpenOptions;
-use std::io::Write;
+use std::{
+    cmp::Reverse,
+    fmt::Display,
+    fs::File,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+#[cfg(feature = "mpi")]
+use crate::mpi::Threading;
+use crate::mpi::{
+    topology::{Color, SimpleCommunicator},
+    traits::*,
+    Rank,
+};
+use clap::{builder::RangedU64ValueParser, Arg, ArgAction, ArgMatches, Command};
+use tracing::{debug, error, info, info_span, warn};
 
 use self::{
-    algorithm::config::AlgorithmConfig,
-    mpi_utils::{mpi_execute_and_synchronize_at, ROOT_RANK},
+    abort::ExitReason,
+    algorithm::{
+        assignment,
+        config::{AlgorithmConfig, ConfigLoadError, ParallelModel},
+        datatypes::{locks_from_csv, Gene, Individual},
+        pareto,
+        random::{get_random_generator, random_seed, rank_seed, run_seed, PlannerRng},
+        report::ConflictReport,
+        resources::{self, Group, Room, Teacher},
+        synthetic::{self, InstanceSpec},
+    },
+    elites::EliteArchive,
+    experiments::{RunRecord, RunSummary},
+    mpi_utils::{
+        gather_serde, mpi_execute_and_synchronize_at, set_broadcast_chunk_size, ROOT_RANK,
+    },
+    sweep::{Setting, SweepRecord, SweepSpec},
+    timing::RankTimings,
 };
 
-use crate::algorithm::{calculate_fitness, crossover, mutate};
-use crate::mpi_utils::mpi_gather_and_synchronize;
-use crate::{algorithm::datatypes::Tuple, mpi_utils::mpi_split_data_across_nodes};
+// the algorithm is the library of the package, so benchmarks can use it too
+use planner::algorithm;
+
+use crate::algorithm::datatypes::Tuple;
+use crate::mpi_datatypes::broadcast_tuples;
+use crate::mpi_utils::broadcast_serde;
 
-/// For more details, see the [PDF documentation](../Dokumentacja.pdf).
-mod algorithm;
+// modules import MPI from `crate::mpi`, which is the single process one without the feature
+#[cfg(feature = "mpi")]
+extern crate mpi;
+#[cfg(not(feature = "mpi"))]
+use single_process as mpi;
+
+mod abort;
+mod aggregator;
+mod compression;
+mod database;
+mod elites;
+mod experiments;
+mod global_best;
+mod html;
+mod ics;
+mod logging;
+mod mpi_datatypes;
 mod mpi_utils;
+mod output;
+mod runner;
+mod score;
+/// The parts of the `mpi` crate used by the planner, for ranks running as threads of a single
+/// process
+///
+/// Built instead of MPI without the `mpi` feature, so the planner runs where MPI isn't installed.
+/// Every rank has a mailbox the others put their messages in, collectives are made of such
+/// messages.
+#[cfg(not(feature = "mpi"))]
+mod single_process;
+mod solvers;
+mod sweep;
+mod threads;
+mod timing;
+mod validation;
+mod verify;
+mod xlsx;
 
-/// Read the configuration and tuples from the command line arguments
-fn root_init() -> (AlgorithmConfig, Vec<Tuple>) {
-    let args = Command::new("Genetic Algorithm")
+/// Define the command line interface
+fn build_cli() -> Command {
+    let command = Command::new("Genetic Algorithm")
         .arg(
             Arg::new("config")
                 .short('c')
+                .long("config")
                 .value_name("FILE")
-                .help("Sets a custom config file")
+                .help("Sets a custom config file (.json, .toml or .yaml)")
                 .action(ArgAction::Set)
-                .required(false),
+                .required(false)
+                .global(true),
         )
         .arg(
             Arg::new("tuples")
                 .short('t')
+                .long("tuples")
+                .value_name("FILE")
+                .help("Custom location of tuples (.csv, .json or .yaml, optionally .gz or .zst), - for stdin")
+                .action(ArgAction::Set)
+                .required(false)
+                .global(true),
+        )
+        .arg(
+            Arg::new("locks")
+                .long("locks")
+                .value_name("FILE")
+                .help("Pin tuples to periods, CSV with Period and TupleId columns")
+                .action(ArgAction::Set)
+                .required(false)
+                .global(true),
+        )
+        .arg(
+            Arg::new("rooms")
+                .long("rooms")
                 .value_name("FILE")
-                .help("Custom location of tuples")
+                .help("Rooms with their capacity, kind and building (.csv, .json or .yaml)")
+                .action(ArgAction::Set)
+                .required(false)
+                .global(true),
+        )
+        .arg(
+            Arg::new("teachers")
+                .long("teachers")
+                .value_name("FILE")
+                .help("Teachers with workload limits and unavailable periods (.csv, .json or .yaml)")
+                .action(ArgAction::Set)
+                .required(false)
+                .global(true),
+        )
+        .arg(
+            Arg::new("student-groups")
+                .long("student-groups")
+                .value_name("FILE")
+                .help("Student groups with their names and sizes (.csv, .json or .yaml)")
+                .action(ArgAction::Set)
+                .required(false)
+                .global(true),
+        )
+        .arg(
+            Arg::new("lenient")
+                .long("lenient")
+                .help("Fall back to the default config when it can't be loaded or is invalid")
+                .action(ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("log-level")
+                .long("log-level")
+                .value_name("LEVEL")
+                .help("Log level or filter directives, overrides RUST_LOG (default: info)")
+                .action(ArgAction::Set)
+                .required(false)
+                .global(true),
+        )
+        .arg(
+            Arg::new("log-json")
+                .long("log-json")
+                .help("Write logs as JSON lines")
+                .action(ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("inject")
+                .long("inject")
+                .value_name("FILE")
+                .help("Seed the first population with elites exported by previous runs")
+                .num_args(1..)
+                .action(ArgAction::Append)
+                .required(false),
+        )
+        .arg(
+            Arg::new("export-elites")
+                .long("export-elites")
+                .value_name("FILE")
+                .help("Export the best individuals of the run for use with --inject")
+                .action(ArgAction::Set)
+                .required(false),
+        )
+        .arg(
+            Arg::new("db")
+                .long("db")
+                .value_name("FILE")
+                .help("Record the configuration, the metrics of every generation and the best schedule in an SQLite database, adding to it")
                 .action(ArgAction::Set)
                 .required(false),
         )
-        .get_matches();
+        .arg(
+            Arg::new("seed")
+                .long("seed")
+                .value_name("SEED")
+                .help("Seed of the random number generators, rank r uses SEED + r")
+                .value_parser(clap::value_parser!(u64))
+                .action(ArgAction::Set)
+                .required(false),
+        )
+        .arg(
+            Arg::new("runs")
+                .long("runs")
+                .value_name("N")
+                .help("Number of independent runs, a summary is written to runs.csv")
+                .value_parser(RangedU64ValueParser::<usize>::new().range(1..))
+                .default_value("1")
+                .action(ArgAction::Set),
+        )
+        .arg(
+            Arg::new("split-runs")
+                .long("split-runs")
+                .help("Split the nodes into groups executing the runs concurrently")
+                // on this side, the global one reaches subcommands without this argument
+                .conflicts_with("islands-per-run")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("islands-per-run")
+                .long("islands-per-run")
+                .value_name("K")
+                .help("Split the nodes into groups of K consecutive ranks executing the runs concurrently")
+                .value_parser(RangedU64ValueParser::<usize>::new().range(1..))
+                .action(ArgAction::Set)
+                .global(true),
+        )
+        .arg(
+            Arg::new("timings")
+                .long("timings")
+                .help("Print the compute and communication time of every rank, also written to timings.json")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("logger-rank")
+                .long("logger-rank")
+                .help("Dedicate the last rank to collecting the metrics of the islands and writing all output files")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("initial-schedule")
+                .long("initial-schedule")
+                .value_name("FILE")
+                .help("Warm-start from a previously exported schedule (.csv or .json)")
+                .action(ArgAction::Set)
+                .required(false),
+        )
+        .subcommand(
+            Command::new("score")
+                .about("Evaluate existing schedule files against the tuples")
+                .arg(
+                    Arg::new("batch")
+                        .long("batch")
+                        .value_name("DIR")
+                        .help("Directory with schedule files (.csv or .json) to score")
+                        .action(ArgAction::Set)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Where to write the ranked scores")
+                        .action(ArgAction::Set)
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            Command::new("sweep")
+                .about("Run the algorithm for every combination of config values in a grid")
+                .arg(
+                    Arg::new("spec")
+                        .long("spec")
+                        .value_name("FILE")
+                        .help("Sweep specification (.json, .toml or .yaml)")
+                        .action(ArgAction::Set)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("groups")
+                        .long("groups")
+                        .value_name("N")
+                        .help("Number of node groups running settings concurrently (default: all nodes)")
+                        .value_parser(RangedU64ValueParser::<usize>::new().range(1..))
+                        .action(ArgAction::Set)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Where to write the results table (default: sweep.csv)")
+                        .action(ArgAction::Set)
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            Command::new("generate")
+                .about("Write a synthetic instance to stress-test configurations")
+                .arg(
+                    // shadows the global tuples file
+                    Arg::new("tuples")
+                        .long("tuples")
+                        .value_name("N")
+                        .help("Number of tuples")
+                        .value_parser(RangedU64ValueParser::<usize>::new().range(1..=u16::MAX as u64))
+                        .default_value("200")
+                        .action(ArgAction::Set),
+                )
+                .arg(
+                    Arg::new("teachers")
+                        .long("teachers")
+                        .value_name("N")
+                        .help("Number of teachers")
+                        .value_parser(RangedU64ValueParser::<usize>::new().range(1..))
+                        .default_value("20")
+                        .action(ArgAction::Set),
+                )
+                .arg(
+                    Arg::new("rooms")
+                        .long("rooms")
+                        .value_name("N")
+                        .help("Number of rooms")
+                        .value_parser(RangedU64ValueParser::<usize>::new().range(1..))
+                        .default_value("20")
+                        .action(ArgAction::Set),
+                )
+                .arg(
+                    Arg::new("density")
+                        .long("density")
+                        .value_name("FRACTION")
+                        .help("Fraction of pairs of tuples sharing a teacher or a room")
+                        .value_parser(parse_fraction)
+                        .default_value("0.3")
+                        .action(ArgAction::Set),
+                )
+                .arg(
+                    Arg::new("seed")
+                        .long("seed")
+                        .value_name("SEED")
+                        .help("Seed of the generator, the same seed gives the same instance")
+                        .value_parser(clap::value_parser!(u64))
+                        .default_value("0")
+                        .action(ArgAction::Set),
+                )
+                .arg(
+                    Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Where to write the tuples (.csv or .json, default: tuples.csv)")
+                        .action(ArgAction::Set)
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            Command::new("verify")
+                .about("Check a schedule against all constraints and list every violation")
+                .arg(
+                    Arg::new("schedule")
+                        .long("schedule")
+                        .value_name("FILE")
+                        .help("Schedule file (.csv or .json) to check")
+                        .action(ArgAction::Set)
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("explain")
+                        .long("explain")
+                        .value_name("FILE")
+                        .help("Write a JSON report of every penalty making up the fitness")
+                        .action(ArgAction::Set)
+                        .required(false),
+                ),
+        );
 
-    let config_path = args
-        .get_one::<String>("config")
-        .map(String::as_str)
-        .unwrap_or("config.json");
+    #[cfg(not(feature = "mpi"))]
+    let command = command.arg(
+        Arg::new("ranks")
+            .long("ranks")
+            .value_name("N")
+            .help("Number of ranks, run as threads exchanging messages like MPI processes")
+            .value_parser(RangedU64ValueParser::<usize>::new().range(1..))
+            .default_value("1")
+            .action(ArgAction::Set),
+    );
+
+    with_config_override_args(command)
+}
+
+/// Add a `--field-name` argument for every config field not shadowed by another argument
+fn with_config_override_args(command: Command) -> Command {
+    let taken: Vec<String> = command
+        .get_arguments()
+        .filter_map(|arg| arg.get_long().map(String::from))
+        .collect();
+
+    let override_args: Vec<Arg> = AlgorithmConfig::field_names()
+        .into_iter()
+        .map(|field| (field.replace('_', "-"), field))
+        .filter(|(long, _)| !taken.contains(long))
+        .map(|(long, field)| {
+            Arg::new(override_id(&field))
+                .long(long)
+                .value_name("VALUE")
+                .help(format!("Overrides `{}` of the config", field))
+                .help_heading("Config overrides")
+                .action(ArgAction::Set)
+                .required(false)
+                .global(true)
+        })
+        .collect();
+
+    command.args(override_args)
+}
+
+/// Id of the argument overriding a config field, apart from the ids of other arguments like
+/// `--locks`, which takes a file instead of the `locks` field
+fn override_id(field: &str) -> String {
+    format!("config.{}", field)
+}
+
+/// Config overrides given as command line arguments
+fn cli_overrides(args: &ArgMatches) -> Vec<(String, String)> {
+    AlgorithmConfig::field_names()
+        .into_iter()
+        .filter_map(|field| {
+            let value = args
+                .try_get_one::<String>(&override_id(&field))
+                .ok()
+                .flatten()?
+                .clone();
+            Some((field, value))
+        })
+        .collect()
+}
+
+/// Read the configuration and tuples from the command line arguments
+fn root_init(args: &ArgMatches) -> (AlgorithmConfig, Vec<Tuple>) {
+    let explicit_config_path = args.get_one::<String>("config").map(String::as_str);
+    let config_path = explicit_config_path.unwrap_or("config.json");
+    let lenient = args.get_flag("lenient");
 
     let tuples_path = args
         .get_one::<String>("tuples")
         .map(String::as_str)
         .unwrap_or("tuples.csv");
 
-    let config = AlgorithmConfig::from_json(config_path).unwrap_or_default();
-    let tuples = Tuple::from_csv(tuples_path).expect("Tuples could not be loaded");
+    let file_config = if lenient {
+        AlgorithmConfig::from_file(config_path).unwrap_or_default()
+    } else if explicit_config_path.is_none() && !Path::new(config_path).exists() {
+        AlgorithmConfig::default()
+    } else {
+        AlgorithmConfig::from_file_strict(config_path)
+            .unwrap_or_else(|err| exit_with_config_error(config_path, err))
+    };
+
+    // file < environment variables < command line arguments
+    let mut config = file_config
+        .with_overrides(AlgorithmConfig::env_overrides())
+        .and_then(|config| config.with_overrides(cli_overrides(args)))
+        .unwrap_or_else(|err| exit_with_config_error(config_path, err));
+    let (mut tuples, locations) = Tuple::from_file_located(tuples_path, &config)
+        .unwrap_or_else(|err| exit_with_input_error(tuples_path, err));
+
+    if let Some(locks_path) = args.get_one::<String>("locks") {
+        let locks =
+            locks_from_csv(locks_path).unwrap_or_else(|err| exit_with_input_error(locks_path, err));
+        config.locks.extend(locks);
+    }
+
+    if let Some(rooms_path) = args.get_one::<String>("rooms") {
+        let rooms = Room::from_file(rooms_path)
+            .unwrap_or_else(|err| exit_with_input_error(rooms_path, err));
+        config
+            .rooms
+            .extend(rooms.into_iter().map(|room| (room.id.clone(), room)));
+    }
+
+    if let Some(teachers_path) = args.get_one::<String>("teachers") {
+        let teachers = Teacher::from_file(teachers_path)
+            .unwrap_or_else(|err| exit_with_input_error(teachers_path, err));
+        config.teachers.extend(
+            teachers
+                .into_iter()
+                .map(|teacher| (teacher.id.clone(), teacher)),
+        );
+    }
+
+    if let Some(groups_path) = args.get_one::<String>("student-groups") {
+        let groups = Group::from_file(groups_path)
+            .unwrap_or_else(|err| exit_with_input_error(groups_path, err));
+        config
+            .student_groups
+            .extend(groups.into_iter().map(|group| (group.id.clone(), group)));
+    }
+
+    if !config.teachers.is_empty() {
+        resources::apply_unavailability(&config.teachers, &mut tuples);
+    }
+
+    let diagnostics = validation::validate_input(&config, &tuples, &locations);
+    if !diagnostics.is_empty() {
+        error!(
+            tuples = tuples_path,
+            "Found {} problems in the input",
+            diagnostics.len()
+        );
+        for (number, diagnostic) in diagnostics.iter().enumerate() {
+            error!("{}. {}", number + 1, diagnostic);
+        }
+        ExitReason::InvalidInput.exit();
+    }
+
+    if !lenient {
+        if let Err(err) = config.validate() {
+            exit_with_config_error(config_path, err);
+        }
+    }
 
     return (config, tuples);
 }
 
+/// Report an input file that can't be loaded and stop
+fn exit_with_input_error(path: &str, err: impl Display) -> ! {
+    error!(file = path, "{}", err);
+    ExitReason::InvalidInput.exit();
+}
+
+/// Report a config that can't be used and stop
+fn exit_with_config_error(config_path: &str, err: ConfigLoadError) -> ! {
+    error!(config = config_path, "{}", err);
+    error!("Use --lenient to fall back to the default configuration");
+    ExitReason::InvalidInput.exit();
+}
+
+/// Load elites from all files passed with `--inject`, skipping incompatible ones
+fn load_injected_elites(
+    args: &ArgMatches,
+    config: &AlgorithmConfig,
+    tuples: &[Tuple],
+) -> Vec<Individual> {
+    let Some(paths) = args.get_many::<String>("inject") else {
+        return Vec::new();
+    };
+
+    let mut individuals = Vec::new();
+
+    for path in paths {
+        match EliteArchive::from_json(path, config, tuples) {
+            Ok(archive) => {
+                if archive.config_hash != elites::config_hash(config) {
+                    warn!("Elites from {} were evolved with a different config", path);
+                }
+                info!(
+                    "Injecting {} elites from {}",
+                    archive.individuals.len(),
+                    path
+                );
+                individuals.extend(archive.individuals);
+            }
+            Err(err) => warn!("Skipping elites from {}: {}", path, err),
+        }
+    }
+
+    individuals
+}
+
+/// Load the schedule passed with `--initial-schedule` and turn it into copies and mutated
+/// variants for the first population
+///
+/// Tuples removed since the schedule was exported are dropped and new tuples are placed in
+/// random periods, so small changes of the input don't prevent the warm start.
+fn load_warm_start(
+    args: &ArgMatches,
+    config: &AlgorithmConfig,
+    tuples: &[Tuple],
+    rng: &mut PlannerRng,
+) -> Vec<Individual> {
+    let Some(path) = args.get_one::<String>("initial-schedule") else {
+        return Vec::new();
+    };
+
+    let mut schedule = Individual::from_file(path, config.number_of_periods)
+        .unwrap_or_else(|err| exit_with_input_error(path, err));
+
+    let genes: Vec<Gene> = tuples.iter().map(|tuple| tuple.id).collect();
+    algorithm::repair_genes(
+        &mut schedule,
+        &genes,
+        config.number_of_periods,
+        config.domains.as_deref(),
+        rng,
+    );
+    algorithm::apply_locks(&mut schedule, &config.locks);
+
+    let count = (config.population_size as f32 * config.warm_start_fraction).round() as usize;
+    info!("Warm-starting with {} individuals from {}", count, path);
+
+    algorithm::create_variants(config, &schedule, tuples, count, rng)
+}
+
+/// Score every schedule in a directory and write the ranking as CSV
+fn run_score(args: &ArgMatches) {
+    let (config, tuples) = root_init(args);
+
+    let batch_dir = args.get_one::<String>("batch").unwrap();
+    let output_path = args
+        .get_one::<String>("output")
+        .map(String::as_str)
+        .unwrap_or("scores.csv");
+
+    let scores = score::score_schedules(batch_dir, &config, &tuples)
+        .unwrap_or_else(|err| exit_with_input_error(batch_dir, err));
+    score::write_scores(&scores, output_path)
+        .unwrap_or_else(|err| exit_with_input_error(output_path, err));
+
+    info!(
+        "Scored {} schedules, ranking written to {}",
+        scores.len(),
+        output_path
+    );
+}
+
+/// Write a synthetic instance, see [`synthetic::generate`]
+fn run_generate(args: &ArgMatches) {
+    let spec = InstanceSpec {
+        tuples: *args.get_one::<usize>("tuples").unwrap(),
+        rooms: *args.get_one::<usize>("rooms").unwrap(),
+        teachers: *args.get_one::<usize>("teachers").unwrap(),
+        density: *args.get_one::<f64>("density").unwrap(),
+        seed: *args.get_one::<u64>("seed").unwrap(),
+    };
+    let output_path = args
+        .get_one::<String>("output")
+        .map(String::as_str)
+        .unwrap_or("tuples.csv");
+
+    let tuples = synthetic::generate(&spec);
+    if output_path.ends_with(".json") {
+        let file = File::create(output_path).expect("Could not create the tuples file");
+        serde_json::to_writer_pretty(file, &tuples).expect("Could not write the tuples");
+    } else {
+        Tuple::to_csv(&tuples, output_path).expect("Could not write the tuples");
+    }
+
+    info!(
+        density = synthetic::conflict_density(&tuples),
+        "Generated {} tuples, written to {}",
+        tuples.len(),
+        output_path
+    );
+}
+
+/// Parse a number from 0 to 1
+fn parse_fraction(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(fraction) if (0.0..=1.0).contains(&fraction) => Ok(fraction),
+        _ => Err(format!("{} is not a number from 0 to 1", value)),
+    }
+}
+
+/// Check a schedule against all constraints, exiting with status 1 if any is violated
+fn run_verify(args: &ArgMatches) {
+    let (config, tuples) = root_init(args);
+
+    let schedule_path = args.get_one::<String>("schedule").unwrap();
+    let schedule = Individual::from_file(schedule_path, config.number_of_periods)
+        .unwrap_or_else(|err| exit_with_input_error(schedule_path, err));
+
+    if let Some(report_path) = args.get_one::<String>("explain") {
+        let report = ConflictReport::explain(&config, &schedule, &tuples);
+        let file = File::create(report_path).expect("Could not create the report file");
+        serde_json::to_writer_pretty(file, &report).expect("Could not write the report");
+        info!(
+            adaptation = report.adaptation,
+            "Report written to {}", report_path
+        );
+    }
+
+    let violations = verify::verify_schedule(&config, &schedule, &tuples);
+    for violation in &violations {
+        println!("{}", violation);
+    }
+
+    if violations.is_empty() {
+        println!("{}: no violations", schedule_path);
+    } else {
+        println!("{}: {} violations", schedule_path, violations.len());
+        std::process::exit(1);
+    }
+}
+
+/// Run every setting of the sweep spec, spreading the runs over groups of nodes, and write a
+/// table of results at root
+fn run_sweep(
+    args: &ArgMatches,
+    world: &SimpleCommunicator,
+    config: &AlgorithmConfig,
+    tuples: &[Tuple],
+    seed: u64,
+    stop_requested: &AtomicBool,
+) {
+    let spec_path = args.get_one::<String>("spec").unwrap();
+    let (settings, runs): (Vec<Setting>, usize) = mpi_execute_and_synchronize_at(
+        || {
+            let spec = SweepSpec::from_file(spec_path)
+                .unwrap_or_else(|err| exit_with_config_error(spec_path, err));
+            let settings = spec
+                .settings(config)
+                .unwrap_or_else(|err| exit_with_config_error(spec_path, err));
+            (settings, spec.runs)
+        },
+        world,
+        ROOT_RANK,
+    );
+
+    let jobs = settings.len() * runs;
+    let groups = args
+        .get_one::<usize>("groups")
+        .copied()
+        .unwrap_or(world.size() as usize)
+        .min(jobs)
+        .max(1);
+    let (group, groups, communicator) = split_into_run_groups(args, world, groups);
+    if world.rank() == ROOT_RANK {
+        info!(
+            "Sweeping {} settings, {} runs each, on {} groups",
+            settings.len(),
+            runs,
+            groups
+        );
+    }
+
+    let group_rank = communicator.rank();
+    let mut records = Vec::new();
+
+    for job in (group..jobs).step_by(groups) {
+        if experiments::stop_token(stop_requested, &communicator) {
+            break;
+        }
+
+        let (setting, run) = (job / runs, job % runs);
+        let _job_span = info_span!("sweep", setting = setting + 1, run = run + 1).entered();
+
+        let mut config = config
+            .clone()
+            .with_overrides(settings[setting].clone())
+            .expect("Settings are checked at root");
+        if config.parallel_model == ParallelModel::Island {
+            config.population_size = adapt_population_size_to_worker_number(
+                config.population_size,
+                group_rank,
+                communicator.size(),
+            );
+        }
+
+        let (record, _) = experiments::execute_run(
+            &config,
+            tuples,
+            &communicator,
+            run,
+            run_seed(seed, run as u64),
+            Vec::new(),
+            stop_requested,
+        );
+        if group_rank == ROOT_RANK {
+            records.push(SweepRecord { setting, record });
+        }
+    }
+
+    let records = gather_serde(&records, world, ROOT_RANK);
+
+    if world.rank() == ROOT_RANK {
+        let output_path = args
+            .get_one::<String>("output")
+            .map(String::as_str)
+            .unwrap_or("sweep.csv");
+        sweep::write_results(&settings, &records, config, output_path)
+            .expect("Could not write sweep results");
+        info!("Sweep results written to {}", output_path);
+    }
+}
+
+/// Split the nodes into the groups executing runs concurrently
+///
+/// `--islands-per-run K` makes groups of K consecutive nodes, otherwise the nodes are dealt into
+/// `groups` groups. Returns the group of this node, the number of groups and the communicator of
+/// the group.
+fn split_into_run_groups(
+    args: &ArgMatches,
+    nodes: &SimpleCommunicator,
+    groups: usize,
+) -> (usize, usize, SimpleCommunicator) {
+    let Some(&islands) = args.get_one::<usize>("islands-per-run") else {
+        let (group, communicator) = experiments::split_into_groups(nodes, groups);
+        return (group, groups, communicator);
+    };
+
+    let size = nodes.size() as usize;
+    if !size.is_multiple_of(islands) {
+        if nodes.rank() == ROOT_RANK {
+            error!(
+                islands_per_run = islands,
+                ranks = size,
+                "The number of ranks running the algorithm is not a multiple of --islands-per-run"
+            );
+        }
+        ExitReason::InvalidInput.exit();
+    }
+
+    let (group, groups, communicator) = experiments::split_into_groups_of(nodes, islands);
+    if nodes.rank() == ROOT_RANK {
+        info!(
+            groups,
+            islands_per_run = islands,
+            "Running independent groups of islands"
+        );
+    }
+    (group, groups, communicator)
+}
+
 /// If the population size is not divisible by the number of nodes, increase the population size
 fn adapt_population_size_to_worker_number(population_size: usize, rank: Rank, size: Rank) -> usize {
     let mut new_population_size = population_size;
@@ -63,7 +831,7 @@ fn adapt_population_size_to_worker_number(population_size: usize, rank: Rank, si
         new_population_size = population_size + size as usize - (population_size % size as usize);
 
         if rank == ROOT_RANK {
-            println!(
+            info!(
                 "Changing population size from {} to {}, to match node number",
                 population_size, new_population_size
             )
@@ -73,83 +841,396 @@ fn adapt_population_size_to_worker_number(population_size: usize, rank: Rank, si
     new_population_size
 }
 
+/// Catch Ctrl-C and SIGTERM, so an interrupted run still writes its best schedule
+///
+/// The returned flag is set by the first signal, only the root's flag is taken into account, see
+/// [`GaRunner::run`]. A second signal exits immediately.
+fn install_stop_handler() -> Arc<AtomicBool> {
+    let stop_requested = Arc::new(AtomicBool::new(false));
+    let handler_flag = Arc::clone(&stop_requested);
+
+    ctrlc::set_handler(move || {
+        if handler_flag.swap(true, Ordering::SeqCst) {
+            ExitReason::Interrupted.exit();
+        }
+    })
+    .expect("Could not install the signal handler");
+
+    stop_requested
+}
+
 fn main() {
-    let (universe, threading) = mpi::initialize_with_threading(Threading::Multiple).unwrap();
-    assert_eq!(threading, mpi::environment::threading_support());
+    let args = build_cli().get_matches();
+    logging::init_logging(
+        args.get_one::<String>("log-level").map(String::as_str),
+        args.get_flag("log-json"),
+    );
+
+    if let Some(("score", score_args)) = args.subcommand() {
+        run_score(score_args);
+        return;
+    }
+    if let Some(("verify", verify_args)) = args.subcommand() {
+        run_verify(verify_args);
+        return;
+    }
+    if let Some(("generate", generate_args)) = args.subcommand() {
+        run_generate(generate_args);
+        return;
+    }
+
+    let stop_requested = install_stop_handler();
 
-    let world = universe.world();
+    #[cfg(feature = "mpi")]
+    {
+        let (universe, threading) = mpi::initialize_with_threading(Threading::Multiple).unwrap();
+        assert_eq!(threading, mpi::environment::threading_support());
+        abort::install_abort_hook(|code| SimpleCommunicator::world().abort(code));
+        run(&args, universe.world(), &stop_requested);
+    }
+    // without MPI the ranks are threads of this process, exiting it stops them all
+    #[cfg(not(feature = "mpi"))]
+    {
+        abort::install_abort_hook(|code| std::process::exit(code));
+        mpi::run_ranks(*args.get_one::<usize>("ranks").unwrap(), |world| {
+            run(&args, world, &stop_requested)
+        });
+    }
+}
 
+/// Solve the problem on a rank of the world
+fn run(args: &ArgMatches, world: SimpleCommunicator, stop_requested: &AtomicBool) {
     let size = world.size();
     let rank = world.rank();
+    let _rank_span = info_span!("rank", rank).entered();
 
-    let (mut config, tuples) = mpi_execute_and_synchronize_at(root_init, &world, ROOT_RANK);
+    // arguments after `sweep` (e.g. `-c`) are part of its matches
+    let sweep_args = match args.subcommand() {
+        Some(("sweep", sweep_args)) => Some(sweep_args),
+        _ => None,
+    };
 
-    config.population_size =
-        adapt_population_size_to_worker_number(config.population_size, rank, size);
+    let (mut config, mut tuples) = if rank == ROOT_RANK {
+        root_init(sweep_args.unwrap_or(args))
+    } else {
+        (AlgorithmConfig::default(), Vec::new())
+    };
+    broadcast_serde(&mut config, &world, ROOT_RANK);
+    // thread ranks share the threads of their process
+    let ranks_on_node = if cfg!(feature = "mpi") {
+        world.split_shared(rank).size()
+    } else {
+        1
+    };
+    match threads::configure(config.threads, ranks_on_node as usize) {
+        Ok(threads) if rank == ROOT_RANK => info!(threads, ranks_on_node, "Threads per rank"),
+        Ok(_) => {}
+        Err(err) => warn!(rank, "Thread pool not resized: {}", err),
+    }
+    compression::configure(config.compression, config.compression_threshold);
+    set_broadcast_chunk_size(config.broadcast_chunk_size);
+    broadcast_tuples(&mut tuples, &world, ROOT_RANK);
 
-    println!("{:?}", config);
+    debug!(?config);
 
-    let mut population = algorithm::create_first_population(&config, &tuples);
+    let seed = mpi_execute_and_synchronize_at(
+        || {
+            args.get_one::<u64>("seed")
+                .copied()
+                .unwrap_or_else(random_seed)
+        },
+        &world,
+        ROOT_RANK,
+    );
+    if rank == ROOT_RANK {
+        info!(seed);
+    }
 
-    for generation_number in 0..config.max_generations {
-        let mut population_to_be_processed =
-            mpi_split_data_across_nodes(&population, &world, ROOT_RANK);
+    if let Some(sweep_args) = sweep_args {
+        run_sweep(sweep_args, &world, &config, &tuples, seed, stop_requested);
+        return;
+    }
 
-        if rank == ROOT_RANK {
-            println!("Generation: {}", generation_number + 1);
+    // with --logger-rank the last rank doesn't evolve, it collects the reports of the islands and
+    // writes all output files
+    let logger = (args.get_flag("logger-rank") && size > 1).then(|| (size - 1, world.duplicate()));
+    let writer_rank = logger
+        .as_ref()
+        .map_or(ROOT_RANK, |(logger_rank, _)| *logger_rank);
+    let workers = logger.as_ref().and_then(|(logger_rank, _)| {
+        world.split_by_color(if rank == *logger_rank {
+            Color::undefined()
+        } else {
+            Color::with_value(0)
+        })
+    });
+
+    let db_path = args.get_one::<String>("db");
+    if db_path.is_some() {
+        aggregator::keep_history(rank);
+    }
+
+    let (records, elites) = match logger {
+        Some((logger_rank, reports)) if rank == logger_rank => {
+            info!("Collecting the reports of the islands");
+            aggregator::serve(&reports, size as usize - 1).expect("Could not write metrics");
+            (Vec::new(), Vec::new())
+        }
+        logger => {
+            if let Some((logger_rank, reports)) = logger {
+                aggregator::connect(reports, logger_rank);
+            }
+            let workers = workers.as_ref().unwrap_or(&world);
+            let results = execute_runs(args, workers, &mut config, &tuples, seed, stop_requested);
+            aggregator::finish();
+            results
         }
+    };
 
-        population_to_be_processed = population_to_be_processed
-            .par_iter()
-            .map(|_| crossover(&config, &population))
-            .map(|mut individual| {
-                mutate(&config, &mut individual);
-                individual
-            })
-            .map(|mut individual| {
-                individual.adaptation = calculate_fitness(&individual, &tuples, false);
-                individual
-            })
-            .collect();
+    // the logger writes the outputs with the configuration the islands used
+    if writer_rank != ROOT_RANK {
+        broadcast_serde(&mut config, &world, ROOT_RANK);
+    }
 
-        population = mpi_gather_and_synchronize(&population_to_be_processed, &world, ROOT_RANK);
+    // results of all groups are collected by the rank writing the outputs
+    let records = gather_serde(&records, &world, writer_rank);
+    let generations = match db_path {
+        Some(_) => gather_serde(&aggregator::take_history(), &world, writer_rank),
+        None => Vec::new(),
+    };
+    let mut elites = gather_serde(&elites, &world, writer_rank);
+    elites.sort_by_key(|individual| Reverse(individual.adaptation));
 
-        population.sort_by(|a, b| b.adaptation.partial_cmp(&a.adaptation).unwrap());
+    if args.get_flag("timings") {
+        let timings = gather_serde(&[RankTimings::current(rank)], &world, writer_rank);
+        if rank == writer_rank {
+            println!("{}", timing::format_table(&timings));
+            timing::write_json(&timings, "timings.json").expect("Could not write timings");
+        }
+    }
 
-        // early stop, print results
-        if rank == ROOT_RANK {
-            println!("Best adaptation: {}", population[0].adaptation);
+    let runs = *args.get_one::<usize>("runs").unwrap();
+    if rank == writer_rank {
+        if runs > 1 {
+            let summary = RunSummary::new(&records, config.target_fitness);
+            info!(
+                runs = summary.runs,
+                mean_adaptation = summary.mean_adaptation,
+                std_dev_adaptation = summary.std_dev_adaptation,
+                success_rate = summary.success_rate,
+                mean_seconds = summary.mean_seconds,
+                "Summary of the runs"
+            );
+            experiments::write_runs(&records, "runs.csv").expect("Could not write runs");
+        }
+
+        #[cfg(feature = "ilp")]
+        if let Some(best) = elites.first_mut().filter(|_| config.ilp_refinement) {
+            match algorithm::ilp::refine(&config, best, &tuples) {
+                Ok(Some(refined)) => {
+                    info!(
+                        before = best.adaptation,
+                        after = refined.adaptation,
+                        "Best individual refined by the integer program"
+                    );
+                    *best = refined;
+                }
+                Ok(None) => info!("Integer program found no better schedule"),
+                Err(err) => warn!(%err, "Integer program could not be solved"),
+            }
+        }
+
+        let Some(best_individual) = elites.first() else {
+            warn!("Stopped before any run finished, nothing to write");
+            return;
+        };
+        info!(
+            adaptation = best_individual.adaptation,
+            "Best individual found"
+        );
+
+        // timetables show the rooms and teachers chosen by the algorithm
+        let assigned_tuples = assignment::effective_tuples(&config, best_individual, &tuples);
+        output::write_timetable(
+            best_individual,
+            &assigned_tuples,
+            &config.period_layout(),
+            "timetable.txt",
+        )
+        .expect("Could not write to file");
+        html::write_html_timetable(best_individual, &assigned_tuples, &config, "timetable.html")
+            .expect("Could not write the HTML timetable");
+        xlsx::write_spreadsheet(best_individual, &assigned_tuples, &config, "timetable.xlsx")
+            .expect("Could not write the spreadsheet");
+        output::write_personal_timetables(best_individual, &assigned_tuples, &config, "timetables")
+            .expect("Could not write timetables of groups and teachers");
+        ics::write_calendars(best_individual, &assigned_tuples, &config, "timetables")
+            .expect("Could not write calendars of groups and teachers");
+
+        best_individual
+            .to_csv("schedule.csv")
+            .expect("Could not write schedule");
+        if config.assigns_resources() {
+            let file = File::create("schedule.json").expect("Could not create schedule");
+            serde_json::to_writer_pretty(file, best_individual).expect("Could not write schedule");
         }
-        if population[0].adaptation == 0 {
+
+        let report = ConflictReport::explain(&config, best_individual, &tuples);
+        for kind in &report.breakdown {
+            info!(
+                kind = ?kind.kind,
+                count = kind.count,
+                penalty = kind.penalty,
+                "Remaining violations"
+            );
+        }
+        output::write_penalties(&report.breakdown, "penalties.csv")
+            .expect("Could not write penalties");
+        if config.multi_objective {
+            let front = pareto::front(&config, &elites, &tuples);
+            info!(
+                size = front.len(),
+                "Pareto front written to pareto_front.json"
+            );
+            let file = File::create("pareto_front.json").expect("Could not create Pareto front");
+            serde_json::to_writer_pretty(file, &front).expect("Could not write Pareto front");
+        }
+        for satisfaction in &report.preferences {
+            info!(
+                teacher = satisfaction.teacher,
+                taught = satisfaction.taught,
+                preferred = ?satisfaction.preferred,
+                not_disliked = ?satisfaction.not_disliked,
+                "Teacher preferences satisfied (%)"
+            );
+        }
+
+        if let Some(path) = args.get_one::<String>("export-elites") {
+            EliteArchive::new(&config, &tuples, &elites, config.elite_count)
+                .to_json(path)
+                .expect("Could not export elites");
+        }
+
+        if let Some(path) = db_path {
+            let results = database::Results {
+                config: &config,
+                tuples_path: args
+                    .get_one::<String>("tuples")
+                    .map_or("tuples.csv", String::as_str),
+                seed,
+                runs: &records,
+                generations: &generations,
+                best: Some((best_individual, &assigned_tuples)),
+            };
+            let invocation =
+                database::record(path, &results).expect("Could not write to the database");
+            info!(invocation, "Results recorded in {}", path);
+        }
+    }
+}
+
+/// Execute this rank's share of the `--runs` with the other `workers`
+///
+/// Returns the records and elites of the runs led by this rank, empty on the other ranks.
+fn execute_runs(
+    args: &ArgMatches,
+    workers: &SimpleCommunicator,
+    config: &mut AlgorithmConfig,
+    tuples: &[Tuple],
+    seed: u64,
+    stop_requested: &AtomicBool,
+) -> (Vec<RunRecord>, Vec<Individual>) {
+    let runs = *args.get_one::<usize>("runs").unwrap();
+
+    // with --split-runs every group of nodes executes its share of the runs on its own
+    let groups = if args.get_flag("split-runs") {
+        runs.min(workers.size() as usize)
+    } else {
+        1
+    };
+    let (group, groups, communicator) = split_into_run_groups(args, workers, groups);
+    let group_rank = communicator.rank();
+    if workers.rank() == ROOT_RANK && groups > runs {
+        warn!(
+            runs,
+            groups, "Fewer runs than groups, some groups stay idle"
+        );
+    }
+
+    // islands split the population evenly, master-worker chunks may differ in size
+    if config.parallel_model == ParallelModel::Island {
+        config.population_size = adapt_population_size_to_worker_number(
+            config.population_size,
+            group_rank,
+            communicator.size(),
+        );
+    }
+
+    let mut rng = get_random_generator(rank_seed(seed, workers.rank() as u64));
+
+    let injected_individuals = mpi_execute_and_synchronize_at(
+        || {
+            let mut individuals = load_injected_elites(args, config, tuples);
+            individuals.extend(load_warm_start(args, config, tuples, &mut rng));
+            individuals
+        },
+        workers,
+        ROOT_RANK,
+    );
+
+    let mut records = Vec::new();
+    let mut elites = Vec::new();
+
+    for run in (group..runs).step_by(groups) {
+        if experiments::stop_token(stop_requested, &communicator) {
             break;
         }
+
+        let _run_span = info_span!("run", run = run + 1).entered();
+        aggregator::begin_run(run + 1);
+        let (record, run_elites) = experiments::execute_run(
+            config,
+            tuples,
+            &communicator,
+            run,
+            run_seed(seed, run as u64),
+            injected_individuals.clone(),
+            stop_requested,
+        );
+
+        if group_rank == ROOT_RANK {
+            records.push(record);
+            elites.extend(run_elites);
+        }
     }
 
-    if rank == ROOT_RANK {
-        let best_individual = &population[0];
-        println!("Best adaptation: {}", best_individual.adaptation);
-        let out_file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open("timetable.txt")
-            .expect("Could not open file");
-
-        let mut buf_writer = std::io::BufWriter::new(out_file);
-        writeln!(buf_writer, "Najlepszy plan zajęć").expect("Could not write to file");
+    (records, elites)
+}
 
-        best_individual
-            .chromosomes
-            .iter()
-            .enumerate()
-            .for_each(|(index, chromosome)| {
-                let mapped_tuples = chromosome
-                    .genes
-                    .iter()
-                    .map(|gene| tuples.iter().find(|tuple| tuple.id == *gene).unwrap());
-                let tuples_as_string = mapped_tuples.map(|tuple| tuple.to_string()).join("\n - ");
-                writeln!(buf_writer, "{}:\n - {}", index + 1, tuples_as_string)
-                    .expect("Could not write to file");
-            });
+#[cfg(all(test, not(feature = "mpi")))]
+mod tests {
+    use super::*;
+    use crate::mpi_utils::all_reduce_sum;
+
+    #[test]
+    fn test_runs_are_split_into_groups_of_islands() {
+        mpi::run_ranks(6, |world| {
+            let rank = world.rank();
+
+            let args = build_cli().try_get_matches_from(["planner", "--islands-per-run", "3"]);
+            let (group, groups, communicator) = split_into_run_groups(&args.unwrap(), &world, 1);
+            assert_eq!((group, groups), (rank as usize / 3, 2));
+            assert_eq!((communicator.rank(), communicator.size()), (rank % 3, 3));
+            // islands of a run are consecutive ranks
+            let members = all_reduce_sum(&[rank], &communicator)[0];
+            assert_eq!(members, [3, 12][group]);
+
+            // without --islands-per-run the ranks are dealt into the groups
+            let args = build_cli().try_get_matches_from(["planner"]);
+            let (group, groups, communicator) = split_into_run_groups(&args.unwrap(), &world, 3);
+            assert_eq!((group, groups), (rank as usize % 3, 3));
+            let members = all_reduce_sum(&[rank], &communicator)[0];
+            assert_eq!(members, [3, 5, 7][group]);
+        });
     }
 }
@@ -0,0 +1,418 @@
+use std::collections::BTreeMap;
+
+use crate::mpi::{ffi::MPI_Comm, traits::*, Rank};
+
+use crate::{
+    algorithm::{
+        datatypes::{
+            Chromosome, Gene, Individual, PeriodId, Population, Tuple, TupleId, TupleKind,
+        },
+        symbols::{RoomId, Symbols, TeacherId},
+    },
+    compression::{pack, unpack},
+    mpi_utils::{broadcast_serde, broadcast_vec, verify_broadcast, MPITransferable, TransferError},
+    timing::{timed, Phase},
+};
+
+/// Fixed-size part of a [`Tuple`], sent as an MPI derived datatype
+///
+/// The strings of all tuples are concatenated into a single byte buffer, the header stores the
+/// length of each of them. Teachers and rooms are sent by id, their names once in the
+/// [`Symbols`].
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "mpi", derive(Equivalence))]
+struct TupleHeader {
+    id: u16,
+    label_len: u64,
+    room: u32,
+    teacher: u32,
+    group_len: u64,
+    forbidden_periods_len: u64,
+    duration: u64,
+    kind: u8,
+    /// Length of the allowed rooms, joined with [`LIST_SEPARATOR`]
+    allowed_rooms_len: u64,
+    /// Length of the qualified teachers, joined with [`LIST_SEPARATOR`]
+    qualified_teachers_len: u64,
+    course_len: u64,
+}
+
+#[cfg(not(feature = "mpi"))]
+impl Equivalence for TupleHeader {}
+
+/// Fixed-size part of a [`Chromosome`], genes of all chromosomes are sent as one flat buffer
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "mpi", derive(Equivalence))]
+struct ChromosomeHeader {
+    id: u64,
+    gene_count: u64,
+}
+
+#[cfg(not(feature = "mpi"))]
+impl Equivalence for ChromosomeHeader {}
+
+/// Fixed-size part of an [`Individual`], followed by `chromosome_count` chromosome headers
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "mpi", derive(Equivalence))]
+struct IndividualHeader {
+    adaptation: i32,
+    chromosome_count: u64,
+}
+
+#[cfg(not(feature = "mpi"))]
+impl Equivalence for IndividualHeader {}
+
+/// Rooms and teachers an individual assigns, see [`Individual::rooms`]
+type Assignments = (BTreeMap<Gene, String>, BTreeMap<Gene, String>);
+
+/// Separates the allowed rooms and qualified teachers of a tuple in the strings buffer
+const LIST_SEPARATOR: &str = "\n";
+
+fn pack_tuples(tuples: &[Tuple]) -> (Vec<TupleHeader>, Vec<u8>, Vec<u64>) {
+    let mut headers = Vec::with_capacity(tuples.len());
+    let mut strings = Vec::new();
+    let mut periods = Vec::new();
+
+    for tuple in tuples {
+        let allowed_rooms = tuple.allowed_rooms.join(LIST_SEPARATOR);
+        let qualified_teachers = tuple.qualified_teachers.join(LIST_SEPARATOR);
+        headers.push(TupleHeader {
+            id: tuple.id.0,
+            label_len: tuple.label.len() as u64,
+            room: tuple.room_id.0,
+            teacher: tuple.teacher_id.0,
+            group_len: tuple.group.len() as u64,
+            forbidden_periods_len: tuple.forbidden_periods.len() as u64,
+            duration: tuple.duration as u64,
+            kind: tuple.kind as u8,
+            allowed_rooms_len: allowed_rooms.len() as u64,
+            qualified_teachers_len: qualified_teachers.len() as u64,
+            course_len: tuple.course.len() as u64,
+        });
+        strings.extend_from_slice(tuple.label.as_bytes());
+        strings.extend_from_slice(tuple.group.as_bytes());
+        strings.extend_from_slice(allowed_rooms.as_bytes());
+        strings.extend_from_slice(qualified_teachers.as_bytes());
+        strings.extend_from_slice(tuple.course.as_bytes());
+        periods.extend(tuple.forbidden_periods.iter().map(|period| *period as u64));
+    }
+
+    (headers, strings, periods)
+}
+
+fn split_list(list: &str) -> Vec<String> {
+    list.split(LIST_SEPARATOR)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Tuples sent by [`pack_tuples`], interned like on the data owner
+fn unpack_tuples(
+    headers: &[TupleHeader],
+    strings: &[u8],
+    periods: &[u64],
+    symbols: &Symbols,
+) -> Vec<Tuple> {
+    let mut offset = 0;
+    let mut take_string = |len: u64| {
+        let end = offset + len as usize;
+        let string = String::from_utf8(strings[offset..end].to_vec()).unwrap();
+        offset = end;
+        string
+    };
+
+    let mut periods_offset = 0;
+    let mut take_periods = |len: u64| {
+        let end = periods_offset + len as usize;
+        let taken = periods[periods_offset..end]
+            .iter()
+            .map(|period| *period as usize)
+            .collect();
+        periods_offset = end;
+        taken
+    };
+
+    let mut tuples: Vec<Tuple> = headers
+        .iter()
+        .map(|header| Tuple {
+            id: TupleId(header.id),
+            label: take_string(header.label_len),
+            room: symbols.room(RoomId(header.room)).to_string(),
+            teacher: symbols.teacher(TeacherId(header.teacher)).to_string(),
+            group: take_string(header.group_len),
+            forbidden_periods: take_periods(header.forbidden_periods_len),
+            duration: header.duration as usize,
+            kind: match header.kind {
+                1 => TupleKind::Exercise,
+                2 => TupleKind::Lab,
+                _ => TupleKind::Lecture,
+            },
+            allowed_rooms: split_list(&take_string(header.allowed_rooms_len)),
+            qualified_teachers: split_list(&take_string(header.qualified_teachers_len)),
+            course: take_string(header.course_len),
+            ..Tuple::default()
+        })
+        .collect();
+    Symbols::intern(&mut tuples);
+    tuples
+}
+
+fn pack_chromosomes(chromosomes: &[Chromosome]) -> (Vec<ChromosomeHeader>, Vec<u16>) {
+    let headers = chromosomes
+        .iter()
+        .map(|chromosome| ChromosomeHeader {
+            id: chromosome.id.index() as u64,
+            gene_count: chromosome.genes.len() as u64,
+        })
+        .collect();
+    let genes = chromosomes
+        .iter()
+        .flat_map(|chromosome| chromosome.genes.iter().map(|gene| gene.0))
+        .collect();
+
+    (headers, genes)
+}
+
+fn unpack_chromosomes(headers: &[ChromosomeHeader], genes: &[u16]) -> Vec<Chromosome> {
+    let mut offset = 0;
+
+    headers
+        .iter()
+        .map(|header| {
+            let end = offset + header.gene_count as usize;
+            let chromosome = Chromosome {
+                id: PeriodId(header.id as usize),
+                genes: genes[offset..end]
+                    .iter()
+                    .map(|gene| TupleId(*gene))
+                    .collect(),
+            };
+            offset = end;
+            chromosome
+        })
+        .collect()
+}
+
+/// Broadcast tuples from the data owner to all processes using derived datatypes
+///
+/// Cheaper than [`broadcast_serde`] on large tuple sets, as
+/// only three buffers are allocated regardless of the number of tuples.
+pub fn broadcast_tuples(
+    tuples: &mut Vec<Tuple>,
+    communicator: &impl Communicator<Raw = MPI_Comm>,
+    data_owner_rank: Rank,
+) {
+    timed(Phase::Broadcast, || {
+        let mut symbols = Symbols::default();
+        let (mut headers, strings, mut periods) = if communicator.rank() == data_owner_rank {
+            symbols = Symbols::intern(tuples);
+            pack_tuples(tuples)
+        } else {
+            (Vec::new(), Vec::new(), Vec::new())
+        };
+
+        // the strings make up most of the data and compress well
+        let mut strings = pack(strings);
+
+        broadcast_serde(&mut symbols, communicator, data_owner_rank);
+        broadcast_vec(&mut headers, communicator, data_owner_rank);
+        broadcast_vec(&mut strings, communicator, data_owner_rank);
+        broadcast_vec(&mut periods, communicator, data_owner_rank);
+
+        if communicator.rank() != data_owner_rank {
+            let strings = unpack(&strings).unwrap_or_else(|error| {
+                TransferError::Protocol {
+                    source_rank: data_owner_rank,
+                    error: error.into(),
+                }
+                .raise()
+            });
+            *tuples = unpack_tuples(&headers, &strings, &periods, &symbols);
+        }
+        // the tuples as unpacked, so every step of the transfer is covered
+        verify_broadcast(&tuples.to_bytes(), "tuples", communicator, data_owner_rank);
+    })
+}
+
+/// Broadcast chromosomes from the data owner to all processes using derived datatypes
+pub fn broadcast_chromosomes(
+    chromosomes: &mut Vec<Chromosome>,
+    communicator: &impl Communicator<Raw = MPI_Comm>,
+    data_owner_rank: Rank,
+) {
+    let (mut headers, mut genes) = if communicator.rank() == data_owner_rank {
+        pack_chromosomes(chromosomes)
+    } else {
+        (Vec::new(), Vec::new())
+    };
+
+    broadcast_vec(&mut headers, communicator, data_owner_rank);
+    broadcast_vec(&mut genes, communicator, data_owner_rank);
+
+    if communicator.rank() != data_owner_rank {
+        *chromosomes = unpack_chromosomes(&headers, &genes);
+    }
+}
+
+/// Broadcast a population from the data owner to all processes using derived datatypes
+///
+/// Chromosomes of all individuals are packed together with [`broadcast_chromosomes`]. The rooms
+/// and teachers the individuals assign are few and go with [`broadcast_serde`].
+pub fn broadcast_population(
+    population: &mut Population,
+    communicator: &impl Communicator<Raw = MPI_Comm>,
+    data_owner_rank: Rank,
+) {
+    timed(Phase::Broadcast, || {
+        let is_data_owner = communicator.rank() == data_owner_rank;
+
+        let mut headers: Vec<IndividualHeader> = Vec::new();
+        let mut chromosomes: Vec<Chromosome> = Vec::new();
+        let mut assignments: Vec<Assignments> = Vec::new();
+
+        if is_data_owner {
+            for individual in population.iter() {
+                headers.push(IndividualHeader {
+                    adaptation: individual.adaptation,
+                    chromosome_count: individual.chromosomes.len() as u64,
+                });
+                chromosomes.extend(individual.chromosomes.iter().cloned());
+            }
+            if population
+                .iter()
+                .any(|individual| !individual.rooms.is_empty() || !individual.teachers.is_empty())
+            {
+                assignments = population
+                    .iter()
+                    .map(|individual| (individual.rooms.clone(), individual.teachers.clone()))
+                    .collect();
+            }
+        }
+
+        broadcast_vec(&mut headers, communicator, data_owner_rank);
+        broadcast_chromosomes(&mut chromosomes, communicator, data_owner_rank);
+        broadcast_serde(&mut assignments, communicator, data_owner_rank);
+
+        if !is_data_owner {
+            let mut chromosomes = chromosomes.into_iter();
+            let mut assignments = assignments.into_iter();
+            *population = headers
+                .iter()
+                .map(|header| {
+                    let (rooms, teachers) = assignments.next().unwrap_or_default();
+                    Individual {
+                        adaptation: header.adaptation,
+                        chromosomes: chromosomes
+                            .by_ref()
+                            .take(header.chromosome_count as usize)
+                            .collect(),
+                        rooms,
+                        teachers,
+                    }
+                })
+                .collect();
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smallvec::smallvec;
+
+    #[test]
+    fn test_tuples_roundtrip() {
+        let mut tuples = vec![
+            Tuple {
+                id: TupleId(1),
+                label: "Wykład | Automaty komórkowe".to_string(),
+                room: "D-10 225".to_string(),
+                teacher: "dr hab. inż. Malarz, K".to_string(),
+                group: "Informatyka Stosowana II st.".to_string(),
+                forbidden_periods: vec![6, 7],
+                duration: 2,
+                kind: TupleKind::Lab,
+                allowed_rooms: vec!["D-10 225".to_string(), "D-10 226".to_string()],
+                qualified_teachers: vec![
+                    "dr hab. inż. Malarz, K".to_string(),
+                    "Kułakowski".to_string(),
+                ],
+                course: "Automaty komórkowe".to_string(),
+                ..Tuple::default()
+            },
+            Tuple {
+                id: TupleId(2),
+                label: String::new(),
+                room: "D-7".to_string(),
+                teacher: "Sabre Academy".to_string(),
+                group: String::new(),
+                forbidden_periods: Vec::new(),
+                duration: 1,
+                kind: TupleKind::Lecture,
+                allowed_rooms: Vec::new(),
+                qualified_teachers: Vec::new(),
+                course: String::new(),
+                ..Tuple::default()
+            },
+        ];
+
+        let symbols = Symbols::intern(&mut tuples);
+        let (headers, strings, periods) = pack_tuples(&tuples);
+        assert_eq!(
+            unpack_tuples(&headers, &strings, &periods, &symbols),
+            tuples
+        );
+    }
+
+    #[test]
+    fn test_chromosomes_roundtrip() {
+        let chromosomes = vec![
+            Chromosome {
+                id: PeriodId(0),
+                genes: smallvec![TupleId(3), TupleId(1)],
+            },
+            Chromosome::new(PeriodId(1)),
+            Chromosome {
+                id: PeriodId(2),
+                genes: smallvec![TupleId(2)],
+            },
+        ];
+
+        let (headers, genes) = pack_chromosomes(&chromosomes);
+        let unpacked = unpack_chromosomes(&headers, &genes);
+
+        assert_eq!(unpacked.len(), 3);
+        for (unpacked, original) in unpacked.iter().zip(&chromosomes) {
+            assert_eq!(unpacked.id, original.id);
+            assert_eq!(unpacked.genes, original.genes);
+        }
+    }
+
+    #[cfg(not(feature = "mpi"))]
+    #[test]
+    fn test_populations_are_broadcast_with_their_assignments() {
+        crate::mpi::run_ranks(3, |world| {
+            let mut assigning = Individual::with_chromosomes(vec![Chromosome {
+                id: PeriodId(1),
+                genes: smallvec![TupleId(4)],
+            }]);
+            assigning.adaptation = -2;
+            assigning.rooms.insert(TupleId(4), "D-10 225".to_string());
+            let original = vec![Individual::with_chromosomes(Vec::new()), assigning];
+
+            let mut population = if world.rank() == 0 {
+                original.clone()
+            } else {
+                Vec::new()
+            };
+            broadcast_population(&mut population, &world, 0);
+
+            assert_eq!(population.len(), 2);
+            assert_eq!(population[1].adaptation, -2);
+            assert_eq!(population[1].chromosomes[0].genes[..], [TupleId(4)]);
+            assert_eq!(population[1].rooms, original[1].rooms);
+            assert!(population[0].chromosomes.is_empty());
+        });
+    }
+}
@@ -1,54 +1,840 @@
-use mpi::{ffi::MPI_Comm, traits::*, Rank};
+use std::{
+    collections::BTreeSet,
+    ops::Range,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::mpi::{
+    collective::SystemOperation,
+    datatype::{Partition, PartitionMut},
+    ffi::MPI_Comm,
+    request::{self, Request, Scope, StaticScope},
+    topology::{Color, SimpleCommunicator},
+    traits::*,
+    Count, Rank, Tag,
+};
+use rand::seq::SliceRandom;
 use rayon::prelude::*;
 use serde::{de::DeserializeOwned, Serialize};
+use thiserror::Error;
+
+use crate::{
+    abort::ExitReason,
+    algorithm::{
+        config::MigrationTopology,
+        datatypes::{Individual, Population},
+        random::get_random_generator,
+    },
+    compression::{pack, unpack, CompressionError},
+    timing::{timed, Phase},
+};
 
 /// Rank of the root process (data owner)
 pub const ROOT_RANK: Rank = 0;
 
+/// Version of the format of the messages, bump it when the envelope or the serialized types
+/// change, e.g. with [`SCHEMA_VERSION`](crate::algorithm::datatypes::SCHEMA_VERSION). Receivers
+/// reject messages of other versions with [`ProtocolError::VersionMismatch`]
+pub const PROTOCOL_VERSION: u16 = 4;
+
+/// Size of the protocol version and checksum in front of every message, see [`seal`]
+const ENVELOPE_SIZE: usize = std::mem::size_of::<u16>() + std::mem::size_of::<u32>();
+
+/// Most bytes sent by a single broadcast call of the data owner, see [`broadcast_vec`]
+static BROADCAST_CHUNK_SIZE: AtomicUsize = AtomicUsize::new(16 * 1024 * 1024);
+
+/// Tag of the messages of [`StopToken`]
+const STOP_TAG: Tag = 1;
+
+/// Pause between the checks of requests waited for with a deadline, see [`wait_until`]
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Payload of the messages of [`StopToken`], static so sends can outlive a request scope
+static STOP_MESSAGE: bool = true;
+
+/// Size of the length prefix of every frame, see [`frame`]
+const FRAME_HEADER_SIZE: usize = std::mem::size_of::<u64>();
+
 /// Trait for types that can be transferred over MPI as bytes
 ///
-/// Utilizes bincode instead of serde_cbor because cbor
-/// 'compresses' the data. For example int field can have different size when serialized.
-/// In our case, we need to have the same size of data on all nodes to be able to scatter
-/// and gather it.
+/// Utilizes bincode instead of serde_cbor because it is faster and produces more compact output
+/// for our datatypes. Serialized values may differ in size, the helpers below send lengths
+/// alongside the data.
+///
+/// Implemented for every type with the serde derives, so a new type becomes transferable by
+/// deriving `Serialize` and `Deserialize`. Types sent with MPI datatypes instead, like the tuples,
+/// live in `mpi_datatypes`.
 pub trait MPITransferable: Serialize + DeserializeOwned {
     /// Serialize the object into a byte vector
-    fn into_bytes(self) -> Vec<u8> {
-        bincode::serialize(&self).unwrap()
+    fn to_bytes(&self) -> Vec<u8> {
+        bincode::serialize(self).unwrap()
     }
-    
+
     /// Deserialize the object from a byte vector
-    fn from_bytes(bytes: &[u8]) -> Self {
-        bincode::deserialize(bytes).unwrap()
+    fn from_bytes(bytes: &[u8]) -> bincode::Result<Self> {
+        bincode::deserialize(bytes)
     }
 }
 
 impl<T: Serialize + DeserializeOwned> MPITransferable for T {}
 
-/// Synchronize a variable between all processes
-pub fn mpi_synchronize_ref<T: MPITransferable + Clone>(
-    variable: &mut T,
+/// Why a received message can't be read
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ProtocolError {
+    #[error("Message of {0} bytes is too short for its header")]
+    Truncated(usize),
+    #[error("Protocol version {found} doesn't match version {expected} of this binary, all ranks must run the same build")]
+    VersionMismatch { found: u16, expected: u16 },
+    #[error("Checksum {found:08x} doesn't match {expected:08x}, the message was corrupted")]
+    ChecksumMismatch { found: u32, expected: u32 },
+    #[error(transparent)]
+    Compression(#[from] CompressionError),
+}
+
+/// Why data received from another rank can't be used
+///
+/// The ranks can't go on without the data, so the helpers [`raise`](TransferError::raise) it as
+/// the payload of a panic, which the abort hook of `main` turns into its [`ExitReason`].
+#[derive(Error, Debug)]
+pub enum TransferError {
+    #[error("Invalid message from rank {source_rank}: {error}")]
+    Protocol {
+        source_rank: Rank,
+        error: ProtocolError,
+    },
+    #[error("Message from rank {source_rank} can't be deserialized: {error}")]
+    Deserialization {
+        source_rank: Rank,
+        error: bincode::Error,
+    },
+    #[error(
+        "Message from rank {source_rank} declares {declared} bytes, but only {available} follow"
+    )]
+    SizeMismatch {
+        source_rank: Rank,
+        declared: usize,
+        available: usize,
+    },
+    #[error("Broadcast of {what} corrupted on rank {rank}: checksum {found:08x}, expected {expected:08x}")]
+    CorruptedBroadcast {
+        what: String,
+        rank: Rank,
+        found: u32,
+        expected: u32,
+    },
+}
+
+impl TransferError {
+    pub fn exit_reason(&self) -> ExitReason {
+        match self {
+            TransferError::Protocol { .. } => ExitReason::InvalidMessage,
+            TransferError::Deserialization { .. } => ExitReason::Deserialization,
+            TransferError::SizeMismatch { .. } | TransferError::CorruptedBroadcast { .. } => {
+                ExitReason::SizeMismatch
+            }
+        }
+    }
+
+    /// Stop the rank with this error, see [`TransferError`]
+    pub fn raise(self) -> ! {
+        std::panic::panic_any(self)
+    }
+}
+
+/// Prefix a payload with [`PROTOCOL_VERSION`] and its checksum, both little-endian
+fn seal(payload: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(ENVELOPE_SIZE + payload.len());
+    message.extend_from_slice(&PROTOCOL_VERSION.to_le_bytes());
+    message.extend_from_slice(&checksum(payload).to_le_bytes());
+    message.extend_from_slice(payload);
+    message
+}
+
+/// Payload of a message created by [`seal`], checking its version and checksum
+fn open(message: &[u8]) -> Result<&[u8], ProtocolError> {
+    if message.len() < ENVELOPE_SIZE {
+        return Err(ProtocolError::Truncated(message.len()));
+    }
+    let (version, rest) = message.split_at(std::mem::size_of::<u16>());
+    let (expected, payload) = rest.split_at(std::mem::size_of::<u32>());
+
+    let version = u16::from_le_bytes(version.try_into().unwrap());
+    if version != PROTOCOL_VERSION {
+        return Err(ProtocolError::VersionMismatch {
+            found: version,
+            expected: PROTOCOL_VERSION,
+        });
+    }
+    let expected = u32::from_le_bytes(expected.try_into().unwrap());
+    let found = checksum(payload);
+    if found != expected {
+        return Err(ProtocolError::ChecksumMismatch { found, expected });
+    }
+
+    Ok(payload)
+}
+
+/// Bytes of a message sent by the serde helpers: compressed, then sealed
+fn encode(bytes: Vec<u8>) -> Vec<u8> {
+    seal(&pack(bytes))
+}
+
+/// Bytes of a message created by [`encode`] on rank `source`
+///
+/// Raises [`TransferError::Protocol`] when the message can't be read.
+fn decode(message: &[u8], source: Rank) -> Vec<u8> {
+    match open(message).and_then(|payload| Ok(unpack(payload)?)) {
+        Ok(bytes) => bytes,
+        Err(error) => TransferError::Protocol {
+            source_rank: source,
+            error,
+        }
+        .raise(),
+    }
+}
+
+/// Value serialized by rank `source`, raising [`TransferError::Deserialization`] if it isn't a `T`
+fn deserialize<T: MPITransferable>(bytes: &[u8], source: Rank) -> T {
+    T::from_bytes(bytes).unwrap_or_else(|error| {
+        TransferError::Deserialization {
+            source_rank: source,
+            error,
+        }
+        .raise()
+    })
+}
+
+/// Serialize items into a single buffer, each prefixed with its length as a little-endian `u64`
+fn frame<T: MPITransferable + Sync>(items: &[T]) -> Vec<u8> {
+    let serialized: Vec<Vec<u8>> = items.par_iter().map(|item| item.to_bytes()).collect();
+
+    let total_size = serialized
+        .iter()
+        .map(|bytes| FRAME_HEADER_SIZE + bytes.len())
+        .sum();
+    let mut buffer = Vec::with_capacity(total_size);
+
+    for bytes in serialized {
+        buffer.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+        buffer.extend_from_slice(&bytes);
+    }
+
+    buffer
+}
+
+/// Deserialize items from a buffer created by [`frame`] (or several of them concatenated) on
+/// rank `source`
+///
+/// Raises [`TransferError::SizeMismatch`] when a length runs past the end of the buffer.
+fn unframe<T: MPITransferable>(mut buffer: &[u8], source: Rank) -> Vec<T> {
+    let mut items = Vec::new();
+
+    while !buffer.is_empty() {
+        let size_mismatch = |declared| TransferError::SizeMismatch {
+            source_rank: source,
+            declared,
+            available: buffer.len(),
+        };
+        if buffer.len() < FRAME_HEADER_SIZE {
+            size_mismatch(FRAME_HEADER_SIZE).raise();
+        }
+        let (header, rest) = buffer.split_at(FRAME_HEADER_SIZE);
+        let length = u64::from_le_bytes(header.try_into().unwrap()) as usize;
+        if rest.len() < length {
+            size_mismatch(FRAME_HEADER_SIZE.saturating_add(length)).raise();
+        }
+        let (bytes, rest) = rest.split_at(length);
+
+        items.push(deserialize(bytes, source));
+        buffer = rest;
+    }
+
+    items
+}
+
+/// Split `length` items into `parts` contiguous ranges, sizes differing by at most one
+pub fn split_evenly(length: usize, parts: usize) -> Vec<Range<usize>> {
+    let base = length / parts;
+    let remainder = length % parts;
+
+    let mut start = 0;
+    (0..parts)
+        .map(|part| {
+            let end = start + base + usize::from(part < remainder);
+            let range = start..end;
+            start = end;
+            range
+        })
+        .collect()
+}
+
+/// Offsets of consecutive blocks of the given sizes
+fn displacements(counts: &[Count]) -> Vec<Count> {
+    counts
+        .iter()
+        .scan(0, |offset, count| {
+            let displacement = *offset;
+            *offset += count;
+            Some(displacement)
+        })
+        .collect()
+}
+
+/// Limit the broadcasts of this process as data owner to chunks of at most `bytes` bytes
+pub fn set_broadcast_chunk_size(bytes: usize) {
+    BROADCAST_CHUNK_SIZE.store(bytes.max(1), Ordering::Relaxed);
+}
+
+/// CRC-32 of a payload
+pub fn checksum(bytes: &[u8]) -> u32 {
+    crc32fast::hash(bytes)
+}
+
+/// Broadcast a vector of MPI datatypes in chunks, sending its length first
+///
+/// The data owner also sends how many items go in a chunk, so processes don't need to agree on
+/// the chunk size beforehand and MPI never gets a message over the limits of some clusters.
+pub fn broadcast_vec<T: Equivalence + Clone + Default>(
+    data: &mut Vec<T>,
     communicator: &impl Communicator<Raw = MPI_Comm>,
     data_owner_rank: Rank,
 ) {
     let data_owner_process = communicator.process_at_rank(data_owner_rank);
-    let current_rank = communicator.rank();
-    let mut serialized_data_len;
-    let mut serialized_data;
 
-    if current_rank == data_owner_rank {
-        serialized_data = variable.clone().into_bytes();
+    let chunk_size = BROADCAST_CHUNK_SIZE.load(Ordering::Relaxed);
+    let mut header = [
+        data.len(),
+        (chunk_size / std::mem::size_of::<T>().max(1)).max(1),
+    ];
+    data_owner_process.broadcast_into(&mut header[..]);
+    let [len, chunk_len] = header;
 
-        serialized_data_len = serialized_data.len();
-        data_owner_process.broadcast_into(&mut serialized_data_len);
-        data_owner_process.broadcast_into(&mut serialized_data);
-    } else {
-        serialized_data_len = 0;
-        data_owner_process.broadcast_into(&mut serialized_data_len);
-        serialized_data = vec![0; serialized_data_len];
-        data_owner_process.broadcast_into(&mut serialized_data);
+    data.resize(len, T::default());
+    for chunk in data.chunks_mut(chunk_len) {
+        data_owner_process.broadcast_into(chunk);
+    }
+}
+
+/// Check that a broadcast arrived intact by comparing the checksum of the data owner's `bytes`
+/// with the local one
+///
+/// Raises [`TransferError::CorruptedBroadcast`] naming `what` was broadcast on mismatch, as the
+/// processes can't go on with different data. Only needed for data broadcast as plain MPI
+/// datatypes (the tuples), whatever goes through [`broadcast_serde`] is already checked by the
+/// checksum of its message.
+pub fn verify_broadcast(
+    bytes: &[u8],
+    what: &str,
+    communicator: &impl Communicator<Raw = MPI_Comm>,
+    data_owner_rank: Rank,
+) {
+    let local = checksum(bytes);
+    let mut expected = local;
+    communicator
+        .process_at_rank(data_owner_rank)
+        .broadcast_into(&mut expected);
+
+    if local != expected {
+        TransferError::CorruptedBroadcast {
+            what: what.to_string(),
+            rank: communicator.rank(),
+            found: local,
+            expected,
+        }
+        .raise();
+    }
+}
+
+/// Broadcast a value from the data owner to all processes
+///
+/// The serialized value is sent by [`broadcast_vec`]. Like the messages of the other helpers, it
+/// may be compressed (see [`pack`]) and carries the protocol version and a checksum, verified by
+/// the receivers.
+pub fn broadcast_serde<T: MPITransferable>(
+    value: &mut T,
+    communicator: &impl Communicator<Raw = MPI_Comm>,
+    data_owner_rank: Rank,
+) {
+    timed(Phase::Broadcast, || {
+        let is_data_owner = communicator.rank() == data_owner_rank;
+
+        let mut serialized_data = if is_data_owner {
+            encode(value.to_bytes())
+        } else {
+            Vec::new()
+        };
+        broadcast_vec(&mut serialized_data, communicator, data_owner_rank);
+
+        if !is_data_owner {
+            *value = deserialize(&decode(&serialized_data, data_owner_rank), data_owner_rank);
+        }
+    })
+}
+
+/// Split data owned by one process across all processes
+///
+/// Every process receives a contiguous chunk, chunk sizes differ by at most one item. Items can
+/// have different serialized sizes.
+pub fn scatter_serde<T: MPITransferable + Sync>(
+    data: &[T],
+    communicator: &impl Communicator<Raw = MPI_Comm>,
+    data_owner_rank: Rank,
+) -> Vec<T> {
+    timed(Phase::Scatter, || {
+        let data_owner_process = communicator.process_at_rank(data_owner_rank);
+        let mut received_len: Count = 0;
+        let mut received_data: Vec<u8>;
+
+        if communicator.rank() == data_owner_rank {
+            let chunks: Vec<Vec<u8>> = split_evenly(data.len(), communicator.size() as usize)
+                .into_iter()
+                .map(|range| encode(frame(&data[range])))
+                .collect();
+
+            let counts: Vec<Count> = chunks.iter().map(|chunk| chunk.len() as Count).collect();
+            let displs = displacements(&counts);
+            let serialized_data = chunks.concat();
+
+            data_owner_process.scatter_into_root(&counts[..], &mut received_len);
+            received_data = vec![0; received_len as usize];
+
+            let partition = Partition::new(&serialized_data[..], counts, displs);
+            data_owner_process.scatter_varcount_into_root(&partition, &mut received_data[..]);
+        } else {
+            data_owner_process.scatter_into(&mut received_len);
+            received_data = vec![0; received_len as usize];
+            data_owner_process.scatter_varcount_into(&mut received_data[..]);
+        }
+
+        unframe(&decode(&received_data, data_owner_rank), data_owner_rank)
+    })
+}
+
+/// Gather data from all processes at the data owner, in rank order
+///
+/// Only the data owner receives the items, other processes get an empty vector.
+pub fn gather_serde<T: MPITransferable + Sync>(
+    gather_from: &[T],
+    communicator: &impl Communicator<Raw = MPI_Comm>,
+    data_owner_rank: Rank,
+) -> Vec<T> {
+    timed(Phase::Gather, || {
+        let data_owner_process = communicator.process_at_rank(data_owner_rank);
+        let serialized_data = encode(frame(gather_from));
+        let serialized_data_len = serialized_data.len() as Count;
+
+        if communicator.rank() != data_owner_rank {
+            data_owner_process.gather_into(&serialized_data_len);
+            data_owner_process.gather_varcount_into(&serialized_data[..]);
+            return Vec::new();
+        }
+
+        let mut counts: Vec<Count> = vec![0; communicator.size() as usize];
+        data_owner_process.gather_into_root(&serialized_data_len, &mut counts[..]);
+
+        let displs = displacements(&counts);
+        let mut buffer: Vec<u8> = vec![0; counts.iter().sum::<Count>() as usize];
+        {
+            let mut partition = PartitionMut::new(&mut buffer[..], &counts[..], &displs[..]);
+            data_owner_process.gather_varcount_into_root(&serialized_data[..], &mut partition);
+        }
+
+        counts
+            .iter()
+            .zip(&displs)
+            .enumerate()
+            .flat_map(|(rank, (count, displ))| {
+                let start = *displ as usize;
+                let source = rank as Rank;
+                unframe(
+                    &decode(&buffer[start..start + *count as usize], source),
+                    source,
+                )
+            })
+            .collect()
+    })
+}
+
+/// Split a population owned by one process into even contiguous chunks, one per process
+///
+/// Chunk sizes differ by at most one individual, the first processes get the larger chunks.
+pub fn scatter_population(
+    population: &[Individual],
+    communicator: &impl Communicator<Raw = MPI_Comm>,
+    data_owner_rank: Rank,
+) -> Population {
+    scatter_serde(population, communicator, data_owner_rank)
+}
+
+/// Join the chunks of a population at the data owner, in rank order
+///
+/// Chunks may differ in size, e.g. the elites of every island. Other processes get an empty
+/// population.
+pub fn gather_population(
+    chunk: &[Individual],
+    communicator: &impl Communicator<Raw = MPI_Comm>,
+    data_owner_rank: Rank,
+) -> Population {
+    gather_serde(chunk, communicator, data_owner_rank)
+}
+
+/// Communicators of hierarchical migration, see [`split_by_node`]
+pub struct NodeCommunicators {
+    /// Processes on the same shared-memory node
+    pub node: SimpleCommunicator,
+    /// Processes with the same rank on their node, one per node
+    pub peers: SimpleCommunicator,
+}
+
+/// Split the communicator by shared-memory node
+///
+/// Ranks keep their order in both communicators. Nodes may run different numbers of processes,
+/// so the peer communicators of high local ranks may span fewer nodes.
+pub fn split_by_node(communicator: &impl Communicator<Raw = MPI_Comm>) -> NodeCommunicators {
+    let rank = communicator.rank();
+    let node = communicator.split_shared(rank);
+    let peers = communicator
+        .split_by_color_with_key(Color::with_value(node.rank()), rank)
+        .expect("Every process has a peer communicator");
+    NodeCommunicators { node, peers }
+}
+
+/// Ranks a process sends its migrants to and receives migrants from
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Neighbours {
+    pub destinations: Vec<Rank>,
+    pub sources: Vec<Rank>,
+}
+
+impl Neighbours {
+    /// The neighbours except `ranks`, e.g. the ones that stopped answering
+    pub fn without(mut self, ranks: &BTreeSet<Rank>) -> Self {
+        self.destinations.retain(|rank| !ranks.contains(rank));
+        self.sources.retain(|rank| !ranks.contains(rank));
+        self
+    }
+}
+
+/// Neighbours of `rank` among `size` processes in the topology
+///
+/// `seed` only matters for [`MigrationTopology::Random`], all processes must pass the same one so
+/// their rings agree. A single process has no neighbours.
+pub fn neighbours(topology: MigrationTopology, rank: Rank, size: Rank, seed: u64) -> Neighbours {
+    if size < 2 {
+        return Neighbours::default();
+    }
+
+    match topology {
+        MigrationTopology::Ring => Neighbours {
+            destinations: vec![(rank + 1) % size],
+            sources: vec![(rank + size - 1) % size],
+        },
+        MigrationTopology::FullyConnected => {
+            let others: Vec<Rank> = (0..size).filter(|other| *other != rank).collect();
+            Neighbours {
+                destinations: others.clone(),
+                sources: others,
+            }
+        }
+        MigrationTopology::Random => {
+            let mut order: Vec<Rank> = (0..size).collect();
+            order.shuffle(&mut get_random_generator(seed));
+            let position = order.iter().position(|other| *other == rank).unwrap();
+            let size = size as usize;
+            Neighbours {
+                destinations: vec![order[(position + 1) % size]],
+                sources: vec![order[(position + size - 1) % size]],
+            }
+        }
+        MigrationTopology::Grid => {
+            let rows = (1..=size)
+                .take_while(|rows| rows * rows <= size)
+                .filter(|rows| size % rows == 0)
+                .last()
+                .unwrap();
+            let columns = size / rows;
+            let (row, column) = (rank / columns, rank % columns);
+
+            let mut others = vec![
+                ((row + rows - 1) % rows) * columns + column,
+                ((row + 1) % rows) * columns + column,
+                row * columns + (column + columns - 1) % columns,
+                row * columns + (column + 1) % columns,
+            ];
+            others.sort_unstable();
+            others.dedup();
+            others.retain(|other| *other != rank);
+            Neighbours {
+                destinations: others.clone(),
+                sources: others,
+            }
+        }
+    }
+}
+
+/// Send a value to every `destinations` rank and receive one of the same type from every
+/// `sources` rank, running `work` while the messages are in flight
+///
+/// The value and its length are sent and the incoming lengths are received with non-blocking
+/// requests posted before `work` starts, so all processes can exchange with their neighbours at
+/// once (e.g. in a ring) without deadlocking and without stalling on the transfer. Only the
+/// incoming data itself is received after `work` returns, by then it has usually arrived.
+/// Received values are in the order of `sources`. Only the time not spent in `work` counts as
+/// [`Phase::Migration`].
+///
+/// With a `timeout` the process waits at most that long after `work` for the incoming lengths and
+/// for its own sends, cancelling the rest. Neighbours that missed the deadline are left out of the
+/// values and listed in [`Exchange::unresponsive`].
+pub fn exchange_serde_while<T, F, R>(
+    value: &T,
+    destinations: &[Rank],
+    sources: &[Rank],
+    communicator: &impl Communicator<Raw = MPI_Comm>,
+    timeout: Option<Duration>,
+    work: F,
+) -> Exchange<T, R>
+where
+    T: MPITransferable,
+    F: FnOnce() -> R,
+{
+    timed(Phase::Migration, || {
+        let serialized_data = encode(value.to_bytes());
+        let serialized_data_len = serialized_data.len();
+        let mut received_lens: Vec<usize> = vec![0; sources.len()];
+
+        let (received_data, result, unresponsive) = request::scope(|scope| {
+            let (len_requests, data_requests): (Vec<_>, Vec<_>) = destinations
+                .iter()
+                .map(|destination| {
+                    let destination_process = communicator.process_at_rank(*destination);
+                    (
+                        destination_process.immediate_send(scope, &serialized_data_len),
+                        destination_process.immediate_send(scope, &serialized_data[..]),
+                    )
+                })
+                .unzip();
+
+            // the length buffers are borrowed until the end of the scope, so they get their own
+            let (result, answered, deadline) = request::scope(|len_scope| {
+                let len_receive_requests: Vec<_> = sources
+                    .iter()
+                    .zip(received_lens.iter_mut())
+                    .map(|(source, received_len)| {
+                        communicator
+                            .process_at_rank(*source)
+                            .immediate_receive_into(len_scope, received_len)
+                    })
+                    .collect();
+                let result = timed(Phase::Compute, work);
+                let deadline = timeout.map(|timeout| Instant::now() + timeout);
+                let answered: Vec<bool> = len_receive_requests
+                    .into_iter()
+                    .map(|request| wait_until(request, deadline))
+                    .collect();
+                (result, answered, deadline)
+            });
+
+            // a neighbour that sent the length has sent the data too
+            let received_data: Vec<Option<Vec<u8>>> = sources
+                .iter()
+                .zip(&received_lens)
+                .zip(&answered)
+                .map(|((source, received_len), answered)| {
+                    answered.then(|| {
+                        let mut received_data = vec![0; *received_len];
+                        communicator
+                            .process_at_rank(*source)
+                            .receive_into(&mut received_data[..]);
+                        received_data
+                    })
+                })
+                .collect();
+
+            let delivered: Vec<bool> = len_requests
+                .into_iter()
+                .zip(data_requests)
+                .map(|(len_request, data_request)| {
+                    let len_delivered = wait_until(len_request, deadline);
+                    wait_until(data_request, deadline) && len_delivered
+                })
+                .collect();
+
+            let mut unresponsive: Vec<Rank> = sources
+                .iter()
+                .zip(&answered)
+                .chain(destinations.iter().zip(&delivered))
+                .filter(|(_, responded)| !**responded)
+                .map(|(rank, _)| *rank)
+                .collect();
+            unresponsive.sort_unstable();
+            unresponsive.dedup();
+            (received_data, result, unresponsive)
+        });
+
+        let values = received_data
+            .iter()
+            .zip(sources)
+            .filter_map(|(bytes, source)| {
+                bytes
+                    .as_ref()
+                    .map(|bytes| deserialize(&decode(bytes, *source), *source))
+            })
+            .collect();
+        Exchange {
+            values,
+            result,
+            unresponsive,
+        }
+    })
+}
+
+/// Outcome of [`exchange_serde_while`]
+#[derive(Debug)]
+pub struct Exchange<T, R> {
+    /// Values of the sources that answered, in the order of `sources`
+    pub values: Vec<T>,
+    /// Return value of the work done during the transfer
+    pub result: R,
+    /// Neighbours that didn't answer or take their value before the timeout, sorted
+    pub unresponsive: Vec<Rank>,
+}
+
+/// Wait for the request, cancelling it when it isn't done by the deadline
+///
+/// Returns whether the request completed, it always does without a deadline.
+fn wait_until<'a, D: ?Sized, S: Scope<'a>>(
+    mut request: Request<'a, D, S>,
+    deadline: Option<Instant>,
+) -> bool {
+    let Some(deadline) = deadline else {
+        request.wait();
+        return true;
+    };
+
+    loop {
+        match request.test() {
+            Ok(_) => return true,
+            Err(pending) if Instant::now() >= deadline => {
+                pending.cancel();
+                pending.wait();
+                return false;
+            }
+            Err(pending) => {
+                request = pending;
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+    }
+}
+
+/// Send a value to the `destination` process, its length first
+///
+/// Blocks until MPI has buffered the value or the destination receives it with
+/// [`receive_serde_from_any`].
+pub fn send_serde<T: MPITransferable>(
+    value: &T,
+    destination: Rank,
+    communicator: &impl Communicator<Raw = MPI_Comm>,
+) {
+    timed(Phase::Gather, || {
+        let serialized_data = encode(value.to_bytes());
+        let destination_process = communicator.process_at_rank(destination);
+        destination_process.send(&serialized_data.len());
+        destination_process.send(&serialized_data[..]);
+    })
+}
+
+/// Receive a value sent by [`send_serde`] from whichever process sends first, and its rank
+///
+/// Messages of a process arrive in the order they were sent, so the data is the next message of
+/// the process whose length arrived.
+pub fn receive_serde_from_any<T: MPITransferable>(
+    communicator: &impl Communicator<Raw = MPI_Comm>,
+) -> (T, Rank) {
+    let (received_len, status) = communicator.any_process().receive::<usize>();
+    let source = status.source_rank();
+
+    let mut received_data = vec![0; received_len];
+    communicator
+        .process_at_rank(source)
+        .receive_into(&mut received_data[..]);
+    (deserialize(&decode(&received_data, source), source), source)
+}
+
+/// Stop signal for processes that don't synchronize while they work
+///
+/// A process that [`raise`](StopToken::raise)s the token sends a message to all others without
+/// waiting, they see it the next time they [`poll`](StopToken::poll). All processes call
+/// [`finish`](StopToken::finish) together at the end, which receives the messages nobody polled
+/// for, so none are left behind for later receives.
+pub struct StopToken<'a, C: Communicator<Raw = MPI_Comm>> {
+    communicator: &'a C,
+    sends: Vec<Request<'static, bool, StaticScope>>,
+    received: usize,
+}
+
+impl<'a, C: Communicator<Raw = MPI_Comm>> StopToken<'a, C> {
+    pub fn new(communicator: &'a C) -> Self {
+        StopToken {
+            communicator,
+            sends: Vec::new(),
+            received: 0,
+        }
+    }
+
+    /// Tell all other processes to stop, only the first call sends anything
+    pub fn raise(&mut self) {
+        if self.is_raised() {
+            return;
+        }
+
+        let rank = self.communicator.rank();
+        self.sends = (0..self.communicator.size())
+            .filter(|other| *other != rank)
+            .map(|other| {
+                self.communicator
+                    .process_at_rank(other)
+                    .immediate_send_with_tag(StaticScope, &STOP_MESSAGE, STOP_TAG)
+            })
+            .collect();
+    }
+
+    /// Whether this or any other process raised the token, doesn't block
+    pub fn poll(&mut self) -> bool {
+        while self
+            .communicator
+            .any_process()
+            .immediate_probe_with_tag(STOP_TAG)
+            .is_some()
+        {
+            self.communicator
+                .any_process()
+                .receive_with_tag::<bool>(STOP_TAG);
+            self.received += 1;
+        }
+
+        self.is_raised() || self.received > 0
+    }
 
-        *variable = T::from_bytes(&serialized_data);
+    fn is_raised(&self) -> bool {
+        !self.sends.is_empty()
+    }
+
+    /// Receive the remaining messages and wait until the own ones are delivered
+    pub fn finish(mut self) {
+        let raised = all_reduce_sum(&[self.is_raised() as u32], self.communicator)[0] as usize;
+        let expected = raised - usize::from(self.is_raised());
+
+        for _ in self.received..expected {
+            self.communicator
+                .any_process()
+                .receive_with_tag::<bool>(STOP_TAG);
+        }
+        for request in self.sends.drain(..) {
+            request.wait();
+        }
     }
 }
 
@@ -60,7 +846,7 @@ pub fn mpi_execute_and_synchronize_at<F, R>(
 ) -> R
 where
     F: FnOnce() -> R,
-    R: MPITransferable + Clone + Default,
+    R: MPITransferable + Default,
 {
     let current_rank = communicator.rank();
     let mut value_placeholder = if current_rank == executor_rank {
@@ -69,95 +855,292 @@ where
         R::default()
     };
 
-    mpi_synchronize_ref(&mut value_placeholder, communicator, executor_rank);
-    return value_placeholder;
+    broadcast_serde(&mut value_placeholder, communicator, executor_rank);
+    value_placeholder
 }
 
-/// Serialize a vector of MPITransferable objects into a single byte vector
-///
-/// Helper method for [`mpi_split_data_across_nodes`] and [`mpi_gather_and_synchronize`]
-fn serialize_vec<T: Default + MPITransferable + Clone + Send>(data: Vec<T>) -> (usize, Vec<u8>) {
-    let serialized_data: Vec<Vec<u8>> = data.into_par_iter().map(|x| x.into_bytes()).collect();
+/// Maximum of a value across all processes, available on every process
+pub fn all_reduce_max<T: Equivalence + Default>(
+    value: T,
+    communicator: &impl Communicator<Raw = MPI_Comm>,
+) -> T {
+    timed(Phase::Reduce, || {
+        let mut result = T::default();
+        communicator.all_reduce_into(&value, &mut result, SystemOperation::max());
+        result
+    })
+}
 
-    // Assert that the data is the same size on all nodes
-    for i in 1..serialized_data.len() {
-        let current_len = serialized_data[i].len();
-        let prev_len = serialized_data[i - 1].len();
-        assert_eq!(prev_len, current_len);
+/// Element-wise sum of equally long vectors across all processes, available on every process
+pub fn all_reduce_sum<T: Equivalence + Default + Clone>(
+    values: &[T],
+    communicator: &impl Communicator<Raw = MPI_Comm>,
+) -> Vec<T> {
+    timed(Phase::Reduce, || {
+        let mut result = vec![T::default(); values.len()];
+        communicator.all_reduce_into(values, &mut result[..], SystemOperation::sum());
+        result
+    })
+}
+
+/// Share a flag of the root with all processes, e.g. a stop request
+pub fn broadcast_flag(
+    flag: bool,
+    communicator: &impl Communicator<Raw = MPI_Comm>,
+    root_rank: Rank,
+) -> bool {
+    timed(Phase::Broadcast, || {
+        let mut flag = flag;
+        communicator
+            .process_at_rank(root_rank)
+            .broadcast_into(&mut flag);
+        flag
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames_roundtrip_values_of_different_sizes() {
+        let values = vec![vec![1], vec![], vec![2, 3, 4]];
+        let buffer = frame(&values);
+        assert_eq!(unframe::<Vec<i32>>(&buffer, 0), values);
     }
 
-    let data_size = serialized_data[0].len();
-    let serialized_data: Vec<u8> = serialized_data.into_iter().flatten().collect();
+    #[test]
+    fn test_concatenated_frames_keep_order() {
+        let buffer = [frame(&["a".to_string()]), frame(&["b".to_string()])].concat();
+        assert_eq!(unframe::<String>(&buffer, 0), vec!["a", "b"]);
+    }
 
-    (data_size, serialized_data)
-}
+    #[test]
+    fn test_unusable_frames_raise_transfer_errors() {
+        let error_of = |buffer: Vec<u8>| {
+            let payload = std::panic::catch_unwind(|| unframe::<String>(&buffer, 2)).unwrap_err();
+            *payload.downcast::<TransferError>().unwrap()
+        };
 
-/// Split data in a vector across all nodes evenly
-///
-/// Expects `T` elements to be the same size when serialized
-pub fn mpi_split_data_across_nodes<T: Default + MPITransferable + Clone + Send>(
-    data: &[T],
-    communicator: &impl Communicator<Raw = MPI_Comm>,
-    data_owner_rank: Rank,
-) -> Vec<T> {
-    assert_ne!(data.len(), 0);
-    let size = communicator.size();
-    let rank = communicator.rank();
-    let process = communicator.process_at_rank(data_owner_rank);
-    let split_size = data.len() / size as usize;
-    assert_eq!(data.len() % size as usize, 0);
+        let truncated = error_of(frame(&["abc".to_string()])[..9].to_vec());
+        assert!(matches!(
+            truncated,
+            TransferError::SizeMismatch {
+                source_rank: 2,
+                declared: 19,
+                available: 9
+            }
+        ));
+        assert_eq!(truncated.exit_reason(), ExitReason::SizeMismatch);
 
-    let mut rec_data: Vec<u8>;
-    let mut data_size = 0;
+        // a string whose length prefix claims more bytes than the frame holds
+        let not_a_string = error_of(frame(&[u64::MAX]));
+        assert_eq!(not_a_string.exit_reason(), ExitReason::Deserialization);
+    }
 
-    if rank == data_owner_rank {
-        let serialized_data: Vec<u8>;
+    #[test]
+    fn test_sealed_messages_reject_other_versions_and_corruption() {
+        let message = seal(b"payload");
+        assert_eq!(open(&message), Ok(&b"payload"[..]));
+        assert_eq!(decode(&encode(b"payload".to_vec()), 0), b"payload");
 
-        (data_size, serialized_data) = serialize_vec(data.to_owned());
+        let mut other_version = message.clone();
+        other_version[0] += 1;
+        assert_eq!(
+            open(&other_version),
+            Err(ProtocolError::VersionMismatch {
+                found: PROTOCOL_VERSION + 1,
+                expected: PROTOCOL_VERSION
+            })
+        );
 
-        mpi_synchronize_ref(&mut data_size, communicator, data_owner_rank);
-        rec_data = vec![0; data_size * split_size];
+        let mut corrupted = message.clone();
+        *corrupted.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            open(&corrupted),
+            Err(ProtocolError::ChecksumMismatch { .. })
+        ));
+        assert_eq!(open(&message[..3]), Err(ProtocolError::Truncated(3)));
 
-        process.scatter_into_root(&serialized_data, &mut rec_data);
-    } else {
-        mpi_synchronize_ref(&mut data_size, communicator, data_owner_rank);
-        rec_data = vec![0; data_size * split_size];
-        process.scatter_into(&mut rec_data);
+        // intact envelope around a payload with an unknown compression flag
+        let unknown_flag = seal(&[7, 1, 2]);
+        let payload = std::panic::catch_unwind(|| decode(&unknown_flag, 1)).unwrap_err();
+        let error = *payload.downcast::<TransferError>().unwrap();
+        assert!(matches!(
+            error,
+            TransferError::Protocol {
+                source_rank: 1,
+                error: ProtocolError::Compression(CompressionError::UnknownFlag(7))
+            }
+        ));
+        assert_eq!(error.exit_reason(), ExitReason::InvalidMessage);
     }
 
-    rec_data
-        .chunks(data_size)
-        .map(|chunk| T::from_bytes(chunk))
-        .collect()
-}
+    #[test]
+    fn test_topologies_are_symmetric() {
+        let ring = neighbours(MigrationTopology::Ring, 0, 4, 0);
+        assert_eq!((ring.destinations, ring.sources), (vec![1], vec![3]));
+        assert_eq!(
+            neighbours(MigrationTopology::FullyConnected, 2, 4, 0).destinations,
+            vec![0, 1, 3]
+        );
+        // 6 ranks make a grid of 2 rows and 3 columns
+        assert_eq!(
+            neighbours(MigrationTopology::Grid, 4, 6, 0).destinations,
+            vec![1, 3, 5]
+        );
+        assert!(neighbours(MigrationTopology::Grid, 0, 1, 0)
+            .destinations
+            .is_empty());
 
-/// Gather data (shards of split data) from all nodes into a single vector
-///
-/// Expects `T` elements to be the same size when serialized
-pub fn mpi_gather_and_synchronize<T: Default + MPITransferable + Clone + Send>(
-    gather_from: &[T],
-    communicator: &impl Communicator<Raw = MPI_Comm>,
-    data_owner_rank: Rank,
-) -> Vec<T> {
-    assert_ne!(gather_from.len(), 0);
-    let rank = communicator.rank();
-    let process = communicator.process_at_rank(data_owner_rank);
+        for seed in 0..5 {
+            let random: Vec<Neighbours> = (0..5)
+                .map(|rank| neighbours(MigrationTopology::Random, rank, 5, seed))
+                .collect();
+            for (rank, neighbours) in random.iter().enumerate() {
+                let destination = neighbours.destinations[0];
+                assert_ne!(destination, rank as Rank);
+                assert_eq!(random[destination as usize].sources, vec![rank as Rank]);
+            }
+        }
+    }
 
-    let mut gathered_data = Vec::new();
+    #[test]
+    fn test_split_evenly_covers_all_items() {
+        assert_eq!(split_evenly(7, 3), vec![0..3, 3..5, 5..7]);
+        assert_eq!(split_evenly(2, 4), vec![0..1, 1..2, 2..2, 2..2]);
+        assert_eq!(displacements(&[3, 0, 2]), vec![0, 3, 3]);
+    }
 
-    let (data_size, serialized_data) = serialize_vec(gather_from.to_owned());
+    #[cfg(not(feature = "mpi"))]
+    #[test]
+    fn test_populations_are_scattered_evenly_and_gathered_in_order() {
+        crate::mpi::run_ranks(3, |world| {
+            let population: Population = (0..7)
+                .map(|adaptation| Individual {
+                    adaptation,
+                    ..Individual::default()
+                })
+                .collect();
+            let owned = if world.rank() == ROOT_RANK {
+                population.clone()
+            } else {
+                Vec::new()
+            };
 
-    if rank == data_owner_rank {
-        let mut buffer: Vec<u8> = vec![0; serialized_data.len() * communicator.size() as usize];
-        process.gather_into_root(&serialized_data, &mut buffer);
-        gathered_data = buffer
-            .chunks(data_size)
-            .map(|chunk| T::from_bytes(chunk))
-            .collect();
-    } else {
-        process.gather_into(&serialized_data);
+            let adaptations = |individuals: &[Individual]| -> Vec<i32> {
+                individuals
+                    .iter()
+                    .map(|individual| individual.adaptation)
+                    .collect()
+            };
+
+            let chunk = scatter_population(&owned, &world, ROOT_RANK);
+            let expected = split_evenly(population.len(), 3)[world.rank() as usize].clone();
+            assert_eq!(adaptations(&chunk), adaptations(&population[expected]));
+
+            let gathered = gather_population(&chunk, &world, ROOT_RANK);
+            if world.rank() == ROOT_RANK {
+                assert_eq!(adaptations(&gathered), adaptations(&population));
+            } else {
+                assert!(gathered.is_empty());
+            }
+        });
+    }
+
+    #[cfg(not(feature = "mpi"))]
+    #[test]
+    fn test_exchanges_leave_out_silent_neighbours_after_the_timeout() {
+        crate::mpi::run_ranks(3, |world| {
+            // rank 2 never takes part, as if it had died
+            let rank = world.rank();
+            if rank == 2 {
+                return;
+            }
+
+            let neighbours = neighbours(MigrationTopology::Ring, rank, 3, 0);
+            let exchange = exchange_serde_while(
+                &rank,
+                &neighbours.destinations,
+                &neighbours.sources,
+                &world,
+                Some(Duration::from_millis(50)),
+                || rank * 10,
+            );
+            assert_eq!(exchange.result, rank * 10);
+            if rank == 0 {
+                assert!(exchange.values.is_empty());
+                assert_eq!(exchange.unresponsive, vec![2]);
+            } else {
+                assert_eq!(exchange.values, vec![0]);
+                assert!(exchange.unresponsive.is_empty());
+            }
+
+            let lost: BTreeSet<Rank> = exchange.unresponsive.iter().copied().collect();
+            assert_eq!(
+                neighbours.without(&lost).sources.is_empty(),
+                !exchange.unresponsive.is_empty()
+            );
+        });
     }
 
-    mpi_synchronize_ref(&mut gathered_data, communicator, data_owner_rank);
-    gathered_data
+    #[cfg(not(feature = "mpi"))]
+    #[test]
+    fn test_a_raised_stop_token_reaches_every_process() {
+        crate::mpi::run_ranks(3, |world| {
+            let mut stop_token = StopToken::new(&world);
+            assert!(!stop_token.poll());
+            // nobody raises the token before all processes polled it once
+            all_reduce_sum(&[0_u32], &world);
+
+            if world.rank() == 1 {
+                stop_token.raise();
+                stop_token.raise();
+                assert!(stop_token.poll());
+            } else {
+                let start = std::time::Instant::now();
+                while !stop_token.poll() {
+                    assert!(start.elapsed() < Duration::from_secs(10));
+                    std::thread::yield_now();
+                }
+            }
+            stop_token.finish();
+
+            // the messages of the first token are all received, a new one starts lowered
+            let mut stop_token = StopToken::new(&world);
+            assert!(!stop_token.poll());
+            stop_token.finish();
+        });
+    }
+
+    #[cfg(not(feature = "mpi"))]
+    #[test]
+    fn test_broadcasts_arrive_whole_over_several_chunks() {
+        // 2 items of 4 bytes in a chunk, so the vector takes 5 of them
+        set_broadcast_chunk_size(8);
+        crate::mpi::run_ranks(3, |world| {
+            let expected: Vec<u32> = (0..10).map(|item| item * 7).collect();
+            let mut data = match world.rank() {
+                ROOT_RANK => expected.clone(),
+                rank => vec![u32::MAX; 3 * rank as usize],
+            };
+
+            broadcast_vec(&mut data, &world, ROOT_RANK);
+            assert_eq!(data, expected);
+
+            verify_broadcast(&[1, 2, 3], "bytes", &world, ROOT_RANK);
+            let local = [world.rank() as u8];
+            let corrupted =
+                std::panic::catch_unwind(|| verify_broadcast(&local, "bytes", &world, ROOT_RANK));
+            match corrupted {
+                Ok(()) => assert_eq!(world.rank(), ROOT_RANK),
+                Err(error) => assert!(matches!(
+                    error.downcast_ref::<TransferError>(),
+                    Some(TransferError::CorruptedBroadcast { what, rank, .. })
+                        if what == "bytes" && *rank == world.rank()
+                )),
+            }
+        });
+        set_broadcast_chunk_size(16 * 1024 * 1024);
+    }
 }
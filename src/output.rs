@@ -0,0 +1,271 @@
+use std::{
+    collections::BTreeSet,
+    fs::{self, OpenOptions},
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::algorithm::{
+    config::AlgorithmConfig,
+    datatypes::{Individual, Tuple},
+    periods::{DaySlot, PeriodLayout},
+    report::{ConflictKind, PenaltyBreakdown},
+};
+
+/// Row of the per-period penalty breakdown
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct PenaltyRecord {
+    period: usize,
+    kind: ConflictKind,
+    penalty: i32,
+}
+
+/// Write a human readable timetable, listing tuples of every period
+///
+/// With more than one day in the layout, periods are grouped under day headers and numbered
+/// within their day.
+pub fn write_timetable(
+    individual: &Individual,
+    tuples: &[Tuple],
+    layout: &PeriodLayout,
+    path: impl AsRef<Path>,
+) -> std::io::Result<()> {
+    write_filtered_timetable(
+        individual,
+        tuples,
+        layout,
+        "Najlepszy plan zajęć",
+        |_| true,
+        path,
+    )
+}
+
+/// Write a timetable of every student group and every teacher of the tuples
+///
+/// The timetables are written like [`write_timetable`] to `groups/<group>.txt` and
+/// `teachers/<teacher>.txt` in `dir`, titled with the names from the configuration when known.
+/// Tuples without a group are only in the timetables of their teachers.
+pub fn write_personal_timetables(
+    individual: &Individual,
+    tuples: &[Tuple],
+    config: &AlgorithmConfig,
+    dir: impl AsRef<Path>,
+) -> std::io::Result<()> {
+    let layout = config.period_layout();
+    let groups_dir = dir.as_ref().join("groups");
+    let teachers_dir = dir.as_ref().join("teachers");
+    fs::create_dir_all(&groups_dir)?;
+    fs::create_dir_all(&teachers_dir)?;
+
+    let groups: BTreeSet<&str> = tuples
+        .iter()
+        .map(|tuple| tuple.group.as_str())
+        .filter(|group| !group.is_empty())
+        .collect();
+    for group in groups {
+        write_filtered_timetable(
+            individual,
+            tuples,
+            &layout,
+            &format!("Plan zajęć: {}", group_name(config, group)),
+            |tuple| tuple.group == group,
+            groups_dir.join(file_name(group, "txt")),
+        )?;
+    }
+
+    let teachers: BTreeSet<&str> = tuples.iter().map(|tuple| tuple.teacher.as_str()).collect();
+    for teacher in teachers {
+        write_filtered_timetable(
+            individual,
+            tuples,
+            &layout,
+            &format!("Plan zajęć: {}", teacher_name(config, teacher)),
+            |tuple| tuple.teacher == teacher,
+            teachers_dir.join(file_name(teacher, "txt")),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Name of a student group from the configuration, its id when unknown
+pub fn group_name<'a>(config: &'a AlgorithmConfig, group: &'a str) -> &'a str {
+    config
+        .student_groups
+        .get(group)
+        .map(|group| group.name.as_str())
+        .filter(|name| !name.is_empty())
+        .unwrap_or(group)
+}
+
+/// Name of a teacher from the configuration, their id when unknown
+pub fn teacher_name<'a>(config: &'a AlgorithmConfig, teacher: &'a str) -> &'a str {
+    config
+        .teachers
+        .get(teacher)
+        .map(|teacher| teacher.name.as_str())
+        .filter(|name| !name.is_empty())
+        .unwrap_or(teacher)
+}
+
+/// Hue of the classes of a course in the timetables, from the part of the label after the kind
+/// (`Wykład | ...`) when the course isn't given, so its lectures and labs match
+pub fn course_hue(tuple: &Tuple) -> u16 {
+    let course = match tuple.course.as_str() {
+        "" => tuple.label.rsplit('|').next().unwrap_or_default().trim(),
+        course => course,
+    };
+    // FNV-1a, the same in every build unlike the default hasher
+    let hash = course
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+    (hash % 360) as u16
+}
+
+/// Name of the timetable file of a group or teacher, characters other than letters, digits, `-`
+/// and `_` are replaced with `_`
+pub fn file_name(id: &str, extension: &str) -> String {
+    let stem: String = id
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}.{}", stem, extension)
+}
+
+/// Write a timetable of the tuples matching `filter`, see [`write_timetable`]
+fn write_filtered_timetable(
+    individual: &Individual,
+    tuples: &[Tuple],
+    layout: &PeriodLayout,
+    title: &str,
+    filter: impl Fn(&Tuple) -> bool,
+    path: impl AsRef<Path>,
+) -> std::io::Result<()> {
+    let out_file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)?;
+
+    let mut buf_writer = BufWriter::new(out_file);
+    writeln!(buf_writer, "{}", title)?;
+
+    for (index, chromosome) in individual.chromosomes.iter().enumerate() {
+        let DaySlot { day, slot } = layout.day_slot(index);
+        if slot == 0 && layout.days > 1 {
+            writeln!(buf_writer, "Dzień {}", day + 1)?;
+        }
+
+        let mapped_tuples = chromosome
+            .genes
+            .iter()
+            .map(|gene| tuples.iter().find(|tuple| tuple.id == *gene).unwrap())
+            .filter(|tuple| filter(tuple));
+        let tuples_as_string = mapped_tuples.map(|tuple| tuple.to_string()).join("\n - ");
+        writeln!(buf_writer, "{}:\n - {}", slot + 1, tuples_as_string)?;
+    }
+
+    buf_writer.flush()
+}
+
+/// Write the penalty of every kind of conflict in every period as CSV, skipping periods where the
+/// kind costs nothing
+pub fn write_penalties(
+    breakdown: &[PenaltyBreakdown],
+    path: impl AsRef<Path>,
+) -> Result<(), csv::Error> {
+    let mut writer = csv::Writer::from_path(path)?;
+
+    for kind in breakdown {
+        for (period, penalty) in kind.periods.iter().enumerate() {
+            if *penalty != 0 {
+                writer.serialize(PenaltyRecord {
+                    period,
+                    kind: kind.kind,
+                    penalty: *penalty,
+                })?;
+            }
+        }
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::{
+        datatypes::{Chromosome, PeriodId, TupleId},
+        resources::Group,
+    };
+    use smallvec::smallvec;
+
+    #[test]
+    fn test_timetables_are_written_per_group_and_teacher() {
+        let tuples: Vec<Tuple> = [
+            (1, "Wykład | Sieci", "Smith", "FT-1"),
+            (2, "Laboratorium | Sieci", "Jones", "FT-2"),
+            (3, "Konsultacje", "Smith", ""),
+        ]
+        .into_iter()
+        .map(|(id, label, teacher, group)| Tuple {
+            id: TupleId(id),
+            label: label.to_string(),
+            room: "A".to_string(),
+            teacher: teacher.to_string(),
+            group: group.to_string(),
+            ..Tuple::default()
+        })
+        .collect();
+        let individual = Individual::with_chromosomes(vec![
+            Chromosome {
+                id: PeriodId(0),
+                genes: smallvec![TupleId(1), TupleId(2)],
+            },
+            Chromosome {
+                id: PeriodId(1),
+                genes: smallvec![TupleId(3)],
+            },
+        ]);
+        let group = Group {
+            id: "FT-1".to_string(),
+            name: "Fizyka Techniczna 1".to_string(),
+            size: Some(30),
+        };
+        let config = AlgorithmConfig {
+            number_of_periods: 2,
+            student_groups: [(group.id.clone(), group)].into(),
+            ..AlgorithmConfig::default()
+        };
+        let dir = std::env::temp_dir().join("planner_personal_timetables_test");
+        let _ = fs::remove_dir_all(&dir);
+
+        write_personal_timetables(&individual, &tuples, &config, &dir).unwrap();
+
+        let read = |path: &str| fs::read_to_string(dir.join(path)).unwrap();
+        assert_eq!(
+            read("groups/FT-1.txt"),
+            "Plan zajęć: Fizyka Techniczna 1\n1:\n - Wykład | Sieci, A, Smith\n2:\n - \n"
+        );
+        assert!(read("groups/FT-2.txt").starts_with("Plan zajęć: FT-2\n"));
+        // classes without a group are only in the timetables of their teachers
+        assert_eq!(fs::read_dir(dir.join("groups")).unwrap().count(), 2);
+        let smith = read("teachers/Smith.txt");
+        assert!(smith.contains("Wykład | Sieci") && smith.contains("Konsultacje"));
+        assert!(!smith.contains("Laboratorium"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
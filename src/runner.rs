@@ -0,0 +1,973 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::mpi::{ffi::MPI_Comm, traits::*, Rank};
+use rand::RngCore;
+use rayon::prelude::*;
+use tracing::{debug, info, info_span, warn};
+
+use crate::{
+    aggregator,
+    algorithm::{
+        self, annealing,
+        config::{AlgorithmConfig, MigrationTopology, ParallelModel},
+        conflicts::ConflictMatrix,
+        datatypes::{Individual, Population, Tuple},
+        fitness::Fitness,
+        hypermutation::HypermutationController,
+        local_search,
+        metrics::{placement_entropy, PopulationMetrics},
+        migration::IslandConfig,
+        operators::Operators,
+        pareto,
+        penalties::PenaltyController,
+        pool::IndividualPool,
+        population_size::{resize_population, PopulationSizeController},
+        propagation::Domains,
+        random::{get_random_generator, PlannerRng},
+        selection,
+    },
+    global_best::GlobalBest,
+    mpi_datatypes::broadcast_population,
+    mpi_utils::{
+        all_reduce_max, all_reduce_sum, broadcast_flag, broadcast_serde, exchange_serde_while,
+        gather_population, gather_serde, mpi_execute_and_synchronize_at, neighbours,
+        scatter_population, split_by_node, split_evenly, NodeCommunicators, ROOT_RANK,
+    },
+};
+
+/// Communicator islands migrate over, see [`GaRunner::island_generation`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum MigrationScope {
+    World,
+    /// Islands of the same shared-memory node
+    Node,
+    /// Islands of the same local rank on all nodes
+    Peers,
+}
+
+/// Runs the genetic algorithm on all nodes of a communicator
+///
+/// With [`ParallelModel::Global`] the population lives on root, which broadcasts it every
+/// generation. Every node breeds and evaluates a share of the offspring, root gathers them back.
+///
+/// With [`ParallelModel::Island`] every node evolves its own part of the population and every
+/// `migration_interval` generations sends its best individuals to its neighbours in the
+/// `migration_topology`. The exchange is non-blocking and overlaps with the creation of the next
+/// generation. With `node_migration_interval` the topology is applied within every shared-memory
+/// node at that interval, and among the islands of the same local rank on all nodes every
+/// `migration_interval` generations. With `migration_timeout` neighbours that stop answering are
+/// dropped from the topology of the island, which keeps evolving with the others. The islands
+/// then don't wait for each other between migrations either, see [`GaRunner::run`].
+///
+/// With [`ParallelModel::MasterWorker`] the whole population lives on the root, which creates the
+/// offspring and scatters them for fitness evaluation, gathering only the adaptations back.
+pub struct GaRunner<'a, C: Communicator<Raw = MPI_Comm>> {
+    /// Configuration of this node, sizes are per island
+    config: AlgorithmConfig,
+    tuples: &'a [Tuple],
+    communicator: &'a C,
+    rng: PlannerRng,
+    operators: Operators,
+    island_config: IslandConfig,
+    /// Communicators of hierarchical migration, taken out while migrating
+    node_communicators: Option<NodeCommunicators>,
+    /// Neighbours that stopped answering, never migrated with again
+    lost_neighbours: BTreeMap<MigrationScope, BTreeSet<Rank>>,
+    /// Seed the `random` topology is shuffled from, the same on all nodes
+    topology_seed: u64,
+    /// Best adaptation of all nodes with `global_best_window`, otherwise it's reduced
+    global_best: Option<GlobalBest>,
+    population: Population,
+    /// Replaced individuals, reused for the offspring of the next generations
+    pool: IndividualPool,
+    population_size_controller: Option<PopulationSizeController>,
+    hypermutation_controller: Option<HypermutationController>,
+    penalty_controller: Option<PenaltyController>,
+}
+
+impl<'a, C: Communicator<Raw = MPI_Comm>> GaRunner<'a, C> {
+    /// Create the first population of this node and seed it with `injected` individuals
+    ///
+    /// The [`ConflictMatrix`] of the tuples is built on root and broadcast to all nodes, unless
+    /// rooms are assigned by the algorithm.
+    pub fn new(
+        config: &AlgorithmConfig,
+        tuples: &'a [Tuple],
+        communicator: &'a C,
+        mut rng: PlannerRng,
+        injected: Vec<Individual>,
+    ) -> Self {
+        let is_root = communicator.rank() == ROOT_RANK;
+        let island_count = match config.parallel_model {
+            ParallelModel::Island => communicator.size() as usize,
+            ParallelModel::Global | ParallelModel::MasterWorker => 1,
+        };
+        let island_config;
+        let config = match config.parallel_model {
+            ParallelModel::Island if !config.islands.is_empty() => {
+                let rank = communicator.rank() as usize;
+                island_config = config.for_island(rank).unwrap_or_else(|err| {
+                    warn!(rank, "Island overrides not applied: {}", err);
+                    config.clone()
+                });
+                debug!(rank, overrides = ?config.islands[rank % config.islands.len()]);
+                &island_config
+            }
+            _ => config,
+        };
+
+        let mut conflict_matrix = if is_root && !config.assigns_resources() {
+            ConflictMatrix::new(config, tuples)
+        } else {
+            ConflictMatrix::default()
+        };
+        broadcast_serde(&mut conflict_matrix, communicator, ROOT_RANK);
+
+        let config = AlgorithmConfig {
+            population_size: config.population_size / island_count,
+            min_population_size: config.min_population_size / island_count,
+            max_population_size: config.max_population_size / island_count,
+            offspring_per_generation: config
+                .offspring_per_generation
+                .map(|count| (count / island_count).max(1)),
+            ..config.clone()
+        };
+        let config = if config.assigns_resources() {
+            config
+        } else {
+            config.with_conflict_matrix(conflict_matrix)
+        };
+        let config = if config.constraint_propagation {
+            let domains = Domains::propagate(&config, tuples);
+            if is_root {
+                info!(
+                    "Constraint propagation left {} of {} placements",
+                    domains.size(),
+                    tuples.len() * config.number_of_periods
+                );
+            }
+            config.with_domains(domains)
+        } else {
+            config
+        };
+
+        let holds_population = config.parallel_model == ParallelModel::Island || is_root;
+
+        let population = if holds_population {
+            let mut population = algorithm::create_first_population(&config, tuples, &mut rng);
+            population.par_iter_mut().for_each(|individual| {
+                individual.adaptation = algorithm::calculate_fitness(&config, individual, tuples);
+            });
+            let left_out =
+                algorithm::inject_individuals(&config, &mut population, injected, tuples);
+            if left_out > 0 {
+                warn!(
+                    left_out,
+                    "More injected individuals than the population holds, the last ones are left out"
+                );
+            }
+            population
+        } else {
+            Vec::new()
+        };
+
+        let population_size_controller = (config.adaptive_population_size && holds_population)
+            .then(|| PopulationSizeController::new(&config, 1));
+        let hypermutation_controller = (config.hypermutation_stagnation_generations > 0)
+            .then(|| HypermutationController::new(&config));
+        let penalty_controller =
+            (config.penalty_adaptation_interval > 0).then(|| PenaltyController::new(&config));
+
+        let island_config = IslandConfig::from_config(&config);
+        let node_communicators = (config.parallel_model == ParallelModel::Island
+            && island_config.is_hierarchical())
+        .then(|| split_by_node(communicator));
+        if let Some(node_communicators) = node_communicators.as_ref().filter(|_| is_root) {
+            info!(
+                "Hierarchical migration over {} nodes",
+                node_communicators.peers.size()
+            );
+        }
+
+        let global_best = config
+            .global_best_window
+            .then(|| GlobalBest::new(communicator));
+
+        let topology_seed = match (config.parallel_model, island_config.topology) {
+            (ParallelModel::Island, MigrationTopology::Random) => {
+                mpi_execute_and_synchronize_at(|| rng.next_u64(), communicator, ROOT_RANK)
+            }
+            _ => 0,
+        };
+
+        GaRunner {
+            operators: Operators::from_config(&config),
+            island_config,
+            node_communicators,
+            lost_neighbours: BTreeMap::new(),
+            topology_seed,
+            global_best,
+            config,
+            tuples,
+            communicator,
+            rng,
+            population,
+            pool: IndividualPool::default(),
+            population_size_controller,
+            hypermutation_controller,
+            penalty_controller,
+        }
+    }
+
+    /// Evolve the population until `max_generations` or `target_fitness` is reached
+    ///
+    /// `stop_requested` is checked on root after every generation and broadcast as a stop token,
+    /// so all nodes finish the same generation and the population can still be gathered.
+    ///
+    /// With a `migration_timeout` islands run without any collective between migrations, so one
+    /// that hangs doesn't stall the others: each one stops on its own `stop_requested` or when its
+    /// own best adaptation reaches `target_fitness`, and logs and adapts to its local statistics.
+    /// The nodes still meet when the result is gathered after the run.
+    ///
+    /// Returns the number of generations run.
+    pub fn run(&mut self, stop_requested: &AtomicBool) -> usize {
+        let is_root = self.communicator.rank() == ROOT_RANK;
+        let independent = self.runs_independently();
+
+        let mut generations = 0;
+
+        for generation_number in 0..self.config.max_generations {
+            let _generation_span =
+                info_span!("generation", number = generation_number + 1).entered();
+
+            match self.config.parallel_model {
+                ParallelModel::Global => self.global_generation(generation_number),
+                ParallelModel::Island => self.island_generation(generation_number),
+                ParallelModel::MasterWorker => self.master_worker_generation(generation_number),
+            }
+            if self.config.immigrant_fraction > 0.0 && !self.population.is_empty() {
+                algorithm::add_random_immigrants(
+                    &self.config,
+                    &mut self.population,
+                    self.tuples,
+                    &mut self.rng,
+                );
+            }
+            if self.config.max_duplicate_copies > 0 && !self.population.is_empty() {
+                let duplicates = algorithm::deduplicate_population(
+                    &self.config,
+                    &mut self.population,
+                    self.tuples,
+                    &mut self.rng,
+                );
+                debug!(duplicates);
+            }
+            if self.config.local_search_elites > 0 && !self.population.is_empty() {
+                local_search::improve_elites(
+                    &self.config,
+                    &mut self.population,
+                    self.tuples,
+                    &mut self.rng,
+                );
+            }
+            let stats = self.population.stats();
+            aggregator::report_generation(generation_number + 1, &stats, &self.population);
+            let global_best_adaptation = match &self.global_best {
+                Some(global_best) => {
+                    global_best.offer(stats.best);
+                    global_best.read()
+                }
+                None if independent => stats.best,
+                None => all_reduce_max(stats.best, self.communicator),
+            };
+            generations += 1;
+
+            let interval = self.config.diversity_interval;
+            let diversity = (interval > 0 && (generation_number + 1) % interval == 0).then(|| {
+                if independent {
+                    self.population.diversity()
+                } else {
+                    self.global_diversity()
+                }
+            });
+            debug!(
+                local_best_adaptation = stats.best,
+                local_worst_adaptation = stats.worst,
+                local_mean_adaptation = stats.mean,
+                local_median_adaptation = stats.median,
+                local_std_dev_adaptation = stats.std_dev,
+                local_diversity = self.population.diversity()
+            );
+            if is_root {
+                info!(best_adaptation = global_best_adaptation, diversity);
+            }
+            let target_reached = global_best_adaptation >= self.config.target_fitness;
+            if independent {
+                if stop_requested.load(Ordering::SeqCst) {
+                    warn!(
+                        rank = self.communicator.rank(),
+                        "Stop requested, finishing after this generation"
+                    );
+                    break;
+                }
+                if target_reached {
+                    break;
+                }
+            } else {
+                // nodes may read different values from the window, so root decides for all of them
+                if target_reached && self.global_best.is_none() {
+                    break;
+                }
+
+                let stop = is_root && stop_requested.load(Ordering::SeqCst);
+                if broadcast_flag(
+                    stop || (is_root && target_reached),
+                    self.communicator,
+                    ROOT_RANK,
+                ) {
+                    if stop {
+                        warn!("Stop requested, finishing after this generation");
+                    }
+                    break;
+                }
+            }
+
+            self.adjust_population_size(global_best_adaptation);
+            self.adjust_mutation_probability(global_best_adaptation, is_root);
+            self.adjust_penalty_weights(is_root);
+        }
+
+        // adaptations of the result use the configured weights
+        if self.penalty_controller.is_some() {
+            self.config.penalty_multipliers.clear();
+            self.evaluate_population();
+        }
+
+        if !self.population.is_empty() {
+            debug!(stats = ?self.population.stats(), "Final population");
+        }
+
+        generations
+    }
+
+    /// Whether islands skip the collectives of every generation, with a `migration_timeout`
+    fn runs_independently(&self) -> bool {
+        self.config.parallel_model == ParallelModel::Island && self.island_config.timeout.is_some()
+    }
+
+    /// Individuals the offspring are written over, see [`IndividualPool`]
+    pub fn pool(&self) -> &IndividualPool {
+        &self.pool
+    }
+
+    /// Diversity of the populations of all nodes together, see [`PopulationMetrics::diversity`]
+    ///
+    /// Placement counts are summed across nodes, so this is the diversity of the union of the
+    /// populations rather than a mean of local diversities.
+    pub fn global_diversity(&self) -> f64 {
+        let genes: Vec<_> = self.tuples.iter().map(|tuple| tuple.id).collect();
+        let number_of_periods = self.config.number_of_periods;
+
+        let counts = self.population.placement_counts(&genes, number_of_periods);
+        let counts = all_reduce_sum(&counts, self.communicator);
+        placement_entropy(&counts, number_of_periods)
+    }
+
+    /// Gather the best individuals of every node at root, other nodes get an empty vector
+    ///
+    /// With `multi_objective` every node contributes its whole Pareto front, see [`pareto::front`].
+    pub fn gather_elites(&mut self) -> Vec<Individual> {
+        self.population
+            .sort_by_key(|individual| Reverse(individual.adaptation));
+        let count = self.config.elite_count.max(1).min(self.population.len());
+
+        let local_elites = if self.config.multi_objective {
+            pareto::front(&self.config, &self.population, self.tuples)
+                .into_iter()
+                .map(|point| point.individual)
+                .collect()
+        } else {
+            self.population[..count].to_vec()
+        };
+        let mut elites = gather_population(&local_elites, self.communicator, ROOT_RANK);
+        elites.sort_by_key(|individual| Reverse(individual.adaptation));
+        elites
+    }
+
+    /// Breed and evaluate a share of the offspring on every node from the population of root
+    ///
+    /// Root broadcasts its population, then gathers the offspring in rank order and replaces the
+    /// generation. Only root keeps the population between generations.
+    fn global_generation(&mut self, generation_number: usize) {
+        broadcast_population(&mut self.population, self.communicator, ROOT_RANK);
+
+        let shares = split_evenly(self.offspring_count(), self.communicator.size() as usize);
+        let share = shares[self.communicator.rank() as usize].len();
+        let (mut offspring, parents) = self.create_offspring(share);
+        offspring.par_iter_mut().for_each(|individual| {
+            individual.adaptation =
+                algorithm::calculate_fitness(&self.config, individual, self.tuples);
+        });
+
+        let mut offspring = gather_population(&offspring, self.communicator, ROOT_RANK);
+        let parents = gather_population(&parents, self.communicator, ROOT_RANK);
+        if self.communicator.rank() == ROOT_RANK {
+            self.accept_offspring(&mut offspring, parents, generation_number);
+            self.replace_generation(offspring);
+        } else {
+            let population = std::mem::take(&mut self.population);
+            self.pool.recycle(population);
+        }
+    }
+
+    /// Replace the local population with its offspring, migrating at the interval
+    ///
+    /// Migrants are picked from the parent generation by the [`IslandConfig`]. They travel while
+    /// the offspring are being created and evaluated and replace offspring on arrival.
+    fn island_generation(&mut self, generation_number: usize) {
+        let node_communicators = self.node_communicators.take();
+
+        match &node_communicators {
+            Some(node_communicators) if self.island_config.migrates_after(generation_number) => {
+                self.migrating_generation(
+                    &node_communicators.peers,
+                    MigrationScope::Peers,
+                    generation_number,
+                )
+            }
+            Some(node_communicators)
+                if self
+                    .island_config
+                    .migrates_within_node_after(generation_number) =>
+            {
+                self.migrating_generation(
+                    &node_communicators.node,
+                    MigrationScope::Node,
+                    generation_number,
+                )
+            }
+            None if self.island_config.migrates_after(generation_number) => self
+                .migrating_generation(self.communicator, MigrationScope::World, generation_number),
+            _ => {
+                let offspring = self.create_evaluated_offspring(generation_number);
+                self.replace_generation(offspring);
+            }
+        }
+
+        self.node_communicators = node_communicators;
+    }
+
+    /// Generation exchanging migrants with the neighbours in `communicator`
+    ///
+    /// Neighbours missing the `migration_timeout` are remembered as lost for the `scope`.
+    fn migrating_generation(
+        &mut self,
+        communicator: &impl Communicator<Raw = MPI_Comm>,
+        scope: MigrationScope,
+        generation_number: usize,
+    ) {
+        let size = communicator.size();
+        if size < 2 {
+            let offspring = self.create_evaluated_offspring(generation_number);
+            self.replace_generation(offspring);
+            return;
+        }
+
+        // the random topology is shuffled anew for every migration, the same way on all nodes
+        let seed = self.topology_seed.wrapping_add(generation_number as u64);
+        let mut neighbours =
+            neighbours(self.island_config.topology, communicator.rank(), size, seed);
+        if let Some(lost) = self.lost_neighbours.get(&scope) {
+            neighbours = neighbours.without(lost);
+        }
+
+        let emigrants = self
+            .island_config
+            .select_emigrants(&self.population, &mut self.rng);
+
+        let exchange = exchange_serde_while(
+            &emigrants,
+            &neighbours.destinations,
+            &neighbours.sources,
+            communicator,
+            self.island_config.timeout,
+            || self.create_evaluated_offspring(generation_number),
+        );
+        self.replace_generation(exchange.result);
+
+        let immigrants: Vec<Vec<Individual>> = exchange.values;
+        self.island_config.place_immigrants(
+            &mut self.population,
+            immigrants.into_iter().flatten().collect(),
+            &mut self.rng,
+        );
+
+        if !exchange.unresponsive.is_empty() {
+            warn!(
+                rank = communicator.rank(),
+                ?scope,
+                lost = ?exchange.unresponsive,
+                "Neighbours stopped answering, migrating without them"
+            );
+            self.lost_neighbours
+                .entry(scope)
+                .or_default()
+                .extend(exchange.unresponsive);
+        }
+    }
+
+    /// Number of children bred every generation, see `offspring_per_generation`
+    fn offspring_count(&self) -> usize {
+        self.config
+            .offspring_per_generation
+            .unwrap_or(self.population.len())
+    }
+
+    /// Create the children of a generation and calculate their fitness
+    fn create_evaluated_offspring(&mut self, generation_number: usize) -> Population {
+        let (mut offspring, parents) = self.create_offspring(self.offspring_count());
+        offspring.par_iter_mut().for_each(|individual| {
+            individual.adaptation =
+                algorithm::calculate_fitness(&self.config, individual, self.tuples);
+        });
+        self.accept_offspring(&mut offspring, parents, generation_number);
+        offspring
+    }
+
+    /// Apply simulated-annealing acceptance to evaluated offspring, if enabled
+    fn accept_offspring(
+        &mut self,
+        offspring: &mut Population,
+        parents: Vec<Individual>,
+        generation_number: usize,
+    ) {
+        if let Some(temperature) = annealing::temperature(&self.config, generation_number) {
+            annealing::accept_offspring(offspring, parents, temperature, &mut self.rng);
+        }
+    }
+
+    /// Breed offspring at root and let every node evaluate a chunk of them
+    fn master_worker_generation(&mut self, generation_number: usize) {
+        let (mut offspring, parents) = if self.communicator.rank() == ROOT_RANK {
+            self.create_offspring(self.offspring_count())
+        } else {
+            (Vec::new(), Vec::new())
+        };
+
+        let chunk = scatter_population(&offspring, self.communicator, ROOT_RANK);
+        let adaptations: Vec<i32> = chunk
+            .par_iter()
+            .map(|individual| algorithm::calculate_fitness(&self.config, individual, self.tuples))
+            .collect();
+        let adaptations = gather_serde(&adaptations, self.communicator, ROOT_RANK);
+
+        if self.communicator.rank() == ROOT_RANK {
+            for (individual, adaptation) in offspring.iter_mut().zip(adaptations) {
+                individual.adaptation = adaptation;
+            }
+            self.accept_offspring(&mut offspring, parents, generation_number);
+            self.replace_generation(offspring);
+        }
+    }
+
+    /// Replace the population with its evaluated offspring, by Pareto fronts with
+    /// `multi_objective`
+    ///
+    /// The individuals that don't survive go to the [`IndividualPool`].
+    fn replace_generation(&mut self, offspring: Population) {
+        let replaced = if self.config.multi_objective {
+            pareto::replace_generation(&self.config, &mut self.population, offspring, self.tuples)
+        } else {
+            algorithm::replace_generation(&mut self.population, offspring)
+        };
+        self.pool.recycle(replaced);
+    }
+
+    /// Create `count` children of the population, without evaluating them
+    ///
+    /// The children are written over individuals taken from the [`IndividualPool`].
+    ///
+    /// With simulated-annealing acceptance the better parent of every child is returned as well,
+    /// otherwise the parents are empty.
+    fn create_offspring(&mut self, count: usize) -> (Population, Vec<Individual>) {
+        // children are created in parallel, each with its own generator seeded from the node's
+        // one, so the result doesn't depend on thread scheduling
+        let child_seeds: Vec<u64> = (0..count).map(|_| self.rng.next_u64()).collect();
+
+        // with universal sampling all parents are selected up front in a single pass
+        let parent_pairs = self.config.universal_sampling.then(|| {
+            selection::universal_parent_pairs(&self.config, &self.population, count, &mut self.rng)
+        });
+
+        let annealing = self.config.initial_temperature.is_some();
+        let children = self.pool.take(count);
+
+        let (offspring, parents): (Population, Vec<Option<Individual>>) = child_seeds
+            .into_par_iter()
+            .zip(children)
+            .enumerate()
+            .map(|(child, (child_seed, mut individual))| {
+                let mut child_rng = get_random_generator(child_seed);
+                let better_parent = match &parent_pairs {
+                    Some(pairs) => {
+                        let (mother, father) = (
+                            &self.population[pairs[child].0],
+                            &self.population[pairs[child].1],
+                        );
+                        self.operators.breed_parents_into(
+                            &self.config,
+                            mother,
+                            father,
+                            self.tuples,
+                            &mut child_rng,
+                            &mut individual,
+                        );
+                        algorithm::better_of(mother, father)
+                    }
+                    None => self.operators.breed_into(
+                        &self.config,
+                        &self.population,
+                        self.tuples,
+                        &mut child_rng,
+                        &mut individual,
+                    ),
+                };
+
+                let parent = annealing.then(|| better_parent.clone());
+                (individual, parent)
+            })
+            .unzip();
+
+        (offspring, parents.into_iter().flatten().collect())
+    }
+
+    /// Raise or decay the mutation probability if hypermutation is enabled
+    ///
+    /// All nodes see the same global best adaptation, so they burst in the same generation.
+    fn adjust_mutation_probability(&mut self, best_adaptation: i32, is_root: bool) {
+        let Some(controller) = self.hypermutation_controller.as_mut() else {
+            return;
+        };
+
+        let burst = controller.observe(best_adaptation);
+        if burst && is_root {
+            info!(
+                "Best adaptation stagnated, raising mutation probability to {}",
+                controller.mutation_probability()
+            );
+        }
+        self.config.mutation_probability = controller.mutation_probability();
+    }
+
+    /// Re-weight the constraints if adaptive penalties are enabled
+    ///
+    /// The penalties of the best individual of every node are summed, so all nodes agree on the
+    /// multipliers, unless islands run independently. When they change the population is evaluated
+    /// again.
+    fn adjust_penalty_weights(&mut self, is_root: bool) {
+        let independent = self.runs_independently();
+        let Some(controller) = self.penalty_controller.as_mut() else {
+            return;
+        };
+
+        let fitness = Fitness::from_config(&self.config);
+        let names: Vec<&str> = fitness
+            .constraints
+            .iter()
+            .map(|constraint| constraint.name())
+            .collect();
+        let penalties: Vec<i32> = match self.population.iter().max_by_key(|i| i.adaptation) {
+            Some(best) => fitness
+                .penalties(&self.config, best, self.tuples)
+                .into_iter()
+                .map(|(_, penalty)| penalty)
+                .collect(),
+            None => vec![0; names.len()],
+        };
+        let penalties = if independent {
+            penalties
+        } else {
+            all_reduce_sum(&penalties, self.communicator)
+        };
+
+        if !controller.observe(names.into_iter().zip(penalties)) {
+            return;
+        }
+        self.config.penalty_multipliers = controller.multipliers().clone();
+        if is_root {
+            debug!(multipliers = ?self.config.penalty_multipliers, "Penalty weights adapted");
+        }
+        self.evaluate_population();
+    }
+
+    /// Calculate the fitness of the whole local population again
+    fn evaluate_population(&mut self) {
+        let (config, tuples) = (&self.config, self.tuples);
+        self.population.par_iter_mut().for_each(|individual| {
+            individual.adaptation = algorithm::calculate_fitness(config, individual, tuples);
+        });
+    }
+
+    /// Resize the local population if adaptive population size is enabled
+    fn adjust_population_size(&mut self, best_adaptation: i32) {
+        let Some(controller) = self.population_size_controller.as_mut() else {
+            return;
+        };
+
+        let new_size = controller.next_size(self.population.len(), best_adaptation);
+        if new_size != self.population.len() {
+            if self.communicator.rank() == ROOT_RANK {
+                info!(
+                    "Resizing population from {} to {}",
+                    self.population.len(),
+                    new_size
+                );
+            }
+            resize_population(
+                &self.config,
+                &mut self.population,
+                self.tuples,
+                new_size,
+                &mut self.rng,
+            );
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "mpi")))]
+mod tests {
+    use std::sync::{atomic::AtomicBool, Mutex};
+
+    use super::*;
+    use crate::{
+        algorithm::{datatypes::TupleId, random::rank_seed},
+        mpi::run_ranks,
+    };
+
+    /// Classes of three teachers and four groups sharing two rooms
+    fn tuples() -> Vec<Tuple> {
+        (1..=12)
+            .map(|id| Tuple {
+                id: TupleId(id),
+                label: format!("Zajęcia {}", id),
+                room: format!("R{}", id % 2),
+                teacher: format!("T{}", id % 3),
+                group: format!("G{}", id % 4),
+                ..Tuple::default()
+            })
+            .collect()
+    }
+
+    fn config() -> AlgorithmConfig {
+        AlgorithmConfig {
+            population_size: 30,
+            max_generations: 5,
+            migration_interval: 1,
+            migration_size: 2,
+            elite_count: 2,
+            target_fitness: 1,
+            ..AlgorithmConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_the_first_population_is_evaluated() {
+        let config = AlgorithmConfig {
+            parallel_model: ParallelModel::Island,
+            ..config()
+        };
+        let tuples = tuples();
+
+        run_ranks(2, |world| {
+            let rng = get_random_generator(rank_seed(1, world.rank() as u64));
+            let runner = GaRunner::new(&config, &tuples, &world, rng, Vec::new());
+
+            assert!(!runner.population.is_empty());
+            for individual in &runner.population {
+                assert_eq!(
+                    individual.adaptation,
+                    algorithm::calculate_fitness(&runner.config, individual, &tuples)
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn test_global_population_stays_on_root() {
+        let config = config();
+        let tuples = tuples();
+
+        run_ranks(3, |world| {
+            let rank = world.rank();
+            let rng = get_random_generator(rank_seed(1, rank as u64));
+            let mut runner = GaRunner::new(&config, &tuples, &world, rng, Vec::new());
+            assert_eq!(runner.run(&AtomicBool::new(false)), 5);
+
+            let expected = if rank == ROOT_RANK { 30 } else { 0 };
+            assert_eq!(runner.population.len(), expected);
+            let elites = runner.gather_elites();
+            assert_eq!(elites.len(), expected.min(2));
+        });
+    }
+
+    #[test]
+    fn test_islands_stop_when_any_of_them_reaches_the_target() {
+        let config = AlgorithmConfig {
+            parallel_model: ParallelModel::Island,
+            migration_interval: 0,
+            offspring_per_generation: Some(3),
+            ..config()
+        };
+        let tuples = tuples();
+
+        run_ranks(3, |world| {
+            let rank = world.rank();
+            let rng = get_random_generator(rank_seed(1, rank as u64));
+            let mut runner = GaRunner::new(&config, &tuples, &world, rng, Vec::new());
+            // only the last island holds an individual reaching the target, the best parents
+            // survive the single child of every generation
+            if rank == 2 {
+                runner.population[0].adaptation = config.target_fitness;
+            }
+
+            assert_eq!(runner.run(&AtomicBool::new(false)), 1);
+            let local_best = runner.population.stats().best;
+            assert_eq!(local_best >= config.target_fitness, rank == 2);
+        });
+    }
+
+    #[test]
+    fn test_elites_of_all_islands_are_gathered_best_first() {
+        let config = AlgorithmConfig {
+            parallel_model: ParallelModel::Island,
+            ..config()
+        };
+        let tuples = tuples();
+
+        run_ranks(3, |world| {
+            let rank = world.rank();
+            let rng = get_random_generator(rank_seed(1, rank as u64));
+            let mut runner = GaRunner::new(&config, &tuples, &world, rng, Vec::new());
+            // island r holds the adaptations -r, -r - 3, -r - 6, ...
+            for (index, individual) in runner.population.iter_mut().enumerate() {
+                individual.adaptation = -rank - 3 * index as i32;
+            }
+
+            let adaptations: Vec<i32> = runner
+                .gather_elites()
+                .iter()
+                .map(|individual| individual.adaptation)
+                .collect();
+            if rank == ROOT_RANK {
+                assert_eq!(adaptations, [0, -1, -2, -3, -4, -5]);
+            } else {
+                assert!(adaptations.is_empty());
+            }
+        });
+    }
+
+    #[test]
+    fn test_migrants_arrive_while_the_offspring_are_bred() {
+        let config = AlgorithmConfig {
+            parallel_model: ParallelModel::Island,
+            max_generations: 1,
+            migration_size: 1,
+            ..config()
+        };
+        let tuples = tuples();
+
+        run_ranks(3, |world| {
+            let rank = world.rank();
+            let rng = get_random_generator(rank_seed(1, rank as u64));
+            let mut runner = GaRunner::new(&config, &tuples, &world, rng, Vec::new());
+            // every island marks its evaluated emigrant, no offspring come close to the marks
+            runner.population[0].adaptation = 1000 + rank;
+
+            assert_eq!(runner.run(&AtomicBool::new(false)), 1);
+            let source = (rank + 2) % 3;
+            let marks: Vec<i32> = runner
+                .population
+                .iter()
+                .map(|individual| individual.adaptation)
+                .filter(|adaptation| *adaptation >= 1000)
+                .collect();
+            // the offspring replaced the whole generation, the emigrant of the ring's source
+            // took the place of the worst child
+            assert_eq!(marks, [1000 + source]);
+        });
+    }
+
+    #[test]
+    fn test_runs_with_the_same_seed_agree() {
+        let config = AlgorithmConfig {
+            parallel_model: ParallelModel::Island,
+            migration_topology: MigrationTopology::Random,
+            ..config()
+        };
+        let tuples = tuples();
+        let best_of = |seed: u64| {
+            let best = Mutex::new(None);
+            run_ranks(3, |world| {
+                let rng = get_random_generator(rank_seed(seed, world.rank() as u64));
+                let mut runner = GaRunner::new(&config, &tuples, &world, rng, Vec::new());
+                runner.run(&AtomicBool::new(false));
+                if let Some(elite) = runner.gather_elites().first() {
+                    *best.lock().unwrap() = Some((elite.adaptation, elite.placement_hash()));
+                }
+            });
+            best.into_inner().unwrap().unwrap()
+        };
+
+        assert_eq!(best_of(7), best_of(7));
+    }
+
+    #[test]
+    fn test_a_stop_requested_on_root_finishes_every_rank() {
+        let tuples = tuples();
+
+        for parallel_model in [ParallelModel::Global, ParallelModel::Island] {
+            let config = AlgorithmConfig {
+                parallel_model,
+                ..config()
+            };
+            run_ranks(3, |world| {
+                let rank = world.rank();
+                let rng = get_random_generator(rank_seed(1, rank as u64));
+                let mut runner = GaRunner::new(&config, &tuples, &world, rng, Vec::new());
+                // only root handles the signal, the others learn about it from the stop token
+                let stop_requested = AtomicBool::new(rank == ROOT_RANK);
+
+                assert_eq!(runner.run(&stop_requested), 1);
+                assert_eq!(runner.gather_elites().is_empty(), rank != ROOT_RANK);
+            });
+        }
+    }
+
+    #[test]
+    fn test_islands_with_a_timeout_run_without_a_hung_rank() {
+        let config = AlgorithmConfig {
+            parallel_model: ParallelModel::Island,
+            migration_timeout: 0.05,
+            ..config()
+        };
+        let tuples = tuples();
+
+        run_ranks(3, |world| {
+            let rank = world.rank();
+            let rng = get_random_generator(rank_seed(1, rank as u64));
+            let mut runner = GaRunner::new(&config, &tuples, &world, rng, Vec::new());
+            // rank 2 hangs after setting up, the others must not wait for it every generation
+            if rank == 2 {
+                return;
+            }
+
+            assert_eq!(runner.run(&AtomicBool::new(false)), 5);
+            let lost = runner.lost_neighbours.get(&MigrationScope::World);
+            assert_eq!(lost.is_some(), rank == 0);
+        });
+    }
+}
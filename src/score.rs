@@ -0,0 +1,231 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use itertools::Itertools;
+use rayon::prelude::*;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::algorithm::{
+    calculate_fitness,
+    config::AlgorithmConfig,
+    conflicts::ConflictMatrix,
+    datatypes::{Gene, Individual, Tuple},
+};
+
+/// Extensions of files treated as schedules when scoring a directory
+const SCHEDULE_EXTENSIONS: [&str; 2] = ["csv", "json"];
+
+/// Row of the ranked scoring report
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct ScoreRecord {
+    pub rank: usize,
+    pub file: String,
+    pub adaptation: i32,
+}
+
+/// Evaluate every schedule file in a directory against the given tuples
+///
+/// Files are scored in parallel and returned ranked by adaptation, best first. Files that can't
+/// be loaded or don't place every tuple exactly once are reported and skipped, see
+/// [`placement_problem`].
+pub fn score_schedules(
+    dir: impl AsRef<Path>,
+    config: &AlgorithmConfig,
+    tuples: &[Tuple],
+) -> std::io::Result<Vec<ScoreRecord>> {
+    let paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| SCHEDULE_EXTENSIONS.contains(&ext))
+        })
+        .collect();
+
+    let with_matrix;
+    let config = if config.assigns_resources() {
+        config
+    } else {
+        with_matrix = config
+            .clone()
+            .with_conflict_matrix(ConflictMatrix::new(config, tuples));
+        &with_matrix
+    };
+    let mut scores: Vec<(String, i32)> = paths
+        .par_iter()
+        .filter_map(|path| {
+            let individual = match Individual::from_file(path, config.number_of_periods) {
+                Ok(individual) => individual,
+                Err(err) => {
+                    warn!("Skipping {}: {}", path.display(), err);
+                    return None;
+                }
+            };
+
+            if let Some(problem) = placement_problem(&individual, tuples) {
+                warn!("Skipping {}: {}", path.display(), problem);
+                return None;
+            }
+
+            let adaptation = calculate_fitness(config, &individual, tuples);
+            Some((path.display().to_string(), adaptation))
+        })
+        .collect();
+
+    scores.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    Ok(scores
+        .into_iter()
+        .enumerate()
+        .map(|(index, (file, adaptation))| ScoreRecord {
+            rank: index + 1,
+            file,
+            adaptation,
+        })
+        .collect())
+}
+
+/// What keeps a schedule from placing every tuple exactly once: its unknown, missing and
+/// duplicated tuple ids. An empty or partial schedule has no clashes and would outscore the
+/// complete ones
+fn placement_problem(individual: &Individual, tuples: &[Tuple]) -> Option<String> {
+    let mut placements: HashMap<Gene, usize> = HashMap::new();
+    for gene in individual
+        .chromosomes
+        .iter()
+        .flat_map(|chromosome| chromosome.genes.iter())
+    {
+        *placements.entry(*gene).or_default() += 1;
+    }
+    let known_genes: HashSet<Gene> = tuples.iter().map(|tuple| tuple.id).collect();
+
+    let mut unknown: Vec<Gene> = placements
+        .keys()
+        .filter(|gene| !known_genes.contains(gene))
+        .copied()
+        .collect();
+    let mut duplicated: Vec<Gene> = placements
+        .iter()
+        .filter(|(gene, &count)| count > 1 && known_genes.contains(gene))
+        .map(|(gene, _)| *gene)
+        .collect();
+    let missing: Vec<Gene> = tuples
+        .iter()
+        .map(|tuple| tuple.id)
+        .filter(|gene| !placements.contains_key(gene))
+        .collect();
+    unknown.sort();
+    duplicated.sort();
+
+    let problems: Vec<String> = [
+        ("unknown", unknown),
+        ("missing", missing),
+        ("duplicated", duplicated),
+    ]
+    .into_iter()
+    .filter(|(_, genes)| !genes.is_empty())
+    .map(|(kind, genes)| format!("{} tuple ids {}", kind, genes.iter().join(", ")))
+    .collect();
+    (!problems.is_empty()).then(|| problems.join("; "))
+}
+
+/// Write the ranked scores as CSV
+pub fn write_scores(records: &[ScoreRecord], path: impl AsRef<Path>) -> Result<(), csv::Error> {
+    let mut writer = csv::Writer::from_path(path)?;
+
+    for record in records {
+        writer.serialize(record)?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::datatypes::{Chromosome, PeriodId, TupleId};
+    use smallvec::smallvec;
+
+    fn tuples() -> Vec<Tuple> {
+        (1..=3)
+            .map(|id| Tuple {
+                id: TupleId(id),
+                label: "Wykład".to_string(),
+                room: format!("Room {}", id),
+                teacher: format!("Teacher {}", id),
+                ..Tuple::default()
+            })
+            .collect()
+    }
+
+    /// Files scored next to a complete schedule when a file with `content` is added
+    fn scored_with(name: &str, content: &str) -> Vec<String> {
+        let config = AlgorithmConfig {
+            number_of_periods: 2,
+            ..AlgorithmConfig::default()
+        };
+        let dir = std::env::temp_dir().join(format!("planner_score_test_{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("complete.csv"), "Period,TupleId\n0,1\n0,2\n1,3\n").unwrap();
+        fs::write(dir.join(format!("{}.csv", name)), content).unwrap();
+
+        let scores = score_schedules(&dir, &config, &tuples()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        scores
+            .into_iter()
+            .map(|record| {
+                Path::new(&record.file)
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_empty_schedules_are_skipped() {
+        assert_eq!(scored_with("empty", ""), ["complete.csv"]);
+    }
+
+    #[test]
+    fn test_partial_schedules_are_skipped() {
+        assert_eq!(
+            scored_with("partial", "Period,TupleId\n0,1\n"),
+            ["complete.csv"]
+        );
+    }
+
+    #[test]
+    fn test_schedules_with_duplicates_are_skipped() {
+        assert_eq!(
+            scored_with("duplicates", "Period,TupleId\n0,1\n0,2\n1,3\n1,2\n"),
+            ["complete.csv"]
+        );
+
+        let individual = Individual::with_chromosomes(vec![
+            Chromosome {
+                id: PeriodId(0),
+                genes: smallvec![TupleId(1)],
+            },
+            Chromosome {
+                id: PeriodId(1),
+                genes: smallvec![TupleId(1), TupleId(7)],
+            },
+        ]);
+        assert_eq!(
+            placement_problem(&individual, &tuples()).as_deref(),
+            Some("unknown tuple ids 7; missing tuple ids 2, 3; duplicated tuple ids 1")
+        );
+    }
+}
@@ -0,0 +1,957 @@
+use std::{
+    any::Any,
+    collections::VecDeque,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Condvar, Mutex},
+    thread,
+};
+
+use self::{point_to_point::Status, topology::SimpleCommunicator};
+
+pub type Rank = i32;
+pub type Count = i32;
+pub type Tag = i32;
+
+/// Tag of the messages of collectives, user tags are never negative
+const COLLECTIVE_TAG: Tag = -1;
+
+/// Run `work` on `ranks` threads, each getting its own world of all of them
+///
+/// A panicking rank exits the process, as the others would wait for its messages forever.
+pub fn run_ranks(ranks: usize, work: impl Fn(SimpleCommunicator) + Sync) {
+    let group = Group::new(ranks);
+
+    thread::scope(|scope| {
+        for rank in 0..ranks {
+            let world = SimpleCommunicator(Endpoint::new(rank as Rank, &group));
+            let work = &work;
+            thread::Builder::new()
+                .name(format!("rank-{}", rank))
+                .spawn_scoped(scope, move || {
+                    if panic::catch_unwind(AssertUnwindSafe(|| work(world))).is_err() {
+                        std::process::exit(101);
+                    }
+                })
+                .expect("Could not spawn the thread of a rank");
+        }
+    });
+}
+
+struct Message {
+    source: Rank,
+    tag: Tag,
+    payload: Box<dyn Any + Send>,
+}
+
+/// Messages sent to a rank and not received yet, in the order they were sent
+#[derive(Default)]
+struct Mailbox {
+    messages: Mutex<VecDeque<Message>>,
+    arrived: Condvar,
+}
+
+/// Mailboxes of all ranks of a communicator
+struct Group {
+    mailboxes: Vec<Mailbox>,
+}
+
+impl Group {
+    fn new(size: usize) -> Arc<Group> {
+        Arc::new(Group {
+            mailboxes: (0..size).map(|_| Mailbox::default()).collect(),
+        })
+    }
+}
+
+/// A rank of a communicator, sending messages to the mailboxes of the others
+#[derive(Clone)]
+pub struct Endpoint {
+    rank: Rank,
+    group: Arc<Group>,
+}
+
+impl Endpoint {
+    fn new(rank: Rank, group: &Arc<Group>) -> Self {
+        Endpoint {
+            rank,
+            group: Arc::clone(group),
+        }
+    }
+
+    fn size(&self) -> Rank {
+        self.group.mailboxes.len() as Rank
+    }
+
+    /// Send without waiting, messages are buffered until received
+    fn send<T: Any + Send>(&self, destination: Rank, tag: Tag, payload: T) {
+        let mailbox = &self.group.mailboxes[destination as usize];
+        mailbox.messages.lock().unwrap().push_back(Message {
+            source: self.rank,
+            tag,
+            payload: Box::new(payload),
+        });
+        mailbox.arrived.notify_all();
+    }
+
+    /// Wait for the first message matching the source and tag, any by default
+    ///
+    /// Like in MPI, receiving from any tag only matches user messages.
+    fn receive<T: Any>(&self, source: Option<Rank>, tag: Option<Tag>) -> (T, Status) {
+        let mailbox = &self.group.mailboxes[self.rank as usize];
+        let mut messages = mailbox.messages.lock().unwrap();
+        loop {
+            if let Some(received) = take(&mut messages, source, tag) {
+                return received;
+            }
+            messages = mailbox.arrived.wait(messages).unwrap();
+        }
+    }
+
+    /// Like [`receive`](Endpoint::receive), but `None` instead of waiting
+    fn try_receive<T: Any>(&self, source: Option<Rank>, tag: Option<Tag>) -> Option<(T, Status)> {
+        let mailbox = &self.group.mailboxes[self.rank as usize];
+        take(&mut mailbox.messages.lock().unwrap(), source, tag)
+    }
+
+    fn probe(&self, source: Option<Rank>, tag: Option<Tag>) -> Option<Status> {
+        let mailbox = &self.group.mailboxes[self.rank as usize];
+        let messages = mailbox.messages.lock().unwrap();
+        messages
+            .iter()
+            .find(|message| matches(message, source, tag))
+            .map(|message| Status(message.source))
+    }
+
+    /// Ranks of the other members of the communicator
+    fn others(&self) -> impl Iterator<Item = Rank> + '_ {
+        (0..self.size()).filter(move |rank| *rank != self.rank)
+    }
+
+    /// Endpoint in the communicator of the ranks with the same color, ordered by key then rank
+    ///
+    /// Rank 0 collects the colors and creates the groups.
+    fn split(&self, color: Option<i32>, key: Rank) -> Option<Endpoint> {
+        if self.rank != 0 {
+            self.send(0, COLLECTIVE_TAG, (color, key));
+            return self.receive(Some(0), Some(COLLECTIVE_TAG)).0;
+        }
+
+        let mut requests = vec![(color, key, 0)];
+        for rank in self.others() {
+            let ((color, key), _) =
+                self.receive::<(Option<i32>, Rank)>(Some(rank), Some(COLLECTIVE_TAG));
+            requests.push((color, key, rank));
+        }
+
+        let mut endpoints: Vec<Option<Endpoint>> = vec![None; self.size() as usize];
+        let mut colors: Vec<i32> = requests.iter().filter_map(|(color, _, _)| *color).collect();
+        colors.sort_unstable();
+        colors.dedup();
+        for color in colors {
+            let mut members: Vec<(Rank, Rank)> = requests
+                .iter()
+                .filter(|request| request.0 == Some(color))
+                .map(|(_, key, rank)| (*key, *rank))
+                .collect();
+            members.sort_unstable();
+
+            let group = Group::new(members.len());
+            for (new_rank, (_, rank)) in members.into_iter().enumerate() {
+                endpoints[rank as usize] = Some(Endpoint::new(new_rank as Rank, &group));
+            }
+        }
+
+        for rank in self.others() {
+            self.send(rank, COLLECTIVE_TAG, endpoints[rank as usize].take());
+        }
+        endpoints[0].take()
+    }
+}
+
+/// Remove the first matching message from the mailbox
+fn take<T: Any>(
+    messages: &mut VecDeque<Message>,
+    source: Option<Rank>,
+    tag: Option<Tag>,
+) -> Option<(T, Status)> {
+    let position = messages
+        .iter()
+        .position(|message| matches(message, source, tag))?;
+    let message = messages.remove(position).unwrap();
+    let payload = message.payload.downcast::<T>().unwrap_or_else(|_| {
+        panic!(
+            "Message from rank {} isn't a {}",
+            message.source,
+            std::any::type_name::<T>()
+        )
+    });
+    Some((*payload, Status(message.source)))
+}
+
+fn matches(message: &Message, source: Option<Rank>, tag: Option<Tag>) -> bool {
+    source.is_none_or(|source| message.source == source)
+        && match tag {
+            Some(tag) => message.tag == tag,
+            None => message.tag >= 0,
+        }
+}
+
+pub mod ffi {
+    /// Handle of a communicator, unused as communicators are never passed to MPI
+    #[allow(non_camel_case_types)]
+    pub type MPI_Comm = ();
+}
+
+pub mod raw {
+    /// Only the associated type, which the helpers name in their bounds
+    pub trait AsRaw {
+        type Raw;
+    }
+}
+
+pub mod datatype {
+    use std::borrow::Borrow;
+
+    use super::{collective::SystemOperation, Count};
+
+    /// Types sent by value between the ranks
+    pub trait Equivalence: Copy + Send + 'static {
+        /// Combine the values of two ranks, only numbers can be reduced
+        fn reduce(self, _other: Self, _operation: SystemOperation) -> Self {
+            panic!(
+                "Values of {} can't be reduced",
+                std::any::type_name::<Self>()
+            )
+        }
+    }
+
+    macro_rules! numbers {
+        ($($number:ty),*) => {$(
+            impl Equivalence for $number {
+                fn reduce(self, other: Self, operation: SystemOperation) -> Self {
+                    match operation {
+                        SystemOperation::Max => self.max(other),
+                        SystemOperation::Sum => self + other,
+                    }
+                }
+            }
+        )*};
+    }
+
+    numbers!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64);
+
+    impl Equivalence for bool {}
+
+    /// Values sent by a collective
+    pub trait Buffer {
+        type Item: Equivalence;
+        fn items(&self) -> &[Self::Item];
+    }
+
+    /// Values received by a collective
+    pub trait BufferMut {
+        type Item: Equivalence;
+        fn items_mut(&mut self) -> &mut [Self::Item];
+    }
+
+    impl<T: Equivalence> Buffer for T {
+        type Item = T;
+        fn items(&self) -> &[T] {
+            std::slice::from_ref(self)
+        }
+    }
+
+    impl<T: Equivalence> Buffer for [T] {
+        type Item = T;
+        fn items(&self) -> &[T] {
+            self
+        }
+    }
+
+    impl<T: Equivalence> BufferMut for T {
+        type Item = T;
+        fn items_mut(&mut self) -> &mut [T] {
+            std::slice::from_mut(self)
+        }
+    }
+
+    impl<T: Equivalence> BufferMut for [T] {
+        type Item = T;
+        fn items_mut(&mut self) -> &mut [T] {
+            self
+        }
+    }
+
+    /// Buffer divided into the parts of every rank by `counts` and `displs`
+    pub struct Partition<'b, B: ?Sized, C, D> {
+        buffer: &'b B,
+        counts: C,
+        displs: D,
+    }
+
+    impl<'b, B: ?Sized + Buffer, C: Borrow<[Count]>, D: Borrow<[Count]>> Partition<'b, B, C, D> {
+        pub fn new(buffer: &'b B, counts: C, displs: D) -> Self {
+            Partition {
+                buffer,
+                counts,
+                displs,
+            }
+        }
+
+        pub(super) fn part(&self, rank: usize) -> &[B::Item] {
+            let start = self.displs.borrow()[rank] as usize;
+            &self.buffer.items()[start..start + self.counts.borrow()[rank] as usize]
+        }
+    }
+
+    pub struct PartitionMut<'b, B: ?Sized, C, D> {
+        buffer: &'b mut B,
+        counts: C,
+        displs: D,
+    }
+
+    impl<'b, B: ?Sized + BufferMut, C: Borrow<[Count]>, D: Borrow<[Count]>> PartitionMut<'b, B, C, D> {
+        pub fn new(buffer: &'b mut B, counts: C, displs: D) -> Self {
+            PartitionMut {
+                buffer,
+                counts,
+                displs,
+            }
+        }
+
+        pub(super) fn part_mut(&mut self, rank: usize) -> &mut [B::Item] {
+            let start = self.displs.borrow()[rank] as usize;
+            &mut self.buffer.items_mut()[start..start + self.counts.borrow()[rank] as usize]
+        }
+    }
+}
+
+pub mod request {
+    use std::{cell::Cell, marker::PhantomData};
+
+    use super::point_to_point::Status;
+
+    pub struct LocalScope<'a>(PhantomData<&'a ()>);
+
+    #[derive(Debug, Clone, Copy)]
+    pub struct StaticScope;
+
+    pub trait Scope<'a> {}
+
+    impl<'a> Scope<'a> for &LocalScope<'a> {}
+
+    impl Scope<'static> for StaticScope {}
+
+    pub fn scope<'a, F, R>(work: F) -> R
+    where
+        F: FnOnce(&LocalScope<'a>) -> R,
+    {
+        work(&LocalScope(PhantomData))
+    }
+
+    /// Finishes an operation, blocking when passed `true` and returning `None` when it can't yet
+    pub(super) type Completion<'a> = Box<dyn FnMut(bool) -> Option<Status> + 'a>;
+
+    /// Operation completed by [`wait`](Request::wait) or [`test`](Request::test)
+    ///
+    /// Sends complete at once as messages are buffered, receives when waited for.
+    pub struct Request<'a, D: ?Sized, S = &'a LocalScope<'a>> {
+        completion: Option<Completion<'a>>,
+        cancelled: Cell<bool>,
+        marker: PhantomData<(&'a D, S)>,
+    }
+
+    impl<'a, D: ?Sized, S> Request<'a, D, S> {
+        pub(super) fn new(completion: Option<Completion<'a>>) -> Self {
+            Request {
+                completion,
+                cancelled: Cell::new(false),
+                marker: PhantomData,
+            }
+        }
+
+        pub fn wait(mut self) -> Status {
+            self.complete(true)
+                .expect("Blocking completions always finish")
+        }
+
+        /// The status if the operation is done, otherwise the request back
+        pub fn test(mut self) -> Result<Status, Self> {
+            self.complete(false).ok_or(self)
+        }
+
+        /// Give up the operation, waiting for it afterwards returns at once
+        pub fn cancel(&self) {
+            self.cancelled.set(true);
+        }
+
+        fn complete(&mut self, block: bool) -> Option<Status> {
+            match &mut self.completion {
+                Some(completion) if !self.cancelled.get() => completion(block),
+                _ => Some(Status(0)),
+            }
+        }
+    }
+}
+
+pub mod point_to_point {
+    use super::{
+        datatype::{Buffer, BufferMut, Equivalence},
+        request::{self, Request, Scope},
+        topology::{AnyProcess, Process},
+        Endpoint, Rank, Tag,
+    };
+
+    #[derive(Debug, Clone, Copy)]
+    pub struct Status(pub(super) Rank);
+
+    impl Status {
+        pub fn source_rank(&self) -> Rank {
+            self.0
+        }
+    }
+
+    /// Process messages are received from, a specific one or any
+    pub trait Source {
+        fn endpoint(&self) -> &Endpoint;
+
+        /// Rank of the process, `None` for any
+        fn source_rank(&self) -> Option<Rank>;
+
+        fn immediate_probe_with_tag(&self, tag: Tag) -> Option<Status> {
+            self.endpoint().probe(self.source_rank(), Some(tag))
+        }
+
+        fn receive<Msg: Equivalence>(&self) -> (Msg, Status) {
+            let (message, status) = self
+                .endpoint()
+                .receive::<Vec<Msg>>(self.source_rank(), None);
+            (message[0], status)
+        }
+
+        fn receive_with_tag<Msg: Equivalence>(&self, tag: Tag) -> (Msg, Status) {
+            let (message, status) = self
+                .endpoint()
+                .receive::<Vec<Msg>>(self.source_rank(), Some(tag));
+            (message[0], status)
+        }
+
+        fn receive_into<Buf: ?Sized + BufferMut>(&self, buffer: &mut Buf) -> Status {
+            let (message, status) = self
+                .endpoint()
+                .receive::<Vec<Buf::Item>>(self.source_rank(), None);
+            buffer.items_mut().copy_from_slice(&message);
+            status
+        }
+
+        fn immediate_receive_into<'a, Sc: Scope<'a>, Buf: ?Sized + BufferMut>(
+            &self,
+            _scope: Sc,
+            buffer: &'a mut Buf,
+        ) -> Request<'a, Buf, Sc> {
+            let endpoint = self.endpoint().clone();
+            let source = self.source_rank();
+            Request::new(Some(Box::new(move |block| {
+                let (message, status) = if block {
+                    endpoint.receive::<Vec<Buf::Item>>(source, None)
+                } else {
+                    endpoint.try_receive::<Vec<Buf::Item>>(source, None)?
+                };
+                buffer.items_mut().copy_from_slice(&message);
+                Some(status)
+            })))
+        }
+    }
+
+    impl Source for Process<'_> {
+        fn endpoint(&self) -> &Endpoint {
+            self.endpoint()
+        }
+
+        fn source_rank(&self) -> Option<Rank> {
+            Some(self.rank())
+        }
+    }
+
+    impl Source for AnyProcess<'_> {
+        fn endpoint(&self) -> &Endpoint {
+            self.endpoint()
+        }
+
+        fn source_rank(&self) -> Option<Rank> {
+            None
+        }
+    }
+
+    pub trait Destination {
+        fn send<Buf: ?Sized + Buffer>(&self, buffer: &Buf) {
+            request::scope(|scope| self.immediate_send(scope, buffer).wait());
+        }
+
+        fn immediate_send<'a, Sc: Scope<'a>, Buf: ?Sized + Buffer>(
+            &self,
+            scope: Sc,
+            buffer: &'a Buf,
+        ) -> Request<'a, Buf, Sc> {
+            self.immediate_send_with_tag(scope, buffer, 0)
+        }
+
+        fn immediate_send_with_tag<'a, Sc: Scope<'a>, Buf: ?Sized + Buffer>(
+            &self,
+            scope: Sc,
+            buffer: &'a Buf,
+            tag: Tag,
+        ) -> Request<'a, Buf, Sc>;
+    }
+
+    impl Destination for Process<'_> {
+        fn immediate_send_with_tag<'a, Sc: Scope<'a>, Buf: ?Sized + Buffer>(
+            &self,
+            _scope: Sc,
+            buffer: &'a Buf,
+            tag: Tag,
+        ) -> Request<'a, Buf, Sc> {
+            self.endpoint()
+                .send(self.rank(), tag, buffer.items().to_vec());
+            Request::new(None)
+        }
+    }
+}
+
+pub mod collective {
+    use std::borrow::Borrow;
+
+    use super::{
+        datatype::{Buffer, BufferMut, Equivalence, Partition, PartitionMut},
+        topology::{Communicator, Process},
+        Count, COLLECTIVE_TAG,
+    };
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum SystemOperation {
+        Max,
+        Sum,
+    }
+
+    impl SystemOperation {
+        pub fn max() -> Self {
+            SystemOperation::Max
+        }
+
+        pub fn sum() -> Self {
+            SystemOperation::Sum
+        }
+    }
+
+    /// Collectives of all ranks, reduced on rank 0
+    pub trait CommunicatorCollectives: Communicator {
+        fn all_reduce_into<S, R>(&self, send: &S, receive: &mut R, operation: SystemOperation)
+        where
+            S: ?Sized + Buffer,
+            R: ?Sized + BufferMut<Item = S::Item>,
+        {
+            let endpoint = self.endpoint();
+            let mut values = send.items().to_vec();
+
+            if endpoint.rank == 0 {
+                for rank in endpoint.others() {
+                    let (other, _) =
+                        endpoint.receive::<Vec<S::Item>>(Some(rank), Some(COLLECTIVE_TAG));
+                    for (value, other) in values.iter_mut().zip(other) {
+                        *value = value.reduce(other, operation);
+                    }
+                }
+                for rank in endpoint.others() {
+                    endpoint.send(rank, COLLECTIVE_TAG, values.clone());
+                }
+            } else {
+                endpoint.send(0, COLLECTIVE_TAG, values);
+                values = endpoint.receive(Some(0), Some(COLLECTIVE_TAG)).0;
+            }
+
+            receive.items_mut().copy_from_slice(&values);
+        }
+    }
+
+    impl<C: Communicator> CommunicatorCollectives for C {}
+
+    /// Collectives rooted at a process, called by all ranks
+    pub trait Root {
+        fn broadcast_into<Buf: ?Sized + BufferMut>(&self, buffer: &mut Buf);
+
+        fn gather_into<S: ?Sized + Buffer>(&self, send: &S);
+
+        fn gather_into_root<S, R>(&self, send: &S, receive: &mut R)
+        where
+            S: ?Sized + Buffer,
+            R: ?Sized + BufferMut<Item = S::Item>;
+
+        fn gather_varcount_into<S: ?Sized + Buffer>(&self, send: &S) {
+            self.gather_into(send)
+        }
+
+        fn gather_varcount_into_root<S, B, C, D>(
+            &self,
+            send: &S,
+            receive: &mut PartitionMut<'_, B, C, D>,
+        ) where
+            S: ?Sized + Buffer,
+            B: ?Sized + BufferMut<Item = S::Item>,
+            C: Borrow<[Count]>,
+            D: Borrow<[Count]>;
+
+        fn scatter_into<R: ?Sized + BufferMut>(&self, receive: &mut R);
+
+        fn scatter_into_root<S, R>(&self, send: &S, receive: &mut R)
+        where
+            S: ?Sized + Buffer,
+            R: ?Sized + BufferMut<Item = S::Item>;
+
+        fn scatter_varcount_into<R: ?Sized + BufferMut>(&self, receive: &mut R) {
+            self.scatter_into(receive)
+        }
+
+        fn scatter_varcount_into_root<B, C, D, R>(
+            &self,
+            send: &Partition<'_, B, C, D>,
+            receive: &mut R,
+        ) where
+            B: ?Sized + Buffer,
+            C: Borrow<[Count]>,
+            D: Borrow<[Count]>,
+            R: ?Sized + BufferMut<Item = B::Item>;
+    }
+
+    impl Process<'_> {
+        /// Receive the part of a collective sent by this root
+        fn receive_from_root<T: Equivalence>(&self, receive: &mut [T]) {
+            let (values, _) = self
+                .endpoint()
+                .receive::<Vec<T>>(Some(self.rank()), Some(COLLECTIVE_TAG));
+            receive.copy_from_slice(&values);
+        }
+
+        /// Send the parts of a scatter to all other ranks, returning the part of this root
+        fn send_parts<'p, T: Equivalence>(&self, part: impl Fn(usize) -> &'p [T]) -> &'p [T] {
+            let endpoint = self.endpoint();
+            for rank in endpoint.others() {
+                endpoint.send(rank, COLLECTIVE_TAG, part(rank as usize).to_vec());
+            }
+            part(self.rank() as usize)
+        }
+
+        /// Receive the parts of a gather from every rank, in rank order
+        fn gather_parts<T: Equivalence>(&self, own: &[T], mut store: impl FnMut(usize, &[T])) {
+            let endpoint = self.endpoint();
+            for rank in 0..endpoint.size() {
+                if rank == self.rank() {
+                    store(rank as usize, own);
+                } else {
+                    let (values, _) = endpoint.receive::<Vec<T>>(Some(rank), Some(COLLECTIVE_TAG));
+                    store(rank as usize, &values);
+                }
+            }
+        }
+    }
+
+    impl Root for Process<'_> {
+        fn broadcast_into<Buf: ?Sized + BufferMut>(&self, buffer: &mut Buf) {
+            if self.endpoint().rank == self.rank() {
+                let values = buffer.items_mut();
+                self.send_parts(|_| &*values);
+            } else {
+                self.receive_from_root(buffer.items_mut());
+            }
+        }
+
+        fn gather_into<S: ?Sized + Buffer>(&self, send: &S) {
+            self.endpoint()
+                .send(self.rank(), COLLECTIVE_TAG, send.items().to_vec());
+        }
+
+        fn gather_into_root<S, R>(&self, send: &S, receive: &mut R)
+        where
+            S: ?Sized + Buffer,
+            R: ?Sized + BufferMut<Item = S::Item>,
+        {
+            let send = send.items();
+            let receive = receive.items_mut();
+            self.gather_parts(send, |rank, values| {
+                receive[rank * send.len()..(rank + 1) * send.len()].copy_from_slice(values)
+            });
+        }
+
+        fn gather_varcount_into_root<S, B, C, D>(
+            &self,
+            send: &S,
+            receive: &mut PartitionMut<'_, B, C, D>,
+        ) where
+            S: ?Sized + Buffer,
+            B: ?Sized + BufferMut<Item = S::Item>,
+            C: Borrow<[Count]>,
+            D: Borrow<[Count]>,
+        {
+            self.gather_parts(send.items(), |rank, values| {
+                receive.part_mut(rank).copy_from_slice(values)
+            });
+        }
+
+        fn scatter_into<R: ?Sized + BufferMut>(&self, receive: &mut R) {
+            self.receive_from_root(receive.items_mut());
+        }
+
+        fn scatter_into_root<S, R>(&self, send: &S, receive: &mut R)
+        where
+            S: ?Sized + Buffer,
+            R: ?Sized + BufferMut<Item = S::Item>,
+        {
+            let receive = receive.items_mut();
+            let count = receive.len();
+            let send = send.items();
+            receive
+                .copy_from_slice(self.send_parts(|rank| &send[rank * count..(rank + 1) * count]));
+        }
+
+        fn scatter_varcount_into_root<B, C, D, R>(
+            &self,
+            send: &Partition<'_, B, C, D>,
+            receive: &mut R,
+        ) where
+            B: ?Sized + Buffer,
+            C: Borrow<[Count]>,
+            D: Borrow<[Count]>,
+            R: ?Sized + BufferMut<Item = B::Item>,
+        {
+            receive
+                .items_mut()
+                .copy_from_slice(self.send_parts(|rank| send.part(rank)));
+        }
+    }
+}
+
+pub mod topology {
+    use super::{ffi::MPI_Comm, raw::AsRaw, Endpoint, Rank};
+
+    /// Communicator of threads, see [`run_ranks`](super::run_ranks)
+    pub struct SimpleCommunicator(pub(super) Endpoint);
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Color(Option<i32>);
+
+    impl Color {
+        pub fn undefined() -> Color {
+            Color(None)
+        }
+
+        pub fn with_value(value: i32) -> Color {
+            Color(Some(value))
+        }
+    }
+
+    pub trait Communicator: AsRaw<Raw = MPI_Comm> {
+        fn endpoint(&self) -> &Endpoint;
+
+        fn size(&self) -> Rank {
+            self.endpoint().size()
+        }
+
+        fn rank(&self) -> Rank {
+            self.endpoint().rank
+        }
+
+        fn process_at_rank(&self, rank: Rank) -> Process<'_> {
+            Process {
+                endpoint: self.endpoint(),
+                rank,
+            }
+        }
+
+        fn any_process(&self) -> AnyProcess<'_> {
+            AnyProcess(self.endpoint())
+        }
+
+        fn split_by_color(&self, color: Color) -> Option<SimpleCommunicator> {
+            self.split_by_color_with_key(color, self.rank())
+        }
+
+        fn split_by_color_with_key(&self, color: Color, key: Rank) -> Option<SimpleCommunicator> {
+            self.endpoint().split(color.0, key).map(SimpleCommunicator)
+        }
+
+        /// Communicator of the same ranks, whose messages don't mix with the ones of this one
+        fn duplicate(&self) -> SimpleCommunicator {
+            SimpleCommunicator(self.endpoint().split(Some(0), self.rank()).unwrap())
+        }
+
+        /// All threads share the memory of their process
+        fn split_shared(&self, key: Rank) -> SimpleCommunicator {
+            SimpleCommunicator(self.endpoint().split(Some(0), key).unwrap())
+        }
+    }
+
+    impl AsRaw for SimpleCommunicator {
+        type Raw = MPI_Comm;
+    }
+
+    impl Communicator for SimpleCommunicator {
+        fn endpoint(&self) -> &Endpoint {
+            &self.0
+        }
+    }
+
+    pub struct Process<'a> {
+        endpoint: &'a Endpoint,
+        rank: Rank,
+    }
+
+    impl Process<'_> {
+        pub fn rank(&self) -> Rank {
+            self.rank
+        }
+
+        pub(super) fn endpoint(&self) -> &Endpoint {
+            self.endpoint
+        }
+    }
+
+    pub struct AnyProcess<'a>(&'a Endpoint);
+
+    impl AnyProcess<'_> {
+        pub(super) fn endpoint(&self) -> &Endpoint {
+            self.0
+        }
+    }
+}
+
+/// Memory of a rank shared with the others, standing in for MPI windows as all ranks are threads
+/// of one process
+pub mod window {
+    use std::sync::Arc;
+
+    use super::{topology::Communicator, Rank, COLLECTIVE_TAG};
+
+    /// The value created by `root`, called by all ranks of the communicator together
+    pub fn share<T: Send + Sync + 'static>(
+        communicator: &impl Communicator,
+        root: Rank,
+        value: impl FnOnce() -> T,
+    ) -> Arc<T> {
+        let endpoint = communicator.endpoint();
+        if communicator.rank() != root {
+            return endpoint.receive(Some(root), Some(COLLECTIVE_TAG)).0;
+        }
+
+        let shared = Arc::new(value());
+        for rank in endpoint.others() {
+            endpoint.send(rank, COLLECTIVE_TAG, Arc::clone(&shared));
+        }
+        shared
+    }
+}
+
+pub mod traits {
+    pub use super::{
+        collective::{CommunicatorCollectives, Root},
+        datatype::Equivalence,
+        point_to_point::{Destination, Source},
+        topology::Communicator,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::{
+        collective::SystemOperation,
+        datatype::{Partition, PartitionMut},
+        request::{self, StaticScope},
+        topology::Color,
+        traits::*,
+        *,
+    };
+
+    #[test]
+    fn test_threads_run_the_collectives_of_ranks() {
+        let results = Mutex::new(Vec::new());
+
+        run_ranks(3, |world| {
+            let rank = world.rank();
+            let root = world.process_at_rank(0);
+
+            let mut sum = [0u32; 2];
+            world.all_reduce_into(&[rank as u32, 1][..], &mut sum[..], SystemOperation::sum());
+            assert_eq!(sum, [3, 3]);
+
+            let mut value = if rank == 0 { 7u64 } else { 0 };
+            root.broadcast_into(&mut value);
+            assert_eq!(value, 7);
+
+            // every rank sends `rank + 1` bytes
+            let send = vec![rank as u8; rank as usize + 1];
+            if rank == 0 {
+                let mut gathered = [0u8; 6];
+                let mut partition =
+                    PartitionMut::new(&mut gathered[..], &[1, 2, 3][..], &[0, 1, 3][..]);
+                root.gather_varcount_into_root(&send[..], &mut partition);
+                assert_eq!(gathered, [0, 1, 1, 2, 2, 2]);
+
+                let partition = Partition::new(&gathered[..], vec![1, 2, 3], vec![0, 1, 3]);
+                let mut scattered = [9u8; 1];
+                root.scatter_varcount_into_root(&partition, &mut scattered[..]);
+                assert_eq!(scattered, [0]);
+            } else {
+                root.gather_varcount_into(&send[..]);
+                let mut scattered = vec![9u8; rank as usize + 1];
+                root.scatter_varcount_into(&mut scattered[..]);
+                assert_eq!(scattered, send);
+            }
+
+            // a ring of messages, each rank receiving while its own one is pending
+            let size = world.size();
+            let mut received = -1;
+            request::scope(|scope| {
+                let sent = world
+                    .process_at_rank((rank + 1) % size)
+                    .immediate_send(scope, &rank);
+                world
+                    .process_at_rank((rank + size - 1) % size)
+                    .immediate_receive_into(scope, &mut received)
+                    .wait();
+                sent.wait();
+            });
+            assert_eq!(received, (rank + size - 1) % size);
+
+            if rank == 2 {
+                world
+                    .process_at_rank(0)
+                    .immediate_send_with_tag(StaticScope, &true, 5)
+                    .wait();
+            }
+            if rank == 0 {
+                let (stop, status) = world.any_process().receive_with_tag::<bool>(5);
+                assert!(stop && status.source_rank() == 2);
+                assert!(world.any_process().immediate_probe_with_tag(5).is_none());
+            }
+
+            let color = match rank {
+                1 => Color::with_value(1),
+                _ => Color::undefined(),
+            };
+            let odd = world.split_by_color(color);
+            assert_eq!(
+                odd.map(|odd| (odd.rank(), odd.size())),
+                (rank == 1).then_some((0, 1))
+            );
+
+            let reversed = world
+                .split_by_color_with_key(Color::with_value(0), -rank)
+                .unwrap();
+            results.lock().unwrap().push((rank, reversed.rank()));
+        });
+
+        let mut results = results.into_inner().unwrap();
+        results.sort_unstable();
+        assert_eq!(results, vec![(0, 2), (1, 1), (2, 0)]);
+    }
+}
@@ -0,0 +1,227 @@
+use std::{
+    cmp::Reverse,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::mpi::{ffi::MPI_Comm, traits::*};
+use rayon::prelude::*;
+use tracing::{debug, info};
+
+use crate::{
+    algorithm::{
+        self,
+        chc::Chc,
+        config::{AlgorithmConfig, SolverKind},
+        conflicts::ConflictMatrix,
+        datatypes::{Individual, Tuple},
+        propagation::Domains,
+        random::PlannerRng,
+        tabu,
+    },
+    mpi_utils::{all_reduce_max, broadcast_flag, gather_population, StopToken, ROOT_RANK},
+    runner::GaRunner,
+};
+
+/// Schedules found by a [`Solver`]
+#[derive(Debug, Clone, Default)]
+pub struct Solution {
+    /// Best schedules, best first. Only filled in at the root of the communicator
+    pub elites: Vec<Individual>,
+    /// Generations or iterations the solver ran
+    pub iterations: usize,
+}
+
+/// A way of finding schedules, picked by `solver` of the configuration
+///
+/// All nodes of the communicator call [`Solver::solve`] together.
+pub trait Solver {
+    /// Short name, e.g. for logs
+    fn name(&self) -> &str;
+
+    fn solve(&mut self, config: &AlgorithmConfig, tuples: &[Tuple]) -> Solution;
+}
+
+/// Create the solver selected by the configuration
+///
+/// `injected` individuals seed the population of the genetic algorithm, the other solvers ignore
+/// them.
+pub fn from_config<'a, C: Communicator<Raw = MPI_Comm>>(
+    config: &AlgorithmConfig,
+    communicator: &'a C,
+    rng: PlannerRng,
+    injected: Vec<Individual>,
+    stop_requested: &'a AtomicBool,
+) -> Box<dyn Solver + 'a> {
+    match config.solver {
+        SolverKind::Genetic => Box::new(GeneticSolver {
+            communicator,
+            rng,
+            injected,
+            stop_requested,
+        }),
+        SolverKind::Tabu => Box::new(TabuSolver {
+            communicator,
+            rng,
+            stop_requested,
+        }),
+        SolverKind::Chc => Box::new(ChcSolver {
+            communicator,
+            rng,
+            stop_requested,
+        }),
+    }
+}
+
+/// The configuration with the conflict matrix and the allowed starts of the tuples attached, for
+/// solvers building their own populations
+fn prepare(config: &AlgorithmConfig, tuples: &[Tuple]) -> AlgorithmConfig {
+    let config = if config.assigns_resources() {
+        config.clone()
+    } else {
+        config
+            .clone()
+            .with_conflict_matrix(ConflictMatrix::new(config, tuples))
+    };
+
+    if config.constraint_propagation {
+        let domains = Domains::propagate(&config, tuples);
+        config.with_domains(domains)
+    } else {
+        config
+    }
+}
+
+/// The genetic algorithm of [`GaRunner`]
+pub struct GeneticSolver<'a, C: Communicator<Raw = MPI_Comm>> {
+    communicator: &'a C,
+    rng: PlannerRng,
+    injected: Vec<Individual>,
+    stop_requested: &'a AtomicBool,
+}
+
+impl<C: Communicator<Raw = MPI_Comm>> Solver for GeneticSolver<'_, C> {
+    fn name(&self) -> &str {
+        "genetic"
+    }
+
+    fn solve(&mut self, config: &AlgorithmConfig, tuples: &[Tuple]) -> Solution {
+        let injected = std::mem::take(&mut self.injected);
+        let mut runner = GaRunner::new(
+            config,
+            tuples,
+            self.communicator,
+            self.rng.clone(),
+            injected,
+        );
+        let iterations = runner.run(self.stop_requested);
+        debug!(
+            reused = runner.pool().reused(),
+            created = runner.pool().created(),
+            "Offspring individuals"
+        );
+
+        // every node contributes its best individuals, the winner is picked at root
+        Solution {
+            elites: runner.gather_elites(),
+            iterations,
+        }
+    }
+}
+
+/// Baseline without a population: every node improves its own greedy schedule by
+/// [`tabu::tabu_search`] and the best schedules of all nodes are gathered at root
+///
+/// Nodes don't synchronize while searching, the first one to reach `target_fitness` (or root when
+/// asked to stop) stops the others by a [`StopToken`].
+pub struct TabuSolver<'a, C: Communicator<Raw = MPI_Comm>> {
+    communicator: &'a C,
+    rng: PlannerRng,
+    stop_requested: &'a AtomicBool,
+}
+
+impl<C: Communicator<Raw = MPI_Comm>> Solver for TabuSolver<'_, C> {
+    fn name(&self) -> &str {
+        "tabu"
+    }
+
+    fn solve(&mut self, config: &AlgorithmConfig, tuples: &[Tuple]) -> Solution {
+        let config = prepare(config, tuples);
+        let is_root = self.communicator.rank() == ROOT_RANK;
+        let mut stop_token = StopToken::new(self.communicator);
+
+        let (best, iterations) = tabu::tabu_search(&config, tuples, &mut self.rng, || {
+            if is_root && self.stop_requested.load(Ordering::SeqCst) {
+                stop_token.raise();
+            }
+            stop_token.poll()
+        });
+        if best.adaptation >= config.target_fitness {
+            debug!("Target fitness reached, stopping the other nodes");
+            stop_token.raise();
+        }
+        stop_token.finish();
+
+        let mut elites = gather_population(&[best], self.communicator, ROOT_RANK);
+        elites.sort_by_key(|individual| Reverse(individual.adaptation));
+        Solution { elites, iterations }
+    }
+}
+
+/// Every node evolves its own population of `population_size` split across the nodes by [`Chc`],
+/// and the best individuals of all nodes are gathered at root
+///
+/// Like the genetic algorithm, all nodes stop together at `max_generations`, once any of them
+/// reaches `target_fitness` or when root is asked to stop.
+pub struct ChcSolver<'a, C: Communicator<Raw = MPI_Comm>> {
+    communicator: &'a C,
+    rng: PlannerRng,
+    stop_requested: &'a AtomicBool,
+}
+
+impl<C: Communicator<Raw = MPI_Comm>> Solver for ChcSolver<'_, C> {
+    fn name(&self) -> &str {
+        "chc"
+    }
+
+    fn solve(&mut self, config: &AlgorithmConfig, tuples: &[Tuple]) -> Solution {
+        let is_root = self.communicator.rank() == ROOT_RANK;
+        let config = AlgorithmConfig {
+            population_size: (config.population_size / self.communicator.size() as usize).max(2),
+            ..prepare(config, tuples)
+        };
+
+        let mut population = algorithm::create_first_population(&config, tuples, &mut self.rng);
+        population.par_iter_mut().for_each(|individual| {
+            individual.adaptation = algorithm::calculate_fitness(&config, individual, tuples);
+        });
+        population.sort_by_key(|individual| Reverse(individual.adaptation));
+
+        let mut chc = Chc::new(&config, tuples);
+        let mut generations = 0;
+        while generations < config.max_generations {
+            if chc.generation(&config, &mut population, tuples, &mut self.rng) {
+                debug!("Converged, restarting around the best individual");
+            }
+            generations += 1;
+
+            let best_adaptation = all_reduce_max(population[0].adaptation, self.communicator);
+            if is_root {
+                info!(best_adaptation);
+            }
+            let stop = is_root && self.stop_requested.load(Ordering::SeqCst);
+            if best_adaptation >= config.target_fitness
+                || broadcast_flag(stop, self.communicator, ROOT_RANK)
+            {
+                break;
+            }
+        }
+
+        population.truncate(config.elite_count.max(1));
+        let mut elites = gather_population(&population, self.communicator, ROOT_RANK);
+        elites.sort_by_key(|individual| Reverse(individual.adaptation));
+        Solution {
+            elites,
+            iterations: generations,
+        }
+    }
+}
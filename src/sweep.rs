@@ -0,0 +1,205 @@
+use std::{collections::BTreeMap, path::Path};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    algorithm::config::{self, AlgorithmConfig, ConfigLoadError},
+    experiments::{RunRecord, RunSummary},
+};
+
+/// One combination of swept values, as `(field, value)` config overrides
+pub type Setting = Vec<(String, String)>;
+
+/// Values a config field takes in a sweep
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum SweepValues {
+    /// Explicit values, e.g. `[1000, 5000]` or `["island", "master_worker"]`
+    List(Vec<serde_json::Value>),
+    /// Numbers from `start` to `end` (inclusive) by `step`
+    Range { start: f64, end: f64, step: f64 },
+}
+
+impl SweepValues {
+    /// The values as text accepted by [`AlgorithmConfig::with_overrides`]
+    fn expand(&self) -> Vec<String> {
+        match self {
+            SweepValues::List(values) => values
+                .iter()
+                .map(|value| match value {
+                    serde_json::Value::String(text) => text.clone(),
+                    other => other.to_string(),
+                })
+                .collect(),
+            SweepValues::Range { start, end, step } => {
+                let count = ((end - start) / step + 1e-9).floor().max(0.0) as usize + 1;
+                (0..count)
+                    .map(|index| {
+                        // rounding keeps 0.1 + 2 * 0.1 from becoming 0.30000000000000004
+                        let value = start + step * index as f64;
+                        ((value * 1e9).round() / 1e9).to_string()
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Grid of config values to evaluate, read from a JSON, TOML or YAML file
+///
+/// Every combination of the `parameters` values is run `runs` times, e.g.
+///
+/// ```toml
+/// runs = 5
+///
+/// [parameters]
+/// population_size = [1000, 5000]
+/// mutation_probability = { start = 0.01, end = 0.1, step = 0.03 }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SweepSpec {
+    pub parameters: BTreeMap<String, SweepValues>,
+    pub runs: usize,
+}
+
+impl Default for SweepSpec {
+    fn default() -> Self {
+        SweepSpec {
+            parameters: BTreeMap::new(),
+            runs: 1,
+        }
+    }
+}
+
+impl SweepSpec {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<SweepSpec, ConfigLoadError> {
+        config::read_file(path)
+    }
+
+    /// All combinations of the swept values, checked to give a valid configuration
+    pub fn settings(&self, config: &AlgorithmConfig) -> Result<Vec<Setting>, ConfigLoadError> {
+        let known = AlgorithmConfig::field_names();
+        let unknown: Vec<String> = self
+            .parameters
+            .keys()
+            .filter(|field| !known.contains(field))
+            .cloned()
+            .collect();
+        if !unknown.is_empty() {
+            return Err(ConfigLoadError::UnknownFields(unknown));
+        }
+
+        let settings: Vec<Setting> = self
+            .parameters
+            .iter()
+            .map(|(field, values)| {
+                values
+                    .expand()
+                    .into_iter()
+                    .map(|value| (field.clone(), value))
+                    .collect::<Vec<_>>()
+            })
+            .multi_cartesian_product()
+            .collect();
+
+        // without parameters the product is empty, the base configuration is the only setting
+        let settings = if settings.is_empty() {
+            vec![Vec::new()]
+        } else {
+            settings
+        };
+
+        for setting in &settings {
+            config.clone().with_overrides(setting.clone())?.validate()?;
+        }
+
+        Ok(settings)
+    }
+}
+
+/// Result of one run of a sweep, `setting` indexes [`SweepSpec::settings`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepRecord {
+    pub setting: usize,
+    pub record: RunRecord,
+}
+
+/// Write a row per setting with the swept values and the [`RunSummary`] of its runs
+pub fn write_results(
+    settings: &[Setting],
+    records: &[SweepRecord],
+    config: &AlgorithmConfig,
+    path: impl AsRef<Path>,
+) -> Result<(), csv::Error> {
+    let mut writer = csv::Writer::from_path(path)?;
+
+    let fields: Vec<&str> = settings
+        .first()
+        .map(|setting| setting.iter().map(|(field, _)| field.as_str()).collect())
+        .unwrap_or_default();
+    let statistics = [
+        "Runs",
+        "MeanAdaptation",
+        "StdDevAdaptation",
+        "SuccessRate",
+        "MeanSeconds",
+    ];
+    writer.write_record(fields.iter().chain(statistics.iter()))?;
+
+    for (index, setting) in settings.iter().enumerate() {
+        let runs: Vec<RunRecord> = records
+            .iter()
+            .filter(|record| record.setting == index)
+            .map(|record| record.record.clone())
+            .collect();
+
+        let target_fitness = config
+            .clone()
+            .with_overrides(setting.clone())
+            .map_or(config.target_fitness, |config| config.target_fitness);
+        let summary = RunSummary::new(&runs, target_fitness);
+
+        let values = setting.iter().map(|(_, value)| value.clone());
+        let statistics = [
+            summary.runs.to_string(),
+            summary.mean_adaptation.to_string(),
+            summary.std_dev_adaptation.to_string(),
+            summary.success_rate.to_string(),
+            summary.mean_seconds.to_string(),
+        ];
+        writer.write_record(values.chain(statistics))?;
+    }
+
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_are_the_product_of_values() {
+        let spec: SweepSpec = toml::from_str(
+            "[parameters]\n\
+             population_size = [100, 200]\n\
+             parallel_model = [\"island\", \"master_worker\"]\n\
+             mutation_probability = { start = 0.1, end = 0.3, step = 0.1 }\n",
+        )
+        .unwrap();
+
+        let settings = spec.settings(&AlgorithmConfig::default()).unwrap();
+        assert_eq!(settings.len(), 12);
+        assert!(settings.contains(&vec![
+            ("mutation_probability".to_string(), "0.3".to_string()),
+            ("parallel_model".to_string(), "master_worker".to_string()),
+            ("population_size".to_string(), "200".to_string()),
+        ]));
+
+        let invalid: SweepSpec =
+            serde_json::from_str(r#"{"parameters": {"population_size": [1]}}"#).unwrap();
+        assert!(invalid.settings(&AlgorithmConfig::default()).is_err());
+    }
+}
@@ -0,0 +1,74 @@
+use std::{env, num::NonZeroUsize, sync::OnceLock, thread};
+
+/// Environment variables hinting the threads of a process, in order of precedence
+const THREAD_HINTS: [&str; 2] = ["RAYON_NUM_THREADS", "OMP_NUM_THREADS"];
+
+/// Threads of the rayon pool of a process sharing its node with `ranks_on_node` processes
+///
+/// The `threads` config field comes first, then the first valid [`THREAD_HINTS`] variable found by
+/// `hint`. `OMP_NUM_THREADS` may list the threads of nested levels, only the outermost counts.
+/// Without either the cores are divided evenly among the processes of the node.
+pub fn thread_count(
+    configured: Option<usize>,
+    hint: impl Fn(&str) -> Option<String>,
+    cores: usize,
+    ranks_on_node: usize,
+) -> usize {
+    let hinted = || {
+        THREAD_HINTS.iter().find_map(|variable| {
+            let value = hint(variable)?;
+            let threads = value.split(',').next()?.trim().parse::<usize>().ok()?;
+            (threads > 0).then_some(threads)
+        })
+    };
+
+    configured
+        .or_else(hinted)
+        .unwrap_or_else(|| (cores / ranks_on_node.max(1)).max(1))
+}
+
+/// Threads of the global pool, or why it couldn't be sized
+static CONFIGURED: OnceLock<Result<usize, String>> = OnceLock::new();
+
+/// Size the global rayon pool of this process, see [`thread_count`]
+///
+/// Must be called before anything runs on the pool, returns the number of threads. Only the first
+/// call sizes the pool, so ranks running as threads of one process may all call it.
+pub fn configure(configured: Option<usize>, ranks_on_node: usize) -> Result<usize, String> {
+    CONFIGURED
+        .get_or_init(|| {
+            let cores = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+            let threads = thread_count(
+                configured,
+                |variable| env::var(variable).ok(),
+                cores,
+                ranks_on_node,
+            );
+
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build_global()
+                .map_err(|err| err.to_string())?;
+            Ok(threads)
+        })
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thread_count_prefers_config_then_hints_then_cores() {
+        let no_hints = |_: &str| None;
+        let omp = |variable: &str| (variable == "OMP_NUM_THREADS").then(|| "4,2".to_string());
+
+        assert_eq!(thread_count(Some(3), omp, 32, 4), 3);
+        assert_eq!(thread_count(None, omp, 32, 4), 4);
+        assert_eq!(thread_count(None, no_hints, 32, 4), 8);
+        assert_eq!(thread_count(None, no_hints, 2, 4), 1);
+
+        let invalid = |_: &str| Some("0".to_string());
+        assert_eq!(thread_count(None, invalid, 12, 3), 4);
+    }
+}
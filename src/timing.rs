@@ -0,0 +1,157 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    fs::File,
+    io,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use crate::mpi::Rank;
+use serde::{Deserialize, Serialize};
+
+/// What a process spends its time on, see [`timed`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Compute,
+    Broadcast,
+    Scatter,
+    Gather,
+    Reduce,
+    /// Waiting for migrants beyond the work overlapping with their transfer
+    Migration,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 6] = [
+        Phase::Compute,
+        Phase::Broadcast,
+        Phase::Scatter,
+        Phase::Gather,
+        Phase::Reduce,
+        Phase::Migration,
+    ];
+}
+
+/// Time spent in every phase by the current thread, and in the timers entered but not left yet
+#[derive(Debug, Default)]
+struct Timings {
+    totals: BTreeMap<Phase, Duration>,
+    /// Time spent in nested timers of every open timer, innermost last
+    nested: Vec<Duration>,
+}
+
+thread_local! {
+    static TIMINGS: RefCell<Timings> = RefCell::new(Timings::default());
+}
+
+/// Run `work` and add its duration to the phase
+///
+/// Timers nest and only count their own time: a broadcast inside a timed computation adds to
+/// [`Phase::Broadcast`] and is left out of [`Phase::Compute`]. Times are kept per thread, MPI calls
+/// and the timers around them are made by the main thread of every process.
+pub fn timed<R>(phase: Phase, work: impl FnOnce() -> R) -> R {
+    TIMINGS.with(|timings| timings.borrow_mut().nested.push(Duration::ZERO));
+    let started = Instant::now();
+    let result = work();
+    let elapsed = started.elapsed();
+
+    TIMINGS.with(|timings| {
+        let mut timings = timings.borrow_mut();
+        let nested = timings.nested.pop().unwrap_or_default();
+        *timings.totals.entry(phase).or_default() += elapsed.saturating_sub(nested);
+        if let Some(parent) = timings.nested.last_mut() {
+            *parent += elapsed;
+        }
+    });
+    result
+}
+
+/// Seconds a process spent in every phase
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RankTimings {
+    pub rank: Rank,
+    pub seconds: BTreeMap<Phase, f64>,
+}
+
+impl RankTimings {
+    /// Times of the current thread so far
+    pub fn current(rank: Rank) -> Self {
+        let seconds = TIMINGS.with(|timings| {
+            timings
+                .borrow()
+                .totals
+                .iter()
+                .map(|(phase, duration)| (*phase, duration.as_secs_f64()))
+                .collect()
+        });
+        RankTimings { rank, seconds }
+    }
+
+    pub fn seconds_in(&self, phase: Phase) -> f64 {
+        self.seconds.get(&phase).copied().unwrap_or(0.0)
+    }
+
+    /// Fraction of the measured time spent communicating
+    pub fn communication_share(&self) -> f64 {
+        let total: f64 = self.seconds.values().sum();
+        if total == 0.0 {
+            return 0.0;
+        }
+        (total - self.seconds_in(Phase::Compute)) / total
+    }
+}
+
+/// Seconds of every rank in every phase as an aligned text table
+pub fn format_table(timings: &[RankTimings]) -> String {
+    let mut table = format!("{:>6}", "rank");
+    for phase in Phase::ALL {
+        table += &format!(" {:>10}", format!("{:?}", phase).to_lowercase());
+    }
+    table += &format!(" {:>14}\n", "communication");
+
+    for rank_timings in timings {
+        table += &format!("{:>6}", rank_timings.rank);
+        for phase in Phase::ALL {
+            table += &format!(" {:>10.3}", rank_timings.seconds_in(phase));
+        }
+        table += &format!(" {:>13.1}%\n", rank_timings.communication_share() * 100.0);
+    }
+    table
+}
+
+/// Write the timings of all ranks as JSON
+pub fn write_json(timings: &[RankTimings], path: impl AsRef<Path>) -> io::Result<()> {
+    let file = File::create(path)?;
+    serde_json::to_writer_pretty(file, timings)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_timers_count_only_their_own_time() {
+        let started = Instant::now();
+        timed(Phase::Compute, || {
+            std::thread::sleep(Duration::from_millis(20));
+            timed(Phase::Broadcast, || {
+                std::thread::sleep(Duration::from_millis(30))
+            });
+        });
+        let total = started.elapsed().as_secs_f64();
+
+        let timings = RankTimings::current(3);
+        let compute = timings.seconds_in(Phase::Compute);
+        let broadcast = timings.seconds_in(Phase::Broadcast);
+        assert!(compute >= 0.02 && broadcast >= 0.03);
+        assert!(compute + broadcast <= total);
+        assert!(timings.communication_share() > 0.5);
+
+        let table = format_table(&[timings]);
+        assert!(table.starts_with("  rank    compute  broadcast"));
+        assert_eq!(table.lines().count(), 2);
+    }
+}
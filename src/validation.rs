@@ -0,0 +1,271 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{
+    algorithm::{
+        config::AlgorithmConfig,
+        datatypes::{Gene, Tuple},
+        resources,
+        sources::TupleLocation,
+    },
+    verify::Described,
+};
+
+/// A problem of the input that would break the run or make it pointless
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputProblem<'a> {
+    /// A tuple has the id of an earlier one
+    DuplicateId {
+        tuple: &'a Tuple,
+        first: TupleLocation,
+    },
+    /// A tuple has no label, room or teacher
+    EmptyField {
+        tuple: &'a Tuple,
+        field: &'static str,
+    },
+    /// A room or allowed room of a tuple is not one of the configured rooms
+    UnknownRoom { tuple: &'a Tuple, room: &'a str },
+    /// A teacher or qualified teacher of a tuple is not one of the configured teachers
+    UnknownTeacher { tuple: &'a Tuple, teacher: &'a str },
+    /// The group of a tuple is not one of the configured student groups
+    UnknownGroup { tuple: &'a Tuple, group: &'a str },
+    /// A lock pins a tuple id that isn't in the tuples
+    LockedUnknownTuple { gene: Gene, period: usize },
+    /// A tuple is locked to a period after the last one
+    LockOutOfRange {
+        tuple: &'a Tuple,
+        period: usize,
+        periods: usize,
+    },
+    /// A tuple is locked to a period it can't start in, forbidden or too late in the day
+    LockedToForbiddenPeriod { tuple: &'a Tuple, period: usize },
+}
+
+impl Display for InputProblem<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputProblem::DuplicateId { tuple, first } => write!(
+                f,
+                "Tuple {} has the id of the tuple at {}",
+                Described(tuple),
+                first
+            ),
+            InputProblem::EmptyField { tuple, field } => {
+                write!(f, "Tuple #{} has an empty {}", tuple.id, field)
+            }
+            InputProblem::UnknownRoom { tuple, room } => write!(
+                f,
+                "Tuple {} takes place in the unknown room {:?}",
+                Described(tuple),
+                room
+            ),
+            InputProblem::UnknownTeacher { tuple, teacher } => write!(
+                f,
+                "Tuple {} is taught by the unknown teacher {:?}",
+                Described(tuple),
+                teacher
+            ),
+            InputProblem::UnknownGroup { tuple, group } => write!(
+                f,
+                "Tuple {} is attended by the unknown student group {:?}",
+                Described(tuple),
+                group
+            ),
+            InputProblem::LockedUnknownTuple { gene, period } => write!(
+                f,
+                "Period {} is locked for the unknown tuple id {}",
+                period + 1,
+                gene
+            ),
+            InputProblem::LockOutOfRange {
+                tuple,
+                period,
+                periods,
+            } => write!(
+                f,
+                "Tuple {} is locked to period {}, there are {} periods",
+                Described(tuple),
+                period + 1,
+                periods
+            ),
+            InputProblem::LockedToForbiddenPeriod { tuple, period } => write!(
+                f,
+                "Tuple {} is locked to period {}, where it can't start",
+                Described(tuple),
+                period + 1
+            ),
+        }
+    }
+}
+
+/// A problem with where it was found, if it concerns a single tuple
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic<'a> {
+    pub location: Option<TupleLocation>,
+    pub problem: InputProblem<'a>,
+}
+
+impl Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.location {
+            Some(location) => write!(f, "{}: {}", location, self.problem),
+            None => self.problem.fmt(f),
+        }
+    }
+}
+
+/// Check the tuples against each other, the configured resources and the locks, reporting every
+/// problem found in the order of the tuples, then of the locks
+///
+/// `locations` are where the tuples were read from, one per tuple. Unavailable periods of the
+/// teachers should already be forbidden, so that locks to them are reported too.
+pub fn validate_input<'a>(
+    config: &'a AlgorithmConfig,
+    tuples: &'a [Tuple],
+    locations: &[TupleLocation],
+) -> Vec<Diagnostic<'a>> {
+    let mut diagnostics = Vec::new();
+    let mut first_index: HashMap<Gene, usize> = HashMap::new();
+
+    for ((index, tuple), &location) in tuples.iter().enumerate().zip(locations) {
+        let mut problems = Vec::new();
+
+        match first_index.get(&tuple.id) {
+            Some(&first) => problems.push(InputProblem::DuplicateId {
+                tuple,
+                first: locations[first],
+            }),
+            None => {
+                first_index.insert(tuple.id, index);
+            }
+        }
+
+        for (field, value) in [
+            ("label", &tuple.label),
+            ("room", &tuple.room),
+            ("teacher", &tuple.teacher),
+        ] {
+            if value.trim().is_empty() {
+                problems.push(InputProblem::EmptyField { tuple, field });
+            }
+        }
+
+        let tuple_slice = std::slice::from_ref(tuple);
+        if !config.rooms.is_empty() {
+            for (_, room) in resources::unknown_rooms(&config.rooms, tuple_slice) {
+                problems.push(InputProblem::UnknownRoom { tuple, room });
+            }
+        }
+        if !config.teachers.is_empty() {
+            for (_, teacher) in resources::unknown_teachers(&config.teachers, tuple_slice) {
+                problems.push(InputProblem::UnknownTeacher { tuple, teacher });
+            }
+        }
+        if !config.student_groups.is_empty() {
+            for (_, group) in resources::unknown_groups(&config.student_groups, tuple_slice) {
+                problems.push(InputProblem::UnknownGroup { tuple, group });
+            }
+        }
+
+        diagnostics.extend(problems.into_iter().map(|problem| Diagnostic {
+            location: Some(location),
+            problem,
+        }));
+    }
+
+    let layout = config.period_layout();
+    for (&gene, &period) in &config.locks {
+        let Some(&index) = first_index.get(&gene) else {
+            diagnostics.push(Diagnostic {
+                location: None,
+                problem: InputProblem::LockedUnknownTuple { gene, period },
+            });
+            continue;
+        };
+
+        let tuple = &tuples[index];
+        let problem = if period >= config.number_of_periods {
+            InputProblem::LockOutOfRange {
+                tuple,
+                period,
+                periods: config.number_of_periods,
+            }
+        } else if !tuple.can_start_at(period, &layout) {
+            InputProblem::LockedToForbiddenPeriod { tuple, period }
+        } else {
+            continue;
+        };
+        diagnostics.push(Diagnostic {
+            location: Some(locations[index]),
+            problem,
+        });
+    }
+
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::{datatypes::TupleId, resources::Room};
+
+    #[test]
+    fn test_every_problem_is_reported_at_its_tuple() {
+        let tuples: Vec<Tuple> = [(1, "A", "Smith"), (2, "B", ""), (1, "A", "Jones")]
+            .into_iter()
+            .map(|(id, room, teacher)| Tuple {
+                id: TupleId(id),
+                label: "Wykład".to_string(),
+                room: room.to_string(),
+                teacher: teacher.to_string(),
+                forbidden_periods: vec![0],
+                ..Tuple::default()
+            })
+            .collect();
+        let locations: Vec<TupleLocation> = (2..5).map(TupleLocation::Line).collect();
+        let config = AlgorithmConfig {
+            number_of_periods: 4,
+            rooms: [("A".to_string(), Room::default())].into(),
+            locks: [(TupleId(1), 0), (TupleId(2), 9), (TupleId(5), 1)].into(),
+            ..AlgorithmConfig::default()
+        };
+
+        let diagnostics = validate_input(&config, &tuples, &locations);
+
+        let problems: Vec<(Option<TupleLocation>, String)> = diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.location, diagnostic.problem.to_string()))
+            .collect();
+        assert_eq!(
+            problems,
+            [
+                (
+                    Some(TupleLocation::Line(3)),
+                    "Tuple #2 has an empty teacher"
+                ),
+                (
+                    Some(TupleLocation::Line(3)),
+                    "Tuple #2 (Wykład, B, ) takes place in the unknown room \"B\""
+                ),
+                (
+                    Some(TupleLocation::Line(4)),
+                    "Tuple #1 (Wykład, A, Jones) has the id of the tuple at line 2"
+                ),
+                (
+                    Some(TupleLocation::Line(2)),
+                    "Tuple #1 (Wykład, A, Smith) is locked to period 1, where it can't start"
+                ),
+                (
+                    Some(TupleLocation::Line(3)),
+                    "Tuple #2 (Wykład, B, ) is locked to period 10, there are 4 periods"
+                ),
+                (None, "Period 2 is locked for the unknown tuple id 5"),
+            ]
+            .map(|(location, problem)| (location, problem.to_string()))
+        );
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "line 3: Tuple #2 has an empty teacher"
+        );
+    }
+}
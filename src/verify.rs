@@ -0,0 +1,470 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+};
+
+use itertools::Itertools;
+
+use crate::algorithm::{
+    config::AlgorithmConfig,
+    constraints,
+    datatypes::{Gene, Individual, Tuple, TupleKind},
+    periods::DaySlot,
+};
+
+/// A constraint broken by a schedule, with the tuples responsible for it
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation<'a> {
+    /// The schedule references a tuple id that isn't in the tuples
+    UnknownTuple { period: usize, gene: Gene },
+    /// A tuple is not scheduled at all
+    MissingTuple { tuple: &'a Tuple },
+    /// A tuple is scheduled more than once
+    DuplicateTuple {
+        tuple: &'a Tuple,
+        periods: Vec<usize>,
+    },
+    /// A teacher has more than one class in a period
+    TeacherClash {
+        period: usize,
+        tuples: Vec<&'a Tuple>,
+    },
+    /// Classes of different teachers share a room and their kinds don't allow it
+    RoomClash {
+        period: usize,
+        tuples: Vec<&'a Tuple>,
+    },
+    /// A tuple takes place in one of its forbidden periods
+    ForbiddenPeriod { period: usize, tuple: &'a Tuple },
+    /// The block of a tuple doesn't fit within the day it starts in
+    BlockOverflow { period: usize, tuple: &'a Tuple },
+    /// A locked tuple is not in the period it is pinned to
+    LockedElsewhere {
+        period: usize,
+        locked_period: usize,
+        tuple: &'a Tuple,
+    },
+    /// A teacher teaches more back-to-back periods than allowed
+    ConsecutiveHours {
+        teacher: &'a str,
+        day: usize,
+        hours: usize,
+        limit: usize,
+    },
+    /// A teacher teaches more periods than allowed in a day, or in the whole week without a day
+    Workload {
+        teacher: &'a str,
+        day: Option<usize>,
+        hours: usize,
+        limit: usize,
+    },
+    /// More classes take place in a period than allowed, in the rooms of a kind or in total
+    /// without a kind
+    TooManyClasses {
+        period: usize,
+        kind: Option<TupleKind>,
+        classes: usize,
+        limit: usize,
+    },
+}
+
+/// Tuple with its id, as shown in violation reports
+pub(crate) struct Described<'a>(pub &'a Tuple);
+
+impl Display for Described<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{} ({})", self.0.id, self.0)
+    }
+}
+
+fn describe_all(tuples: &[&Tuple]) -> String {
+    tuples.iter().map(|tuple| Described(tuple)).join(", ")
+}
+
+impl Display for Violation<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::UnknownTuple { period, gene } => {
+                write!(f, "Period {}: unknown tuple id {}", period + 1, gene)
+            }
+            Violation::MissingTuple { tuple } => {
+                write!(f, "Tuple {} is not scheduled", Described(tuple))
+            }
+            Violation::DuplicateTuple { tuple, periods } => write!(
+                f,
+                "Tuple {} is scheduled in periods {}",
+                Described(tuple),
+                periods.iter().map(|period| period + 1).join(", ")
+            ),
+            Violation::TeacherClash { period, tuples } => write!(
+                f,
+                "Period {}: teacher {} has {} classes: {}",
+                period + 1,
+                tuples[0].teacher,
+                tuples.len(),
+                describe_all(tuples)
+            ),
+            Violation::RoomClash { period, tuples } => write!(
+                f,
+                "Period {}: room {} is shared by {}",
+                period + 1,
+                tuples[0].room,
+                describe_all(tuples)
+            ),
+            Violation::ForbiddenPeriod { period, tuple } => write!(
+                f,
+                "Period {}: tuple {} can't take place in this period",
+                period + 1,
+                Described(tuple)
+            ),
+            Violation::BlockOverflow { period, tuple } => write!(
+                f,
+                "Period {}: tuple {} lasts {} periods and doesn't fit in the day",
+                period + 1,
+                Described(tuple),
+                tuple.length()
+            ),
+            Violation::LockedElsewhere {
+                period,
+                locked_period,
+                tuple,
+            } => write!(
+                f,
+                "Period {}: tuple {} is locked to period {}",
+                period + 1,
+                Described(tuple),
+                locked_period + 1
+            ),
+            Violation::ConsecutiveHours {
+                teacher,
+                day,
+                hours,
+                limit,
+            } => write!(
+                f,
+                "Day {}: teacher {} teaches {} consecutive periods, the limit is {}",
+                day + 1,
+                teacher,
+                hours,
+                limit
+            ),
+            Violation::Workload {
+                teacher,
+                day: Some(day),
+                hours,
+                limit,
+            } => write!(
+                f,
+                "Day {}: teacher {} teaches {} periods, the limit is {}",
+                day + 1,
+                teacher,
+                hours,
+                limit
+            ),
+            Violation::Workload {
+                teacher,
+                day: None,
+                hours,
+                limit,
+            } => write!(
+                f,
+                "Teacher {} teaches {} periods in the week, the limit is {}",
+                teacher, hours, limit
+            ),
+            Violation::TooManyClasses {
+                period,
+                kind: None,
+                classes,
+                limit,
+            } => write!(
+                f,
+                "Period {}: {} classes take place, the limit is {}",
+                period + 1,
+                classes,
+                limit
+            ),
+            Violation::TooManyClasses {
+                period,
+                kind: Some(kind),
+                classes,
+                limit,
+            } => write!(
+                f,
+                "Period {}: {} classes take place in {:?} rooms, the limit is {}",
+                period + 1,
+                classes,
+                kind,
+                limit
+            ),
+        }
+    }
+}
+
+/// Check a schedule against every constraint of the planner, independently of its fitness
+///
+/// Violations are listed in the order of the checks above, by period within every check.
+/// Gaps are preferences rather than constraints and are not reported.
+pub fn verify_schedule<'a>(
+    config: &AlgorithmConfig,
+    individual: &Individual,
+    tuples: &'a [Tuple],
+) -> Vec<Violation<'a>> {
+    let layout = config.period_layout();
+    let tuples_by_id: HashMap<Gene, &Tuple> =
+        tuples.iter().map(|tuple| (tuple.id, tuple)).collect();
+    let mut violations = Vec::new();
+
+    let mut periods_of: BTreeMap<Gene, Vec<usize>> = BTreeMap::new();
+    for (period, chromosome) in individual.chromosomes.iter().enumerate() {
+        for gene in &chromosome.genes {
+            if tuples_by_id.contains_key(gene) {
+                periods_of.entry(*gene).or_default().push(period);
+            } else {
+                violations.push(Violation::UnknownTuple {
+                    period,
+                    gene: *gene,
+                });
+            }
+        }
+    }
+
+    for tuple in tuples {
+        match periods_of.get(&tuple.id) {
+            None => violations.push(Violation::MissingTuple { tuple }),
+            Some(periods) if periods.len() > 1 => violations.push(Violation::DuplicateTuple {
+                tuple,
+                periods: periods.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+
+    for (period, genes) in constraints::active_genes(individual, tuples, &layout)
+        .iter()
+        .enumerate()
+    {
+        let active: Vec<&Tuple> = genes
+            .iter()
+            .filter_map(|gene| tuples_by_id.get(gene).copied())
+            .collect();
+
+        let teachers = active
+            .iter()
+            .copied()
+            .into_group_map_by(|tuple| &tuple.teacher);
+        for (_, clashing) in teachers.into_iter().sorted_by_key(|(teacher, _)| *teacher) {
+            if clashing.len() > 1 {
+                violations.push(Violation::TeacherClash {
+                    period,
+                    tuples: clashing,
+                });
+            }
+        }
+
+        let rooms = active
+            .iter()
+            .copied()
+            .into_group_map_by(|tuple| &tuple.room);
+        for (_, sharing) in rooms.into_iter().sorted_by_key(|(room, _)| *room) {
+            let clashes = sharing
+                .iter()
+                .tuple_combinations()
+                .any(|(a, b)| a.teacher != b.teacher && !config.can_share_room(a.kind, b.kind));
+            if clashes {
+                violations.push(Violation::RoomClash {
+                    period,
+                    tuples: sharing,
+                });
+            }
+        }
+
+        if let Some(limit) = config
+            .max_classes_per_period
+            .filter(|limit| active.len() > *limit)
+        {
+            violations.push(Violation::TooManyClasses {
+                period,
+                kind: None,
+                classes: active.len(),
+                limit,
+            });
+        }
+        let room_kinds = active.iter().counts_by(|tuple| {
+            config
+                .rooms
+                .get(&tuple.room)
+                .and_then(|room| room.kind)
+                .unwrap_or(tuple.kind)
+        });
+        for (kind, classes) in room_kinds.into_iter().sorted() {
+            if let Some(limit) = config
+                .max_classes_per_room_kind
+                .get(&kind)
+                .filter(|limit| classes > **limit)
+            {
+                violations.push(Violation::TooManyClasses {
+                    period,
+                    kind: Some(kind),
+                    classes,
+                    limit: *limit,
+                });
+            }
+        }
+    }
+
+    for (period, chromosome) in individual.chromosomes.iter().enumerate() {
+        for tuple in chromosome
+            .genes
+            .iter()
+            .filter_map(|gene| tuples_by_id.get(gene).copied())
+        {
+            let forbidden = layout
+                .clamped_block(period, tuple.length())
+                .any(|period| !tuple.can_take_place_in(period));
+            if forbidden {
+                violations.push(Violation::ForbiddenPeriod { period, tuple });
+            }
+
+            if layout.block(period, tuple.length()).is_none() {
+                violations.push(Violation::BlockOverflow { period, tuple });
+            }
+
+            match config.locks.get(&tuple.id) {
+                Some(locked_period) if *locked_period != period => {
+                    violations.push(Violation::LockedElsewhere {
+                        period,
+                        locked_period: *locked_period,
+                        tuple,
+                    })
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut teacher_slots: BTreeMap<(&str, usize), Vec<usize>> = BTreeMap::new();
+    for (period, chromosome) in individual.chromosomes.iter().enumerate() {
+        let DaySlot { day, slot } = layout.day_slot(period);
+        for tuple in chromosome
+            .genes
+            .iter()
+            .filter_map(|gene| tuples_by_id.get(gene).copied())
+        {
+            let block_len = layout.clamped_block(period, tuple.length()).len();
+            teacher_slots
+                .entry((tuple.teacher.as_str(), day))
+                .or_default()
+                .extend(slot..slot + block_len);
+        }
+    }
+
+    let mut workload = Vec::new();
+    let mut weekly_hours: BTreeMap<&str, usize> = BTreeMap::new();
+    for ((teacher, day), mut slots) in teacher_slots {
+        slots.sort_unstable();
+        slots.dedup();
+
+        *weekly_hours.entry(teacher).or_default() += slots.len();
+        if let Some(limit) = config
+            .teachers
+            .get(teacher)
+            .and_then(|limits| limits.max_daily_hours)
+            .filter(|limit| slots.len() > *limit)
+        {
+            workload.push(Violation::Workload {
+                teacher,
+                day: Some(day),
+                hours: slots.len(),
+                limit,
+            });
+        }
+
+        let Some(limit) = config.max_consecutive_hours_of(teacher) else {
+            continue;
+        };
+
+        for run in slots.chunk_by(|previous, next| previous + 1 == *next) {
+            if run.len() > limit {
+                violations.push(Violation::ConsecutiveHours {
+                    teacher,
+                    day,
+                    hours: run.len(),
+                    limit,
+                });
+            }
+        }
+    }
+
+    for (teacher, hours) in weekly_hours {
+        if let Some(limit) = config
+            .teachers
+            .get(teacher)
+            .and_then(|limits| limits.max_weekly_hours)
+            .filter(|limit| hours > *limit)
+        {
+            workload.push(Violation::Workload {
+                teacher,
+                day: None,
+                hours,
+                limit,
+            });
+        }
+    }
+    violations.extend(workload);
+
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::datatypes::{Chromosome, PeriodId, TupleId};
+    use smallvec::smallvec;
+
+    #[test]
+    fn test_violations_name_offending_tuples() {
+        let tuples: Vec<Tuple> = [(1, "A", "Smith"), (2, "B", "Smith"), (3, "C", "Jones")]
+            .into_iter()
+            .map(|(id, room, teacher)| Tuple {
+                id: TupleId(id),
+                room: room.to_string(),
+                teacher: teacher.to_string(),
+                ..Tuple::default()
+            })
+            .collect();
+        let individual = Individual::with_chromosomes(vec![
+            Chromosome {
+                id: PeriodId(0),
+                genes: smallvec![TupleId(1), TupleId(2), TupleId(7)],
+            },
+            Chromosome {
+                id: PeriodId(1),
+                genes: smallvec![TupleId(1)],
+            },
+        ]);
+        let config = AlgorithmConfig {
+            number_of_periods: 2,
+            ..AlgorithmConfig::default()
+        };
+
+        let violations = verify_schedule(&config, &individual, &tuples);
+        assert_eq!(
+            violations,
+            vec![
+                Violation::UnknownTuple {
+                    period: 0,
+                    gene: TupleId(7),
+                },
+                Violation::DuplicateTuple {
+                    tuple: &tuples[0],
+                    periods: vec![0, 1],
+                },
+                Violation::MissingTuple { tuple: &tuples[2] },
+                Violation::TeacherClash {
+                    period: 0,
+                    tuples: vec![&tuples[0], &tuples[1]],
+                },
+            ]
+        );
+    }
+}
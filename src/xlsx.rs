@@ -0,0 +1,341 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    path::Path,
+};
+
+use rust_xlsxwriter::{Format, FormatAlign, FormatBorder, Workbook, XlsxError};
+
+use crate::{
+    algorithm::{
+        config::{AlgorithmConfig, SpreadsheetLayout},
+        datatypes::{Gene, Individual, Tuple},
+    },
+    output::{course_hue, group_name},
+};
+
+/// Background of the cells with more than one class
+const CROWDED: u32 = 0xFFC7CE;
+
+/// A sheet of the spreadsheet, the classes in every slot of every column
+struct Sheet<'a> {
+    name: String,
+    columns: Vec<String>,
+    cells: Vec<Vec<Vec<&'a Tuple>>>,
+}
+
+/// Cells of a column filled with the same classes, merged when they span several slots
+#[derive(Debug, PartialEq)]
+struct Block<'a> {
+    column: usize,
+    first_slot: usize,
+    last_slot: usize,
+    tuples: Vec<&'a Tuple>,
+}
+
+/// Write a spreadsheet of the individual with a sheet per day or per student group, see
+/// `spreadsheet_layout`
+///
+/// Time slots are the rows and rooms or days the columns. A class lasting several periods takes
+/// one merged cell, classes of a course share a colour and cells with more than one class are
+/// red and list all of them.
+pub fn write_spreadsheet(
+    individual: &Individual,
+    tuples: &[Tuple],
+    config: &AlgorithmConfig,
+    path: impl AsRef<Path>,
+) -> Result<(), XlsxError> {
+    workbook(individual, tuples, config)?.save(path)
+}
+
+fn workbook(
+    individual: &Individual,
+    tuples: &[Tuple],
+    config: &AlgorithmConfig,
+) -> Result<Workbook, XlsxError> {
+    let layout = config.period_layout();
+    let header = Format::new()
+        .set_bold()
+        .set_align(FormatAlign::Center)
+        .set_border(FormatBorder::Thin);
+    let class = Format::new()
+        .set_text_wrap()
+        .set_align(FormatAlign::Top)
+        .set_border(FormatBorder::Thin);
+
+    let mut workbook = Workbook::new();
+    for sheet in sheets(individual, tuples, config) {
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name(&sheet.name)?;
+        worksheet.set_column_width(0, 8)?;
+        worksheet.set_freeze_panes(1, 1)?;
+
+        for (column, name) in sheet.columns.iter().enumerate() {
+            worksheet.set_column_width(column as u16 + 1, 24)?;
+            worksheet.write_string_with_format(0, column as u16 + 1, name, &header)?;
+        }
+        for slot in 0..layout.slots_per_day {
+            let row = slot as u32 + 1;
+            let label = match config.slot_times.get(slot) {
+                Some(time) => time.format("%H:%M").to_string(),
+                None => (slot + 1).to_string(),
+            };
+            worksheet.set_row_height(row, 45)?;
+            worksheet.write_string_with_format(row, 0, label, &header)?;
+        }
+
+        for block in blocks(&sheet) {
+            let format = match block.tuples.as_slice() {
+                [tuple] => class.clone().set_background_color(rgb(course_hue(tuple))),
+                _ => class.clone().set_background_color(CROWDED),
+            };
+            let text = block
+                .tuples
+                .iter()
+                .map(|tuple| describe(tuple, config.spreadsheet_layout))
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            let (first_row, last_row) = (block.first_slot as u32 + 1, block.last_slot as u32 + 1);
+            let column = block.column as u16 + 1;
+
+            if first_row == last_row {
+                worksheet.write_string_with_format(first_row, column, text, &format)?;
+            } else {
+                worksheet.merge_range(first_row, column, last_row, column, &text, &format)?;
+            }
+        }
+    }
+
+    Ok(workbook)
+}
+
+/// Sheets of the individual, a column per room on every day or a column per day for every group
+fn sheets<'a>(
+    individual: &Individual,
+    tuples: &'a [Tuple],
+    config: &AlgorithmConfig,
+) -> Vec<Sheet<'a>> {
+    let layout = config.period_layout();
+    let by_id: HashMap<Gene, &Tuple> = tuples.iter().map(|tuple| (tuple.id, tuple)).collect();
+    let empty = |columns: usize| vec![vec![Vec::new(); layout.slots_per_day]; columns];
+
+    let mut sheets = Vec::new();
+    match config.spreadsheet_layout {
+        SpreadsheetLayout::Day => {
+            let rooms: Vec<&str> = tuples
+                .iter()
+                .map(|tuple| tuple.room.as_str())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect();
+            for day in 0..layout.days {
+                sheets.push(Sheet {
+                    name: format!("Dzień {}", day + 1),
+                    columns: rooms.iter().map(|room| room.to_string()).collect(),
+                    cells: empty(rooms.len()),
+                });
+            }
+
+            for chromosome in &individual.chromosomes {
+                let start = chromosome.id.index();
+                for tuple in chromosome.genes.iter().filter_map(|gene| by_id.get(gene)) {
+                    let column = rooms.binary_search(&tuple.room.as_str()).unwrap();
+                    for period in layout.clamped_block(start, tuple.length()) {
+                        let day_slot = layout.day_slot(period);
+                        if let Some(sheet) = sheets.get_mut(day_slot.day) {
+                            sheet.cells[column][day_slot.slot].push(*tuple);
+                        }
+                    }
+                }
+            }
+        }
+        SpreadsheetLayout::Group => {
+            let groups: BTreeSet<&str> = tuples
+                .iter()
+                .map(|tuple| tuple.group.as_str())
+                .filter(|group| !group.is_empty())
+                .collect();
+            let mut names = HashSet::new();
+            let mut index = HashMap::new();
+            for group in groups {
+                // two groups with the same name can't share a sheet name, the latter keeps its id
+                let mut name = sheet_name(group_name(config, group));
+                if !names.insert(name.to_lowercase()) {
+                    name = sheet_name(group);
+                    names.insert(name.to_lowercase());
+                }
+                index.insert(group, sheets.len());
+                sheets.push(Sheet {
+                    name,
+                    columns: (0..layout.days)
+                        .map(|day| format!("Dzień {}", day + 1))
+                        .collect(),
+                    cells: empty(layout.days),
+                });
+            }
+
+            for chromosome in &individual.chromosomes {
+                let start = chromosome.id.index();
+                for tuple in chromosome.genes.iter().filter_map(|gene| by_id.get(gene)) {
+                    let Some(&sheet) = index.get(tuple.group.as_str()) else {
+                        continue;
+                    };
+                    for period in layout.clamped_block(start, tuple.length()) {
+                        let day_slot = layout.day_slot(period);
+                        if let Some(column) = sheets[sheet].cells.get_mut(day_slot.day) {
+                            column[day_slot.slot].push(*tuple);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    sheets
+}
+
+/// Blocks of the cells of a sheet, a class alone in consecutive slots takes a single block
+fn blocks<'a>(sheet: &Sheet<'a>) -> Vec<Block<'a>> {
+    let mut blocks = Vec::new();
+
+    for (column, cells) in sheet.cells.iter().enumerate() {
+        let mut slot = 0;
+        while slot < cells.len() {
+            let mut last_slot = slot;
+            if let [tuple] = cells[slot].as_slice() {
+                while matches!(cells.get(last_slot + 1).map(Vec::as_slice), Some([next]) if next.id == tuple.id)
+                {
+                    last_slot += 1;
+                }
+            }
+            if !cells[slot].is_empty() {
+                blocks.push(Block {
+                    column,
+                    first_slot: slot,
+                    last_slot,
+                    tuples: cells[slot].clone(),
+                });
+            }
+            slot = last_slot + 1;
+        }
+    }
+
+    blocks
+}
+
+/// Text of a class, with the teacher and whatever the sheet isn't laid out by
+fn describe(tuple: &Tuple, layout: SpreadsheetLayout) -> String {
+    match layout {
+        SpreadsheetLayout::Day if tuple.group.is_empty() => {
+            format!("{}\n{}", tuple.label, tuple.teacher)
+        }
+        SpreadsheetLayout::Day => format!("{}\n{}, {}", tuple.label, tuple.teacher, tuple.group),
+        SpreadsheetLayout::Group => format!("{}\n{}, {}", tuple.label, tuple.teacher, tuple.room),
+    }
+}
+
+/// Name a sheet can have, at most 31 characters and none of `[]:*?/\`
+fn sheet_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if "[]:*?/\\".contains(c) { '_' } else { c })
+        .take(31)
+        .collect()
+}
+
+/// The pastel colour of a hue, like `hsl(hue, 70%, 85%)` of the HTML timetable
+fn rgb(hue: u16) -> u32 {
+    let (saturation, lightness) = (0.7, 0.85);
+    let chroma = (1.0 - (2.0 * lightness - 1.0_f64).abs()) * saturation;
+    let sector = hue as f64 / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let channel = |value: f64| ((value + lightness - chroma / 2.0) * 255.0).round() as u32;
+    channel(r) << 16 | channel(g) << 8 | channel(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::datatypes::{Chromosome, PeriodId, TupleId};
+    use smallvec::smallvec;
+
+    #[test]
+    fn test_classes_are_merged_over_their_slots() {
+        let tuples: Vec<Tuple> = [
+            (1, "Wykład | Sieci", "A", "FT-1", 4),
+            (2, "Laboratorium | Sieci", "A", "FT-1", 1),
+            (3, "Wykład | Bazy", "B", "FT-2", 2),
+        ]
+        .into_iter()
+        .map(|(id, label, room, group, duration)| Tuple {
+            id: TupleId(id),
+            label: label.to_string(),
+            room: room.to_string(),
+            teacher: "Smith".to_string(),
+            group: group.to_string(),
+            duration,
+            ..Tuple::default()
+        })
+        .collect();
+        // the lecture in A runs past the end of the first day, the lab clashes with its last slot
+        let individual = Individual::with_chromosomes(
+            (0..6)
+                .map(|period| Chromosome {
+                    id: PeriodId(period),
+                    genes: match period {
+                        0 => smallvec![TupleId(1), TupleId(3)],
+                        2 => smallvec![TupleId(2)],
+                        _ => smallvec![],
+                    },
+                })
+                .collect(),
+        );
+        let mut config = AlgorithmConfig {
+            number_of_periods: 6,
+            days: Some(2),
+            ..AlgorithmConfig::default()
+        };
+
+        let day_sheets = sheets(&individual, &tuples, &config);
+        assert_eq!(day_sheets.len(), 2);
+        assert_eq!(
+            (day_sheets[1].name.as_str(), day_sheets[1].columns.len()),
+            ("Dzień 2", 2)
+        );
+        let spans: Vec<(usize, usize, usize, usize)> = blocks(&day_sheets[0])
+            .iter()
+            .map(|block| {
+                (
+                    block.column,
+                    block.first_slot,
+                    block.last_slot,
+                    block.tuples.len(),
+                )
+            })
+            .collect();
+        assert_eq!(spans, [(0, 0, 1, 1), (0, 2, 2, 2), (1, 0, 1, 1)]);
+        assert!(blocks(&day_sheets[1]).is_empty());
+
+        config.spreadsheet_layout = SpreadsheetLayout::Group;
+        let group_sheets = sheets(&individual, &tuples, &config);
+        let names: Vec<&str> = group_sheets
+            .iter()
+            .map(|sheet| sheet.name.as_str())
+            .collect();
+        assert_eq!(names, ["FT-1", "FT-2"]);
+        assert_eq!(blocks(&group_sheets[0]).len(), 2);
+        assert!(workbook(&individual, &tuples, &config)
+            .unwrap()
+            .save_to_buffer()
+            .is_ok());
+
+        assert_eq!(rgb(0), 0xF4BEBE);
+        assert_eq!(sheet_name("IS [1/2]"), "IS _1_2_");
+    }
+}